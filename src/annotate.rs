@@ -0,0 +1,50 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Per‑directory descriptions read from a marker file.
+//!
+//! With `--annotate-from .dirdesc` every directory containing a `.dirdesc`
+//! file is annotated with its first non‑empty line; Markdown files such as
+//! `README.md` contribute their first heading instead. The result is a
+//! self‑documenting structure listing for onboarding docs.
+
+use std::{
+    ffi::OsStr,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+/// Longest description kept, in characters; longer ones end with `…`.
+const MAX_CHARS: usize = 80;
+
+/// Description of `dir` taken from its `file_name` file, if present.
+pub fn directory_description(dir: &Path, file_name: &OsStr) -> Option<String> {
+    let path = dir.join(file_name);
+    let markdown = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("md") || e.eq_ignore_ascii_case("markdown"));
+    let reader = BufReader::new(File::open(&path).ok()?);
+
+    let line = reader
+        .lines()
+        .map_while(Result::ok)
+        .map(|l| l.trim().to_owned())
+        .find_map(|l| {
+            if markdown {
+                l.strip_prefix('#')
+                    .map(|h| h.trim_start_matches('#').trim().to_owned())
+                    .filter(|h| !h.is_empty())
+            } else {
+                Some(l).filter(|l| !l.is_empty())
+            }
+        })?;
+
+    Some(if line.chars().count() > MAX_CHARS {
+        let mut short: String = line.chars().take(MAX_CHARS - 1).collect();
+        short.push('…');
+        short
+    } else {
+        line
+    })
+}
@@ -0,0 +1,80 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Pluggable per‑entry annotations.
+//!
+//! An [`Annotator`] looks at each listed entry and may contribute a short
+//! note. Any number of them can be registered with
+//! [`TreeOptions::annotator`]; the notes of every annotator that has one are
+//! shown after the entry name in registration order, following the
+//! [`TreeOptions::decorate`] hook.
+//!
+//! [`TreeOptions::annotator`]: crate::TreeOptions::annotator
+//! [`TreeOptions::decorate`]: crate::TreeOptions::decorate
+
+use crate::{du::human_size, entry::EntryInfo, platform::access, scan::EntryKind};
+use std::fmt;
+
+/// Source of notes rendered next to entries.
+///
+/// Closures of the form `Fn(&EntryInfo) -> Option<Annotation>` implement the
+/// trait, so simple annotators need no type of their own.
+pub trait Annotator: Send + Sync {
+    /// Note for `entry`, or `None` to leave it unannotated.
+    fn annotate(&self, entry: &EntryInfo<'_>) -> Option<Annotation>;
+}
+
+impl<F> Annotator for F
+where
+    F: Fn(&EntryInfo<'_>) -> Option<Annotation> + Send + Sync,
+{
+    fn annotate(&self, entry: &EntryInfo<'_>) -> Option<Annotation> {
+        self(entry)
+    }
+}
+
+/// One annotator's note for an entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    /// Text shown after the entry name.
+    pub text: String,
+}
+
+impl Annotation {
+    /// Annotation showing `text`.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self { text: text.into() }
+    }
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Annotates regular files with their size in `du -h` style (`4.0K`).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileSize;
+
+impl Annotator for FileSize {
+    fn annotate(&self, entry: &EntryInfo<'_>) -> Option<Annotation> {
+        if entry.kind != EntryKind::File {
+            return None;
+        }
+        let metadata = entry.metadata().ok()?;
+        Some(Annotation::new(human_size(metadata.len())))
+    }
+}
+
+/// Annotates entries the current process may not read with `[unreadable]`,
+/// for permission audits.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Unreadable;
+
+impl Annotator for Unreadable {
+    fn annotate(&self, entry: &EntryInfo<'_>) -> Option<Annotation> {
+        let metadata = entry.metadata().ok()?;
+        (!access::readable(&metadata)).then(|| Annotation::new("[unreadable]"))
+    }
+}
@@ -0,0 +1,84 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! `export-ignore` entries from `.gitattributes`.
+//!
+//! `git archive` leaves out every path carrying the `export-ignore`
+//! attribute. With [`TreeOptions::export_ignore`] the tree hides the same
+//! entries, previewing what a source tarball will contain. Patterns follow
+//! `.gitignore` syntax relative to the directory of the `.gitattributes`
+//! file that declares them; files from the directory being listed up to the
+//! repository root all apply.
+//!
+//! [`TreeOptions::export_ignore`]: crate::TreeOptions::export_ignore
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::{fs, path::Path};
+
+/// Matchers for the `export-ignore` patterns that apply inside a directory.
+#[derive(Debug, Default)]
+pub struct ExportIgnore {
+    matchers: Vec<Gitignore>,
+}
+
+impl ExportIgnore {
+    /// Load the `.gitattributes` files of `dir` and its ancestors, stopping
+    /// at the repository root (the first directory holding `.git`).
+    pub fn for_dir(dir: &Path) -> Self {
+        let mut matchers = Vec::new();
+        for ancestor in dir.ancestors() {
+            if let Some(matcher) = load(ancestor) {
+                matchers.push(matcher);
+            }
+            if ancestor.join(".git").exists() {
+                break;
+            }
+        }
+        Self { matchers }
+    }
+
+    /// Whether `path` is marked `export-ignore`.
+    ///
+    /// The last matching line decides, and deeper files override shallower
+    /// ones, as in git.
+    pub fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        self.matchers
+            .iter()
+            .map(|m| m.matched(path, is_dir))
+            .find(|m| !m.is_none())
+            .is_some_and(|m| m.is_ignore())
+    }
+}
+
+/// Matcher for the lines of `dir/.gitattributes` that set or unset
+/// `export-ignore`, if any.
+fn load(dir: &Path) -> Option<Gitignore> {
+    let contents = fs::read_to_string(dir.join(".gitattributes")).ok()?;
+    let mut builder = GitignoreBuilder::new(dir);
+    let mut any = false;
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(pattern) = fields.next() else {
+            continue;
+        };
+        // Later attributes on a line override earlier ones.
+        let set = fields.fold(None, |state, attr| match attr {
+            "export-ignore" | "export-ignore=true" => Some(true),
+            "-export-ignore" | "!export-ignore" => Some(false),
+            _ => state,
+        });
+        let line = match set {
+            _ if pattern.starts_with('#') => continue,
+            Some(true) => pattern.to_owned(),
+            // Unsetting re‑includes, like a negated ignore pattern.
+            Some(false) => format!("!{pattern}"),
+            None => continue,
+        };
+        any |= builder.add_line(None, &line).is_ok();
+    }
+    if any {
+        builder.build().ok()
+    } else {
+        None
+    }
+}
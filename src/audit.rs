@@ -0,0 +1,193 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Security audit of permission bits and ownership.
+//!
+//! `tree audit` looks for the entries a reviewer of a user‑owned tree wants
+//! to know about: setuid and setgid programs, files and directories anyone
+//! may write, and entries owned by root inside a tree whose root is not.
+//! World‑writable directories with the sticky bit, such as `/tmp`, are
+//! meant to be shared and are not reported; neither are symbolic links,
+//! whose own mode bits mean nothing.
+//!
+//! Only Unix has these notions; elsewhere an audit finds nothing.
+
+use crate::scan::{EntryKind, TreeNode};
+use serde::Serialize;
+use std::{
+    fmt,
+    fs::{self, Metadata},
+    path::{Path, PathBuf},
+};
+
+/// What makes an entry worth reviewing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Issue {
+    /// A regular file with the setuid bit.
+    Setuid,
+    /// A regular file with the setgid bit.
+    Setgid,
+    /// Anyone may write the entry.
+    WorldWritable,
+    /// Owned by root although the audited root is not.
+    RootOwned,
+}
+
+impl Issue {
+    /// Short label, e.g. `world-writable`.
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Setuid => "setuid",
+            Self::Setgid => "setgid",
+            Self::WorldWritable => "world-writable",
+            Self::RootOwned => "root-owned",
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// One entry with at least one [`Issue`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    /// Path relative to the root, `/`‑separated.
+    pub path: String,
+    /// Everything found, in [`Issue`] order.
+    pub issues: Vec<Issue>,
+}
+
+/// Audit every entry of `tree`, scanned from `root`, in display order.
+///
+/// The entries' metadata is read again from disk; pass a tree whose paths
+/// are not redacted.
+#[must_use]
+pub fn audit(tree: &TreeNode, root: &Path) -> Vec<Finding> {
+    let root_is_root_owned = fs::metadata(root).is_ok_and(|m| owner(&m) == Some(0));
+    let mut findings = Vec::new();
+    let mut stack: Vec<&TreeNode> = tree.children.iter().rev().collect();
+    while let Some(node) = stack.pop() {
+        stack.extend(node.children.iter().rev());
+        if node.kind == EntryKind::Symlink {
+            continue;
+        }
+        let Ok(metadata) = fs::symlink_metadata(entry_path(root, node)) else {
+            continue;
+        };
+        let mut issues = issues(&metadata, node.kind);
+        if !root_is_root_owned && owner(&metadata) == Some(0) {
+            issues.push(Issue::RootOwned);
+        }
+        if !issues.is_empty() {
+            findings.push(Finding {
+                path: node.path.clone(),
+                issues,
+            });
+        }
+    }
+    findings
+}
+
+/// Copy of `tree` pruned to the entries with findings and their ancestors,
+/// each decorated with `[setuid, world-writable]` style labels; `None`
+/// without findings.
+#[must_use]
+pub fn audit_tree(tree: &TreeNode, findings: &[Finding]) -> Option<TreeNode> {
+    let children: Vec<TreeNode> = tree
+        .children
+        .iter()
+        .filter_map(|child| {
+            let finding = findings.iter().find(|f| f.path == child.path);
+            let pruned = audit_tree(child, findings);
+            match (finding, pruned) {
+                (Some(finding), pruned) => {
+                    let labels: Vec<&str> = finding.issues.iter().map(|i| i.label()).collect();
+                    Some(with_children(
+                        child,
+                        pruned.map(|p| p.children).unwrap_or_default(),
+                        Some(format!("[{}]", labels.join(", "))),
+                    ))
+                }
+                (None, pruned) => pruned,
+            }
+        })
+        .collect();
+    (!children.is_empty()).then(|| with_children(tree, children, None))
+}
+
+/// Permission‑bit issues of an entry of `kind`.
+#[cfg(unix)]
+fn issues(metadata: &Metadata, kind: EntryKind) -> Vec<Issue> {
+    use std::os::unix::fs::MetadataExt;
+
+    let mode = metadata.mode();
+    let mut issues = Vec::new();
+    if kind == EntryKind::File {
+        if mode & 0o4000 != 0 {
+            issues.push(Issue::Setuid);
+        }
+        if mode & 0o2000 != 0 {
+            issues.push(Issue::Setgid);
+        }
+    }
+    let sticky_dir = kind == EntryKind::Directory && mode & 0o1000 != 0;
+    if mode & 0o002 != 0 && !sticky_dir {
+        issues.push(Issue::WorldWritable);
+    }
+    issues
+}
+
+/// Mode bits are not exposed outside Unix.
+#[cfg(not(unix))]
+const fn issues(_metadata: &Metadata, _kind: EntryKind) -> Vec<Issue> {
+    Vec::new()
+}
+
+/// Owning user id of an entry.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_wraps)] // `None` on other platforms
+fn owner(metadata: &Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.uid())
+}
+
+/// Ownership is not exposed outside Unix.
+#[cfg(not(unix))]
+const fn owner(_metadata: &Metadata) -> Option<u32> {
+    None
+}
+
+/// Location of `node` below `root`, exact even for non‑UTF‑8 names when the
+/// tree carries Base64 paths.
+fn entry_path(root: &Path, node: &TreeNode) -> PathBuf {
+    #[cfg(unix)]
+    if let Some(raw) = node.path_base64.as_deref().and_then(|b64| {
+        use base64::{engine::general_purpose::STANDARD, Engine as _};
+        STANDARD.decode(b64).ok()
+    }) {
+        use std::os::unix::ffi::OsStrExt;
+        return root.join(std::ffi::OsStr::from_bytes(&raw));
+    }
+    root.join(&node.path)
+}
+
+/// `node` without its subtree, given new `children` and `decoration`.
+fn with_children(node: &TreeNode, children: Vec<TreeNode>, decoration: Option<String>) -> TreeNode {
+    TreeNode {
+        name: node.name.clone(),
+        path: node.path.clone(),
+        path_base64: node.path_base64.clone(),
+        kind: node.kind,
+        size: node.size,
+        description: node.description.clone(),
+        decoration,
+        error: node.error.clone(),
+        truncated: node.truncated,
+        children,
+    }
+}
@@ -49,9 +49,25 @@
 //! The actual tree generation and file management is delegated to the
 //! library functions for better separation of concerns and testability.
 
-use anyhow::Result;
-use clap::Parser;
-use std::path::PathBuf;
+use anyhow::{Context, Result};
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand, ValueEnum};
+use std::{
+    io::{IsTerminal, Read, Write},
+    path::PathBuf,
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+use tree::{
+    human_size, AccessFilter, Buckets, Budget, BufPolicy, CancelToken, CaseSensitivity, ChangeKind,
+    Charset, ColumnPlacement, Config, Debouncer, DecorationCache, FilterExpr, Grouping, Limits,
+    MergeStatus, MultiWriter, NumericLocale, OutputFormat, OwnerFilter, PathEncoding,
+    PathRedaction, Priorities, PromptBudget, Rate, Redaction, ShapeStats, Snapshot, SortOptions,
+    TermCaps, Theme, Throttle, TimeStyle, Traversal, TreeOptions, TreeStats, Unreadable,
+};
 
 /// Command-line interface configuration for the tree application.
 ///
@@ -98,9 +114,29 @@ Examples:
   tree                    Print current directory tree
   tree /path/to/project   Print specific directory tree
   tree --clear            Remove all .tree_ignore files
+  tree --format json      Print the tree as a JSON document
+  tree --annotate-from README.md
+                          Describe each directory by its README heading
+  tree --theme high-contrast --color always
+                          Colour with a built-in or configured theme
+  tree --export out.tar.gz  Also bundle tree.txt, tree.json and stats.csv
+  tree --max-lines 40 --traversal breadth-first
+                          Overview of the top levels of a huge tree
+  tree doc --inject README.md
+                          Refresh the tree between <!-- tree --> markers
+  tree verify --manifest tree.json /srv/app
+                          Fail if /srv/app differs from the manifest
+  tree diff --quiet old/ new/
+                          Count the differences; exit status 1 if any
 ")]
 #[command(version)]
+#[command(args_conflicts_with_subcommands = true)]
+#[allow(clippy::struct_excessive_bools)] // independent on/off CLI flags
 struct Cli {
+    /// Optional subcommand; without one the tree is printed.
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Directory path to generate tree for.
     ///
     /// Specifies the root directory to start tree generation from.
@@ -108,6 +144,33 @@ struct Cli {
     #[arg(default_value = ".", value_name = "PATH")]
     path: PathBuf,
 
+    /// Further directories to show after the first.
+    ///
+    /// Text output prints the trees one after another; JSON output wraps
+    /// them in a single `{"roots": [...]}` document.
+    #[arg(
+        value_name = "PATH",
+        conflicts_with_all = ["clear", "paths_from", "git_rev", "find_root", "count_only", "shape_stats", "export"]
+    )]
+    more_paths: Vec<PathBuf>,
+
+    /// Suppress informational messages such as ignore-file creation notices.
+    ///
+    /// Such messages always go to stderr, never into the tree output.
+    /// `tree diff` prints only the change counts.
+    #[arg(long, short = 'q', global = true)]
+    quiet: bool,
+
+    /// Report failures and warnings on stderr as JSON objects, one per line,
+    /// with stable `code` fields instead of English messages alone.
+    #[arg(long, global = true)]
+    json_errors: bool,
+
+    /// Treat entries that could not be read as a failure: the output is
+    /// still complete, but the exit status becomes 2.
+    #[arg(long, global = true)]
+    strict: bool,
+
     /// Clear all `.tree_ignore` files created by previous runs.
     ///
     /// Recursively removes all `.tree_ignore` files from the specified directory
@@ -130,6 +193,965 @@ struct Cli {
     /// Explicitly setting this flag overrides --directories-only if both are specified.
     #[arg(long, short = 'a')]
     all: bool,
+
+    /// Append the number of files in each directory, e.g. `src/ (12 files)`.
+    ///
+    /// Most useful with --directories-only.
+    #[arg(long)]
+    file_counts: bool,
+
+    /// Append the file extensions in each directory, e.g. `src/ [rs:12
+    /// md:3]`.
+    ///
+    /// Most useful with --directories-only.
+    #[arg(long)]
+    ext_rollup: bool,
+
+    /// Append a Merkle digest to every directory, e.g. `src/ [merkle
+    /// 3f2a…]`.
+    ///
+    /// Equal digests mean identical subtrees. Every file is read.
+    #[arg(long)]
+    hash_dirs: bool,
+
+    /// Output format for the rendered tree.
+    ///
+    /// `text` prints the Unicode tree; `json` prints the scanned hierarchy as
+    /// a JSON document suitable for other tools; `sha256sum` prints a checksum
+    /// manifest that `sha256sum -c` accepts from inside PATH, reporting files
+    /// it cannot read and exiting with status 2 after them; `plain-indent`
+    /// spells out `folder`/`file` on indented lines for screen readers;
+    /// `ndjson`, with `--watch`, streams one JSON change event per line
+    /// instead of the tree.
+    #[arg(long, value_enum, default_value_t = FormatArg::Text)]
+    format: FormatArg,
+
+    /// Indent structured output (JSON). Default when stdout is a terminal.
+    #[arg(long, global = true, conflicts_with = "minify")]
+    pretty: bool,
+
+    /// Write structured output (JSON) without whitespace. Default when
+    /// stdout is not a terminal.
+    #[arg(long, global = true)]
+    minify: bool,
+
+    /// Also write tree.txt, tree.json and stats.csv into a `.tar.gz` archive.
+    ///
+    /// The tree is still printed as usual; the archive is an additional
+    /// artefact for attaching directory manifests to bug reports or tickets.
+    #[arg(long, value_name = "FILE")]
+    export: Option<PathBuf>,
+
+    /// Also write the output to FILE, keeping a copy of what is shown: the
+    /// tree, the `--count-only` and `--shape-stats` reports, or the report of
+    /// a subcommand such as `diff` or `audit`.
+    ///
+    /// Colour and hyperlinks follow stdout; add `--color never` for a plain
+    /// copy when printing to a terminal.
+    #[arg(long, global = true, value_name = "FILE")]
+    tee: Option<PathBuf>,
+
+    /// Record the scan to FILE for rendering again later with `tree render`.
+    ///
+    /// Only names, types and sizes are recorded, and the tree is printed
+    /// from the recording exactly as `tree render FILE` will print it, so
+    /// columns needing other metadata (--du, -p, -D, ...) are refused.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = [
+            "clear", "paths_from", "git_rev", "count_only", "shape_stats", "more_paths",
+            "du", "permissions", "xattrs", "context", "mtime", "time_style",
+        ]
+    )]
+    record: Option<PathBuf>,
+
+    /// Show only as much of the tree as fits in about N prompt tokens.
+    ///
+    /// Shallow entries, source directories and recently modified files are
+    /// kept first; every directory that lost entries ends with `+N more`.
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with_all = ["clear", "paths_from", "git_rev", "count_only", "shape_stats", "more_paths", "record"]
+    )]
+    budget_tokens: Option<usize>,
+
+    /// Like `--budget-tokens`, with the budget given in bytes of output.
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with_all = ["budget_tokens", "clear", "paths_from", "git_rev", "count_only", "shape_stats", "more_paths", "record"]
+    )]
+    budget_bytes: Option<usize>,
+
+    /// Show the root path without this leading PATH (`.` when equal).
+    #[arg(long, value_name = "PATH")]
+    strip_prefix: Option<PathBuf>,
+
+    /// Replace every match of REGEX in the displayed paths with REPLACEMENT.
+    ///
+    /// Applies to the root and to every entry's name and path, in all
+    /// formats. May be repeated; rules apply in order. `$1` in REPLACEMENT
+    /// inserts the first capture group.
+    #[arg(long, value_name = "REGEX=REPLACEMENT")]
+    redact: Vec<Redaction>,
+
+    /// Show only files owned by this user (name or uid); directories are
+    /// always shown.
+    #[arg(long, value_name = "NAME|UID")]
+    user: Option<String>,
+
+    /// Show only files owned by this group (name or gid); directories are
+    /// always shown.
+    ///
+    /// Named `--owner-group` rather than `--group` because `--group`
+    /// already selects how directories are grouped among their siblings.
+    #[arg(long, value_name = "NAME|GID")]
+    owner_group: Option<String>,
+
+    /// Show only files the current user may write; directories are always
+    /// shown.
+    #[arg(long, conflicts_with = "readonly_only")]
+    writable_only: bool,
+
+    /// Show only files the current user may not write; directories are
+    /// always shown.
+    #[arg(long)]
+    readonly_only: bool,
+
+    /// Show only files matching a find-like expression, e.g.
+    /// `(size>1M & ext=rs) | mtime<7d`. Tests are size, mtime (age), name
+    /// (glob), ext and type, compared with < <= = >= > or !=, and combined
+    /// with `!`, `&`, `|` and parentheses; directories are always shown and
+    /// ignore rules still apply.
+    #[arg(long, value_name = "EXPR")]
+    filter: Option<FilterExpr>,
+
+    /// Mark entries the current user may not read with `[unreadable]`.
+    #[arg(long)]
+    mark_unreadable: bool,
+
+    /// How non-UTF-8 paths are written in JSON output.
+    ///
+    /// `base64` keeps a readable lossy `path` and adds the exact bytes in a
+    /// `path_base64` field; `escape` writes `\xNN` escapes into `path`
+    /// itself; `lossy` replaces invalid bytes with U+FFFD.
+    #[arg(long, value_enum, default_value_t = PathEncodingArg::Base64)]
+    path_encoding: PathEncodingArg,
+
+    /// When to colour the text output.
+    ///
+    /// `auto` colours when stdout is a terminal, honouring `NO_COLOR`,
+    /// `CLICOLOR`, `CLICOLOR_FORCE` and `TERM=dumb`.
+    #[arg(long, value_enum, default_value_t = ColorArg::Auto, value_name = "WHEN")]
+    color: ColorArg,
+
+    /// Colour theme: a built-in (default, high-contrast, monochrome-bold) or
+    /// one defined under `[themes.<name>]` in the configuration file.
+    ///
+    /// Defaults to the `theme` key of the configuration file, or `default`.
+    #[arg(long, value_name = "NAME")]
+    theme: Option<String>,
+
+    /// Colour the guide lines by depth using the theme's level palette.
+    #[arg(long)]
+    level_colors: bool,
+
+    /// Colour names by modification time using the theme's age palette,
+    /// bright for recent changes and dim for old ones. BUCKETS are the
+    /// ascending age limits of the colour steps.
+    #[arg(
+        long,
+        value_name = "BUCKETS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1h,1d,1w,30d,1y"
+    )]
+    age_colors: Option<Buckets<Duration>>,
+
+    /// Colour file names by size using the theme's size palette, so large
+    /// files stand out. BUCKETS are the ascending size limits of the colour
+    /// steps (binary units). --age-colors wins where both apply.
+    #[arg(
+        long,
+        value_name = "BUCKETS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "64K,1M,16M,128M,1G"
+    )]
+    size_colors: Option<Buckets<u64>>,
+
+    /// Show disk usage like `du -h`: allocated size, hard links counted
+    /// once, directories with everything below them.
+    #[arg(long)]
+    du: bool,
+
+    /// Count a directory reached under several paths (bind mounts,
+    /// symlinks) every time in `--du` and `--count-only` totals.
+    #[arg(long)]
+    no_dedupe: bool,
+
+    /// Show each entry's type and permissions like `ls -l`, with `+` for
+    /// entries carrying an ACL.
+    #[arg(long, short = 'p')]
+    permissions: bool,
+
+    /// Show the names of each entry's extended attributes.
+    #[arg(long)]
+    xattrs: bool,
+
+    /// Show each entry's security context, like `ls -Z` (Linux only).
+    #[arg(long, short = 'Z')]
+    context: bool,
+
+    /// Show each entry's modification time (UTC).
+    #[arg(long, short = 'D')]
+    mtime: bool,
+
+    /// Timestamp style for --mtime: iso, long-iso, full-iso, relative or
+    /// +FORMAT (strftime-style). Implies --mtime.
+    #[arg(long, value_name = "STYLE")]
+    time_style: Option<TimeStyle>,
+
+    /// Group digits and pick the decimal separator of counts and sizes
+    /// (--count-only, --report-header, --du) like LOCALE: c, en, de, fr,
+    /// ch, a locale name such as `de_DE.UTF-8`, or auto to follow `LC_ALL`,
+    /// `LC_NUMERIC` or `LANG`.
+    #[arg(long, value_name = "LOCALE")]
+    numeric_locale: Option<NumericLocale>,
+
+    /// Show the metadata columns (--du, -p, -Z, --mtime) after the names,
+    /// starting at display column COL, instead of before them.
+    #[arg(long, value_name = "COL")]
+    columns_at: Option<usize>,
+
+    /// Output buffering: `none` (the default), `line` to flush after every
+    /// line, or a chunk size such as `64K` for slow pipes and sockets.
+    #[arg(long, global = true, value_name = "MODE", value_parser = parse_buffer_arg)]
+    buffer: Option<BufPolicy>,
+
+    /// Stop with an error instead of listing entries deeper than N
+    /// (default 512).
+    #[arg(long, value_name = "N")]
+    limit_depth: Option<usize>,
+
+    /// Stop with an error instead of listing a directory whose path is
+    /// longer than BYTES (default 4096).
+    #[arg(long, value_name = "BYTES")]
+    limit_path_len: Option<usize>,
+
+    /// Stop with an error after following more than N symlinked
+    /// directories on the way to an entry (default 20; values near the
+    /// kernel's own limit of 32-40 let loops end silently).
+    #[arg(long, value_name = "N")]
+    limit_symlink_hops: Option<usize>,
+
+    /// Walk up from PATH to the nearest directory containing MARKER (e.g.
+    /// `Cargo.toml` or `.git`) and show that directory instead.
+    #[arg(long, value_name = "MARKER")]
+    find_root: Option<String>,
+
+    /// Print only the totals (directories, files, bytes) of what would be
+    /// shown, without rendering the tree.
+    #[arg(long, conflicts_with_all = ["paths_from", "git_rev", "export"])]
+    count_only: bool,
+
+    /// Print a histogram of entries per depth, the maximum depth, the
+    /// average fan-out and the deepest path instead of the tree.
+    #[arg(long, conflicts_with_all = ["count_only", "paths_from", "git_rev", "export"])]
+    shape_stats: bool,
+
+    /// Report wall time, entries per second and stat calls on stderr after
+    /// rendering; with `--watch`, also the decorations reused from the
+    /// previous frame after each one.
+    #[arg(long)]
+    timing: bool,
+
+    /// Give up on an entry's metadata after MS milliseconds and mark it
+    /// `timed out`, so a hung network mount cannot stall the listing.
+    ///
+    /// The number of timed-out entries is reported on stderr.
+    #[arg(long, value_name = "MS")]
+    stat_timeout: Option<u64>,
+
+    /// Retry a directory listing or metadata lookup up to N times when it
+    /// fails with a transient error (EAGAIN, EINTR, a network timeout),
+    /// backing off from 50 ms and doubling each time.
+    ///
+    /// Each retry is logged on stderr and their number is reported at the
+    /// end.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    retry: u32,
+
+    /// Limit I/O to RATE so background scans leave shared storage usable:
+    /// operations per second (`200`, `200/s`) count directory listings and
+    /// metadata lookups, a size per second (`10MB/s`) counts bytes read
+    /// while hashing.
+    #[arg(long, value_name = "RATE")]
+    throttle: Option<Rate>,
+
+    /// Print the tree, then re-scan every --watch-interval and print it
+    /// again whenever an entry is created, removed or modified, until
+    /// Ctrl-C.
+    #[arg(long, conflicts_with_all = [
+        "clear", "export", "paths_from", "git_rev", "more_paths", "count_only", "shape_stats",
+        "record", "budget_tokens", "budget_bytes",
+    ])]
+    watch: bool,
+
+    /// Milliseconds between the scans of --watch.
+    #[arg(long, value_name = "MS", default_value_t = 1000, requires = "watch")]
+    watch_interval: u64,
+
+    /// Append every change seen by --watch to FILE as a JSON line, e.g.
+    /// `{"time":"2025-06-01T12:00:00Z","event":"created","path":"src/new.rs"}`.
+    #[arg(long, value_name = "FILE", requires = "watch")]
+    log_changes: Option<PathBuf>,
+
+    /// Report and re-render for --watch only when a changed path matches
+    /// GLOB (`.gitignore` syntax: `*.rs`, `src/`, `/docs`); repeatable.
+    /// Ignored paths are never watched.
+    #[arg(long, value_name = "GLOB", requires = "watch")]
+    watch_include: Vec<String>,
+
+    /// Run COMMAND through the shell after each batch of --watch changes,
+    /// e.g. `notify-send tree "{count} changes"`. `{path}` expands to the
+    /// quoted changed paths, `{root}` to the watched root, `{count}` to the
+    /// number of changes and `{event}` to the first one's kind (created,
+    /// removed or modified). Watching pauses while it runs.
+    #[arg(long, value_name = "COMMAND", requires = "watch")]
+    notify_cmd: Option<String>,
+
+    /// Wait until --watch has seen no changes for MS milliseconds before
+    /// printing the tree again, so a burst of changes such as a build
+    /// causes a single re-render.
+    #[arg(long, value_name = "MS", default_value_t = 0, requires = "watch")]
+    debounce: u64,
+
+    /// Reduce each burst seen by --watch to the net change of every path:
+    /// a file created and deleted again is not reported at all.
+    #[arg(long, requires = "watch")]
+    batch: bool,
+
+    /// Scan at idle I/O priority and lowest CPU priority (Linux; other Unix
+    /// systems only lower the CPU priority), so large scans on servers do
+    /// not slow down production workloads.
+    #[arg(long)]
+    idle_io: bool,
+
+    /// Render the paths listed in FILE (`-` for stdin) instead of walking
+    /// PATH, which only names the root line. Entries are separated by
+    /// newlines, or by NUL bytes if there are any (`git ls-files -z`).
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["clear", "export"])]
+    paths_from: Option<PathBuf>,
+
+    /// Render the committed tree of revision REV (e.g. `HEAD~3`) below PATH
+    /// via `git ls-tree`, without touching the working directory.
+    #[arg(
+        long,
+        value_name = "REV",
+        conflicts_with_all = ["clear", "export", "paths_from"]
+    )]
+    git_rev: Option<String>,
+
+    /// Show at most N entries per directory, then a `+k more` line.
+    #[arg(long, value_name = "N")]
+    sample: Option<usize>,
+
+    /// Do not descend into directories with more than N entries in total;
+    /// they are shown as `name/ … skipped (N entries)`.
+    #[arg(long, value_name = "N")]
+    auto_skip_over: Option<usize>,
+
+    /// On the first run in a directory, create `.tree_ignore` with only the
+    /// large well-known directories found (`node_modules`, `target`, `.git`, …)
+    /// instead of the full default template.
+    ///
+    /// Without this flag an interactive terminal is asked first.
+    #[arg(long)]
+    suggest_ignores: bool,
+
+    /// Do not honour `.ignore` files.
+    #[arg(long)]
+    no_dot_ignore: bool,
+
+    /// Also honour `.fdignore` and `.rgignore` files, matching fd and ripgrep.
+    #[arg(long)]
+    tool_ignores: bool,
+
+    /// Hide entries marked `export-ignore` in `.gitattributes`, previewing
+    /// what `git archive` would include.
+    #[arg(long)]
+    export_ignore: bool,
+
+    /// Letter-case matching of `.tree_ignore` patterns; auto follows the
+    /// filesystem holding PATH.
+    #[arg(long, value_enum, default_value = "auto", value_name = "MODE")]
+    pattern_case: PatternCaseArg,
+
+    /// Make entry names clickable (OSC 8 `file://` links).
+    ///
+    /// Supported by most modern terminal emulators. Ignored when stdout is
+    /// not a terminal.
+    #[arg(long)]
+    hyperlinks: bool,
+
+    /// Print right-to-left override and other bidirectional control
+    /// characters in names as they are.
+    ///
+    /// By default they are shown as `\u{202e}` style escapes, because a name
+    /// such as `invoice<U+202E>fdp.exe` otherwise displays as
+    /// `invoiceexe.pdf`.
+    #[arg(long)]
+    keep_bidi: bool,
+
+    /// Annotate directories with a description read from FILE inside them.
+    ///
+    /// Uses the first heading of Markdown files (e.g. `README.md`) or the
+    /// first non-empty line of any other file (e.g. `.dirdesc`).
+    #[arg(long, value_name = "FILE")]
+    annotate_from: Option<std::ffi::OsString>,
+
+    /// Worker threads for scanning and hashing (0 = one per core).
+    ///
+    /// Output is the same for every value; use 1 for reproducible benchmarks.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    threads: usize,
+
+    /// Do not annotate unreadable entries with `[error: ...]`.
+    #[arg(long)]
+    hide_errors: bool,
+
+    /// Stop after N entry lines of text output.
+    #[arg(long, value_name = "N")]
+    max_lines: Option<usize>,
+
+    /// Order deciding which entries fit within --max-lines.
+    ///
+    /// breadth-first shows the top levels of a huge tree as an overview.
+    #[arg(long, value_enum, default_value = "depth-first")]
+    traversal: TraversalArg,
+
+    /// Print a header with root, device, scan time, filters and totals.
+    #[arg(long)]
+    report_header: bool,
+
+    /// Glyphs for the tree branches.
+    ///
+    /// auto uses Unicode box drawing unless TERM=dumb.
+    #[arg(long, value_enum, default_value = "auto")]
+    charset: CharsetArg,
+
+    /// How to group directories among their siblings.
+    #[arg(long, value_enum, default_value = "dirs-first", value_name = "MODE")]
+    group: GroupArg,
+
+    /// List files before directories (same as `--group files-first`).
+    #[arg(long, conflicts_with = "group")]
+    files_first: bool,
+
+    /// Order names case-insensitively; names differing only in case keep
+    /// a fixed case-sensitive order (`README.md` before `Readme.md`).
+    #[arg(long)]
+    sort_ignore_case: bool,
+}
+
+/// Colour modes selectable with `--color`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ColorArg {
+    /// Colour when writing to a terminal.
+    Auto,
+    /// Always colour.
+    Always,
+    /// Never colour.
+    Never,
+}
+
+/// Branch glyphs selectable with `--charset`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum CharsetArg {
+    /// Unicode unless the terminal is dumb.
+    Auto,
+    /// Unicode box drawing.
+    Unicode,
+    /// Plain ASCII.
+    Ascii,
+}
+
+/// Grouping modes selectable with `--group`.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum GroupArg {
+    /// Directories before files.
+    DirsFirst,
+    /// Files before directories.
+    FilesFirst,
+    /// Directories and files interleaved by name.
+    Mixed,
+}
+
+impl From<GroupArg> for Grouping {
+    fn from(arg: GroupArg) -> Self {
+        match arg {
+            GroupArg::DirsFirst => Self::DirsFirst,
+            GroupArg::FilesFirst => Self::FilesFirst,
+            GroupArg::Mixed => Self::Mixed,
+        }
+    }
+}
+
+/// Actions of `tree config`.
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Print the effective configuration as TOML: built-in defaults, the
+    /// configuration file (located via `$TREE_CONFIG` or the standard
+    /// paths) and the flags given here.
+    Show {
+        /// Theme override, as `--theme` would apply.
+        #[arg(long, value_name = "NAME")]
+        theme: Option<String>,
+    },
+    /// Print the location of the configuration file in use.
+    Path,
+}
+
+/// Pattern case modes selectable with `--pattern-case`.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum PatternCaseArg {
+    /// Detect the filesystem's case sensitivity.
+    Auto,
+    /// Match pattern letter case exactly.
+    Sensitive,
+    /// Ignore letter case.
+    Insensitive,
+}
+
+impl From<PatternCaseArg> for CaseSensitivity {
+    fn from(arg: PatternCaseArg) -> Self {
+        match arg {
+            PatternCaseArg::Auto => Self::Auto,
+            PatternCaseArg::Sensitive => Self::Sensitive,
+            PatternCaseArg::Insensitive => Self::Insensitive,
+        }
+    }
+}
+
+/// Subcommands beyond the default print mode.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Verify a directory against a previously exported JSON manifest.
+    ///
+    /// Scans PATH and compares it with the manifest (the `tree.json` from
+    /// `--export`, or `--format json` output). Prints one line per missing,
+    /// extra or changed entry and exits with a non-zero status on mismatch.
+    ///
+    /// PATH is never written to; without a `.tree_ignore` the default
+    /// patterns apply. Manifests made with -d list no files and cannot be
+    /// verified.
+    Verify {
+        /// JSON manifest to compare against.
+        #[arg(long, value_name = "FILE")]
+        manifest: PathBuf,
+
+        /// Directory to verify.
+        #[arg(default_value = ".", value_name = "PATH")]
+        path: PathBuf,
+    },
+
+    /// Regenerate the tree block between markers in a documentation file.
+    ///
+    /// Replaces everything between the opening marker and the following
+    /// closing marker (the same marker by default) with a fenced code block
+    /// holding the current tree of PATH. Suitable for pre-commit hooks.
+    Doc {
+        /// Documentation file to update in place.
+        #[arg(long, value_name = "FILE")]
+        inject: PathBuf,
+
+        /// Marker opening the generated block.
+        #[arg(long, default_value = "<!-- tree -->")]
+        marker: String,
+
+        /// Marker closing the generated block (defaults to --marker).
+        #[arg(long)]
+        end_marker: Option<String>,
+
+        /// Do not write; fail if the file is out of date.
+        #[arg(long)]
+        check: bool,
+
+        /// Show directories only.
+        #[arg(long, short = 'd')]
+        directories_only: bool,
+
+        /// Directory whose tree is injected.
+        #[arg(default_value = ".", value_name = "PATH")]
+        path: PathBuf,
+    },
+
+    /// Inspect the configuration file.
+    Config {
+        /// What to show.
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Write a binary snapshot of a directory for fast repeated comparison.
+    ///
+    /// With --base, file hashes are reused from the older snapshot wherever
+    /// size and modification time are unchanged, and the differences from it
+    /// are printed in the same form as `verify`.
+    Snapshot {
+        /// Snapshot file to write.
+        #[arg(long, short = 'o', value_name = "FILE")]
+        output: PathBuf,
+
+        /// Record a SHA-256 hash for every file.
+        #[arg(long)]
+        hashes: bool,
+
+        /// Previous snapshot to update from and compare with.
+        #[arg(long, value_name = "FILE")]
+        base: Option<PathBuf>,
+
+        /// Directory to snapshot.
+        #[arg(default_value = ".", value_name = "PATH")]
+        path: PathBuf,
+    },
+
+    /// Compare two directories entry by entry.
+    ///
+    /// Prints one line per entry added, removed or modified in NEW relative
+    /// to OLD — only the counts with --quiet — and, like diff(1), exits with
+    /// status 1 when they differ and 2 when the comparison fails.
+    ///
+    /// With --base, OLD and NEW are two copies of the snapshot and each
+    /// entry either of them changed is reported as changed on the left
+    /// (OLD), the right (NEW), both alike, or both in conflict.
+    Diff {
+        /// Snapshot (from `tree snapshot`) of the common base for a
+        /// three-way comparison.
+        #[arg(long, value_name = "FILE")]
+        base: Option<PathBuf>,
+
+        /// Print NEW as a tree with the changes marked, collapsing every
+        /// directory that is identical on both sides into one line.
+        #[arg(long, conflicts_with = "base")]
+        tree: bool,
+
+        /// Directory with the original layout.
+        #[arg(value_name = "OLD")]
+        old: PathBuf,
+
+        /// Directory compared against it.
+        #[arg(value_name = "NEW")]
+        new: PathBuf,
+    },
+
+    /// Render several directories as one tree, stacked like overlayfs
+    /// layers with the first PATH at the bottom.
+    ///
+    /// Directories merge across layers; any other entry hides what lower
+    /// layers hold at its path. Each entry is annotated with the layers it
+    /// is visible from (`[1 2]`) and those it shadows (`shadows 1`).
+    Overlay {
+        /// Output format.
+        #[arg(long, value_enum, default_value_t = FormatArg::Text)]
+        format: FormatArg,
+
+        /// Layer directories, lowest first.
+        #[arg(value_name = "PATH", required = true, num_args = 2..)]
+        paths: Vec<PathBuf>,
+    },
+
+    /// Show the filesystem of a container image, squashed or layer by layer.
+    ///
+    /// IMAGE is an image tarball written by `docker save` (or an OCI image
+    /// tarball with a manifest.json), or else a local image reference that
+    /// is exported with `docker save`. Whiteouts are applied when squashing.
+    #[cfg(feature = "docker")]
+    Image {
+        /// Show only the entries layer N (from 1, lowest first) stores,
+        /// whiteout files included.
+        #[arg(long, value_name = "N")]
+        layer: Option<usize>,
+
+        /// Output format.
+        #[arg(long, value_enum, default_value_t = FormatArg::Text)]
+        format: FormatArg,
+
+        /// Image tarball or image reference.
+        #[arg(value_name = "IMAGE")]
+        image: String,
+    },
+
+    /// Render a scan recorded with `--record` without reading the directory.
+    Render {
+        /// Output format.
+        #[arg(long, value_enum, default_value_t = FormatArg::Text)]
+        format: FormatArg,
+
+        /// Name shown for the root directory.
+        #[arg(long, default_value = ".", value_name = "NAME")]
+        root_name: String,
+
+        /// Recording written by `--record` (or `tree snapshot`).
+        #[arg(value_name = "FILE")]
+        recording: PathBuf,
+    },
+
+    /// Fail if a directory exceeds a size or file-count budget.
+    ///
+    /// Scans PATH with the usual ignore rules and prints its totals; when a
+    /// limit is exceeded, also lists the largest entries directly below PATH
+    /// and exits with a non-zero status.
+    #[command(group(ArgGroup::new("limits").required(true).multiple(true)))]
+    Budget {
+        /// Largest allowed total file size, e.g. 500M or 2G (binary units).
+        #[arg(long, value_name = "SIZE", value_parser = parse_size_arg, group = "limits")]
+        max_size: Option<u64>,
+
+        /// Largest allowed number of files.
+        #[arg(long, value_name = "N", group = "limits")]
+        max_files: Option<u64>,
+
+        /// Number of largest contributors to list.
+        #[arg(long, value_name = "N", default_value_t = 5)]
+        top: usize,
+
+        /// Directory to check.
+        #[arg(default_value = ".", value_name = "PATH")]
+        path: PathBuf,
+    },
+
+    /// Find sibling names that differ only by letter case or Unicode
+    /// normalization form, which break checkouts on macOS and Windows.
+    ///
+    /// Prints the affected part of the tree with markers (or a JSON list)
+    /// and exits with a non-zero status when any collision is found.
+    CheckCollisions {
+        /// Report format.
+        #[arg(long, value_enum, default_value = "text")]
+        format: ReportFormatArg,
+
+        /// Directory to check.
+        #[arg(default_value = ".", value_name = "PATH")]
+        path: PathBuf,
+    },
+
+    /// Find setuid and setgid programs, world-writable entries and entries
+    /// owned by root inside a tree whose root is not.
+    ///
+    /// Prints the affected part of the tree with markers (or a JSON list)
+    /// and exits with a non-zero status when anything is found.
+    Audit {
+        /// Report format.
+        #[arg(long, value_enum, default_value = "text")]
+        format: ReportFormatArg,
+
+        /// Directory to audit.
+        #[arg(default_value = ".", value_name = "PATH")]
+        path: PathBuf,
+    },
+}
+
+/// Formats of subcommand reports.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ReportFormatArg {
+    /// Human-readable text.
+    Text,
+    /// JSON document.
+    Json,
+}
+
+/// Parse a `--max-size` value.
+fn parse_size_arg(text: &str) -> Result<u64, String> {
+    tree::parse_size(text).ok_or_else(|| format!("invalid size `{text}` (expected e.g. 500M)"))
+}
+
+/// Options of print mode, from the flags and the terminal's capabilities.
+fn print_options(cli: &Cli) -> Result<TreeOptions> {
+    let caps = TermCaps::detect(std::io::stdout().is_terminal());
+    let charset = match cli.charset {
+        CharsetArg::Unicode => Charset::Unicode,
+        CharsetArg::Auto if caps.unicode => Charset::Unicode,
+        CharsetArg::Auto | CharsetArg::Ascii => Charset::Ascii,
+    };
+    let options = TreeOptions {
+        show_files: !cli.directories_only || cli.all,
+        format: cli.format.into(),
+        path_encoding: cli.path_encoding.into(),
+        theme: resolve_theme(cli, caps)?,
+        hyperlinks: cli.hyperlinks && caps.hyperlinks,
+        keep_bidi: cli.keep_bidi,
+        annotate_from: cli.annotate_from.clone(),
+        threads: cli.threads,
+        show_errors: !cli.hide_errors,
+        traversal: cli.traversal.into(),
+        max_lines: cli.max_lines,
+        report_header: cli.report_header,
+        charset,
+        file_counts: cli.file_counts,
+        extension_rollup: cli.ext_rollup,
+        hash_dirs: cli.hash_dirs,
+        level_colors: cli.level_colors,
+        age_colors: cli.age_colors.clone(),
+        size_colors: cli.size_colors.clone(),
+        auto_skip_over: cli.auto_skip_over,
+        sample: cli.sample,
+        suggest_ignores: cli.suggest_ignores,
+        dot_ignore: !cli.no_dot_ignore,
+        tool_ignores: cli.tool_ignores,
+        export_ignore: cli.export_ignore,
+        case_sensitivity: cli.pattern_case.into(),
+        sort: SortOptions::grouped(if cli.files_first {
+            Grouping::FilesFirst
+        } else {
+            cli.group.into()
+        })
+        .ignoring_case(cli.sort_ignore_case),
+        metrics: (cli.timing || cli.stat_timeout.is_some() || cli.retry > 0).then(Arc::default),
+        stat_timeout: cli.stat_timeout.map(Duration::from_millis),
+        retry: cli.retry,
+        throttle: cli.throttle.map(|rate| Arc::new(Throttle::new(rate))),
+        disk_usage: cli.du,
+        dedupe: !cli.no_dedupe,
+        permissions: cli.permissions,
+        xattrs: cli.xattrs,
+        security_context: cli.context,
+        time_style: cli
+            .time_style
+            .clone()
+            .or_else(|| cli.mtime.then(TimeStyle::default)),
+        numeric_locale: cli.numeric_locale,
+        columns: cli
+            .columns_at
+            .map_or(ColumnPlacement::BeforeName, ColumnPlacement::AfterName),
+        limits: limits(cli),
+        owner: owner(cli)?,
+        access: if cli.writable_only {
+            Some(AccessFilter::Writable)
+        } else if cli.readonly_only {
+            Some(AccessFilter::ReadOnly)
+        } else {
+            None
+        },
+        filter_expr: cli.filter.clone(),
+        redaction: PathRedaction {
+            strip_prefix: cli.strip_prefix.clone(),
+            rules: cli.redact.clone(),
+        },
+        ..base_options(cli)
+    };
+    Ok(if cli.mark_unreadable {
+        options.annotator(Unreadable)
+    } else {
+        options
+    })
+}
+
+/// Owner filter from `--user` and `--owner-group`, if either is given.
+fn owner(cli: &Cli) -> Result<Option<OwnerFilter>> {
+    if cli.user.is_none() && cli.owner_group.is_none() {
+        return Ok(None);
+    }
+    let filter = OwnerFilter::resolve(cli.user.as_deref(), cli.owner_group.as_deref())?;
+    Ok(Some(filter))
+}
+
+/// Traversal guards from `--limit-*`, defaulting the ones not given.
+fn limits(cli: &Cli) -> Limits {
+    let defaults = Limits::default();
+    Limits {
+        max_depth: cli.limit_depth.unwrap_or(defaults.max_depth),
+        max_path_len: cli.limit_path_len.unwrap_or(defaults.max_path_len),
+        max_symlink_hops: cli.limit_symlink_hops.unwrap_or(defaults.max_symlink_hops),
+    }
+}
+
+/// Parse `--buffer`: `none`, `line` or a chunk size such as `64K`.
+fn parse_buffer_arg(text: &str) -> Result<BufPolicy, String> {
+    match text {
+        "none" => Ok(BufPolicy::Unbuffered),
+        "line" => Ok(BufPolicy::Line),
+        _ => tree::parse_size(text)
+            .and_then(|size| usize::try_from(size).ok())
+            .filter(|&size| size > 0)
+            .map(BufPolicy::Chunks)
+            .ok_or_else(|| format!("invalid buffering `{text}` (expected none, line or e.g. 64K)")),
+    }
+}
+
+/// Traversal orders selectable with `--traversal`.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum TraversalArg {
+    /// Finish each directory before its next sibling.
+    DepthFirst,
+    /// Visit the tree level by level.
+    BreadthFirst,
+}
+
+impl From<TraversalArg> for Traversal {
+    fn from(arg: TraversalArg) -> Self {
+        match arg {
+            TraversalArg::DepthFirst => Self::DepthFirst,
+            TraversalArg::BreadthFirst => Self::BreadthFirst,
+        }
+    }
+}
+
+/// Path encodings selectable with `--path-encoding`.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum PathEncodingArg {
+    /// Replace invalid bytes with U+FFFD.
+    Lossy,
+    /// Add the exact bytes as a Base64 `path_base64` field.
+    Base64,
+    /// Escape invalid bytes as `\xNN` inside `path`.
+    Escape,
+}
+
+impl From<PathEncodingArg> for PathEncoding {
+    fn from(arg: PathEncodingArg) -> Self {
+        match arg {
+            PathEncodingArg::Lossy => Self::Lossy,
+            PathEncodingArg::Base64 => Self::Base64,
+            PathEncodingArg::Escape => Self::Escape,
+        }
+    }
+}
+
+/// Output formats selectable with `--format`.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum FormatArg {
+    /// Unicode box-drawing tree.
+    Text,
+    /// JSON document.
+    Json,
+    /// Checksum manifest compatible with `sha256sum -c`.
+    Sha256sum,
+    /// Rust literal for test fixtures.
+    RustFixture,
+    /// TypeScript literal for test fixtures.
+    TsFixture,
+    /// Indented "folder"/"file" lines for screen readers.
+    PlainIndent,
+    /// One JSON change event per line (with `--watch`).
+    Ndjson,
+}
+
+impl From<FormatArg> for OutputFormat {
+    fn from(arg: FormatArg) -> Self {
+        match arg {
+            FormatArg::Text => Self::Text,
+            FormatArg::Json => Self::Json,
+            FormatArg::Sha256sum => Self::Sha256sum,
+            FormatArg::RustFixture => Self::RustFixture,
+            FormatArg::TsFixture => Self::TsFixture,
+            FormatArg::PlainIndent => Self::PlainIndent,
+            FormatArg::Ndjson => Self::Ndjson,
+        }
+    }
 }
 
 /// Application entry point and main execution logic.
@@ -149,16 +1171,154 @@ struct Cli {
 ///
 /// ## Exit Behavior
 ///
-/// - **Success**: Returns `Ok(())` and exits with code 0
-/// - **Error**: Returns `Err(...)` and exits with code 1 (handled by anyhow)
+/// - **0**: Success
+/// - **1**: Usage or validation error (bad arguments, missing path, manifest
+///   mismatch, stale document, ...)
+/// - **2**: Partial traversal errors, after the output is complete; only
+///   with `--strict`, or with `--format sha256sum`, whose manifest then
+///   lacks the unreadable files
+/// - **3**: I/O failure
+///
+/// `tree diff` follows diff(1) instead: 0 when the trees are the same, 1
+/// when they differ and 2 when the comparison failed.
 ///
 /// ## Performance Notes
 ///
 /// The main function itself has minimal overhead - all heavy lifting is
 /// delegated to the optimized library functions. Memory usage is bounded
 /// by the tree library's streaming implementation.
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+fn main() -> ExitCode {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(err) if err.use_stderr() => {
+            let _ = err.print();
+            return ExitCode::from(if invoked_diff() {
+                EXIT_TROUBLE
+            } else {
+                EXIT_USAGE
+            });
+        }
+        Err(err) => err.exit(),
+    };
+    if let Err(err) = run(&cli) {
+        let code = error_code(&err);
+        if cli.json_errors {
+            let record = serde_json::json!({
+                "level": "error",
+                "code": code,
+                "message": format!("{err:#}"),
+            });
+            eprintln!("{record}");
+        } else if code == "cancelled" {
+            eprintln!("tree: {err:#}");
+        } else {
+            eprintln!("Error: {err:?}");
+        }
+        return ExitCode::from(match exit_status(code) {
+            EXIT_INTERRUPTED => EXIT_INTERRUPTED,
+            _ if matches!(cli.command, Some(Command::Diff { .. })) => EXIT_TROUBLE,
+            status => status,
+        });
+    }
+    if DIFFERED.load(Ordering::Relaxed) {
+        return ExitCode::from(EXIT_DIFFERENT);
+    }
+    let manifest = matches!(cli.format, FormatArg::Sha256sum);
+    if (cli.strict || manifest) && SKIPPED.load(Ordering::Relaxed) > 0 {
+        return ExitCode::from(EXIT_PARTIAL);
+    }
+    ExitCode::SUCCESS
+}
+
+/// Exit status for usage and validation errors.
+const EXIT_USAGE: u8 = 1;
+/// Exit status for runs that skipped unreadable entries under `--strict`,
+/// or left unreadable files out of a checksum manifest.
+const EXIT_PARTIAL: u8 = 2;
+/// Exit status for I/O failures.
+const EXIT_IO: u8 = 3;
+/// Exit status of `tree diff` when the trees differ, as with diff(1).
+const EXIT_DIFFERENT: u8 = 1;
+/// Exit status of `tree diff` when the comparison failed, as with diff(1).
+const EXIT_TROUBLE: u8 = 2;
+/// Exit status after Ctrl-C, as shells report a SIGINT.
+const EXIT_INTERRUPTED: u8 = 130;
+
+/// Number of entries reported unreadable during this run.
+static SKIPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// Set when `tree diff` found differences.
+static DIFFERED: AtomicBool = AtomicBool::new(false);
+
+/// The token of every walk in this run, cancelled on Ctrl-C so the walk
+/// stops after a complete line; a second Ctrl-C exits at once.
+fn cancel_token() -> CancelToken {
+    static TOKEN: OnceLock<CancelToken> = OnceLock::new();
+    TOKEN
+        .get_or_init(|| {
+            let token = CancelToken::new();
+            let handler = token.clone();
+            // Without a handler Ctrl-C simply keeps killing the process.
+            let _ = ctrlc::set_handler(move || {
+                if handler.is_cancelled() {
+                    std::process::exit(EXIT_INTERRUPTED.into());
+                }
+                handler.cancel();
+            });
+            token
+        })
+        .clone()
+}
+
+/// Whether the arguments that failed to parse were meant for `tree diff`.
+fn invoked_diff() -> bool {
+    Cli::command()
+        .ignore_errors(true)
+        .try_get_matches()
+        .is_ok_and(|matches| matches.subcommand_name() == Some("diff"))
+}
+
+/// Map a fatal error code to the documented exit status.
+fn exit_status(code: &str) -> u8 {
+    match code {
+        "path-missing" | "not-a-directory" | "other" => EXIT_USAGE,
+        "cancelled" => EXIT_INTERRUPTED,
+        _ => EXIT_IO,
+    }
+}
+
+/// Stable code for a fatal error: the [`tree::TreeError`] code when the
+/// library failed, else the I/O kind found anywhere in the chain.
+fn error_code(err: &anyhow::Error) -> &'static str {
+    if let Some(err) = err.downcast_ref::<tree::TreeError>() {
+        return err.code();
+    }
+    if err
+        .chain()
+        .any(<dyn std::error::Error>::is::<tree::Cancelled>)
+    {
+        return "cancelled";
+    }
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<std::io::Error>())
+        .map_or("other", |io| {
+            tree::TreeError::Io(std::io::Error::from(io.kind())).code()
+        })
+}
+
+/// Execute the parsed command line.
+fn run(cli: &Cli) -> Result<()> {
+    if cli.idle_io {
+        // Before any worker starts, so every scan thread inherits it.
+        if let Err(err) = tree::idle_io() {
+            if !cli.quiet {
+                eprintln!("tree: --idle-io ignored: {err}");
+            }
+        }
+    }
+    if let Some(command) = &cli.command {
+        return run_command(command, cli, &mut output(cli)?);
+    }
 
     if cli.clear {
         // Clear mode: Remove all .tree_ignore files and report count
@@ -166,9 +1326,854 @@ fn main() -> Result<()> {
         println!("Removed {removed} .tree_ignore file(s)");
     } else {
         // Print mode: Generate and display directory tree
-        let show_files = !cli.directories_only || cli.all;
-        tree::print_with_options(&cli.path, &mut std::io::stdout(), show_files)?;
+        let root = match &cli.find_root {
+            Some(marker) => tree::find_root(&cli.path, marker)?,
+            None => cli.path.clone(),
+        };
+        let walks_disk = cli.paths_from.is_none() && cli.git_rev.is_none();
+        if !cli.suggest_ignores && !cli.quiet && walks_disk {
+            offer_ignores(&root)?;
+        }
+        let options = print_options(cli)?;
+        let mut out = output(cli)?;
+        let started = Instant::now();
+        if let Some(list) = &cli.paths_from {
+            print_paths(list, &root, &options, &mut out)?;
+        } else if let Some(rev) = &cli.git_rev {
+            let tree = tree::git_tree(&root, rev, options.sort)?;
+            tree::render_node(&tree, &mut out, &options)?;
+        } else if !cli.more_paths.is_empty() {
+            let roots: Vec<&std::path::Path> = std::iter::once(root.as_path())
+                .chain(cli.more_paths.iter().map(PathBuf::as_path))
+                .collect();
+            tree::render_roots(&roots, &mut out, &options)?;
+        } else if cli.count_only {
+            let locale = options.numeric_locale.unwrap_or_default();
+            writeln!(out, "{}", totals(&tree::count(&root, &options)?, locale))?;
+        } else if cli.shape_stats {
+            print_shape(&ShapeStats::of(&tree::scan(&root, &options)?), &mut out)?;
+        } else if let Some(recording) = &cli.record {
+            record(&root, recording, &options, &mut out)?;
+        } else if let Some(budget) = prompt_budget(cli) {
+            fit(&root, budget, &options, &mut out)?;
+        } else if cli.watch {
+            watch(cli, &root, &options, &mut out)?;
+        } else {
+            tree::render(&root, &mut out, &options)?;
+        }
+        if let Some(metrics) = &options.metrics {
+            if cli.timing {
+                eprintln!("tree: {}", metrics.summary(started.elapsed()));
+            } else {
+                if metrics.stat_timeouts() > 0 {
+                    eprintln!(
+                        "tree: {} metadata lookups timed out",
+                        metrics.stat_timeouts()
+                    );
+                }
+                if metrics.retries() > 0 {
+                    eprintln!("tree: {} transient errors retried", metrics.retries());
+                }
+            }
+        }
+
+        if let Some(archive) = &cli.export {
+            tree::export(&root, &options, archive)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the tree of `root`, then print it again whenever changes have
+/// settled per `--debounce`, logging them per `--log-changes`, until
+/// Ctrl-C.
+fn watch(
+    cli: &Cli,
+    root: &std::path::Path,
+    options: &TreeOptions,
+    out: &mut MultiWriter,
+) -> Result<()> {
+    let mut log = match &cli.log_changes {
+        Some(path) => Some(
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("opening {}", path.display()))?,
+        ),
+        None => None,
+    };
+    let mut watcher = tree::Watcher::new(root, options)?;
+    if !cli.watch_include.is_empty() {
+        watcher = watcher.include(&cli.watch_include)?;
+    }
+    let mut debouncer = Debouncer::new(Duration::from_millis(cli.debounce), cli.batch);
+    let stream = options.format == OutputFormat::Ndjson;
+    let clear = !stream && std::io::stdout().is_terminal();
+    let cache = Arc::new(DecorationCache::new());
+    let options = &TreeOptions {
+        decoration_cache: Some(Arc::clone(&cache)),
+        ..options.clone()
+    };
+    if !stream {
+        render_watched(root, out, options, &cache, cli.timing)?;
+    }
+    let cancel = options.cancel.clone().unwrap_or_default();
+    loop {
+        let wake = Instant::now() + Duration::from_millis(cli.watch_interval);
+        while Instant::now() < wake {
+            if cancel.is_cancelled() {
+                return Ok(());
+            }
+            std::thread::sleep(
+                Duration::from_millis(50).min(wake.saturating_duration_since(Instant::now())),
+            );
+        }
+        let events = match debouncer.drive(&mut watcher, Instant::now()) {
+            Err(tree::TreeError::Cancelled(_)) => return Ok(()),
+            events => events?,
+        };
+        let Some(events) = events else { continue };
+        if let Some(log) = &mut log {
+            for event in &events {
+                writeln!(log, "{}", serde_json::to_string(event)?)?;
+            }
+            log.flush()?;
+        }
+        if let Some(template) = &cli.notify_cmd {
+            notify(&tree::notify_command(template, root, &events), cli.quiet);
+        }
+        if stream {
+            for event in &events {
+                writeln!(out, "{}", serde_json::to_string(event)?)?;
+            }
+            out.flush()?;
+            continue;
+        }
+        if clear {
+            write!(out, "\x1b[2J\x1b[H")?;
+        }
+        match render_watched(root, out, options, &cache, cli.timing) {
+            Err(tree::TreeError::Cancelled(_)) => return Ok(()),
+            rendered => rendered?,
+        }
+    }
+}
+
+/// Render one frame of `--watch`, then drop cache entries the frame did not
+/// use and, with `--timing`, report how many decorations were reused.
+fn render_watched(
+    root: &std::path::Path,
+    out: &mut MultiWriter,
+    options: &TreeOptions,
+    cache: &DecorationCache,
+    timing: bool,
+) -> Result<(), tree::TreeError> {
+    tree::render(root, out, options)?;
+    out.flush()?;
+    cache.sweep();
+    if timing {
+        eprintln!("tree: {}", cache.summary());
+    }
+    Ok(())
+}
+
+/// Run the expanded `--notify-cmd` through the platform shell, warning on
+/// stderr when it cannot be started or fails.
+fn notify(command: &str, quiet: bool) {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let status = std::process::Command::new(shell)
+        .args([flag, command])
+        .stdin(std::process::Stdio::null())
+        .status();
+    if quiet {
+        return;
     }
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!("tree: --notify-cmd failed ({status})"),
+        Err(err) => eprintln!("tree: --notify-cmd could not run: {err}"),
+    }
+}
 
+/// The `--budget-tokens` or `--budget-bytes` limit, if any.
+fn prompt_budget(cli: &Cli) -> Option<PromptBudget> {
+    cli.budget_tokens
+        .map(PromptBudget::Tokens)
+        .or_else(|| cli.budget_bytes.map(PromptBudget::Bytes))
+}
+
+/// Print the entries of `root` that fit in `budget`, best first by the
+/// default [`Priorities`].
+fn fit(
+    root: &std::path::Path,
+    budget: PromptBudget,
+    options: &TreeOptions,
+    out: &mut MultiWriter,
+) -> Result<()> {
+    // Scan unredacted so modification times can be read from the paths;
+    // `render_node` applies the redaction.
+    let scan_options = TreeOptions {
+        redaction: PathRedaction::default(),
+        ..options.clone()
+    };
+    let tree = tree::scan(root, &scan_options)?;
+    let priorities = Priorities::default();
+    let fitted = tree::fit_to_budget(&tree, root, budget.bytes(), |c| priorities.score(c));
+    tree::render_node(&fitted, out, options)?;
+    Ok(())
+}
+
+/// One‑line summary for `--count-only`, e.g. `3 directories, 12 files,
+/// 40960 bytes`, with numbers written per `locale`; symlinks and special
+/// files are listed when there are any.
+fn totals(stats: &TreeStats, locale: NumericLocale) -> String {
+    let mut parts = vec![
+        format!("{} directories", locale.count(stats.directories)),
+        format!("{} files", locale.count(stats.files)),
+    ];
+    if stats.symlinks > 0 {
+        parts.push(format!("{} symlinks", locale.count(stats.symlinks)));
+    }
+    if stats.special > 0 {
+        parts.push(format!("{} special files", locale.count(stats.special)));
+    }
+    parts.push(format!("{} bytes", locale.count(stats.bytes)));
+    parts.join(", ")
+}
+
+/// Print `--shape-stats` to `out`: a histogram with bars up to 40 columns
+/// wide, then the summary figures.
+fn print_shape(shape: &ShapeStats, out: &mut MultiWriter<'_>) -> std::io::Result<()> {
+    const BAR: u64 = 40;
+    let widest = shape.per_depth.iter().copied().max().unwrap_or(0);
+    writeln!(out, "depth  entries")?;
+    for (depth, &count) in shape.per_depth.iter().enumerate() {
+        let bar = (count * BAR).div_ceil(widest);
+        writeln!(
+            out,
+            "{:>5}  {count:>7}  {}",
+            depth + 1,
+            "#".repeat(usize::try_from(bar).unwrap_or(0))
+        )?;
+    }
+    writeln!(out, "max depth:       {}", shape.max_depth())?;
+    writeln!(out, "average fanout:  {:.1}", shape.average_fanout)?;
+    writeln!(out, "deepest path:    {}", shape.deepest_path)
+}
+
+/// Execute a subcommand; `cli` supplies the global flags and `out` receives
+/// the reports, as the main rendering does.
+fn run_command(command: &Command, cli: &Cli, out: &mut MultiWriter<'_>) -> Result<()> {
+    match command {
+        Command::Verify { manifest, path } => verify(path, manifest, cli.quiet),
+        Command::Doc {
+            inject,
+            marker,
+            end_marker,
+            check,
+            directories_only,
+            path,
+        } => {
+            let end = end_marker.as_deref().unwrap_or(marker);
+            let options = TreeOptions {
+                show_files: !directories_only,
+                ..base_options(cli)
+            };
+            doc(path, inject, marker, end, *check, &options, cli.quiet)
+        }
+        Command::Config { action } => config(action, cli.quiet),
+        Command::Snapshot {
+            output,
+            hashes,
+            base,
+            path,
+        } => {
+            let options = base_options(cli);
+            snapshot(path, output, *hashes, base.as_deref(), &options, cli.quiet)
+        }
+        Command::Diff {
+            base,
+            tree,
+            old,
+            new,
+        } => {
+            let options = base_options(cli);
+            diff(base.as_deref(), *tree, old, new, &options, cli.quiet, out)
+        }
+        Command::Overlay { format, paths } => {
+            let options = TreeOptions {
+                format: (*format).into(),
+                ..base_options(cli)
+            };
+            let tree = tree::overlay(paths, &options)?;
+            tree::render_node(&tree, out, &options)?;
+            Ok(())
+        }
+        #[cfg(feature = "docker")]
+        Command::Image {
+            layer,
+            format,
+            image,
+        } => {
+            let options = TreeOptions {
+                format: (*format).into(),
+                ..base_options(cli)
+            };
+            show_image(image, *layer, &options, out)
+        }
+        Command::Render {
+            format,
+            root_name,
+            recording,
+        } => {
+            let options = TreeOptions {
+                format: (*format).into(),
+                ..base_options(cli)
+            };
+            let tree = Snapshot::load(recording)
+                .with_context(|| format!("reading {}", recording.display()))?
+                .to_tree(root_name, options.path_encoding);
+            tree::render_node(&tree, out, &options)?;
+            Ok(())
+        }
+        Command::Budget {
+            max_size,
+            max_files,
+            top,
+            path,
+        } => {
+            let budget = Budget {
+                max_bytes: *max_size,
+                max_files: *max_files,
+            };
+            let options = base_options(cli);
+            check_budget(path, budget, *top, &options, out)
+        }
+        Command::CheckCollisions { format, path } => {
+            let options = base_options(cli);
+            check_collisions(path, *format, &options, cli.quiet, out)
+        }
+        Command::Audit { format, path } => {
+            let options = base_options(cli);
+            audit(path, *format, &options, cli.quiet, out)
+        }
+    }
+}
+
+/// Render the paths listed in `list` (`-` for stdin) under a root line
+/// showing `root`.
+fn print_paths(
+    list: &std::path::Path,
+    root: &std::path::Path,
+    options: &TreeOptions,
+    out: &mut MultiWriter<'_>,
+) -> Result<()> {
+    let input = if list.as_os_str() == "-" {
+        let mut input = Vec::new();
+        std::io::stdin().read_to_end(&mut input)?;
+        input
+    } else {
+        std::fs::read(list).with_context(|| format!("reading {}", list.display()))?
+    };
+    let input = String::from_utf8_lossy(&input);
+    let tree = tree::tree_from_paths(
+        &root.display().to_string(),
+        tree::parse_path_list(&input),
+        options.sort,
+    );
+    tree::render_node(&tree, out, options)?;
     Ok(())
 }
+
+/// Scan `root` once into the `--record` file, then print the recording.
+fn record(
+    root: &std::path::Path,
+    recording: &std::path::Path,
+    options: &TreeOptions,
+    out: &mut MultiWriter<'_>,
+) -> Result<()> {
+    let snapshot = Snapshot::capture(root, options, false)?;
+    snapshot
+        .save(recording)
+        .with_context(|| format!("writing {}", recording.display()))?;
+    let tree = snapshot.to_tree(&root.display().to_string(), options.path_encoding);
+    tree::render_node(&tree, out, options)?;
+    Ok(())
+}
+
+/// Run `tree image`: render layer `layer` (from 1) of `reference`, or the
+/// filesystem all its layers compose to.
+#[cfg(feature = "docker")]
+fn show_image(
+    reference: &str,
+    layer: Option<usize>,
+    options: &TreeOptions,
+    out: &mut MultiWriter<'_>,
+) -> Result<()> {
+    let path = std::path::Path::new(reference);
+    let image = if path.is_file() {
+        tree::Image::open(path).with_context(|| format!("reading {reference}"))?
+    } else {
+        tree::Image::from_docker(reference)?
+    };
+    let tree = match layer {
+        Some(n) => n
+            .checked_sub(1)
+            .and_then(|index| image.layer_tree(index, options.sort))
+            .with_context(|| {
+                format!("no layer {n}; the image has {} layers", image.layers.len())
+            })?,
+        None => image.squashed_tree(reference, options.sort),
+    };
+    tree::render_node(&tree, out, options)?;
+    Ok(())
+}
+
+/// Destination of the rendering: stdout, plus the `--tee` file if given.
+fn output(cli: &Cli) -> Result<MultiWriter<'static>> {
+    let out = MultiWriter::new().with(std::io::stdout());
+    Ok(match &cli.tee {
+        Some(path) => out.with(
+            std::fs::File::create(path).with_context(|| format!("creating {}", path.display()))?,
+        ),
+        None => out,
+    })
+}
+
+/// Decide whether to colour and, if so, load the selected theme.
+///
+/// In `auto` mode this follows [`TermCaps`]: `NO_COLOR`, `CLICOLOR`,
+/// `CLICOLOR_FORCE` and `TERM=dumb` are honoured.
+///
+/// The configuration file is only read when colour is actually enabled, so a
+/// broken config never affects plain or redirected output.
+fn resolve_theme(cli: &Cli, caps: TermCaps) -> Result<Option<Theme>> {
+    let enabled = match cli.color {
+        ColorArg::Always => true,
+        ColorArg::Never => false,
+        ColorArg::Auto => caps.color,
+    };
+    if !enabled {
+        return Ok(None);
+    }
+    let config = Config::load()?;
+    let name = cli
+        .theme
+        .as_deref()
+        .or(config.theme.as_deref())
+        .unwrap_or("default");
+    Ok(Some(config.theme(name)?))
+}
+
+/// Default options with diagnostics sent to stderr, as text or as JSON
+/// lines with `json_errors`.
+///
+/// `quiet` discards informational messages (and, in text mode, warnings).
+/// Warnings are always counted so `--strict` can fail the run. Structured
+/// output is indented per `--pretty`/`--minify`, or when stdout is a
+/// terminal, and rendered trees are buffered per `--buffer`.
+fn base_options(cli: &Cli) -> TreeOptions {
+    let (quiet, json_errors) = (cli.quiet, cli.json_errors);
+    TreeOptions {
+        cancel: Some(cancel_token()),
+        pretty: cli.pretty || (!cli.minify && std::io::stdout().is_terminal()),
+        buffering: cli.buffer.unwrap_or_default(),
+        ..TreeOptions::default()
+    }
+    .on_diagnostic(move |message| {
+        if message.is_warning() {
+            SKIPPED.fetch_add(1, Ordering::Relaxed);
+        }
+        if json_errors {
+            if quiet && !message.is_warning() {
+                return;
+            }
+            let level = if message.is_warning() {
+                "warning"
+            } else {
+                "info"
+            };
+            let mut record = serde_json::json!({
+                "level": level,
+                "code": message.code(),
+                "message": message.to_string(),
+            });
+            if let Some(path) = message.path() {
+                record["path"] = path.to_string_lossy().into();
+            }
+            eprintln!("{record}");
+        } else if !quiet {
+            eprintln!("tree: {message}");
+        }
+    })
+}
+
+/// On a first run in an interactive terminal, offer to ignore the bulky
+/// directories found under `root`.
+///
+/// Accepting writes `.tree_ignore` with just those names; declining writes
+/// an empty one so the question is not asked again. When nothing is found,
+/// or input is not interactive, the library writes its default template.
+fn offer_ignores(root: &std::path::Path) -> Result<()> {
+    if root.join(".tree_ignore").exists()
+        || !std::io::stdin().is_terminal()
+        || !std::io::stderr().is_terminal()
+    {
+        return Ok(());
+    }
+    let suggestions = tree::suggest_ignores(root);
+    if suggestions.is_empty() {
+        return Ok(());
+    }
+    let list: Vec<String> = suggestions.iter().map(ToString::to_string).collect();
+    eprint!(
+        "tree: no .tree_ignore in {}. Ignore {}? [Y/n] ",
+        root.display(),
+        list.join(", ")
+    );
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    let accepted = matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "" | "y" | "yes"
+    );
+    let names: Vec<&str> = if accepted {
+        suggestions.iter().map(|s| s.name.as_str()).collect()
+    } else {
+        Vec::new()
+    };
+    tree::write_ignore_file(root, &names)?;
+    Ok(())
+}
+
+/// Run `tree doc`, rewriting the marked block of `file` with the tree of `path`.
+fn doc(
+    path: &std::path::Path,
+    file: &std::path::Path,
+    start: &str,
+    end: &str,
+    check: bool,
+    options: &TreeOptions,
+    quiet: bool,
+) -> Result<()> {
+    let mut rendered = Vec::new();
+    tree::render(path, &mut rendered, options)?;
+
+    let current = std::fs::read_to_string(file)?;
+    let updated = tree::inject(&current, &String::from_utf8_lossy(&rendered), start, end)
+        .map_err(|e| anyhow::anyhow!("{}: {e}", file.display()))?;
+
+    if updated == current {
+        if !quiet {
+            println!("{} is up to date", file.display());
+        }
+    } else if check {
+        anyhow::bail!(
+            "{} is out of date; run `tree doc` to update it",
+            file.display()
+        );
+    } else {
+        std::fs::write(file, updated)?;
+        if !quiet {
+            println!("Updated {}", file.display());
+        }
+    }
+    Ok(())
+}
+
+/// Run `tree diff`, printing every change from `old` to `new`, or only
+/// their counts when `quiet`; three-way against `base` when given, as a
+/// merged tree with `as_tree`.
+fn diff(
+    base: Option<&std::path::Path>,
+    as_tree: bool,
+    old: &std::path::Path,
+    new: &std::path::Path,
+    options: &TreeOptions,
+    quiet: bool,
+    out: &mut MultiWriter<'_>,
+) -> Result<()> {
+    if let Some(base) = base {
+        return diff3(base, old, new, options, quiet, out);
+    }
+    if as_tree {
+        return diff_as_tree(old, new, options, out);
+    }
+    let changes = tree::diff_trees(&tree::scan(old, options)?, &tree::scan(new, options)?);
+    if quiet {
+        let count = |kind| changes.iter().filter(|c| c.kind == kind).count();
+        writeln!(
+            out,
+            "{} added, {} removed, {} modified",
+            count(ChangeKind::Added),
+            count(ChangeKind::Removed),
+            count(ChangeKind::Modified)
+        )?;
+    } else {
+        for change in &changes {
+            writeln!(out, "{change}")?;
+        }
+    }
+    DIFFERED.store(!changes.is_empty(), Ordering::Relaxed);
+    Ok(())
+}
+
+/// Run `tree diff --tree`, printing `new` merged with `old` as one tree.
+fn diff_as_tree(
+    old: &std::path::Path,
+    new: &std::path::Path,
+    options: &TreeOptions,
+    out: &mut MultiWriter<'_>,
+) -> Result<()> {
+    let (old, new) = (tree::scan(old, options)?, tree::scan(new, options)?);
+    let merged = tree::diff_tree(&old, &new, options.sort);
+    tree::render_node(&merged, out, options)?;
+    DIFFERED.store(!tree::diff_trees(&old, &new).is_empty(), Ordering::Relaxed);
+    Ok(())
+}
+
+/// Run `tree diff --base`, printing every entry `left` or `right` changed
+/// relative to the `base` snapshot, or only the counts when `quiet`.
+fn diff3(
+    base: &std::path::Path,
+    left: &std::path::Path,
+    right: &std::path::Path,
+    options: &TreeOptions,
+    quiet: bool,
+    out: &mut MultiWriter<'_>,
+) -> Result<()> {
+    let base = Snapshot::load(base)
+        .with_context(|| format!("reading {}", base.display()))?
+        .to_tree(".", options.path_encoding);
+    let changes = tree::diff_three_way(
+        &base,
+        &tree::scan(left, options)?,
+        &tree::scan(right, options)?,
+    );
+    if quiet {
+        let count = |status| changes.iter().filter(|c| c.status == status).count();
+        writeln!(
+            out,
+            "{} left, {} right, {} both, {} conflicts",
+            count(MergeStatus::Left),
+            count(MergeStatus::Right),
+            count(MergeStatus::Both),
+            count(MergeStatus::Conflict)
+        )?;
+    } else {
+        for change in &changes {
+            writeln!(out, "{change}")?;
+        }
+    }
+    DIFFERED.store(!changes.is_empty(), Ordering::Relaxed);
+    Ok(())
+}
+
+/// Run `tree verify`, printing one line per difference.
+///
+/// Differences are reported from the manifest's point of view: entries the
+/// manifest expects but the directory lacks are `missing`, unexpected ones are
+/// `extra`. Any difference turns into an error so the process exits non-zero.
+fn verify(path: &std::path::Path, manifest: &std::path::Path, quiet: bool) -> Result<()> {
+    let changes = tree::verify(path, manifest)?;
+    print_changes(&changes);
+    if !changes.is_empty() {
+        anyhow::bail!("{} difference(s) from manifest", changes.len());
+    }
+    if !quiet {
+        println!("OK: {} matches {}", path.display(), manifest.display());
+    }
+    Ok(())
+}
+
+/// Run `tree budget`: print the totals against the limits and, when a limit
+/// is exceeded, the `top` largest contributors before failing.
+fn check_budget(
+    path: &std::path::Path,
+    budget: Budget,
+    top: usize,
+    options: &TreeOptions,
+    out: &mut MultiWriter<'_>,
+) -> Result<()> {
+    let report = budget.check(&tree::scan(path, options)?);
+    let limit = |max: Option<u64>, show: fn(u64) -> String| {
+        max.map_or_else(
+            || "no limit".to_owned(),
+            |max| format!("limit {}", show(max)),
+        )
+    };
+    writeln!(
+        out,
+        "size:  {} ({})",
+        human_size(report.bytes),
+        limit(budget.max_bytes, human_size)
+    )?;
+    writeln!(
+        out,
+        "files: {} ({})",
+        report.files,
+        limit(budget.max_files, |n| n.to_string())
+    )?;
+    if report.is_within() {
+        return Ok(());
+    }
+    writeln!(out, "largest contributors:")?;
+    for entry in report.contributors.iter().take(top) {
+        writeln!(
+            out,
+            "  {:>6}  {:>8} file(s)  {}",
+            human_size(entry.bytes),
+            entry.files,
+            entry.path
+        )?;
+    }
+    let exceeded: Vec<&str> = [
+        report.over_bytes().then_some("size"),
+        report.over_files().then_some("files"),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    anyhow::bail!("over budget: {}", exceeded.join(", "))
+}
+
+/// Print `value` to `out` as one JSON document, indented when
+/// `options.pretty` is set, as the main command prints its JSON.
+fn print_json<T: serde::Serialize>(
+    value: &T,
+    options: &TreeOptions,
+    out: &mut MultiWriter<'_>,
+) -> Result<()> {
+    let json = if options.pretty {
+        serde_json::to_string_pretty(value)?
+    } else {
+        serde_json::to_string(value)?
+    };
+    writeln!(out, "{json}")?;
+    Ok(())
+}
+
+/// Run `tree check-collisions`, failing when any collision is found.
+fn check_collisions(
+    path: &std::path::Path,
+    format: ReportFormatArg,
+    options: &TreeOptions,
+    quiet: bool,
+    out: &mut MultiWriter<'_>,
+) -> Result<()> {
+    let tree = tree::scan(path, options)?;
+    let collisions = tree::find_collisions(&tree);
+    if format == ReportFormatArg::Json {
+        print_json(&collisions, options, out)?;
+    } else if let Some(pruned) = tree::collision_tree(&tree, &collisions) {
+        tree::render_node(&pruned, out, options)?;
+    } else if !quiet {
+        writeln!(out, "OK: no collisions in {}", path.display())?;
+    }
+    if !collisions.is_empty() {
+        anyhow::bail!("{} collision(s)", collisions.len());
+    }
+    Ok(())
+}
+
+/// Run `tree audit`, failing when anything is found.
+fn audit(
+    path: &std::path::Path,
+    format: ReportFormatArg,
+    options: &TreeOptions,
+    quiet: bool,
+    out: &mut MultiWriter<'_>,
+) -> Result<()> {
+    let tree = tree::scan(path, options)?;
+    let findings = tree::audit(&tree, path);
+    if format == ReportFormatArg::Json {
+        print_json(&findings, options, out)?;
+    } else if let Some(pruned) = tree::audit_tree(&tree, &findings) {
+        tree::render_node(&pruned, out, options)?;
+    } else if !quiet {
+        writeln!(out, "OK: nothing to report in {}", path.display())?;
+    }
+    if !findings.is_empty() {
+        anyhow::bail!("{} finding(s)", findings.len());
+    }
+    Ok(())
+}
+
+/// Take a snapshot of `path` into `output`, updating from `base` if given.
+///
+/// Differences from `base` are listed but do not fail the command: the
+/// snapshot is a record, not an assertion.
+fn snapshot(
+    path: &std::path::Path,
+    output: &std::path::Path,
+    hashes: bool,
+    base: Option<&std::path::Path>,
+    options: &TreeOptions,
+    quiet: bool,
+) -> Result<()> {
+    let snapshot = match base {
+        Some(base) => {
+            let previous = Snapshot::load(base)?;
+            let current = previous.update(path, options)?;
+            print_changes(&previous.diff(&current));
+            current
+        }
+        None => Snapshot::capture(path, options, hashes)?,
+    };
+    snapshot.save(output)?;
+    if !quiet {
+        println!("Wrote {} entries to {}", snapshot.len(), output.display());
+    }
+    Ok(())
+}
+
+/// Run `tree config`.
+fn config(action: &ConfigAction, quiet: bool) -> Result<()> {
+    let path = Config::path();
+    let exists = path.as_deref().is_some_and(std::path::Path::is_file);
+    match action {
+        ConfigAction::Path => {
+            let path =
+                path.ok_or_else(|| anyhow::anyhow!("no configuration location (HOME is not set)"))?;
+            println!("{}", path.display());
+            if !exists && !quiet {
+                eprintln!(
+                    "tree: {} does not exist; defaults are in use",
+                    path.display()
+                );
+            }
+        }
+        ConfigAction::Show { theme } => {
+            match (&path, exists) {
+                (Some(path), true) => println!("# file: {}", path.display()),
+                (Some(path), false) => println!("# file: {} (not found)", path.display()),
+                (None, _) => println!("# file: none"),
+            }
+            let config = Config::load()?.effective(theme.as_deref());
+            print!("{}", config.to_toml()?);
+        }
+    }
+    Ok(())
+}
+
+/// Print one line per change, labelled from the point of view of the
+/// expected (older) side.
+fn print_changes(changes: &[tree::Change]) {
+    for change in changes {
+        let label = match change.kind {
+            ChangeKind::Removed => "missing",
+            ChangeKind::Added => "extra",
+            ChangeKind::Modified => "changed",
+        };
+        match &change.detail {
+            Some(detail) => println!("{label:<8} {} ({detail})", change.path),
+            None => println!("{label:<8} {}", change.path),
+        }
+    }
+}
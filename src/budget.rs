@@ -0,0 +1,105 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Size and file‑count budgets for a scanned tree.
+//!
+//! `tree budget` guards repositories in CI: it scans with the usual ignore
+//! rules, compares the totals with the limits and, when a limit is broken,
+//! names the top‑level entries contributing most to it.
+
+use crate::scan::{EntryKind, TreeNode};
+
+/// Limits a tree must stay within; `None` leaves a dimension unchecked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Budget {
+    /// Largest allowed sum of file sizes in bytes.
+    pub max_bytes: Option<u64>,
+    /// Largest allowed number of files.
+    pub max_files: Option<u64>,
+}
+
+/// Totals of one entry directly below the root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contributor {
+    /// Path relative to the root, `/`‑separated.
+    pub path: String,
+    /// Sum of file sizes at or below the entry.
+    pub bytes: u64,
+    /// Number of files at or below the entry.
+    pub files: u64,
+}
+
+/// Outcome of [`Budget::check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetReport {
+    /// The limits checked.
+    pub budget: Budget,
+    /// Sum of all file sizes.
+    pub bytes: u64,
+    /// Number of files.
+    pub files: u64,
+    /// Entries directly below the root, largest first by the exceeded
+    /// dimension (bytes when both or neither are exceeded).
+    pub contributors: Vec<Contributor>,
+}
+
+impl BudgetReport {
+    /// Whether the size limit is exceeded.
+    #[must_use]
+    pub fn over_bytes(&self) -> bool {
+        self.budget.max_bytes.is_some_and(|max| self.bytes > max)
+    }
+
+    /// Whether the file‑count limit is exceeded.
+    #[must_use]
+    pub fn over_files(&self) -> bool {
+        self.budget.max_files.is_some_and(|max| self.files > max)
+    }
+
+    /// Whether every limit is respected.
+    #[must_use]
+    pub fn is_within(&self) -> bool {
+        !self.over_bytes() && !self.over_files()
+    }
+}
+
+impl Budget {
+    /// Compare `tree` with the limits.
+    #[must_use]
+    pub fn check(&self, tree: &TreeNode) -> BudgetReport {
+        let stats = tree.stats();
+        let mut contributors: Vec<Contributor> = tree
+            .children
+            .iter()
+            .map(|child| {
+                let (bytes, files) = if child.kind == EntryKind::Directory {
+                    let stats = child.stats();
+                    (stats.bytes, stats.files)
+                } else {
+                    (
+                        child.size.unwrap_or(0),
+                        u64::from(child.kind == EntryKind::File),
+                    )
+                };
+                Contributor {
+                    path: child.path.clone(),
+                    bytes,
+                    files,
+                }
+            })
+            .collect();
+        let mut report = BudgetReport {
+            budget: *self,
+            bytes: stats.bytes,
+            files: stats.files,
+            contributors: Vec::new(),
+        };
+        if report.over_files() && !report.over_bytes() {
+            contributors.sort_by(|a, b| b.files.cmp(&a.files).then_with(|| a.path.cmp(&b.path)));
+        } else {
+            contributors.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.path.cmp(&b.path)));
+        }
+        report.contributors = contributors;
+        report
+    }
+}
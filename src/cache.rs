@@ -0,0 +1,174 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Decorations remembered between renders of the same tree.
+//!
+//! Watch mode re-renders the whole tree after every change, yet usually
+//! only a handful of files differ. With
+//! [`TreeOptions::decoration_cache`](crate::TreeOptions::decoration_cache)
+//! set, file hashes and the notes of the
+//! [`decorate`](crate::TreeOptions::decorate) hook and annotators are
+//! stored per file under its modification time and size, and recomputed
+//! only when either changes. Directory notes are never cached: an edit deep
+//! below a directory leaves its own modification time alone.
+
+use std::{
+    collections::HashMap,
+    fs::Metadata,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::SystemTime,
+};
+
+/// Per-file decorations shared by successive renders.
+#[derive(Debug, Default)]
+pub struct DecorationCache {
+    entries: Mutex<HashMap<PathBuf, Cached>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// What is known about one file while it keeps its [`Stamp`].
+#[derive(Debug)]
+#[allow(clippy::option_option)] // outer: computed yet; inner: the value
+struct Cached {
+    stamp: Stamp,
+    digest: Option<Option<[u8; 32]>>,
+    note: Option<Option<String>>,
+    /// Looked up since the last [`DecorationCache::sweep`].
+    used: bool,
+}
+
+impl Cached {
+    /// Nothing cached yet for the version of a file with `stamp`.
+    const fn new(stamp: Stamp) -> Self {
+        Self {
+            stamp,
+            digest: None,
+            note: None,
+            used: true,
+        }
+    }
+}
+
+/// Modification time and size identifying one version of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+impl DecorationCache {
+    /// An empty cache.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decorations served from the cache so far.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Decorations computed because nothing current was cached.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Files with cached decorations.
+    pub fn len(&self) -> usize {
+        self.entries.lock().map_or(0, |entries| entries.len())
+    }
+
+    /// Whether no file has cached decorations.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget files not looked up since the previous sweep, such as deleted
+    /// ones; call it after each render.
+    pub fn sweep(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|_, cached| std::mem::take(&mut cached.used));
+        }
+    }
+
+    /// One-line summary, e.g. `decoration cache: 118 hits, 2 misses, 120 files`.
+    #[must_use]
+    pub fn summary(&self) -> String {
+        format!(
+            "decoration cache: {} hits, {} misses, {} files",
+            self.hits(),
+            self.misses(),
+            self.len()
+        )
+    }
+
+    /// SHA-256 digest of the file at `path` with metadata `meta`, from the
+    /// cache or by `compute`.
+    pub(crate) fn digest(
+        &self,
+        path: &Path,
+        meta: &Metadata,
+        compute: impl FnOnce() -> Option<[u8; 32]>,
+    ) -> Option<[u8; 32]> {
+        self.lookup(path, meta, |cached| &mut cached.digest, compute)
+    }
+
+    /// Joined decoration notes of the file at `path` with metadata `meta`,
+    /// from the cache or by `compute`.
+    pub(crate) fn note(
+        &self,
+        path: &Path,
+        meta: &Metadata,
+        compute: impl FnOnce() -> Option<String>,
+    ) -> Option<String> {
+        self.lookup(path, meta, |cached| &mut cached.note, compute)
+    }
+
+    /// The `slot` of `path`'s entry while its stamp matches `meta`,
+    /// otherwise the result of `compute`, which is stored.
+    ///
+    /// The lock is not held while computing, so concurrent renders may
+    /// occasionally compute the same value twice.
+    fn lookup<T: Clone>(
+        &self,
+        path: &Path,
+        meta: &Metadata,
+        slot: impl Fn(&mut Cached) -> &mut Option<T>,
+        compute: impl FnOnce() -> T,
+    ) -> T {
+        let Ok(modified) = meta.modified() else {
+            return compute();
+        };
+        let stamp = Stamp {
+            modified,
+            len: meta.len(),
+        };
+        let hit = self.entries.lock().ok().and_then(|mut entries| {
+            let cached = entries.get_mut(path).filter(|c| c.stamp == stamp)?;
+            cached.used = true;
+            slot(cached).clone()
+        });
+        if let Some(value) = hit {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return value;
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let value = compute();
+        if let Ok(mut entries) = self.entries.lock() {
+            let cached = entries
+                .entry(path.to_path_buf())
+                .or_insert_with(|| Cached::new(stamp));
+            if cached.stamp != stamp {
+                *cached = Cached::new(stamp);
+            }
+            cached.used = true;
+            *slot(cached) = Some(value.clone());
+        }
+        value
+    }
+}
@@ -0,0 +1,93 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Cooperative cancellation of a walk.
+//!
+//! Attach a [`CancelToken`] with
+//! [`TreeOptions::cancel`](crate::TreeOptions::cancel) and call
+//! [`CancelToken::cancel`] from another thread or a signal handler: the walk
+//! stops before the next entry or directory, the lines already written stay
+//! intact, and the call returns [`crate::TreeError::Cancelled`] saying how
+//! far it got. `tree` cancels this way on Ctrl‑C.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// Shared flag asking a walk to stop; clones observe the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<Inner>,
+}
+
+/// State shared by every clone of a token.
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    /// Entries listed so far, for the [`Cancelled`] report.
+    entries: AtomicU64,
+}
+
+impl CancelToken {
+    /// A token that has not been cancelled.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask every walk holding this token to stop.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether [`Self::cancel`] has been called.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Entries listed by walks holding this token so far.
+    #[must_use]
+    pub fn entries(&self) -> u64 {
+        self.inner.entries.load(Ordering::Relaxed)
+    }
+
+    /// Count `entries` listed entries.
+    pub(crate) fn record(&self, entries: usize) {
+        self.inner
+            .entries
+            .fetch_add(entries as u64, Ordering::Relaxed);
+    }
+
+    /// Fail with [`Cancelled`] once the token is cancelled.
+    ///
+    /// # Errors
+    /// Returns [`Cancelled`] after [`Self::cancel`].
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            return Err(Cancelled {
+                entries: self.entries(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A walk stopped by its [`CancelToken`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled {
+    /// Entries listed before the walk stopped.
+    pub entries: u64,
+}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "scan interrupted after {} entries", self.entries)
+    }
+}
+
+impl std::error::Error for Cancelled {}
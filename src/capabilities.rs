@@ -0,0 +1,46 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Runtime description of what this build of the library supports.
+
+use crate::options::OutputFormat;
+use serde::Serialize;
+
+/// Version, enabled cargo features and supported output formats of this
+/// build, for frontends that adapt their menus to the embedded library.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// Crate version, e.g. `0.2.0`.
+    pub version: &'static str,
+    /// Names of the cargo features compiled in.
+    pub features: Vec<&'static str>,
+    /// Every format [`crate::render`] can produce.
+    pub formats: Vec<OutputFormat>,
+}
+
+/// Every cargo feature of the crate and whether it is compiled in.
+const FEATURES: &[(&str, bool)] = &[
+    ("docker", cfg!(feature = "docker")),
+    ("test-consistency", cfg!(feature = "test-consistency")),
+];
+
+/// Report what this build supports.
+///
+/// ```rust
+/// let caps = tree::capabilities();
+/// assert_eq!(caps.version, env!("CARGO_PKG_VERSION"));
+/// assert!(caps.formats.contains(&tree::OutputFormat::Json));
+/// ```
+#[must_use]
+pub fn capabilities() -> Capabilities {
+    let features = FEATURES
+        .iter()
+        .filter_map(|&(name, enabled)| enabled.then_some(name))
+        .collect();
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        features,
+        formats: OutputFormat::ALL.to_vec(),
+    }
+}
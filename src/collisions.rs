@@ -0,0 +1,137 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Sibling names that collide on case‑insensitive or normalizing
+//! filesystems.
+//!
+//! `Readme.md` and `README.md`, or `café` written precomposed and
+//! decomposed, coexist happily on Linux but break checkouts on macOS and
+//! Windows, where only one of each pair can exist. Siblings are compared by
+//! their lower‑cased canonical decomposition; each group of two or more
+//! becomes a [`Collision`].
+
+use crate::{scan::TreeNode, unicode::nfd};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Why the names of a [`Collision`] clash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CollisionKind {
+    /// The names differ only in letter case.
+    Case,
+    /// The names differ only in Unicode normalization form (NFC vs NFD).
+    Normalization,
+    /// Both letter case and normalization form differ within the group.
+    CaseAndNormalization,
+}
+
+impl CollisionKind {
+    /// Short label, e.g. `case`.
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Case => "case",
+            Self::Normalization => "normalization",
+            Self::CaseAndNormalization => "case and normalization",
+        }
+    }
+}
+
+/// A group of siblings that would map to one name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Collision {
+    /// Path of the containing directory relative to the root, `/`‑separated
+    /// (empty for the root).
+    pub dir: String,
+    /// The colliding names, sorted.
+    pub names: Vec<String>,
+    /// What differs between them.
+    pub kind: CollisionKind,
+}
+
+/// Find every collision in `tree`, ordered by directory and name.
+#[must_use]
+pub fn find_collisions(tree: &TreeNode) -> Vec<Collision> {
+    let mut collisions = Vec::new();
+    collect(tree, &mut collisions);
+    collisions
+}
+
+/// Add the collisions among the children of `node` and below.
+fn collect(node: &TreeNode, collisions: &mut Vec<Collision>) {
+    let mut groups: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for child in &node.children {
+        groups
+            .entry(nfd(&child.name).to_lowercase())
+            .or_default()
+            .push(&child.name);
+    }
+    let mut found: Vec<Collision> = groups
+        .into_values()
+        .filter(|names| names.len() > 1)
+        .map(|mut names| {
+            names.sort_unstable();
+            let mut forms: Vec<_> = names.iter().map(|name| nfd(name)).collect();
+            forms.sort_unstable();
+            forms.dedup();
+            let kind = match (forms.len() > 1, forms.len() < names.len()) {
+                (true, true) => CollisionKind::CaseAndNormalization,
+                (true, false) => CollisionKind::Case,
+                (false, _) => CollisionKind::Normalization,
+            };
+            Collision {
+                dir: node.path.clone(),
+                names: names.into_iter().map(str::to_owned).collect(),
+                kind,
+            }
+        })
+        .collect();
+    found.sort_by(|a, b| a.names.cmp(&b.names));
+    collisions.extend(found);
+    for child in &node.children {
+        collect(child, collisions);
+    }
+}
+
+/// Copy of `tree` pruned to the colliding entries and their ancestors, each
+/// collider decorated with `[collides: <kind>]`; `None` without collisions.
+#[must_use]
+pub fn collision_tree(tree: &TreeNode, collisions: &[Collision]) -> Option<TreeNode> {
+    let children: Vec<TreeNode> = tree
+        .children
+        .iter()
+        .filter_map(|child| {
+            let kind = collisions
+                .iter()
+                .find(|c| c.dir == tree.path && c.names.contains(&child.name))
+                .map(|c| c.kind);
+            let pruned = collision_tree(child, collisions);
+            match (kind, pruned) {
+                (Some(kind), pruned) => Some(with_children(
+                    child,
+                    pruned.map(|p| p.children).unwrap_or_default(),
+                    Some(format!("[collides: {}]", kind.label())),
+                )),
+                (None, pruned) => pruned,
+            }
+        })
+        .collect();
+    (!children.is_empty()).then(|| with_children(tree, children, None))
+}
+
+/// `node` without its subtree, given new `children` and `decoration`.
+fn with_children(node: &TreeNode, children: Vec<TreeNode>, decoration: Option<String>) -> TreeNode {
+    TreeNode {
+        name: node.name.clone(),
+        path: node.path.clone(),
+        path_base64: node.path_base64.clone(),
+        kind: node.kind,
+        size: node.size,
+        description: node.description.clone(),
+        decoration,
+        error: node.error.clone(),
+        truncated: node.truncated,
+        children,
+    }
+}
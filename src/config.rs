@@ -0,0 +1,177 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! User configuration file.
+//!
+//! The file is TOML and entirely optional. It is looked up at `$TREE_CONFIG`
+//! when set, otherwise at `$XDG_CONFIG_HOME/tree/config.toml`,
+//! `~/.config/tree/config.toml` or, on Windows, `%APPDATA%\tree\config.toml`.
+//!
+//! ```toml
+//! theme = "ocean"
+//!
+//! [themes.ocean]
+//! base = "high-contrast"      # start from a built-in theme
+//! directory = "bold #1e90ff"
+//! executable = "green"
+//! levels = ["blue", "magenta", "cyan"]   # guide colours for --level-colors
+//! ages = ["bold red", "yellow", "", "dim"] # newest to oldest for --age-colors
+//! sizes = ["dim", "", "bold red"]          # smallest to largest for --size-colors
+//! ```
+
+use crate::{
+    theme::{Style, Theme},
+    TreeError,
+};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Environment variable overriding the configuration file location.
+pub const CONFIG_ENV: &str = "TREE_CONFIG";
+
+/// Parsed contents of the configuration file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// Name of the theme used when `--theme` is not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    /// User‑defined themes, keyed by name. They shadow built‑ins of the same name.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub themes: BTreeMap<String, ThemeSpec>,
+}
+
+/// A user theme: any role left out falls back to the `base` theme.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ThemeSpec {
+    /// Built‑in theme supplying the unspecified roles (`default` if omitted).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    /// Style for directory names.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directory: Option<Style>,
+    /// Style for regular files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<Style>,
+    /// Style for symbolic links.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symlink: Option<Style>,
+    /// Style for executable files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub executable: Option<Style>,
+    /// Style for entries shown despite matching an ignore rule.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignored: Option<Style>,
+    /// Style for highlighted entries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<Style>,
+    /// Guide‑line palette for `--level-colors`, cycled by depth.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub levels: Option<Vec<Style>>,
+    /// Name palette for `--age-colors`, newest first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ages: Option<Vec<Style>>,
+    /// File name palette for `--size-colors`, smallest first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sizes: Option<Vec<Style>>,
+}
+
+impl Config {
+    /// Location of the configuration file, whether or not it exists.
+    #[must_use]
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os(CONFIG_ENV).filter(|p| !p.is_empty()) {
+            return Some(PathBuf::from(path));
+        }
+        let base = env::var_os("XDG_CONFIG_HOME")
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|h| Path::new(&h).join(".config")))
+            .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+        Some(base.join("tree").join("config.toml"))
+    }
+
+    /// Load the configuration from [`Config::path`]; a missing file yields
+    /// the default configuration.
+    ///
+    /// # Errors
+    /// Returns [`TreeError::Other`] when the file exists but cannot be read
+    /// or is not valid configuration TOML.
+    pub fn load() -> Result<Self, TreeError> {
+        match Self::path() {
+            Some(path) if path.is_file() => Self::from_file(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Load the configuration from an explicit file.
+    ///
+    /// # Errors
+    /// Returns [`TreeError::Other`] when the file cannot be read or parsed.
+    pub fn from_file(path: &Path) -> Result<Self, TreeError> {
+        let text =
+            fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        Ok(toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))?)
+    }
+
+    /// The configuration in effect: `self` with `theme_override` (a
+    /// `--theme` flag) applied and defaults filled in.
+    #[must_use]
+    pub fn effective(mut self, theme_override: Option<&str>) -> Self {
+        let theme = theme_override
+            .map(ToOwned::to_owned)
+            .or(self.theme)
+            .unwrap_or_else(|| "default".to_owned());
+        self.theme = Some(theme);
+        self
+    }
+
+    /// Render the configuration as TOML, in the file's own syntax.
+    ///
+    /// # Errors
+    /// Returns [`TreeError::Other`] if serialisation fails.
+    pub fn to_toml(&self) -> Result<String, TreeError> {
+        Ok(toml::to_string(self).context("serialising configuration")?)
+    }
+
+    /// Resolve a theme by name: user themes first, then the built‑ins.
+    ///
+    /// # Errors
+    /// Returns [`TreeError::Other`] when no theme of that name exists or a
+    /// user theme names an unknown `base`.
+    pub fn theme(&self, name: &str) -> Result<Theme, TreeError> {
+        let Some(spec) = self.themes.get(name) else {
+            return Theme::builtin(name).ok_or_else(|| {
+                TreeError::Other(anyhow::anyhow!(
+                    "unknown theme `{name}` (built-in: {})",
+                    Theme::BUILTIN.join(", ")
+                ))
+            });
+        };
+
+        let base_name = spec.base.as_deref().unwrap_or("default");
+        let base = Theme::builtin(base_name).ok_or_else(|| {
+            TreeError::Other(anyhow::anyhow!(
+                "theme `{name}` has unknown base `{base_name}`"
+            ))
+        })?;
+        let pick = |custom: &Option<Style>, fallback: Style| custom.clone().unwrap_or(fallback);
+        Ok(Theme {
+            directory: pick(&spec.directory, base.directory),
+            file: pick(&spec.file, base.file),
+            symlink: pick(&spec.symlink, base.symlink),
+            executable: pick(&spec.executable, base.executable),
+            ignored: pick(&spec.ignored, base.ignored),
+            highlight: pick(&spec.highlight, base.highlight),
+            levels: spec.levels.clone().unwrap_or(base.levels),
+            ages: spec.ages.clone().unwrap_or(base.ages),
+            sizes: spec.sizes.clone().unwrap_or(base.sizes),
+        })
+    }
+}
@@ -0,0 +1,68 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! File content classification shared by every content‑aware feature.
+//!
+//! Anything that looks inside files — previews, line counts, hashing — asks
+//! [`is_binary`] so downstream tools classify files exactly like `tree` does.
+
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+/// Number of leading bytes inspected by the null‑byte heuristic.
+pub const SNIFF_LEN: usize = 8192;
+
+/// Extensions that are always treated as binary without reading the file.
+const BINARY_EXTENSIONS: &[&str] = &[
+    "7z", "a", "avi", "bin", "bmp", "bz2", "class", "dll", "dylib", "exe", "flac", "gif", "gz",
+    "ico", "iso", "jar", "jpeg", "jpg", "lib", "mkv", "mov", "mp3", "mp4", "o", "obj", "ogg",
+    "otf", "pdf", "png", "pyc", "rlib", "so", "sqlite", "tar", "tgz", "ttf", "wasm", "wav", "webm",
+    "webp", "woff", "woff2", "xz", "zip", "zst",
+];
+
+/// Extensions that are always treated as text without reading the file.
+const TEXT_EXTENSIONS: &[&str] = &[
+    "c", "cfg", "cpp", "css", "csv", "go", "h", "hpp", "html", "ini", "java", "js", "json", "lock",
+    "md", "markdown", "py", "rs", "sh", "svg", "toml", "ts", "txt", "xml", "yaml", "yml",
+];
+
+/// Whether the file at `path` should be treated as binary.
+///
+/// Well‑known extensions decide immediately (case‑insensitively); any other
+/// file is binary when its first [`SNIFF_LEN`] bytes contain a NUL byte.
+/// Empty files are text.
+///
+/// # Errors
+/// Returns the I/O error when the file has to be read and cannot be.
+pub fn is_binary(path: &Path) -> io::Result<bool> {
+    if let Some(known) = classify_extension(path) {
+        return Ok(known);
+    }
+    let mut sample = Vec::with_capacity(SNIFF_LEN);
+    File::open(path)?
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut sample)?;
+    Ok(looks_binary(&sample))
+}
+
+/// Null‑byte heuristic on an in‑memory sample, for callers that already
+/// hold the leading bytes of a file.
+#[must_use]
+pub fn looks_binary(sample: &[u8]) -> bool {
+    sample[..sample.len().min(SNIFF_LEN)].contains(&0)
+}
+
+/// `Some(true)` / `Some(false)` for extensions with a fixed classification.
+fn classify_extension(path: &Path) -> Option<bool> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    if BINARY_EXTENSIONS.contains(&ext.as_str()) {
+        Some(true)
+    } else if TEXT_EXTENSIONS.contains(&ext.as_str()) {
+        Some(false)
+    } else {
+        None
+    }
+}
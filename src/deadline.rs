@@ -0,0 +1,102 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Metadata lookups bounded by a deadline.
+//!
+//! A `stat` on a hung NFS or SMB mount can block for minutes, and a blocked
+//! call cannot be cancelled. With
+//! [`TreeOptions::stat_timeout`](crate::TreeOptions::stat_timeout) set, every
+//! lookup is handed to a small pool of worker threads and abandoned when no
+//! answer arrives in time: the entry gets a `timed out` error note and the
+//! walk moves on. The worker stuck in the kernel is replaced, up to
+//! [`MAX_WORKERS`] in total, so one bad path cannot starve the rest.
+
+use std::{
+    fs::{self, Metadata},
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex, OnceLock,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Workers started when the pool is first used.
+const INITIAL_WORKERS: usize = 4;
+
+/// Most workers ever started, stuck ones included.
+const MAX_WORKERS: usize = 64;
+
+/// A lookup request and where to send its answer.
+type Job = (PathBuf, Sender<io::Result<Metadata>>);
+
+/// The shared queue the workers take requests from.
+struct Pool {
+    jobs: Mutex<Sender<Job>>,
+    queue: Arc<Mutex<Receiver<Job>>>,
+    started: AtomicUsize,
+}
+
+impl Pool {
+    /// The process‑wide pool, started on first use.
+    fn get() -> &'static Self {
+        static POOL: OnceLock<Pool> = OnceLock::new();
+        POOL.get_or_init(|| {
+            let (jobs, queue) = mpsc::channel();
+            let pool = Self {
+                jobs: Mutex::new(jobs),
+                queue: Arc::new(Mutex::new(queue)),
+                started: AtomicUsize::new(0),
+            };
+            for _ in 0..INITIAL_WORKERS {
+                pool.spawn();
+            }
+            pool
+        })
+    }
+
+    /// Start one more worker unless [`MAX_WORKERS`] have been started.
+    fn spawn(&self) {
+        if self.started.fetch_add(1, Ordering::Relaxed) >= MAX_WORKERS {
+            return;
+        }
+        let queue = Arc::clone(&self.queue);
+        let spawned = thread::Builder::new()
+            .name("tree-stat".to_owned())
+            .spawn(move || loop {
+                let job = match queue.lock() {
+                    Ok(queue) => queue.recv(),
+                    Err(_) => return,
+                };
+                let Ok((path, reply)) = job else { return };
+                // The caller may have given up already; nobody to tell then.
+                let _ = reply.send(fs::symlink_metadata(&path));
+            });
+        if spawned.is_err() {
+            self.started.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// `symlink_metadata` of `path`, or an [`io::ErrorKind::TimedOut`] error
+/// when it takes longer than `timeout`.
+///
+/// # Errors
+/// Returns the lookup's own error, or the timeout.
+pub fn symlink_metadata(path: &Path, timeout: Duration) -> io::Result<Metadata> {
+    let pool = Pool::get();
+    let (reply, answer) = mpsc::channel();
+    pool.jobs
+        .lock()
+        .map_err(|_| io::Error::other("stat pool poisoned"))?
+        .send((path.to_path_buf(), reply))
+        .map_err(|_| io::Error::other("stat pool stopped"))?;
+    answer.recv_timeout(timeout).unwrap_or_else(|_| {
+        // Whichever worker holds the request may never come back.
+        pool.spawn();
+        Err(io::Error::new(io::ErrorKind::TimedOut, "stat timed out"))
+    })
+}
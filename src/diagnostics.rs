@@ -0,0 +1,96 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Informational messages emitted while rendering.
+//!
+//! The library never prints on its own: messages are handed to the sink set
+//! with [`TreeOptions::on_diagnostic`](crate::TreeOptions::on_diagnostic).
+//! The CLI writes them to stderr unless `--quiet` is given, so they can never
+//! be interleaved with redirected tree output.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// A single informational message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Diagnostic {
+    /// A default `.tree_ignore` file was created at this path because the
+    /// root did not have one.
+    CreatedIgnoreFile(PathBuf),
+    /// A `.tree_ignore` file listing only these detected bulky directories
+    /// was created at this path
+    /// ([`TreeOptions::suggest_ignores`](crate::TreeOptions::suggest_ignores)).
+    SuggestedIgnoreFile(PathBuf, Vec<String>),
+    /// A directory, or a file being hashed, could not be read (fully); the
+    /// output continues without it. Carries the path and a short reason
+    /// such as `permission denied`.
+    Unreadable(PathBuf, String),
+    /// Reading this path failed with a transient error, given as a short
+    /// reason, and is being tried again; the number is the retry about to
+    /// be made, from 1 ([`TreeOptions::retry`](crate::TreeOptions::retry)).
+    Retried(PathBuf, String, u32),
+}
+
+impl Diagnostic {
+    /// Stable, machine‑readable identifier of the message kind.
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::CreatedIgnoreFile(_) => "created-ignore-file",
+            Self::SuggestedIgnoreFile(..) => "suggested-ignore-file",
+            Self::Unreadable(..) => "unreadable",
+            Self::Retried(..) => "retried",
+        }
+    }
+
+    /// Whether the message reports missing output rather than a notice.
+    #[must_use]
+    pub const fn is_warning(&self) -> bool {
+        matches!(self, Self::Unreadable(..))
+    }
+
+    /// The file or directory the message is about.
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::CreatedIgnoreFile(path)
+            | Self::SuggestedIgnoreFile(path, _)
+            | Self::Unreadable(path, _)
+            | Self::Retried(path, ..) => Some(path),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CreatedIgnoreFile(path) => {
+                write!(f, "created default ignore file {}", path.display())
+            }
+            Self::SuggestedIgnoreFile(path, patterns) if patterns.is_empty() => {
+                write!(f, "created empty ignore file {}", path.display())
+            }
+            Self::Unreadable(path, reason) => {
+                write!(f, "cannot read {}: {reason}", path.display())
+            }
+            Self::Retried(path, reason, attempt) => {
+                write!(
+                    f,
+                    "retrying {} after {reason} (retry {attempt})",
+                    path.display()
+                )
+            }
+            Self::SuggestedIgnoreFile(path, patterns) => {
+                write!(
+                    f,
+                    "created ignore file {} with {}",
+                    path.display(),
+                    patterns.join(", ")
+                )
+            }
+        }
+    }
+}
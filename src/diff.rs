@@ -0,0 +1,373 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Structural comparison of two scanned trees.
+//!
+//! Both trees are flattened into a path‑keyed map so the comparison is a
+//! single ordered merge; the resulting changes are sorted by path, which keeps
+//! reports stable across runs and platforms.
+//!
+//! [`diff_three_way`] compares two copies of a tree against their common
+//! base, telling one‑sided changes apart from entries both copies changed —
+//! identically or in conflicting ways — as sync tools need.
+//!
+//! [`diff_tree`] shows the comparison as one merged tree instead. Every
+//! subtree gets a digest of the names, types and sizes below it, so a
+//! directory that is the same on both sides collapses into a single
+//! `(identical, N entries)` line however large it is.
+
+use crate::{
+    options::SortOptions,
+    paths::sort_nodes,
+    scan::{EntryKind, TreeNode},
+    tree_printer::group_thousands,
+};
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet, HashMap},
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+/// How an entry differs between the expected and the actual tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Present in the actual tree only.
+    Added,
+    /// Present in the expected tree only.
+    Removed,
+    /// Present in both, but with a different type or size.
+    Modified,
+}
+
+/// A single difference between two trees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// Kind of difference.
+    pub kind: ChangeKind,
+    /// Path relative to the tree roots, `/`‑separated.
+    pub path: String,
+    /// Human‑readable description for [`ChangeKind::Modified`] entries.
+    pub detail: Option<String>,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<8} {}", change_name(self.kind), self.path)?;
+        if let Some(detail) = &self.detail {
+            write!(f, " ({detail})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare `expected` against `actual` and return every difference by path.
+///
+/// Root nodes are not compared with each other, only their descendants, so
+/// the same layout scanned from two different locations compares equal.
+#[must_use]
+pub fn diff_trees(expected: &TreeNode, actual: &TreeNode) -> Vec<Change> {
+    let old = flatten(expected);
+    let new = flatten(actual);
+    let mut changes = Vec::new();
+
+    for (key, before) in &old {
+        let path = &before.path;
+        match new.get(key) {
+            None => changes.push(Change {
+                kind: ChangeKind::Removed,
+                path: path.clone(),
+                detail: None,
+            }),
+            Some(after) => {
+                if let Some(detail) = modification(before, after) {
+                    changes.push(Change {
+                        kind: ChangeKind::Modified,
+                        path: path.clone(),
+                        detail: Some(detail),
+                    });
+                }
+            }
+        }
+    }
+    for (_, after) in new.iter().filter(|(k, _)| !old.contains_key(*k)) {
+        changes.push(Change {
+            kind: ChangeKind::Added,
+            path: after.path.clone(),
+            detail: None,
+        });
+    }
+
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    changes
+}
+
+/// `actual` merged with the entries only `expected` has, as one tree.
+///
+/// Changed entries are decorated `[added]`, `[removed]` or `[modified:
+/// size 1 -> 2]`, with added and removed directories keeping their whole
+/// subtree. Directories whose subtrees are the same on both sides have no
+/// children and read `(identical, N entries)`; other unchanged entries are
+/// shown plainly. Siblings are ordered per `sort`.
+#[must_use]
+pub fn diff_tree(expected: &TreeNode, actual: &TreeNode, sort: SortOptions) -> TreeNode {
+    let (mut old, mut new) = (HashMap::new(), HashMap::new());
+    digest(expected, &mut old);
+    digest(actual, &mut new);
+    let sides = Sides { old, new, sort };
+    with_children(
+        actual,
+        sides.merge(&expected.children, &actual.children),
+        None,
+    )
+}
+
+/// Subtree digest and number of entries below a node.
+type Digest = (u64, usize);
+
+/// Digests of both trees of a [`diff_tree`], by path.
+struct Sides<'a> {
+    old: HashMap<&'a str, Digest>,
+    new: HashMap<&'a str, Digest>,
+    sort: SortOptions,
+}
+
+impl Sides<'_> {
+    /// Merged `old` and `new` children of a directory present on both sides.
+    fn merge(&self, old: &[TreeNode], new: &[TreeNode]) -> Vec<TreeNode> {
+        let before: HashMap<&str, &TreeNode> = old.iter().map(|n| (key(n), n)).collect();
+        let mut nodes: Vec<TreeNode> = new
+            .iter()
+            .map(|after| {
+                before.get(key(after)).map_or_else(
+                    || marked(after, "[added]".to_owned()),
+                    |before| self.compare(before, after),
+                )
+            })
+            .collect();
+        let after: BTreeSet<&str> = new.iter().map(key).collect();
+        nodes.extend(
+            old.iter()
+                .filter(|n| !after.contains(key(n)))
+                .map(|n| marked(n, "[removed]".to_owned())),
+        );
+        sort_nodes(&mut nodes, self.sort);
+        nodes
+    }
+
+    /// Merged node of an entry present on both sides.
+    fn compare(&self, before: &TreeNode, after: &TreeNode) -> TreeNode {
+        if let Some(detail) = modification(before, after) {
+            return marked(after, format!("[modified: {detail}]"));
+        }
+        if after.kind != EntryKind::Directory {
+            return after.clone();
+        }
+        let digest = self.new.get(key(after));
+        if digest.is_some() && digest == self.old.get(key(before)) {
+            let entries = digest.map_or(0, |&(_, entries)| entries);
+            let note = format!("(identical, {} entries)", group_thousands(entries));
+            return with_children(after, Vec::new(), Some(note));
+        }
+        with_children(after, self.merge(&before.children, &after.children), None)
+    }
+}
+
+/// Digest of `node`'s subtree, recording it and those of every descendant
+/// in `out` by path.
+fn digest<'a>(node: &'a TreeNode, out: &mut HashMap<&'a str, Digest>) -> Digest {
+    let mut hasher = DefaultHasher::new();
+    let mut entries = 0;
+    for child in &node.children {
+        key(child).hash(&mut hasher);
+        kind_name(child.kind).hash(&mut hasher);
+        child.size.hash(&mut hasher);
+        let (hash, below) = digest(child, out);
+        hash.hash(&mut hasher);
+        entries += below + 1;
+    }
+    let digest = (hasher.finish(), entries);
+    out.insert(key(node), digest);
+    digest
+}
+
+/// Copy of `node` and its subtree with `decoration` on `node`.
+fn marked(node: &TreeNode, decoration: String) -> TreeNode {
+    TreeNode {
+        decoration: Some(decoration),
+        ..node.clone()
+    }
+}
+
+/// `node` without its subtree, given new `children` and `decoration`.
+fn with_children(node: &TreeNode, children: Vec<TreeNode>, decoration: Option<String>) -> TreeNode {
+    TreeNode {
+        name: node.name.clone(),
+        path: node.path.clone(),
+        path_base64: node.path_base64.clone(),
+        kind: node.kind,
+        size: node.size,
+        description: node.description.clone(),
+        decoration,
+        error: node.error.clone(),
+        truncated: None,
+        children,
+    }
+}
+
+/// How `after` differs from `before` in type or size, if it does.
+fn modification(before: &TreeNode, after: &TreeNode) -> Option<String> {
+    if before.kind != after.kind {
+        Some(format!(
+            "type {} -> {}",
+            kind_name(before.kind),
+            kind_name(after.kind)
+        ))
+    } else if before.size != after.size {
+        Some(format!(
+            "size {} -> {}",
+            before.size.unwrap_or(0),
+            after.size.unwrap_or(0)
+        ))
+    } else {
+        None
+    }
+}
+
+/// Key of `node` among its siblings and in digests: its lossless path.
+fn key(node: &TreeNode) -> &str {
+    node.path_base64.as_deref().unwrap_or(&node.path)
+}
+
+/// Which copies changed an entry in a three‑way comparison.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStatus {
+    /// Only the left copy differs from the base.
+    Left,
+    /// Only the right copy differs from the base.
+    Right,
+    /// Both copies made the same change.
+    Both,
+    /// Both copies changed the entry, differently.
+    Conflict,
+}
+
+/// An entry that differs from the base in at least one copy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeChange {
+    /// Path relative to the tree roots, `/`‑separated.
+    pub path: String,
+    /// Which copies changed the entry.
+    pub status: MergeStatus,
+    /// How the left copy differs from the base, if it does.
+    pub left: Option<ChangeKind>,
+    /// How the right copy differs from the base, if it does.
+    pub right: Option<ChangeKind>,
+}
+
+impl fmt::Display for MergeChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.status {
+            MergeStatus::Left => "left",
+            MergeStatus::Right => "right",
+            MergeStatus::Both => "both",
+            MergeStatus::Conflict => "conflict",
+        };
+        write!(f, "{label:<8} {}", self.path)?;
+        match (self.left, self.right) {
+            (Some(left), Some(right)) if self.status == MergeStatus::Conflict => write!(
+                f,
+                " (left {}, right {})",
+                change_name(left),
+                change_name(right)
+            ),
+            (Some(kind), _) | (None, Some(kind)) => write!(f, " ({})", change_name(kind)),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Compare the `left` and `right` copies of `base` and return every entry
+/// either of them changed, by path.
+///
+/// Entries are compared by type and size like in [`diff_trees`]; both
+/// copies made the same change when the entry ends up with the same type
+/// and size in each, or is gone from both.
+#[must_use]
+pub fn diff_three_way(base: &TreeNode, left: &TreeNode, right: &TreeNode) -> Vec<MergeChange> {
+    let (base, left, right) = (flatten(base), flatten(left), flatten(right));
+    let keys: BTreeSet<&str> = base
+        .keys()
+        .chain(left.keys())
+        .chain(right.keys())
+        .copied()
+        .collect();
+    let state = |node: Option<&&TreeNode>| node.map(|n| (n.kind, n.size));
+
+    let mut changes: Vec<MergeChange> = keys
+        .into_iter()
+        .filter_map(|key| {
+            let (in_base, in_left, in_right) = (base.get(key), left.get(key), right.get(key));
+            let (before, after_left, after_right) =
+                (state(in_base), state(in_left), state(in_right));
+            let change = |after| match (before, after) {
+                (None, Some(_)) => Some(ChangeKind::Added),
+                (Some(_), None) => Some(ChangeKind::Removed),
+                (Some(old), Some(new)) if old != new => Some(ChangeKind::Modified),
+                _ => None,
+            };
+            let (left_change, right_change) = (change(after_left), change(after_right));
+            let status = match (left_change, right_change) {
+                (None, None) => return None,
+                (Some(_), None) => MergeStatus::Left,
+                (None, Some(_)) => MergeStatus::Right,
+                _ if after_left == after_right => MergeStatus::Both,
+                _ => MergeStatus::Conflict,
+            };
+            let node = in_left.or(in_right).or(in_base)?;
+            Some(MergeChange {
+                path: node.path.clone(),
+                status,
+                left: left_change,
+                right: right_change,
+            })
+        })
+        .collect();
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    changes
+}
+
+/// Lower‑case name of a change in reports.
+const fn change_name(kind: ChangeKind) -> &'static str {
+    match kind {
+        ChangeKind::Added => "added",
+        ChangeKind::Removed => "removed",
+        ChangeKind::Modified => "modified",
+    }
+}
+
+/// Index every descendant of `root` by its relative path.
+///
+/// The lossless `path_base64` is preferred as key when present so distinct
+/// non‑UTF‑8 names never collapse onto the same lossy path.
+fn flatten(root: &TreeNode) -> BTreeMap<&str, &TreeNode> {
+    let mut map = BTreeMap::new();
+    let mut stack: Vec<&TreeNode> = root.children.iter().collect();
+    while let Some(node) = stack.pop() {
+        map.insert(key(node), node);
+        stack.extend(node.children.iter());
+    }
+    map
+}
+
+/// Lower‑case name used for entry kinds in change details.
+const fn kind_name(kind: EntryKind) -> &'static str {
+    match kind {
+        EntryKind::Directory => "directory",
+        EntryKind::File => "file",
+        EntryKind::Symlink => "symlink",
+        EntryKind::Fifo => "fifo",
+        EntryKind::Socket => "socket",
+        EntryKind::BlockDevice => "block device",
+        EntryKind::CharDevice => "char device",
+    }
+}
@@ -0,0 +1,33 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Injection of a rendered tree into a documentation file between markers.
+
+use anyhow::{bail, Result};
+
+/// Replace everything between `start` and `end` in `document` with a fenced
+/// `text` code block containing `tree`.
+///
+/// `end` is searched for after `start`, so the same marker may be used for
+/// both ends. Text outside the markers, including the markers themselves, is
+/// preserved byte for byte.
+///
+/// # Errors
+/// Returns an error when either marker is missing.
+pub fn inject_block(document: &str, tree: &str, start: &str, end: &str) -> Result<String> {
+    let Some(start_at) = document.find(start) else {
+        bail!("start marker `{start}` not found");
+    };
+    let body_at = start_at + start.len();
+    let Some(end_offset) = document[body_at..].find(end) else {
+        bail!("end marker `{end}` not found after `{start}`");
+    };
+    let end_at = body_at + end_offset;
+
+    let newline = if tree.ends_with('\n') { "" } else { "\n" };
+    Ok(format!(
+        "{}\n```text\n{tree}{newline}```\n{}",
+        &document[..body_at],
+        &document[end_at..]
+    ))
+}
@@ -0,0 +1,248 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Disk usage totals in the manner of `du`.
+//!
+//! Sizes are allocated bytes (`st_blocks × 512` on Unix), so sparse files
+//! count only what they occupy, and each hard‑linked inode is counted once
+//! per walk. Like `du`, totals cover everything on disk below a directory —
+//! ignore rules decide what is listed, not what is counted — and symbolic
+//! links are not followed.
+//!
+//! A directory reached again under another path, as with bind mounts, is
+//! recognised by its `(device, inode)` and counted only where it is met
+//! first, so totals stay trustworthy for capacity planning;
+//! [`DiskUsage::without_dedupe`] counts every occurrence.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+};
+
+/// Memoising disk usage calculator; reuse one value per walk so hard links
+/// are deduplicated across the whole tree.
+#[derive(Debug, Default)]
+pub struct DiskUsage {
+    /// `(device, inode)` of every multiply‑linked file already counted.
+    seen: HashSet<(u64, u64)>,
+    /// `(device, inode)` of every directory already counted, unless
+    /// deduplication is off.
+    seen_dirs: Option<HashSet<(u64, u64)>>,
+    /// Totals of every directory walked so far.
+    totals: HashMap<PathBuf, u64>,
+}
+
+impl DiskUsage {
+    /// A calculator with nothing counted yet.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            seen_dirs: Some(HashSet::new()),
+            ..Self::default()
+        }
+    }
+
+    /// A calculator that counts a directory every time it is reached under
+    /// a new path, bind mounts included; hard links are still counted once.
+    #[must_use]
+    pub fn without_dedupe() -> Self {
+        Self::default()
+    }
+
+    /// Allocated bytes of `path` and, for a directory, everything below it.
+    ///
+    /// Unreadable entries count as zero. The directories being summed are
+    /// kept on the heap, so deep trees do not exhaust the stack.
+    pub fn total(&mut self, path: &Path) -> u64 {
+        // Directories being summed, innermost last.
+        let mut open: Vec<Summing> = Vec::new();
+        let mut next = Some(path.to_path_buf());
+        loop {
+            let total = if let Some(path) = next.take() {
+                match self.enter(path) {
+                    ControlFlow::Break(total) => total,
+                    ControlFlow::Continue(dir) => {
+                        open.push(dir);
+                        continue;
+                    }
+                }
+            } else {
+                let Some(top) = open.last_mut() else {
+                    return 0;
+                };
+                if let Some(entry) = top.entries.next() {
+                    next = Some(entry);
+                    continue;
+                }
+                let Some(done) = open.pop() else {
+                    return 0;
+                };
+                self.totals.insert(done.path, done.total);
+                done.total
+            };
+            match open.last_mut() {
+                Some(parent) => parent.total += total,
+                None => return total,
+            }
+        }
+    }
+
+    /// Start on `path`: its total when it needs no listing (already known,
+    /// unreadable, not a directory or a directory counted before), or the
+    /// directory to sum.
+    fn enter(&mut self, path: PathBuf) -> ControlFlow<u64, Summing> {
+        if let Some(&total) = self.totals.get(&path) {
+            return ControlFlow::Break(total);
+        }
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            return ControlFlow::Break(0);
+        };
+        if !metadata.is_dir() {
+            return ControlFlow::Break(self.file(&metadata));
+        }
+        if let (Some(seen), Some(id)) = (&mut self.seen_dirs, inode_id(&metadata)) {
+            if !seen.insert(id) {
+                self.totals.insert(path, 0);
+                return ControlFlow::Break(0);
+            }
+        }
+        let entries: Vec<_> = fs::read_dir(&path)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .collect();
+        ControlFlow::Continue(Summing {
+            path,
+            total: allocated(&metadata),
+            entries: entries.into_iter(),
+        })
+    }
+
+    /// Allocated bytes of `path` itself, without deduplication.
+    #[must_use]
+    pub fn own(path: &Path) -> u64 {
+        fs::symlink_metadata(path).map_or(0, |m| allocated(&m))
+    }
+
+    /// Allocated bytes of a non‑directory, or zero for a hard link already
+    /// counted.
+    fn file(&mut self, metadata: &fs::Metadata) -> u64 {
+        match link_id(metadata) {
+            Some(id) if !self.seen.insert(id) => 0,
+            _ => allocated(metadata),
+        }
+    }
+}
+
+/// A directory being summed by [`DiskUsage::total`].
+#[derive(Debug)]
+struct Summing {
+    path: PathBuf,
+    /// Its own allocated bytes plus those of the entries summed so far.
+    total: u64,
+    /// Entries still to visit.
+    entries: std::vec::IntoIter<PathBuf>,
+}
+
+/// Bytes allocated on disk for one file.
+#[cfg(unix)]
+fn allocated(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
+
+/// Block counts are not portable; fall back to the apparent size.
+#[cfg(not(unix))]
+fn allocated(metadata: &fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+/// Identity of a file with more than one hard link.
+fn link_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    inode_id(metadata).filter(|_| link_count(metadata) > 1)
+}
+
+/// Number of hard links to an entry.
+#[cfg(unix)]
+fn link_count(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink()
+}
+
+/// Hard links cannot be counted portably.
+#[cfg(not(unix))]
+const fn link_count(_metadata: &fs::Metadata) -> u64 {
+    1
+}
+
+/// `(device, inode)` identity of an entry.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_wraps)] // `None` on other platforms
+pub fn inode_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+/// Entries cannot be identified portably.
+#[cfg(not(unix))]
+pub const fn inode_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// `du -h` style size: bytes below 1 KiB as is, otherwise rounded up to
+/// one decimal below ten units (`4.0K`) and to whole units above (`12M`).
+#[must_use]
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [char; 6] = ['K', 'M', 'G', 'T', 'P', 'E'];
+    if bytes < 1024 {
+        return bytes.to_string();
+    }
+    let mut unit = 0;
+    let mut scale = 1024u64;
+    while unit + 1 < UNITS.len() && bytes >= scale * 1024 {
+        unit += 1;
+        scale *= 1024;
+    }
+    // Tenths of a unit, rounded up.
+    let tenths = (u128::from(bytes) * 10).div_ceil(u128::from(scale));
+    if tenths < 100 {
+        format!("{}.{}{}", tenths / 10, tenths % 10, UNITS[unit])
+    } else {
+        format!(
+            "{}{}",
+            u128::from(bytes).div_ceil(u128::from(scale)),
+            UNITS[unit]
+        )
+    }
+}
+
+/// Parse a size such as `500M`, `1.5G`, `4096` or `10KiB` into bytes.
+///
+/// Units are binary like [`human_size`] (`K` = 1024); a trailing `B` or
+/// `iB` and letter case are ignored. Returns `None` for anything else.
+#[must_use]
+pub fn parse_size(text: &str) -> Option<u64> {
+    const UNITS: [char; 7] = ['B', 'K', 'M', 'G', 'T', 'P', 'E'];
+    let text = text.trim().to_ascii_uppercase();
+    let text = text
+        .strip_suffix("IB")
+        .or_else(|| text.strip_suffix('B'))
+        .unwrap_or(&text);
+    let (number, power) = match text.char_indices().last()? {
+        (at, unit) if unit.is_ascii_alphabetic() => {
+            (&text[..at], UNITS.iter().position(|&u| u == unit)?)
+        }
+        _ => (text, 0),
+    };
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+        return None;
+    }
+    let scale = 1024f64.powi(i32::try_from(power).ok()?);
+    let bytes = number.parse::<f64>().ok()? * scale;
+    // Fractions of a byte round up; the range check keeps the cast exact.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    (bytes < 18_446_744_073_709_551_616.0).then(|| bytes.ceil() as u64)
+}
@@ -0,0 +1,55 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Whitespace policy shared by the structured serializers.
+//!
+//! Serializers write tokens and ask the [`Emitter`] for the whitespace
+//! between them, so one switch ([`TreeOptions::pretty`]) decides between
+//! indented and minified output everywhere.
+//!
+//! [`TreeOptions::pretty`]: crate::TreeOptions::pretty
+
+use std::io::{self, Write};
+
+/// Writer wrapper that lays out structured output pretty or minified.
+///
+/// Pretty output matches `serde_json`'s pretty printer: two‑space indents
+/// and `": "` between keys and values. Minified output has no whitespace.
+pub struct Emitter<'w, W> {
+    writer: &'w mut W,
+    pretty: bool,
+}
+
+impl<'w, W: Write> Emitter<'w, W> {
+    /// Wrap `writer`.
+    pub fn new(writer: &'w mut W, pretty: bool) -> Self {
+        Self { writer, pretty }
+    }
+
+    /// Whether the output is indented.
+    pub const fn pretty(&self) -> bool {
+        self.pretty
+    }
+
+    /// Write a token verbatim.
+    pub fn token(&mut self, token: &str) -> io::Result<()> {
+        self.writer.write_all(token.as_bytes())
+    }
+
+    /// Start a new line at indent `level` (pretty only).
+    pub fn line(&mut self, level: usize) -> io::Result<()> {
+        if self.pretty {
+            self.writer.write_all(b"\n")?;
+            for _ in 0..level {
+                self.writer.write_all(b"  ")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write an object key and the separator before its value.
+    pub fn key(&mut self, key: &str) -> io::Result<()> {
+        let separator = if self.pretty { ": " } else { ":" };
+        write!(self.writer, "\"{key}\"{separator}")
+    }
+}
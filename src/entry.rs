@@ -0,0 +1,64 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Borrowed view of a directory entry handed to user hooks.
+
+use crate::{platform::file_type::special_kind, scan::EntryKind};
+use ignore::DirEntry;
+use std::{ffi::OsStr, fs, io, path::Path};
+
+/// Information about one entry, passed to the [`crate::TreeOptions::filter`]
+/// and [`crate::TreeOptions::decorate`] hooks.
+///
+/// The view borrows from the traversal, so building it costs nothing; call
+/// [`EntryInfo::metadata`] when a hook needs ownership, permissions or times.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct EntryInfo<'a> {
+    /// Full path of the entry (the root joined with its relative path).
+    pub path: &'a Path,
+    /// File name of the entry.
+    pub name: &'a OsStr,
+    /// Kind of entry.
+    pub kind: EntryKind,
+    /// Depth below the root; immediate children of the root have depth 1.
+    pub depth: usize,
+}
+
+impl EntryInfo<'_> {
+    /// Read the entry's metadata without following symbolic links.
+    ///
+    /// # Errors
+    /// Returns the underlying I/O error when the entry cannot be stat'ed.
+    pub fn metadata(&self) -> io::Result<fs::Metadata> {
+        fs::symlink_metadata(self.path)
+    }
+}
+
+/// Classify a walker entry; symlinks resolving to directories count as
+/// directories, matching how the printer descends into them.
+///
+/// The type recorded by the directory listing (`d_type`) is used where the
+/// platform provides it, so only symlinks and entries of unknown type cost a
+/// `stat` (see [`needs_stat`]). Nothing is ever `open`ed, so FIFOs and
+/// devices cannot block the walk.
+pub fn entry_kind(entry: &DirEntry) -> EntryKind {
+    match entry.file_type() {
+        Some(t) if t.is_dir() => EntryKind::Directory,
+        Some(t) if t.is_symlink() => {
+            if entry.path().is_dir() {
+                EntryKind::Directory
+            } else {
+                EntryKind::Symlink
+            }
+        }
+        Some(t) => special_kind(t).unwrap_or(EntryKind::File),
+        None if entry.path().is_dir() => EntryKind::Directory,
+        None => EntryKind::File,
+    }
+}
+
+/// Whether [`entry_kind`] has to `stat` `entry` to classify it.
+pub fn needs_stat(entry: &DirEntry) -> bool {
+    entry.file_type().map_or(true, |t| t.is_symlink())
+}
@@ -0,0 +1,70 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Export of every structured output into a single `.tar.gz` archive.
+//!
+//! The archive bundles `tree.txt` (the text rendering), `tree.json` (the
+//! scanned [`TreeNode`]) and `stats.csv` (aggregate counters) so a directory
+//! manifest can be attached to a bug report or compliance ticket in one step.
+
+use crate::scan::TreeNode;
+use anyhow::{Context, Result};
+use flate2::{write::GzEncoder, Compression};
+use std::{
+    fs::File,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Write `tree.txt`, `tree.json` and `stats.csv` into the archive at `dest`,
+/// with `tree.json` indented when `pretty` is set.
+///
+/// # Errors
+/// Returns an error when the archive cannot be created or written.
+pub fn write_export_archive(dest: &Path, text: &[u8], tree: &TreeNode, pretty: bool) -> Result<()> {
+    let file = File::create(dest).with_context(|| format!("creating {}", dest.display()))?;
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+    let json = if pretty {
+        serde_json::to_vec_pretty(tree)
+    } else {
+        serde_json::to_vec(tree)
+    }
+    .context("serialising tree.json")?;
+    let csv = stats_csv(tree);
+
+    append(&mut archive, "tree.txt", text)?;
+    append(&mut archive, "tree.json", &json)?;
+    append(&mut archive, "stats.csv", csv.as_bytes())?;
+
+    archive
+        .into_inner()
+        .and_then(GzEncoder::finish)
+        .with_context(|| format!("finishing {}", dest.display()))?;
+    Ok(())
+}
+
+/// Render the aggregate statistics of `tree` as a two‑column CSV table.
+pub fn stats_csv(tree: &TreeNode) -> String {
+    let stats = tree.stats();
+    format!(
+        "metric,value\ndirectories,{}\nfiles,{}\nsymlinks,{}\nspecial,{}\nbytes,{}\nerrors,{}\n",
+        stats.directories, stats.files, stats.symlinks, stats.special, stats.bytes, stats.errors
+    )
+}
+
+/// Append an in‑memory file to the archive.
+fn append<W: std::io::Write>(archive: &mut tar::Builder<W>, name: &str, data: &[u8]) -> Result<()> {
+    let mtime = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    header.set_cksum();
+    archive
+        .append_data(&mut header, name, data)
+        .with_context(|| format!("adding {name} to archive"))
+}
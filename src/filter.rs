@@ -0,0 +1,691 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Entry filters combined into one boolean expression.
+//!
+//! A [`FilterExpr`] joins [`FilterTest`]s on size, age, name, extension and
+//! type with `&` ([`FilterExpr::All`]), `|` ([`FilterExpr::Any`]) and `!`
+//! ([`FilterExpr::Not`]), e.g. `(size>1048576 & ext=rs) | mtime<7d`, and is
+//! evaluated in one place together with the owner and access filters.
+//! The same syntax parses back with [`str::parse`], which is how `--filter`
+//! reads its argument.
+
+use crate::{du::parse_size, heat::parse_age, options::TreeOptions, scan::EntryKind};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    cell::OnceCell,
+    cmp::Ordering,
+    fmt,
+    fs::{self, Metadata},
+    path::Path,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+/// Comparison of an entry's value with a bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cmp {
+    /// `<`
+    Less,
+    /// `<=`
+    LessOrEqual,
+    /// `=`
+    Equal,
+    /// `>=`
+    GreaterOrEqual,
+    /// `>`
+    Greater,
+}
+
+impl Cmp {
+    /// Whether `value` compares with `bound` as required.
+    #[must_use]
+    pub fn holds<T: Ord>(self, value: &T, bound: &T) -> bool {
+        let ordering = value.cmp(bound);
+        match self {
+            Self::Less => ordering == Ordering::Less,
+            Self::LessOrEqual => ordering != Ordering::Greater,
+            Self::Equal => ordering == Ordering::Equal,
+            Self::GreaterOrEqual => ordering != Ordering::Less,
+            Self::Greater => ordering == Ordering::Greater,
+        }
+    }
+
+    /// The operator as written in expressions.
+    #[must_use]
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Self::Less => "<",
+            Self::LessOrEqual => "<=",
+            Self::Equal => "=",
+            Self::GreaterOrEqual => ">=",
+            Self::Greater => ">",
+        }
+    }
+}
+
+/// One condition on a non-directory entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterTest {
+    /// Size in bytes, as reported for the entry itself (a symlink's own
+    /// length, not its target's).
+    Size(Cmp, u64),
+    /// Time since the last modification; `mtime<7d` holds for entries
+    /// changed within the last week. Future times count as age zero.
+    Age(Cmp, Duration),
+    /// File name matching a glob where `*` stands for any run of
+    /// characters and `?` for one character.
+    Name(String),
+    /// Extension after the last dot, compared ignoring ASCII case.
+    Ext(String),
+    /// Kind of entry.
+    Type(EntryKind),
+}
+
+/// Boolean combination of [`FilterTest`]s, set as
+/// [`TreeOptions::filter_expr`](crate::TreeOptions::filter_expr).
+///
+/// How it combines with everything else that hides entries:
+///
+/// - Ignore rules run first; an ignored entry is never tested.
+/// - The expression, [`TreeOptions::owner`](crate::TreeOptions::owner) and
+///   [`TreeOptions::access`](crate::TreeOptions::access) must all accept an
+///   entry, and share a single metadata lookup. The
+///   [`TreeOptions::filter`](crate::TreeOptions::filter) hook runs last.
+/// - Directories are never tested: they are always shown and descended
+///   into, so matching files keep their place in the tree. A directory with
+///   no matching entries is shown empty rather than pruned.
+/// - A test whose metadata cannot be read is false, so the entry is hidden;
+///   under `!` it becomes true.
+/// - An empty `All` is true and an empty `Any` is false.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterExpr {
+    /// A single condition.
+    Test(FilterTest),
+    /// Every operand holds.
+    All(Vec<Self>),
+    /// At least one operand holds.
+    Any(Vec<Self>),
+    /// The operand does not hold.
+    Not(Box<Self>),
+}
+
+impl From<FilterTest> for FilterExpr {
+    fn from(test: FilterTest) -> Self {
+        Self::Test(test)
+    }
+}
+
+impl FilterExpr {
+    /// `self & other`, flattening nested [`Self::All`]s.
+    #[must_use]
+    pub fn and(self, other: impl Into<Self>) -> Self {
+        match (self, other.into()) {
+            (Self::All(mut left), Self::All(right)) => {
+                left.extend(right);
+                Self::All(left)
+            }
+            (Self::All(mut left), right) => {
+                left.push(right);
+                Self::All(left)
+            }
+            (left, right) => Self::All(vec![left, right]),
+        }
+    }
+
+    /// `self | other`, flattening nested [`Self::Any`]s.
+    #[must_use]
+    pub fn or(self, other: impl Into<Self>) -> Self {
+        match (self, other.into()) {
+            (Self::Any(mut left), Self::Any(right)) => {
+                left.extend(right);
+                Self::Any(left)
+            }
+            (Self::Any(mut left), right) => {
+                left.push(right);
+                Self::Any(left)
+            }
+            (left, right) => Self::Any(vec![left, right]),
+        }
+    }
+
+    /// `!self`.
+    #[must_use]
+    pub fn negate(self) -> Self {
+        match self {
+            Self::Not(inner) => *inner,
+            other => Self::Not(Box::new(other)),
+        }
+    }
+
+    /// Whether the entry of `kind` at `path` passes; directories always do.
+    #[must_use]
+    pub fn matches(&self, path: &Path, kind: EntryKind) -> bool {
+        kind == EntryKind::Directory || self.eval(&Candidate::new(path, kind, None))
+    }
+
+    /// Evaluate against `entry`, fetching its metadata at most once.
+    pub(crate) fn eval(&self, entry: &Candidate<'_>) -> bool {
+        match self {
+            Self::Test(test) => test.eval(entry),
+            Self::All(operands) => operands.iter().all(|expr| expr.eval(entry)),
+            Self::Any(operands) => operands.iter().any(|expr| expr.eval(entry)),
+            Self::Not(operand) => !operand.eval(entry),
+        }
+    }
+}
+
+impl FilterTest {
+    /// Whether `entry` satisfies the test.
+    fn eval(&self, entry: &Candidate<'_>) -> bool {
+        match self {
+            Self::Size(cmp, bound) => entry
+                .metadata()
+                .is_some_and(|meta| cmp.holds(&meta.len(), bound)),
+            Self::Age(cmp, bound) => entry
+                .metadata()
+                .and_then(|meta| meta.modified().ok())
+                .is_some_and(|modified| {
+                    let age = SystemTime::now()
+                        .duration_since(modified)
+                        .unwrap_or_default();
+                    cmp.holds(&age, bound)
+                }),
+            Self::Name(glob) => entry
+                .path
+                .file_name()
+                .is_some_and(|name| wildcard_match(glob, &name.to_string_lossy())),
+            Self::Ext(ext) => entry
+                .path
+                .extension()
+                .is_some_and(|found| found.to_string_lossy().eq_ignore_ascii_case(ext)),
+            Self::Type(kind) => entry.kind == *kind,
+        }
+    }
+}
+
+/// An entry being filtered, with its metadata read on first use.
+pub struct Candidate<'a> {
+    path: &'a Path,
+    kind: EntryKind,
+    /// Counts the metadata lookup in the scan's statistics and pacing.
+    options: Option<&'a TreeOptions>,
+    metadata: OnceCell<Option<Metadata>>,
+}
+
+impl<'a> Candidate<'a> {
+    /// The entry of `kind` at `path`, looked up through `options` if given.
+    pub(crate) const fn new(
+        path: &'a Path,
+        kind: EntryKind,
+        options: Option<&'a TreeOptions>,
+    ) -> Self {
+        Self {
+            path,
+            kind,
+            options,
+            metadata: OnceCell::new(),
+        }
+    }
+
+    /// Metadata of the entry itself (symlinks are not followed), or `None`
+    /// when it cannot be read.
+    pub(crate) fn metadata(&self) -> Option<&Metadata> {
+        self.metadata
+            .get_or_init(|| {
+                if let Some(options) = self.options {
+                    options.record_stats(1);
+                }
+                fs::symlink_metadata(self.path).ok()
+            })
+            .as_ref()
+    }
+}
+
+/// Whether `name` matches `glob`, where `*` matches any run of characters
+/// and `?` exactly one.
+fn wildcard_match(glob: &str, name: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut g, mut n) = (0, 0);
+    // Position after the last `*` and the name position it is matched up to.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match glob.get(g) {
+            Some('*') => {
+                star = Some((g + 1, n));
+                g += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                g += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((after, matched)) => {
+                    g = after;
+                    n = matched + 1;
+                    star = Some((after, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+impl fmt::Display for Cmp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+impl fmt::Display for FilterTest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Size(cmp, bytes) => write!(f, "size{cmp}{bytes}"),
+            Self::Age(cmp, age) => write!(f, "mtime{cmp}{}", Age(*age)),
+            Self::Name(glob) => write!(f, "name={}", Quoted(glob)),
+            Self::Ext(ext) => write!(f, "ext={}", Quoted(ext)),
+            Self::Type(kind) => write!(f, "type={}", type_name(*kind)),
+        }
+    }
+}
+
+impl fmt::Display for FilterExpr {
+    /// Write the expression so that it reads back the same, with
+    /// parentheses only where precedence (`!` over `&` over `|`) needs them.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |f: &mut fmt::Formatter<'_>, operands: &[Self], op: &str, empty: &str| {
+            if operands.is_empty() {
+                return f.write_str(empty);
+            }
+            for (i, operand) in operands.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(op)?;
+                }
+                // `|` binds looser than `&`, so it needs parentheses inside.
+                if op == " & " && matches!(operand, Self::Any(ops) if ops.len() > 1) {
+                    write!(f, "({operand})")?;
+                } else {
+                    write!(f, "{operand}")?;
+                }
+            }
+            Ok(())
+        };
+        match self {
+            Self::Test(test) => write!(f, "{test}"),
+            Self::All(operands) => join(f, operands, " & ", "true"),
+            Self::Any(operands) => join(f, operands, " | ", "false"),
+            Self::Not(operand) => match **operand {
+                Self::Test(_) | Self::Not(_) => write!(f, "!{operand}"),
+                _ => write!(f, "!({operand})"),
+            },
+        }
+    }
+}
+
+/// Error returned when a filter expression cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterParseError {
+    /// 1-based character position of the problem.
+    column: usize,
+    message: String,
+}
+
+impl fmt::Display for FilterParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid filter at column {}: {}",
+            self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for FilterParseError {}
+
+impl FromStr for FilterExpr {
+    type Err = FilterParseError;
+
+    /// Parse tests such as `size>1M`, `mtime<7d`, `name=*.rs`, `ext=md` or
+    /// `type=link` joined by `!`, `&` and `|` (binding in that order) and
+    /// grouped with parentheses. Values with spaces or operator characters
+    /// go in double quotes; `field!=value` is short for `!field=value`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            next: 0,
+            end: s.chars().count() + 1,
+            depth: 0,
+        };
+        let expr = parser.any()?;
+        match parser.tokens.get(parser.next) {
+            None => Ok(expr),
+            Some((_, column)) => Err(error_at(*column, "unexpected input after the expression")),
+        }
+    }
+}
+
+impl Serialize for FilterExpr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for FilterExpr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Lexical unit of a filter expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Cmp(Cmp),
+    /// `!=`
+    NotEqual,
+    /// A bare word or a quoted string.
+    Word(String),
+}
+
+/// Split `input` into tokens, each with its 1-based column.
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, FilterParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while let Some(&c) = chars.get(i) {
+        let at = i + 1;
+        i += 1;
+        let followed_by_eq = chars.get(i) == Some(&'=');
+        let token = match c {
+            _ if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '&' => Token::And,
+            '|' => Token::Or,
+            '!' | '<' | '>' if followed_by_eq => {
+                i += 1;
+                match c {
+                    '!' => Token::NotEqual,
+                    '<' => Token::Cmp(Cmp::LessOrEqual),
+                    _ => Token::Cmp(Cmp::GreaterOrEqual),
+                }
+            }
+            '!' => Token::Not,
+            '<' => Token::Cmp(Cmp::Less),
+            '>' => Token::Cmp(Cmp::Greater),
+            '=' => Token::Cmp(Cmp::Equal),
+            '"' => {
+                let mut word = String::new();
+                loop {
+                    let next = chars.get(i).copied().ok_or_else(|| unclosed(at))?;
+                    i += 1;
+                    match next {
+                        '"' => break,
+                        '\\' => {
+                            word.push(chars.get(i).copied().ok_or_else(|| unclosed(at))?);
+                            i += 1;
+                        }
+                        other => word.push(other),
+                    }
+                }
+                Token::Word(word)
+            }
+            _ => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.get(i) {
+                    if c.is_whitespace() || "()&|!<>=\"".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    i += 1;
+                }
+                Token::Word(word)
+            }
+        };
+        tokens.push((token, at));
+    }
+    Ok(tokens)
+}
+
+/// An error at `column` with `message`.
+fn error_at(column: usize, message: &str) -> FilterParseError {
+    FilterParseError {
+        column,
+        message: message.to_owned(),
+    }
+}
+
+/// Error for a quote opened at `column` and never closed.
+fn unclosed(column: usize) -> FilterParseError {
+    error_at(column, "unclosed quote")
+}
+
+/// Recursive-descent parser over the tokens of one expression.
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    next: usize,
+    /// Column just past the input, for errors at its end.
+    end: usize,
+    /// Groups and negations open around the current token.
+    depth: usize,
+}
+
+/// Deepest nesting of parentheses and `!` accepted, well within the stack
+/// of the recursive parser.
+const MAX_DEPTH: usize = 256;
+
+impl Parser {
+    /// `all ('|' all)*`
+    fn any(&mut self) -> Result<FilterExpr, FilterParseError> {
+        let mut expr = self.all()?;
+        while self.eat(&Token::Or) {
+            expr = expr.or(self.all()?);
+        }
+        Ok(expr)
+    }
+
+    /// `unary ('&' unary)*`
+    fn all(&mut self) -> Result<FilterExpr, FilterParseError> {
+        let mut expr = self.unary()?;
+        while self.eat(&Token::And) {
+            expr = expr.and(self.unary()?);
+        }
+        Ok(expr)
+    }
+
+    /// `'!' unary | '(' any ')' | 'true' | 'false' | test`
+    fn unary(&mut self) -> Result<FilterExpr, FilterParseError> {
+        let (token, column) = self.take("expected a test such as size>1M")?;
+        match token {
+            Token::Not | Token::Open if self.depth == MAX_DEPTH => Err(error_at(
+                column,
+                &format!("nested more than {MAX_DEPTH} levels deep"),
+            )),
+            Token::Not => {
+                self.depth += 1;
+                let inner = self.unary();
+                self.depth -= 1;
+                Ok(inner?.negate())
+            }
+            Token::Open => {
+                self.depth += 1;
+                let inner = self.any();
+                self.depth -= 1;
+                let inner = inner?;
+                match self.take("expected `)`")? {
+                    (Token::Close, _) => Ok(inner),
+                    (_, column) => Err(error_at(column, "expected `)`")),
+                }
+            }
+            Token::Word(word) if word == "true" => Ok(FilterExpr::All(Vec::new())),
+            Token::Word(word) if word == "false" => Ok(FilterExpr::Any(Vec::new())),
+            Token::Word(field) => self.test(&field, column),
+            _ => Err(error_at(column, "expected a test such as size>1M")),
+        }
+    }
+
+    /// The comparison and value following `field` at `column`.
+    fn test(&mut self, field: &str, column: usize) -> Result<FilterExpr, FilterParseError> {
+        let (cmp, negated) = match self.take("expected a comparison such as < or =")? {
+            (Token::Cmp(cmp), _) => (cmp, false),
+            (Token::NotEqual, _) => (Cmp::Equal, true),
+            (_, at) => return Err(error_at(at, "expected a comparison such as < or =")),
+        };
+        let (value, at) = match self.take("expected a value")? {
+            (Token::Word(value), at) => (value, at),
+            (_, at) => return Err(error_at(at, "expected a value")),
+        };
+        let equality = |test: FilterTest| {
+            if cmp == Cmp::Equal {
+                Ok(test)
+            } else {
+                Err(error_at(
+                    column,
+                    &format!("`{field}` only supports = and !="),
+                ))
+            }
+        };
+        let test = match field {
+            "size" => FilterTest::Size(
+                cmp,
+                parse_size(&value)
+                    .ok_or_else(|| error_at(at, &format!("invalid size `{value}`")))?,
+            ),
+            "mtime" => FilterTest::Age(
+                cmp,
+                parse_age(&value).ok_or_else(|| {
+                    error_at(
+                        at,
+                        &format!("invalid age `{value}` (expected e.g. 7d: s, m, h, d, w or y)"),
+                    )
+                })?,
+            ),
+            "name" => equality(FilterTest::Name(value))?,
+            "ext" => equality(FilterTest::Ext(
+                value.strip_prefix('.').unwrap_or(&value).to_owned(),
+            ))?,
+            "type" => {
+                let kind = kind_named(&value).ok_or_else(|| {
+                    error_at(
+                        at,
+                        &format!(
+                            "unknown type `{value}` (expected file, dir, link, fifo, socket, block or char)"
+                        ),
+                    )
+                })?;
+                equality(FilterTest::Type(kind))?
+            }
+            _ => {
+                return Err(error_at(
+                    column,
+                    &format!("unknown field `{field}` (expected size, mtime, name, ext or type)"),
+                ))
+            }
+        };
+        let expr = FilterExpr::Test(test);
+        Ok(if negated { expr.negate() } else { expr })
+    }
+
+    /// Consume the next token if it is `token`.
+    fn eat(&mut self, token: &Token) -> bool {
+        let matches = self
+            .tokens
+            .get(self.next)
+            .is_some_and(|(next, _)| next == token);
+        if matches {
+            self.next += 1;
+        }
+        matches
+    }
+
+    /// The next token, or an error saying `expected` at the end of input.
+    fn take(&mut self, expected: &str) -> Result<(Token, usize), FilterParseError> {
+        let token = self
+            .tokens
+            .get(self.next)
+            .cloned()
+            .ok_or_else(|| error_at(self.end, expected))?;
+        self.next += 1;
+        Ok(token)
+    }
+}
+
+/// Kind named by a `type=` value.
+fn kind_named(name: &str) -> Option<EntryKind> {
+    [
+        EntryKind::Directory,
+        EntryKind::File,
+        EntryKind::Symlink,
+        EntryKind::Fifo,
+        EntryKind::Socket,
+        EntryKind::BlockDevice,
+        EntryKind::CharDevice,
+    ]
+    .into_iter()
+    .find(|&kind| type_name(kind) == name)
+    .or(match name {
+        "directory" => Some(EntryKind::Directory),
+        "symlink" => Some(EntryKind::Symlink),
+        _ => None,
+    })
+}
+
+/// Name of `kind` in `type=` tests.
+const fn type_name(kind: EntryKind) -> &'static str {
+    match kind {
+        EntryKind::Directory => "dir",
+        EntryKind::File => "file",
+        EntryKind::Symlink => "link",
+        EntryKind::Fifo => "fifo",
+        EntryKind::Socket => "socket",
+        EntryKind::BlockDevice => "block",
+        EntryKind::CharDevice => "char",
+    }
+}
+
+/// A duration in the largest whole unit of `s`, `m`, `h`, `d` or `w`.
+struct Age(Duration);
+
+impl fmt::Display for Age {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.0.as_secs();
+        let (count, unit) = [(7 * 86_400, 'w'), (86_400, 'd'), (3_600, 'h'), (60, 'm')]
+            .into_iter()
+            .find(|&(size, _)| secs > 0 && secs % size == 0)
+            .map_or((secs, 's'), |(size, unit)| (secs / size, unit));
+        write!(f, "{count}{unit}")
+    }
+}
+
+/// A value written bare when it is a plain word and in double quotes
+/// (with `"` and `\` escaped) when it has spaces or operator characters.
+struct Quoted<'a>(&'a str);
+
+impl fmt::Display for Quoted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plain = !self.0.is_empty()
+            && !self
+                .0
+                .chars()
+                .any(|c| c.is_whitespace() || "()&|!<>=\"\\".contains(c));
+        if plain {
+            return f.write_str(self.0);
+        }
+        f.write_str("\"")?;
+        for c in self.0.chars() {
+            if c == '"' || c == '\\' {
+                f.write_str("\\")?;
+            }
+            write!(f, "{c}")?;
+        }
+        f.write_str("\"")
+    }
+}
@@ -0,0 +1,125 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Code‑literal outputs for test fixtures.
+//!
+//! `--format rust-fixture` and `--format ts-fixture` print the scanned
+//! structure as a self‑contained source snippet — a small entry type plus a
+//! nested constant — so a real directory layout can be pasted into the unit
+//! tests of another tool. Only names and entry types are emitted; sizes and
+//! other volatile metadata would make the fixtures churn.
+
+use crate::scan::{EntryKind, TreeNode};
+use std::io::{self, Write};
+
+/// Write `tree` as a Rust `FixtureEntry` enum plus a `TREE` constant.
+///
+/// # Errors
+/// Returns an error when the writer fails.
+pub fn write_rust<W: Write>(tree: &TreeNode, writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "// Generated by `tree --format rust-fixture`.")?;
+    writeln!(writer, "#[derive(Debug, Clone, Copy, PartialEq, Eq)]")?;
+    writeln!(writer, "pub enum FixtureEntry {{")?;
+    writeln!(writer, "    Dir(&'static str, &'static [FixtureEntry]),")?;
+    writeln!(writer, "    File(&'static str),")?;
+    writeln!(writer, "    Symlink(&'static str),")?;
+    writeln!(writer, "}}")?;
+    writeln!(writer)?;
+    write!(writer, "pub const TREE: &[FixtureEntry] = ")?;
+    write_rust_children(&tree.children, 0, writer)?;
+    writeln!(writer, ";")
+}
+
+/// Write `&[ ... ]` for `children`, with the closing bracket at `level`.
+fn write_rust_children<W: Write>(
+    children: &[TreeNode],
+    level: usize,
+    writer: &mut W,
+) -> io::Result<()> {
+    if children.is_empty() {
+        return write!(writer, "&[]");
+    }
+    let indent = "    ".repeat(level);
+    writeln!(writer, "&[")?;
+    for child in children {
+        // `{:?}` of a `str` is a valid Rust string literal.
+        let name = &child.name;
+        match child.kind {
+            EntryKind::Directory => {
+                writeln!(writer, "{indent}    FixtureEntry::Dir(")?;
+                writeln!(writer, "{indent}        {name:?},")?;
+                write!(writer, "{indent}        ")?;
+                write_rust_children(&child.children, level + 2, writer)?;
+                writeln!(writer, ",")?;
+                writeln!(writer, "{indent}    ),")?;
+            }
+            // Special files have no fixture form; an empty file keeps the name.
+            EntryKind::File
+            | EntryKind::Fifo
+            | EntryKind::Socket
+            | EntryKind::BlockDevice
+            | EntryKind::CharDevice => {
+                writeln!(writer, "{indent}    FixtureEntry::File({name:?}),")?;
+            }
+            EntryKind::Symlink => writeln!(writer, "{indent}    FixtureEntry::Symlink({name:?}),")?,
+        }
+    }
+    write!(writer, "{indent}]")
+}
+
+/// Write `tree` as a TypeScript `FixtureEntry` type plus a `tree` constant.
+///
+/// # Errors
+/// Returns an error when the writer fails.
+pub fn write_typescript<W: Write>(tree: &TreeNode, writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "// Generated by `tree --format ts-fixture`.")?;
+    writeln!(writer, "export type FixtureEntry =")?;
+    writeln!(
+        writer,
+        "  | {{ type: \"directory\"; name: string; children: FixtureEntry[] }}"
+    )?;
+    writeln!(writer, "  | {{ type: \"file\"; name: string }}")?;
+    writeln!(writer, "  | {{ type: \"symlink\"; name: string }};")?;
+    writeln!(writer)?;
+    write!(writer, "export const tree: FixtureEntry[] = ")?;
+    write_ts_children(&tree.children, 0, writer)?;
+    writeln!(writer, ";")
+}
+
+/// Write `[ ... ]` for `children`, with the closing bracket at `level`.
+fn write_ts_children<W: Write>(
+    children: &[TreeNode],
+    level: usize,
+    writer: &mut W,
+) -> io::Result<()> {
+    if children.is_empty() {
+        return write!(writer, "[]");
+    }
+    let indent = "  ".repeat(level);
+    writeln!(writer, "[")?;
+    for child in children {
+        // A JSON string is a valid TypeScript string literal.
+        let name = serde_json::to_string(&child.name).map_err(io::Error::from)?;
+        match child.kind {
+            EntryKind::Directory => {
+                write!(
+                    writer,
+                    "{indent}  {{ type: \"directory\", name: {name}, children: "
+                )?;
+                write_ts_children(&child.children, level + 1, writer)?;
+                writeln!(writer, " }},")?;
+            }
+            EntryKind::File
+            | EntryKind::Fifo
+            | EntryKind::Socket
+            | EntryKind::BlockDevice
+            | EntryKind::CharDevice => {
+                writeln!(writer, "{indent}  {{ type: \"file\", name: {name} }},")?;
+            }
+            EntryKind::Symlink => {
+                writeln!(writer, "{indent}  {{ type: \"symlink\", name: {name} }},")?;
+            }
+        }
+    }
+    write!(writer, "{indent}]")
+}
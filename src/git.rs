@@ -0,0 +1,51 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Committed trees read through git plumbing.
+//!
+//! `tree --git-rev REV` shows the layout of a commit without checking it out:
+//! `git ls-tree` lists the entries and [`tree_from_paths`] lays them out, so
+//! neither the working directory nor the index is touched.
+//!
+//! [`tree_from_paths`]: crate::tree_from_paths
+
+use anyhow::{bail, Context, Result};
+use std::{path::Path, process::Command};
+
+/// List the entries of `rev` below `dir` (a directory inside a git work
+/// tree), relative to `dir`.
+///
+/// Trees and submodules are returned with a trailing `/` so empty ones
+/// still show as directories.
+///
+/// # Errors
+/// Returns an error when git cannot be run or rejects the revision.
+pub fn list_revision(dir: &Path, rev: &str) -> Result<Vec<String>> {
+    if rev.starts_with('-') {
+        bail!("invalid revision `{rev}`");
+    }
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["ls-tree", "-r", "-t", "-z", rev])
+        .output()
+        .context("running git")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("git ls-tree {rev}: {}", stderr.trim());
+    }
+    let listing = String::from_utf8_lossy(&output.stdout);
+    Ok(listing
+        .split('\0')
+        .filter_map(|record| {
+            // `<mode> SP <type> SP <object> TAB <path>`
+            let (meta, path) = record.split_once('\t')?;
+            let kind = meta.split(' ').nth(1)?;
+            Some(if kind == "blob" {
+                path.to_owned()
+            } else {
+                format!("{path}/")
+            })
+        })
+        .collect())
+}
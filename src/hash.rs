@@ -0,0 +1,342 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Content hashing helpers.
+//!
+//! Files are streamed through the hasher with `io::copy`, so hashing a large
+//! tree never holds more than one small buffer of file data in memory.
+//!
+//! Directories get a Merkle digest: the SHA‑256 of their children's kinds,
+//! raw names and digests, sorted by name. Two directories with the same
+//! digest hold the same names, types and contents all the way down, so an
+//! unchanged subtree is recognised from one value.
+
+use anyhow::{Context, Result};
+use ignore::DirEntry;
+use sha2::{Digest, Sha256};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::Write as _,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    diagnostics::Diagnostic,
+    options::TreeOptions,
+    parallel::map_ordered,
+    scan::EntryKind,
+    throttle::{Throttle, Throttled},
+    tree_printer::{collect_children, load_ignore_set, IgnoreSet},
+    TreeError,
+};
+
+/// Compute the lower‑case hexadecimal SHA‑256 digest of the file at `path`,
+/// charging the read to `throttle`.
+///
+/// # Errors
+/// Returns an error when the file cannot be opened or read.
+pub fn sha256_file(path: &Path, throttle: Option<&Throttle>) -> Result<String> {
+    Ok(to_hex(&sha256_digest(path, throttle)?))
+}
+
+/// Compute the raw SHA‑256 digest of the file at `path`, charging the open
+/// and every byte read to `throttle`.
+///
+/// # Errors
+/// Returns an error when the file cannot be opened or read.
+pub fn sha256_digest(path: &Path, throttle: Option<&Throttle>) -> Result<[u8; 32]> {
+    let file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let mut file = Throttled::new(file, throttle);
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).with_context(|| format!("reading {}", path.display()))?;
+    Ok(hasher.finalize().into())
+}
+
+/// Merkle digest of a directory from the raw names, kinds and digests of
+/// its children, in any order.
+#[must_use]
+pub fn merkle_digest(mut children: Vec<(Vec<u8>, EntryKind, [u8; 32])>) -> [u8; 32] {
+    children.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut hasher = Sha256::new();
+    for (name, kind, digest) in &children {
+        hasher.update([kind_code(*kind)]);
+        hasher.update((name.len() as u64).to_le_bytes());
+        hasher.update(name);
+        hasher.update(digest);
+    }
+    hasher.finalize().into()
+}
+
+/// Digest of a symbolic link: the SHA‑256 of its raw target.
+#[must_use]
+pub fn link_digest(path: &Path) -> Option<[u8; 32]> {
+    let target = fs::read_link(path).ok()?;
+    Some(Sha256::digest(target.as_os_str().as_encoded_bytes()).into())
+}
+
+/// Code of an entry kind in digests and snapshots.
+pub const fn kind_code(kind: EntryKind) -> u8 {
+    match kind {
+        EntryKind::Directory => 0,
+        EntryKind::File => 1,
+        EntryKind::Symlink => 2,
+        EntryKind::Fifo => 3,
+        EntryKind::Socket => 4,
+        EntryKind::BlockDevice => 5,
+        EntryKind::CharDevice => 6,
+    }
+}
+
+/// Memoising Merkle hasher of directories; reuse one value per walk so each
+/// subtree is hashed once.
+pub struct DirHashes<'a> {
+    ignore_set: &'a IgnoreSet,
+    options: &'a TreeOptions,
+    /// Digests of every directory hashed so far; `None` when something
+    /// below it could not be read.
+    digests: HashMap<PathBuf, Option<[u8; 32]>>,
+}
+
+impl<'a> DirHashes<'a> {
+    /// A hasher listing directories with the filtering of `options`.
+    pub fn new(ignore_set: &'a IgnoreSet, options: &'a TreeOptions) -> Self {
+        Self {
+            ignore_set,
+            options,
+            digests: HashMap::new(),
+        }
+    }
+
+    /// Merkle digest of `dir`, whose children are at `depth` and which was
+    /// reached through `hops` symlinked directories, over the entries a
+    /// listing shows; `None` when an entry below it cannot be read. Special
+    /// files count with an all‑zero digest.
+    ///
+    /// The directories being hashed are kept on the heap, so the depth of
+    /// the tree is bounded by [`TreeOptions::limits`] rather than the stack.
+    ///
+    /// # Errors
+    /// Returns [`crate::LimitExceeded`] when a directory below `dir` exceeds
+    /// [`TreeOptions::limits`] and [`crate::Cancelled`] when the walk is
+    /// cancelled; no digest is recorded for `dir` then.
+    pub fn digest(&mut self, dir: &Path, depth: usize, hops: usize) -> Result<Option<[u8; 32]>> {
+        if let Some(&digest) = self.digests.get(dir) {
+            return Ok(digest);
+        }
+        let mut open = vec![self.open(dir, Vec::new(), depth, hops)?];
+        while let Some(top) = open.last_mut() {
+            let Some((child, kind)) = top.entries.pop() else {
+                let Some(done) = open.pop() else { break };
+                let digest = done.children.map(merkle_digest);
+                self.digests.insert(done.path, digest);
+                if let Some(parent) = open.last_mut() {
+                    parent.add(done.name, EntryKind::Directory, digest);
+                }
+                continue;
+            };
+            let digest = match kind {
+                EntryKind::Directory => {
+                    if let Some(&digest) = self.digests.get(child.path()) {
+                        digest
+                    } else {
+                        let name = child.file_name().as_encoded_bytes().to_vec();
+                        let hops = top.hops + usize::from(child.path_is_symlink());
+                        let dir = self.open(child.path(), name, top.depth + 1, hops)?;
+                        open.push(dir);
+                        continue;
+                    }
+                }
+                EntryKind::File => self.file_digest(&child),
+                EntryKind::Symlink => link_digest(child.path()),
+                _ => Some([0; 32]),
+            };
+            top.add(child.file_name().as_encoded_bytes().to_vec(), kind, digest);
+        }
+        Ok(self.digests.get(dir).copied().flatten())
+    }
+
+    /// Check and list `dir`, named `name` in its parent, for hashing.
+    fn open(&self, dir: &Path, name: Vec<u8>, depth: usize, hops: usize) -> Result<Hashing> {
+        self.options.limits.check(dir, depth, hops)?;
+        self.options.check_cancelled()?;
+        let listing = collect_children(dir, self.ignore_set, self.options, depth);
+        let mut entries = listing.entries;
+        entries.reverse();
+        Ok(Hashing {
+            path: dir.to_path_buf(),
+            name,
+            depth,
+            hops,
+            entries,
+            children: listing.error.is_none().then(Vec::new),
+        })
+    }
+
+    /// Digest of the file `entry`, through
+    /// [`TreeOptions::decoration_cache`] when there is one.
+    fn file_digest(&self, entry: &DirEntry) -> Option<[u8; 32]> {
+        let compute = || sha256_digest(entry.path(), self.options.throttle.as_deref()).ok();
+        match (&self.options.decoration_cache, self.options.stat(entry)) {
+            (Some(cache), Ok(meta)) => cache.digest(entry.path(), &meta, compute),
+            _ => compute(),
+        }
+    }
+}
+
+/// A directory being hashed by [`DirHashes::digest`].
+struct Hashing {
+    path: PathBuf,
+    /// Raw name in the parent directory.
+    name: Vec<u8>,
+    /// Depth of the children.
+    depth: usize,
+    /// Symlinked directories followed to reach the children.
+    hops: usize,
+    /// Children still to hash, the next one last.
+    entries: Vec<(DirEntry, EntryKind)>,
+    /// Names, kinds and digests of the children hashed so far; `None` once
+    /// anything in the directory could not be read.
+    children: Option<Vec<HashedChild>>,
+}
+
+/// Raw name, kind and digest of a hashed entry.
+type HashedChild = (Vec<u8>, EntryKind, [u8; 32]);
+
+impl Hashing {
+    /// Record the digest of a child; an unreadable one spoils the directory
+    /// and leaves its remaining children unhashed.
+    fn add(&mut self, name: Vec<u8>, kind: EntryKind, digest: Option<[u8; 32]>) {
+        if let (Some(children), Some(digest)) = (&mut self.children, digest) {
+            children.push((name, kind, digest));
+        } else {
+            self.children = None;
+            self.entries.clear();
+        }
+    }
+}
+
+/// Write one `HASH  path` line per file below `root`, as `sha256sum` does.
+///
+/// Paths are relative to `root`, so the output can be checked with
+/// `sha256sum -c` from inside the root directory. Names are written as raw
+/// bytes rather than lossily converted, and names containing a backslash,
+/// newline or carriage return are escaped with the line prefixed by `\`,
+/// matching coreutils.
+/// Paths rewritten by [`TreeOptions::redaction`] are written as rewritten.
+///
+/// Files are hashed by up to [`TreeOptions::threads`] workers; lines are
+/// still written in tree order. A file that cannot be read is reported as
+/// [`Diagnostic::Unreadable`] and left out of the manifest.
+///
+/// # Errors
+/// Returns an error when a directory exceeds [`TreeOptions::limits`] or the
+/// writer fails.
+pub fn write_sha256sums<W: Write>(
+    root: &Path,
+    options: &TreeOptions,
+    writer: &mut W,
+) -> Result<()> {
+    let ignore_set = load_ignore_set(root, options)?;
+    let mut files = Vec::new();
+    collect_files(root, &[], &ignore_set, options, 1, 0, &mut files)?;
+
+    let throttle = options.throttle.as_deref();
+    let digests = map_ordered(&files, options.threads, |(path, _)| {
+        sha256_file(path, throttle)
+    });
+    for ((path, rel), digest) in files.iter().zip(digests) {
+        let digest = match digest {
+            Ok(digest) => digest,
+            Err(err) => {
+                let reason = err
+                    .root_cause()
+                    .downcast_ref::<io::Error>()
+                    .map_or_else(|| err.to_string(), |io| io.kind().to_string());
+                options.diagnose(&Diagnostic::Unreadable(path.clone(), reason));
+                continue;
+            }
+        };
+        let lossy = String::from_utf8_lossy(rel);
+        let shown = match options.redaction.apply(&lossy) {
+            Cow::Borrowed(_) => Cow::Borrowed(rel.as_slice()),
+            Cow::Owned(redacted) => Cow::Owned(redacted.into_bytes()),
+        };
+        write_sum_line(writer, &digest, &shown)?;
+    }
+    Ok(())
+}
+
+/// Append every file below `dir`, whose raw relative path is `rel`, to
+/// `files` as `(absolute path, raw relative path)` in tree order.
+/// `hops` counts the symlinked directories followed to reach `dir`.
+fn collect_files(
+    dir: &Path,
+    rel: &[u8],
+    ignore_set: &IgnoreSet,
+    options: &TreeOptions,
+    depth: usize,
+    hops: usize,
+    files: &mut Vec<(PathBuf, Vec<u8>)>,
+) -> Result<(), TreeError> {
+    for (child, kind) in collect_children(dir, ignore_set, options, depth).entries {
+        let mut child_rel = rel.to_vec();
+        if !child_rel.is_empty() {
+            child_rel.push(b'/');
+        }
+        child_rel.extend_from_slice(child.file_name().as_encoded_bytes());
+
+        if kind == EntryKind::Directory {
+            let hops = hops + usize::from(child.path_is_symlink());
+            options.limits.check(child.path(), depth + 1, hops)?;
+            options.check_cancelled()?;
+            collect_files(
+                child.path(),
+                &child_rel,
+                ignore_set,
+                options,
+                depth + 1,
+                hops,
+                files,
+            )?;
+        } else if kind == EntryKind::File {
+            files.push((child.into_path(), child_rel));
+        }
+    }
+    Ok(())
+}
+
+/// Write a single `sha256sum` line, escaping `rel` the way coreutils does.
+fn write_sum_line<W: Write>(writer: &mut W, digest: &str, rel: &[u8]) -> io::Result<()> {
+    let needs_escape = rel.iter().any(|b| matches!(b, b'\\' | b'\n' | b'\r'));
+    if needs_escape {
+        writer.write_all(b"\\")?;
+    }
+    writer.write_all(digest.as_bytes())?;
+    writer.write_all(b"  ")?;
+    if needs_escape {
+        for &byte in rel {
+            match byte {
+                b'\\' => writer.write_all(b"\\\\")?,
+                b'\n' => writer.write_all(b"\\n")?,
+                b'\r' => writer.write_all(b"\\r")?,
+                _ => writer.write_all(&[byte])?,
+            }
+        }
+    } else {
+        writer.write_all(rel)?;
+    }
+    writer.write_all(b"\n")
+}
+
+/// Encode `bytes` as lower‑case hexadecimal.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut out, b| {
+            let _ = write!(out, "{b:02x}");
+            out
+        })
+}
@@ -0,0 +1,148 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Heat‑map buckets for colouring entries by a measured value.
+//!
+//! `tree --age-colors` sorts every entry into a bucket by how long ago it
+//! was modified and paints its name with the matching style of the theme's
+//! [`Theme::ages`](crate::Theme::ages) palette, bright for recent changes
+//! and dim for old ones. Buckets are given by ascending upper bounds, so
+//! `1h,1d,1w` makes four buckets: under an hour, under a day, under a week
+//! and everything older. `--size-colors` does the same for file sizes with
+//! the [`Theme::sizes`](crate::Theme::sizes) palette, so large files stand
+//! out without reading numbers.
+
+use crate::du::parse_size;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr, time::Duration};
+
+/// Ascending bucket bounds; a value falls into the first bucket whose bound
+/// exceeds it, or into the last, unbounded bucket.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Buckets<T> {
+    bounds: Vec<T>,
+}
+
+impl<T: Ord> Buckets<T> {
+    /// Buckets below each of `bounds`, which are sorted and deduplicated.
+    #[must_use]
+    pub fn new(mut bounds: Vec<T>) -> Self {
+        bounds.sort();
+        bounds.dedup();
+        Self { bounds }
+    }
+
+    /// The upper bounds, ascending.
+    #[must_use]
+    pub fn bounds(&self) -> &[T] {
+        &self.bounds
+    }
+
+    /// Number of buckets, one more than the number of bounds.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.bounds.len() + 1
+    }
+
+    /// Always `false`: there is at least the unbounded bucket.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Index of the bucket `value` falls into, from 0.
+    #[must_use]
+    pub fn bucket(&self, value: &T) -> usize {
+        self.bounds.partition_point(|bound| bound <= value)
+    }
+}
+
+impl Default for Buckets<Duration> {
+    /// An hour, a day, a week, 30 days and a year.
+    fn default() -> Self {
+        const DAY: u64 = 24 * 60 * 60;
+        Self::new(
+            [60 * 60, DAY, 7 * DAY, 30 * DAY, 365 * DAY]
+                .map(Duration::from_secs)
+                .to_vec(),
+        )
+    }
+}
+
+impl Default for Buckets<u64> {
+    /// 64 KiB, 1 MiB, 16 MiB, 128 MiB and 1 GiB.
+    fn default() -> Self {
+        const MIB: u64 = 1024 * 1024;
+        Self::new(vec![64 * 1024, MIB, 16 * MIB, 128 * MIB, 1024 * MIB])
+    }
+}
+
+/// Error returned when a bucket list cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BucketsParseError {
+    bound: String,
+    expected: &'static str,
+}
+
+impl fmt::Display for BucketsParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid bucket `{}` (expected {})",
+            self.bound, self.expected
+        )
+    }
+}
+
+impl std::error::Error for BucketsParseError {}
+
+impl FromStr for Buckets<Duration> {
+    type Err = BucketsParseError;
+
+    /// Parse comma‑separated ages such as `1h,1d,1w,30d,1y`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_bounds(s, parse_age, "ages such as 1h,1d,30d: s, m, h, d, w or y")
+    }
+}
+
+impl FromStr for Buckets<u64> {
+    type Err = BucketsParseError;
+
+    /// Parse comma‑separated sizes such as `1M,100M,1G` (binary units).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_bounds(s, parse_size, "sizes such as 1M,100M,1G")
+    }
+}
+
+/// Buckets below the comma‑separated bounds in `s`, each read by `parse`.
+fn parse_bounds<T: Ord>(
+    s: &str,
+    parse: fn(&str) -> Option<T>,
+    expected: &'static str,
+) -> Result<Buckets<T>, BucketsParseError> {
+    s.split(',')
+        .map(|bound| {
+            parse(bound.trim()).ok_or_else(|| BucketsParseError {
+                bound: bound.to_owned(),
+                expected,
+            })
+        })
+        .collect::<Result<_, _>>()
+        .map(Buckets::new)
+}
+
+/// `90m`, `1d` or `2w` as a duration; a year counts 365 days.
+pub fn parse_age(text: &str) -> Option<Duration> {
+    let unit = text.chars().last()?;
+    let seconds = match unit.to_ascii_lowercase() {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        'y' => 365 * 24 * 60 * 60,
+        _ => return None,
+    };
+    let count: u64 = text[..text.len() - unit.len_utf8()].parse().ok()?;
+    count.checked_mul(seconds).map(Duration::from_secs)
+}
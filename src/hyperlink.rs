@@ -0,0 +1,41 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! OSC 8 terminal hyperlinks.
+//!
+//! Terminals such as iTerm2, WezTerm and kitty turn text wrapped in
+//! `ESC ] 8 ; ; URL ESC \` … `ESC ] 8 ; ; ESC \` into a clickable link.
+//! Terminals without support simply print the text.
+
+use std::{fmt::Write as _, path::Path};
+
+/// Build a `file://` URL for `path`, made absolute against the current
+/// directory and percent‑encoded. The host part is left empty, which every
+/// supporting terminal resolves to the local machine.
+pub fn file_url(path: &Path) -> String {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().map_or_else(|_| path.to_path_buf(), |cwd| cwd.join(path))
+    };
+
+    let text = absolute.to_string_lossy().replace('\\', "/");
+    let mut url = String::from("file://");
+    if !text.starts_with('/') {
+        // Windows drive paths: file:///C:/...
+        url.push('/');
+    }
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~:".contains(&byte) {
+            url.push(char::from(byte));
+        } else {
+            let _ = write!(url, "%{byte:02X}");
+        }
+    }
+    url
+}
+
+/// Wrap `text` in an OSC 8 hyperlink to `url`.
+pub fn link(url: &str, text: &str) -> String {
+    format!("\x1b]8;;{url}\x1b\\{text}\x1b]8;;\x1b\\")
+}
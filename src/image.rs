@@ -0,0 +1,241 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Filesystems of container image layers.
+//!
+//! `tree image app.tar` reads an image saved with `docker save` — or any
+//! OCI image tarball carrying a `manifest.json` — and shows the filesystem
+//! the layers compose to; `tree image --layer 2 app:latest` shows what a
+//! single layer adds. Image references that are not files are exported with
+//! `docker save`.
+//!
+//! Layers are read as a stream, keeping only their path lists, so images of
+//! any size need memory proportional to their entry count. Whiteouts are
+//! applied when squashing: `.wh.NAME` removes `NAME` from the layers below
+//! and `.wh..wh..opq` hides everything lower layers put in its directory.
+//! Single layers are listed as stored, whiteout files included.
+
+use crate::{options::SortOptions, paths::tree_from_paths, scan::TreeNode, TreeError};
+use anyhow::{anyhow, Context};
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// Prefix of whiteout entries.
+const WHITEOUT: &str = ".wh.";
+/// Name of the opaque directory marker.
+const OPAQUE: &str = ".wh..wh..opq";
+
+/// The layers of a container image, lowest first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    /// Layers in the order they are applied.
+    pub layers: Vec<ImageLayer>,
+}
+
+/// The entries one layer stores.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageLayer {
+    /// Location of the layer inside the image tarball, e.g.
+    /// `blobs/sha256/…`.
+    pub name: String,
+    /// Relative paths in archive order; directories end with `/`.
+    pub paths: Vec<String>,
+}
+
+/// The part of `manifest.json` naming the layers.
+#[derive(Deserialize)]
+struct ManifestEntry {
+    #[serde(rename = "Layers")]
+    layers: Vec<String>,
+}
+
+impl Image {
+    /// Read an image tarball as written by `docker save`.
+    ///
+    /// # Errors
+    /// Returns an error when the stream is not an image tarball or a layer
+    /// it lists cannot be read (compressed other than with gzip, say).
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, TreeError> {
+        let mut manifest = None;
+        let mut listings = HashMap::new();
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries().context("reading image tarball")? {
+            let entry = entry.context("reading image tarball")?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let name = entry_path(&entry)?;
+            if name == "manifest.json" {
+                let entries: Vec<ManifestEntry> =
+                    serde_json::from_reader(entry).context("parsing manifest.json")?;
+                manifest = Some(entries);
+            } else if let Ok(paths) = list_layer(entry) {
+                // Configs and indexes are kept too when they happen to parse
+                // as tar; only the layers the manifest names are looked up.
+                listings.insert(name, paths);
+            }
+        }
+
+        let manifest = manifest.ok_or_else(|| anyhow!("no manifest.json in image tarball"))?;
+        let [image] = <[ManifestEntry; 1]>::try_from(manifest)
+            .map_err(|m| anyhow!("image tarball holds {} images, expected one", m.len()))?;
+        let layers = image
+            .layers
+            .into_iter()
+            .map(|name| {
+                let paths = listings
+                    .get(&name)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("layer {name} is missing or not a readable tar"))?;
+                Ok(ImageLayer { name, paths })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { layers })
+    }
+
+    /// Read the image tarball at `path`.
+    ///
+    /// # Errors
+    /// Returns an error when the file cannot be opened or read as an image.
+    pub fn open(path: &Path) -> Result<Self, TreeError> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Export the local image `reference` with `docker save` and read it.
+    ///
+    /// # Errors
+    /// Returns an error when docker cannot be run or does not know the
+    /// image.
+    pub fn from_docker(reference: &str) -> Result<Self, TreeError> {
+        if reference.starts_with('-') {
+            return Err(anyhow!("invalid image reference `{reference}`").into());
+        }
+        let mut child = Command::new("docker")
+            .args(["save", reference])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("running docker")?;
+        let stdout = child.stdout.take().context("capturing docker output")?;
+        let image = Self::read_from(BufReader::new(stdout));
+        let output = child.wait_with_output().context("running docker")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("docker save {reference}: {}", stderr.trim()).into());
+        }
+        image
+    }
+
+    /// Tree of the entries layer `index` (from 0) stores, or `None` when
+    /// there is no such layer.
+    #[must_use]
+    pub fn layer_tree(&self, index: usize, sort: SortOptions) -> Option<TreeNode> {
+        let layer = self.layers.get(index)?;
+        Some(tree_from_paths(&layer.name, &layer.paths, sort))
+    }
+
+    /// Tree of the filesystem all layers compose to, named `name`.
+    #[must_use]
+    pub fn squashed_tree(&self, name: &str, sort: SortOptions) -> TreeNode {
+        // Path without trailing slash → whether it is a directory.
+        let mut entries: BTreeMap<String, bool> = BTreeMap::new();
+        for layer in &self.layers {
+            let mut added = Vec::new();
+            for path in &layer.paths {
+                let is_dir = path.ends_with('/');
+                let path = path.trim_end_matches('/');
+                let (dir, file) = path.rsplit_once('/').unwrap_or(("", path));
+                if file == OPAQUE {
+                    remove_below(&mut entries, dir);
+                } else if let Some(hidden) = file.strip_prefix(WHITEOUT) {
+                    let hidden = join(dir, hidden);
+                    remove_below(&mut entries, &hidden);
+                    entries.remove(&hidden);
+                } else {
+                    added.push((path.to_owned(), is_dir));
+                }
+            }
+            for (path, is_dir) in added {
+                if !is_dir {
+                    remove_below(&mut entries, &path);
+                }
+                entries.insert(path, is_dir);
+            }
+        }
+        let paths = entries
+            .into_iter()
+            .map(|(path, is_dir)| if is_dir { path + "/" } else { path });
+        tree_from_paths(name, paths, sort)
+    }
+}
+
+/// Paths stored in one layer, which may be gzip‑compressed.
+fn list_layer<R: Read>(reader: R) -> anyhow::Result<Vec<String>> {
+    let mut reader = BufReader::new(reader);
+    if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        list_tar(GzDecoder::new(reader))
+    } else {
+        list_tar(reader)
+    }
+}
+
+/// Paths stored in an uncompressed layer.
+fn list_tar<R: Read>(reader: R) -> anyhow::Result<Vec<String>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut paths = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let path = entry_path(&entry)?;
+        let path = path.trim_start_matches("./").trim_end_matches('/');
+        if path.is_empty() || path == "." {
+            continue;
+        }
+        if entry.header().entry_type().is_dir() {
+            paths.push(format!("{path}/"));
+        } else {
+            paths.push(path.to_owned());
+        }
+    }
+    Ok(paths)
+}
+
+/// Path of an archive entry as text.
+fn entry_path<R: Read>(entry: &tar::Entry<'_, R>) -> anyhow::Result<String> {
+    let path = entry.path().context("reading entry path")?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Remove every entry strictly below the directory `dir` (`""` for the
+/// root).
+fn remove_below(entries: &mut BTreeMap<String, bool>, dir: &str) {
+    let prefix = if dir.is_empty() {
+        String::new()
+    } else {
+        format!("{dir}/")
+    };
+    let below: Vec<String> = entries
+        .range(prefix.clone()..)
+        .map(|(path, _)| path)
+        .take_while(|path| path.starts_with(&prefix))
+        .cloned()
+        .collect();
+    for path in below {
+        entries.remove(&path);
+    }
+}
+
+/// `dir/name`, or `name` at the root.
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{dir}/{name}")
+    }
+}
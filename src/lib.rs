@@ -71,12 +71,165 @@
 // Allow unused crate dependencies since clap is used by the binary but not the library
 #![allow(unused_crate_dependencies)]
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 /// Internal implementation — **NOT** part of the public API.
 pub(crate) mod tree_printer;
 
+/// Directory descriptions from marker files.
+mod annotate;
+/// Pluggable entry annotations.
+mod annotator;
+/// `.gitattributes` export-ignore patterns.
+mod attributes;
+/// Permission and ownership audit.
+mod audit;
+/// Size and file-count budgets.
+mod budget;
+/// Decorations cached between renders.
+mod cache;
+/// Cooperative cancellation.
+mod cancel;
+/// Build introspection.
+mod capabilities;
+/// Case and normalization collisions between siblings.
+mod collisions;
+/// Configuration file loading.
+mod config;
+/// Binary/text classification of file contents.
+pub mod content;
+/// Metadata lookups bounded by a deadline.
+mod deadline;
+/// Informational messages.
+mod diagnostics;
+/// Structural comparison of scanned trees.
+mod diff;
+/// Documentation block injection.
+mod doc;
+/// `du`-style disk usage totals.
+mod du;
+/// Pretty/minified whitespace for structured output.
+mod emitter;
+/// Borrowed entry view for user hooks.
+mod entry;
+/// Archive export of the structured outputs.
+pub(crate) mod export;
+/// Boolean filter expressions over entries.
+mod filter;
+/// Rust and TypeScript fixture literals.
+mod fixture;
+/// Committed trees via git plumbing.
+mod git;
+/// Content hashing helpers.
+pub(crate) mod hash;
+/// Heat-map buckets.
+mod heat;
+/// OSC 8 terminal hyperlinks.
+mod hyperlink;
+/// Container image layer filesystems.
+#[cfg(feature = "docker")]
+mod image;
+/// Traversal depth, path length and symlink guards.
+mod limits;
+/// Scan work counters.
+mod metrics;
+/// Locale-aware number formatting.
+mod numfmt;
+/// Rendering options.
+mod options;
+/// Output buffering policies.
+mod output;
+/// Overlay composition of several roots.
+mod overlay;
+/// Filtering by owning user and group.
+mod owner;
+/// Bounded worker pool.
+mod parallel;
+/// Trees built from path lists.
+mod paths;
+/// Screen-reader friendly plain text.
+mod plain;
+/// Operating-system specific entry metadata.
+mod platform;
+/// Trees trimmed to a prompt budget.
+mod prompt;
+/// Displayed path rewriting.
+mod redact;
+/// Dependency‑free tree layout.
+pub mod render_core;
+/// Report header for the text output.
+mod report;
+/// In‑memory directory scan.
+mod scan;
+/// Depth and fan-out statistics.
+mod shape;
+/// Binary tree snapshots.
+mod snapshot;
+/// Streaming JSON serializer.
+mod stream;
+/// First-run ignore suggestions.
+mod suggest;
+/// Terminal capability detection.
+mod term_caps;
+/// Colour themes.
+mod theme;
+/// Rate‑limited I/O.
+mod throttle;
+/// Timestamp styles.
+mod timefmt;
+/// Canonical decomposition of file names.
+mod unicode;
+/// Polling watch mode.
+mod watch;
+
+pub use annotator::{Annotation, Annotator, FileSize, Unreadable};
+pub use audit::{audit, audit_tree, Finding, Issue};
+pub use budget::{Budget, BudgetReport, Contributor};
+pub use cache::DecorationCache;
+pub use cancel::{CancelToken, Cancelled};
+pub use capabilities::{capabilities, Capabilities};
+pub use collisions::{collision_tree, find_collisions, Collision, CollisionKind};
+pub use config::{Config, ThemeSpec, CONFIG_ENV};
+pub use diagnostics::Diagnostic;
+pub use diff::{
+    diff_three_way, diff_tree, diff_trees, Change, ChangeKind, MergeChange, MergeStatus,
+};
+pub use du::{human_size, parse_size, DiskUsage};
+pub use entry::EntryInfo;
+pub use filter::{Cmp, FilterExpr, FilterParseError, FilterTest};
+pub use heat::{Buckets, BucketsParseError};
+#[cfg(feature = "docker")]
+pub use image::{Image, ImageLayer};
+pub use limits::{Limit, LimitExceeded, Limits};
+pub use metrics::ScanMetrics;
+pub use numfmt::{NumericLocale, NumericLocaleParseError};
+pub use options::{
+    AccessFilter, BufPolicy, CaseSensitivity, ColumnPlacement, DecorateFn, DiagnosticFn, FilterFn,
+    Grouping, OutputFormat, PathEncoding, SortOptions, Traversal, TreeOptions,
+};
+pub use output::MultiWriter;
+pub use owner::{OwnerFilter, Principal, UnknownPrincipal};
+pub use paths::{parse_path_list, tree_from_paths};
+pub use platform::{
+    priority::idle_io,
+    volume::{MetadataProvider, MetadataSupport, MountTable},
+};
+pub use prompt::{fit_to_budget, Candidate, Priorities, PromptBudget, BYTES_PER_TOKEN};
+pub use redact::{PathRedaction, Redaction, RedactionParseError};
+pub use render_core::{display_width, neutralize_bidi, Charset};
+pub use scan::{EntryKind, TreeNode, TreeStats};
+pub use shape::ShapeStats;
+pub use snapshot::{Snapshot, SnapshotEntry};
+pub use suggest::{suggest_ignores, IgnoreSuggestion, SUGGEST_THRESHOLD};
+pub use term_caps::TermCaps;
+pub use theme::{Style, StyleParseError, Theme};
+pub use throttle::{Rate, RateParseError, Throttle};
+pub use timefmt::{format_time, TimeStyle, TimeStyleParseError};
+pub use watch::{
+    changes, coalesce, notify_command, Debouncer, EventKind, EventSource, WatchEvent, Watcher,
+};
+
 /// Comprehensive error type for all tree operations.
 ///
 /// This enum covers all possible failure modes when working with directory trees.
@@ -101,10 +254,16 @@ pub(crate) mod tree_printer;
 ///     Err(TreeError::PathMissing(path)) => eprintln!("Directory not found: {}", path),
 ///     Err(TreeError::NotADirectory(path)) => eprintln!("Not a directory: {}", path),
 ///     Err(TreeError::Io(io_err)) => eprintln!("I/O error: {}", io_err),
-///     Err(TreeError::Other(err)) => eprintln!("Other error: {}", err),
+///     Err(TreeError::LimitExceeded(limit)) => eprintln!("Walk stopped: {}", limit),
+///     Err(TreeError::Cancelled(cancelled)) => eprintln!("{}", cancelled),
+///     Err(err) => eprintln!("Error: {}", err),
 /// }
 /// ```
+///
+/// The enum is `#[non_exhaustive]`: new failure kinds may be added in minor
+/// releases, so matches need a catch-all arm.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum TreeError {
     /// The supplied path does not exist on the filesystem.
     ///
@@ -129,13 +288,85 @@ pub enum TreeError {
     #[error(transparent)]
     Io(#[from] std::io::Error),
 
+    /// The walk reached a directory beyond one of the [`TreeOptions::limits`].
+    ///
+    /// Raised instead of overflowing the stack on a symlink cycle or passing
+    /// an over‑long path to the operating system; the error names the guard
+    /// and the offending directory.
+    #[error(transparent)]
+    LimitExceeded(#[from] LimitExceeded),
+
+    /// The walk was stopped through [`TreeOptions::cancel`].
+    ///
+    /// Output written before the cancellation is complete up to the last
+    /// line; the error says how many entries had been listed.
+    #[error(transparent)]
+    Cancelled(#[from] Cancelled),
+
     /// Catch-all for other internal errors.
     ///
     /// This handles any unexpected errors from internal operations, such as
     /// file format parsing errors or other edge cases. In practice, this should
     /// be rare in normal usage.
     #[error(transparent)]
-    Other(#[from] anyhow::Error),
+    Other(anyhow::Error),
+}
+
+impl From<anyhow::Error> for TreeError {
+    /// Wrap `err` as [`TreeError::Other`], unless it already is a
+    /// [`TreeError`], a tripped traversal guard or a cancellation, which
+    /// keep their own variants.
+    fn from(err: anyhow::Error) -> Self {
+        let err = match err.downcast::<Self>() {
+            Ok(err) => return err,
+            Err(err) => err,
+        };
+        match err.downcast::<LimitExceeded>() {
+            Ok(limit) => Self::LimitExceeded(limit),
+            Err(err) => match err.downcast::<Cancelled>() {
+                Ok(cancelled) => Self::Cancelled(cancelled),
+                Err(err) => Self::Other(err),
+            },
+        }
+    }
+}
+
+impl TreeError {
+    /// Stable, machine‑readable identifier of the error kind, for wrappers
+    /// that must not parse the English message.
+    ///
+    /// I/O errors map to their [`std::io::ErrorKind`] (`permission-denied`,
+    /// `not-found`, …, or `io` for anything else). Errors wrapped in
+    /// [`TreeError::Other`] report the code of an I/O error in their cause
+    /// chain, or `other`.
+    #[must_use]
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::PathMissing(_) => "path-missing",
+            Self::NotADirectory(_) => "not-a-directory",
+            Self::Io(err) => io_error_code(err),
+            Self::LimitExceeded(_) => "limit-exceeded",
+            Self::Cancelled(_) => "cancelled",
+            Self::Other(err) => err
+                .chain()
+                .find_map(|cause| cause.downcast_ref::<std::io::Error>())
+                .map_or("other", io_error_code),
+        }
+    }
+}
+
+/// Stable code for an I/O error kind.
+fn io_error_code(err: &std::io::Error) -> &'static str {
+    use std::io::ErrorKind;
+    match err.kind() {
+        ErrorKind::NotFound => "not-found",
+        ErrorKind::PermissionDenied => "permission-denied",
+        ErrorKind::AlreadyExists => "already-exists",
+        ErrorKind::InvalidData => "invalid-data",
+        ErrorKind::UnexpectedEof => "unexpected-eof",
+        ErrorKind::BrokenPipe => "broken-pipe",
+        _ => "io",
+    }
 }
 
 /// Print a directory hierarchy to any `Write` sink.
@@ -197,7 +428,8 @@ pub enum TreeError {
 /// - Internal operations encounter unexpected errors ([`TreeError::Other`])
 pub fn print<W: std::io::Write>(root: &Path, writer: &mut W) -> Result<(), TreeError> {
     validate_root(root)?;
-    tree_printer::print_directory_tree_to_writer(root, writer, true).map_err(TreeError::Other)
+    tree_printer::print_directory_tree_to_writer(root, writer, &TreeOptions::default())
+        .map_err(TreeError::from)
 }
 
 /// Generate and print a directory tree with display options.
@@ -246,7 +478,571 @@ pub fn print_with_options<W: std::io::Write>(
     show_files: bool,
 ) -> Result<(), TreeError> {
     validate_root(root)?;
-    tree_printer::print_directory_tree_to_writer(root, writer, show_files).map_err(TreeError::Other)
+    let options = TreeOptions {
+        show_files,
+        ..TreeOptions::default()
+    };
+    tree_printer::print_directory_tree_to_writer(root, writer, &options).map_err(TreeError::from)
+}
+
+/// Render a directory tree in the format selected by `options`.
+///
+/// [`OutputFormat::Text`] streams the Unicode tree exactly like
+/// [`print_with_options`]; [`OutputFormat::Json`] writes the JSON document
+/// of the [`TreeNode`] incrementally during traversal, so huge trees never
+/// have to fit in memory, indented or minified per [`TreeOptions::pretty`];
+/// [`OutputFormat::Sha256sum`] hashes every file and writes a checksum
+/// manifest verifiable with `sha256sum -c` from inside `root`;
+/// [`OutputFormat::RustFixture`] and [`OutputFormat::TsFixture`] write the
+/// scanned structure as source code for test fixtures;
+/// [`OutputFormat::PlainIndent`] writes it as indented `folder`/`file`
+/// lines for screen readers.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::path::Path;
+/// use tree::{render, OutputFormat, TreeOptions};
+///
+/// let options = TreeOptions {
+///     format: OutputFormat::Json,
+///     ..TreeOptions::default()
+/// };
+/// render(Path::new("."), &mut std::io::stdout(), &options)?;
+/// # Ok::<(), tree::TreeError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The root path does not exist ([`TreeError::PathMissing`])
+/// - The root path is not a directory ([`TreeError::NotADirectory`])
+/// - I/O operations fail during tree generation ([`TreeError::Io`])
+/// - Internal operations encounter unexpected errors ([`TreeError::Other`])
+pub fn render<W: std::io::Write>(
+    root: &Path,
+    writer: &mut W,
+    options: &TreeOptions,
+) -> Result<(), TreeError> {
+    validate_root(root)?;
+    let mut writer = output::Buffered::new(writer, options.buffering);
+    write_root(root, &mut writer, options)?;
+    std::io::Write::flush(&mut writer)?;
+    Ok(())
+}
+
+/// Write `root` in `options.format`; the body of [`render`].
+fn write_root<W: std::io::Write>(
+    root: &Path,
+    writer: &mut W,
+    options: &TreeOptions,
+) -> Result<(), TreeError> {
+    match options.format {
+        OutputFormat::Text => {
+            if options.report_header {
+                report::write_header(root, options, writer)?;
+            }
+            tree_printer::print_directory_tree_to_writer(root, writer, options)
+                .map_err(TreeError::from)
+        }
+        OutputFormat::Json => {
+            stream::write_json(root, options, writer)?;
+            writeln!(writer)?;
+            Ok(())
+        }
+        OutputFormat::Sha256sum => Ok(hash::write_sha256sums(root, options, writer)?),
+        OutputFormat::RustFixture => {
+            let tree = scan::scan_tree(root, options)?;
+            Ok(fixture::write_rust(&tree, writer)?)
+        }
+        OutputFormat::TsFixture => {
+            let tree = scan::scan_tree(root, options)?;
+            Ok(fixture::write_typescript(&tree, writer)?)
+        }
+        OutputFormat::PlainIndent => {
+            let tree = scan::scan_tree(root, options)?;
+            Ok(plain::write_plain_indent(&tree, writer, options)?)
+        }
+        OutputFormat::Ndjson => Err(watch_only()),
+    }
+}
+
+/// Render several directories as one output.
+///
+/// Text output prints the trees one after another, separated by a blank
+/// line. JSON output is a single valid document, `{"roots": [...]}`, with
+/// one tree object per root in the order given — rather than concatenated
+/// documents that JSON tooling would reject. A single root renders exactly
+/// like [`render`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::path::Path;
+/// use tree::{render_roots, OutputFormat, TreeOptions};
+///
+/// let options = TreeOptions {
+///     format: OutputFormat::Json,
+///     ..TreeOptions::default()
+/// };
+/// render_roots(&[Path::new("src"), Path::new("tests")], &mut std::io::stdout(), &options)?;
+/// # Ok::<(), tree::TreeError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - A root does not exist ([`TreeError::PathMissing`])
+/// - A root is not a directory ([`TreeError::NotADirectory`])
+/// - Several roots are combined with a format other than text or JSON
+///   ([`TreeError::Other`])
+/// - I/O operations fail during tree generation ([`TreeError::Io`])
+pub fn render_roots<W: std::io::Write, P: AsRef<Path>>(
+    roots: &[P],
+    writer: &mut W,
+    options: &TreeOptions,
+) -> Result<(), TreeError> {
+    if let [root] = roots {
+        return render(root.as_ref(), writer, options);
+    }
+    for root in roots {
+        validate_root(root.as_ref())?;
+    }
+    let mut writer = output::Buffered::new(writer, options.buffering);
+    write_roots(roots, &mut writer, options)?;
+    std::io::Write::flush(&mut writer)?;
+    Ok(())
+}
+
+/// Write several `roots` in `options.format`; the body of
+/// [`render_roots`].
+fn write_roots<W: std::io::Write, P: AsRef<Path>>(
+    roots: &[P],
+    writer: &mut W,
+    options: &TreeOptions,
+) -> Result<(), TreeError> {
+    match options.format {
+        OutputFormat::Text | OutputFormat::PlainIndent => {
+            for (index, root) in roots.iter().enumerate() {
+                if index > 0 {
+                    writeln!(writer)?;
+                }
+                write_root(root.as_ref(), writer, options)?;
+            }
+        }
+        OutputFormat::Json => {
+            stream::write_json_roots(roots, options, writer)?;
+            writeln!(writer)?;
+        }
+        format => {
+            return Err(TreeError::Other(anyhow::anyhow!(
+                "the {} format takes a single directory",
+                format.name()
+            )))
+        }
+    }
+    Ok(())
+}
+
+/// Render an already built [`TreeNode`] in `options.format`.
+///
+/// This is the counterpart of [`render`] for trees that do not come from a
+/// directory walk, such as [`tree_from_paths`]. Only names and entry types
+/// are shown; text output honours [`TreeOptions::charset`] and JSON output
+/// [`TreeOptions::pretty`].
+///
+/// # Examples
+///
+/// ```rust
+/// use tree::{render_node, tree_from_paths, SortOptions, TreeOptions};
+///
+/// let tree = tree_from_paths(".", ["src/lib.rs", "README.md"], SortOptions::default());
+/// let mut out = Vec::new();
+/// render_node(&tree, &mut out, &TreeOptions::default())?;
+/// assert_eq!(String::from_utf8(out).unwrap(), ".\n├── src/\n│   └── lib.rs\n└── README.md\n");
+/// # Ok::<(), tree::TreeError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Writing fails ([`TreeError::Io`])
+/// - `options.format` is [`OutputFormat::Sha256sum`], which needs file
+///   contents ([`TreeError::Other`])
+pub fn render_node<W: std::io::Write>(
+    tree: &TreeNode,
+    writer: &mut W,
+    options: &TreeOptions,
+) -> Result<(), TreeError> {
+    let redacted;
+    let tree = if options.redaction.is_empty() {
+        tree
+    } else {
+        redacted = options.redaction.tree(tree);
+        &redacted
+    };
+    let mut writer = output::Buffered::new(writer, options.buffering);
+    write_node(tree, &mut writer, options)?;
+    std::io::Write::flush(&mut writer)?;
+    Ok(())
+}
+
+/// Write `tree` in `options.format`; the body of [`render_node`].
+fn write_node<W: std::io::Write>(
+    tree: &TreeNode,
+    writer: &mut W,
+    options: &TreeOptions,
+) -> Result<(), TreeError> {
+    match options.format {
+        OutputFormat::Text => Ok(paths::write_text(tree, writer, options)?),
+        OutputFormat::Json => Ok(write_json(tree, writer, options.pretty)?),
+        OutputFormat::Sha256sum => Err(TreeError::Other(anyhow::anyhow!(
+            "the sha256sum format needs a directory to read"
+        ))),
+        OutputFormat::RustFixture => Ok(fixture::write_rust(tree, writer)?),
+        OutputFormat::TsFixture => Ok(fixture::write_typescript(tree, writer)?),
+        OutputFormat::PlainIndent => Ok(plain::write_plain_indent(tree, writer, options)?),
+        OutputFormat::Ndjson => Err(watch_only()),
+    }
+}
+
+/// Error for [`OutputFormat::Ndjson`] outside a watch loop.
+fn watch_only() -> TreeError {
+    TreeError::Other(anyhow::anyhow!(
+        "the ndjson format streams watch events and needs --watch"
+    ))
+}
+
+/// Write `tree` as one JSON document followed by a newline.
+fn write_json<W: std::io::Write>(
+    tree: &TreeNode,
+    writer: &mut W,
+    pretty: bool,
+) -> std::io::Result<()> {
+    if pretty {
+        serde_json::to_writer_pretty(&mut *writer, tree)
+    } else {
+        serde_json::to_writer(&mut *writer, tree)
+    }
+    .map_err(std::io::Error::from)?;
+    writeln!(writer)
+}
+
+/// Write the JSON document of `root` with at most `max_entries` entries
+/// below the root, for embedding in pages and API responses with a bounded
+/// payload.
+///
+/// Entries are kept level by level, so the top of a huge tree survives.
+/// Directories that lost children carry a `"truncated"` member with the
+/// number left out; the rest of the document matches [`render`] with
+/// [`OutputFormat::Json`], pretty‑printed when `options.pretty` is set.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::path::Path;
+/// use tree::{render_json_limited, TreeOptions};
+///
+/// let mut body = Vec::new();
+/// render_json_limited(Path::new("."), 500, &mut body, &TreeOptions::default())?;
+/// # Ok::<(), tree::TreeError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The root path does not exist ([`TreeError::PathMissing`])
+/// - The root path is not a directory ([`TreeError::NotADirectory`])
+/// - I/O operations fail while writing ([`TreeError::Io`])
+/// - Internal operations encounter unexpected errors ([`TreeError::Other`])
+pub fn render_json_limited<W: std::io::Write>(
+    root: &Path,
+    max_entries: usize,
+    writer: &mut W,
+    options: &TreeOptions,
+) -> Result<(), TreeError> {
+    validate_root(root)?;
+    let tree = scan::scan_limited(root, max_entries, options)?;
+    let mut writer = output::Buffered::new(writer, options.buffering);
+    write_json(&tree, &mut writer, options.pretty)?;
+    std::io::Write::flush(&mut writer)?;
+    Ok(())
+}
+
+/// Scan a directory into an in‑memory [`TreeNode`].
+///
+/// The scan applies the same `.gitignore` and `.tree_ignore` filtering as
+/// [`print`], including creating a default `.tree_ignore` when none exists.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::path::Path;
+/// use tree::{scan, TreeOptions};
+///
+/// let tree = scan(Path::new("."), &TreeOptions::default())?;
+/// println!("{} files", tree.stats().files);
+/// # Ok::<(), tree::TreeError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The root path does not exist ([`TreeError::PathMissing`])
+/// - The root path is not a directory ([`TreeError::NotADirectory`])
+/// - Internal operations encounter unexpected errors ([`TreeError::Other`])
+pub fn scan(root: &Path, options: &TreeOptions) -> Result<TreeNode, TreeError> {
+    validate_root(root)?;
+    Ok(scan::scan_tree(root, options)?)
+}
+
+/// Count the directories, files and bytes below `root` without rendering.
+///
+/// The totals equal `scan(root, options)?.stats()`, but no [`TreeNode`]s
+/// are built and no names are formatted, which makes this the fast path
+/// for scripts that only need the numbers. The one difference: with
+/// [`TreeOptions::dedupe`] a directory reached under several paths — a bind
+/// mount, a symlink to a directory inside the tree — is counted once.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::path::Path;
+/// use tree::{count, TreeOptions};
+///
+/// let stats = count(Path::new("."), &TreeOptions::default())?;
+/// println!("{} directories, {} files", stats.directories, stats.files);
+/// # Ok::<(), tree::TreeError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The root path does not exist ([`TreeError::PathMissing`])
+/// - The root path is not a directory ([`TreeError::NotADirectory`])
+/// - Internal operations encounter unexpected errors ([`TreeError::Other`])
+pub fn count(root: &Path, options: &TreeOptions) -> Result<TreeStats, TreeError> {
+    validate_root(root)?;
+    Ok(scan::count_tree(root, options)?)
+}
+
+/// Find the project root above `start`: the nearest of `start` and its
+/// ancestors that contains `marker`, such as `Cargo.toml` or `.git`.
+///
+/// `start` is made absolute first, so `.` searches upwards from the current
+/// directory. The marker may be a file or a directory (`.git` is a file in
+/// linked work trees).
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::path::Path;
+/// use tree::{find_root, print};
+///
+/// let root = find_root(Path::new("src/bin"), ".git")?;
+/// print(&root, &mut std::io::stdout())?;
+/// # Ok::<(), tree::TreeError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - `start` does not exist ([`TreeError::PathMissing`])
+/// - `start` is not a directory ([`TreeError::NotADirectory`])
+/// - No directory up to the filesystem root contains `marker`
+///   ([`TreeError::Other`])
+pub fn find_root(start: &Path, marker: &str) -> Result<PathBuf, TreeError> {
+    validate_root(start)?;
+    if marker.is_empty() {
+        return Err(TreeError::Other(anyhow::anyhow!("empty root marker")));
+    }
+    let start = start.canonicalize()?;
+    start
+        .ancestors()
+        .find(|dir| dir.join(marker).exists())
+        .map(Path::to_path_buf)
+        .ok_or_else(|| {
+            TreeError::Other(anyhow::anyhow!(
+                "no `{marker}` found in {} or any parent directory",
+                start.display()
+            ))
+        })
+}
+
+/// Build the [`TreeNode`] of a committed revision, as `git ls-tree` sees it.
+///
+/// `root` is a directory inside a git work tree; the result holds the
+/// entries of `rev` below it and is named after `rev`. Only git's object
+/// database is read — the working directory and index are left alone — so
+/// `.gitignore` and `.tree_ignore` do not apply. Render it with
+/// [`render_node`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::path::Path;
+/// use tree::{git_tree, render_node, SortOptions, TreeOptions};
+///
+/// let tree = git_tree(Path::new("."), "HEAD~3", SortOptions::default())?;
+/// render_node(&tree, &mut std::io::stdout(), &TreeOptions::default())?;
+/// # Ok::<(), tree::TreeError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The root path does not exist ([`TreeError::PathMissing`])
+/// - The root path is not a directory ([`TreeError::NotADirectory`])
+/// - git cannot be run or rejects the revision ([`TreeError::Other`])
+pub fn git_tree(root: &Path, rev: &str, sort: SortOptions) -> Result<TreeNode, TreeError> {
+    validate_root(root)?;
+    let paths = git::list_revision(root, rev)?;
+    Ok(paths::tree_from_paths(rev, paths, sort))
+}
+
+/// Scan every directory of `roots` and merge them into one tree, stacked
+/// like overlayfs layers with the first root at the bottom.
+///
+/// A directory shows the union of its contents across layers; any other
+/// entry hides what lower layers hold at its path. Each entry's
+/// `decoration` names the layers (numbered from 1) it is visible from and
+/// those it shadows, e.g. `[1 2]` or `[3] shadows 1`. Render the result
+/// with [`render_node`].
+///
+/// # Errors
+///
+/// Returns an error if any root cannot be scanned, as for [`scan`].
+pub fn overlay<P: AsRef<Path>>(roots: &[P], options: &TreeOptions) -> Result<TreeNode, TreeError> {
+    let layers = roots
+        .iter()
+        .map(|root| scan(root.as_ref(), options))
+        .collect::<Result<Vec<_>, _>>()?;
+    let name = layers
+        .iter()
+        .map(|layer| layer.name.as_str())
+        .collect::<Vec<_>>()
+        .join(" + ");
+    Ok(overlay::merge_layers(&name, &layers, options.sort))
+}
+
+/// Write every structured output for `root` into a `.tar.gz` archive.
+///
+/// The archive contains `tree.txt` (text rendering), `tree.json` (the scanned
+/// [`TreeNode`]) and `stats.csv` (aggregate [`TreeStats`]), which makes it a
+/// convenient single attachment for bug reports and compliance tickets.
+/// `options.format` is ignored; all three outputs are always written.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::path::Path;
+/// use tree::{export, TreeOptions};
+///
+/// export(Path::new("."), &TreeOptions::default(), Path::new("manifest.tar.gz"))?;
+/// # Ok::<(), tree::TreeError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The root path does not exist ([`TreeError::PathMissing`])
+/// - The root path is not a directory ([`TreeError::NotADirectory`])
+/// - The archive cannot be written ([`TreeError::Other`])
+pub fn export(root: &Path, options: &TreeOptions, archive: &Path) -> Result<(), TreeError> {
+    validate_root(root)?;
+    let mut text = Vec::new();
+    tree_printer::print_directory_tree_to_writer(root, &mut text, options)?;
+    let tree = scan::scan_tree(root, options)?;
+    export::write_export_archive(archive, &text, &tree, options.pretty)?;
+    Ok(())
+}
+
+/// Verify a directory against a previously exported JSON manifest.
+///
+/// The manifest is the `tree.json` written by [`export`] (or the output of
+/// [`render`] with [`OutputFormat::Json`]). The directory is scanned with
+/// files included and compared entry by entry: entries missing from the
+/// directory are reported as [`ChangeKind::Removed`], unexpected entries as
+/// [`ChangeKind::Added`] and entries whose type or size differ as
+/// [`ChangeKind::Modified`]. An empty result means the directory matches.
+///
+/// Nothing is written into `root`: when it has no `.tree_ignore`, the
+/// default patterns apply without creating the file. The manifest must
+/// list files too; one made with directories only (`-d`) cannot be
+/// verified, as every file would be reported as unexpected.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::path::Path;
+///
+/// let changes = tree::verify(Path::new("/srv/app"), Path::new("manifest.json"))?;
+/// for change in &changes {
+///     println!("{change}");
+/// }
+/// # Ok::<(), tree::TreeError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The root path does not exist ([`TreeError::PathMissing`])
+/// - The root path is not a directory ([`TreeError::NotADirectory`])
+/// - The manifest cannot be read ([`TreeError::Io`])
+/// - The manifest is not a valid tree document ([`TreeError::Other`])
+pub fn verify(root: &Path, manifest: &Path) -> Result<Vec<Change>, TreeError> {
+    validate_root(root)?;
+    let file = std::fs::File::open(manifest)?;
+    let expected: TreeNode = serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| anyhow::anyhow!("invalid manifest {}: {e}", manifest.display()))?;
+    let options = TreeOptions {
+        create_ignore_file: false,
+        ..TreeOptions::default()
+    };
+    let actual = scan::scan_tree(root, &options)?;
+    Ok(diff_trees(&expected, &actual))
+}
+
+/// Replace the block between two markers in a document with a rendered tree.
+///
+/// This is the engine behind `tree doc --inject`: keep a project‑structure
+/// section of a README up to date by regenerating it from a pre‑commit hook.
+/// The tree is wrapped in a fenced `text` code block; the markers and the
+/// rest of the document are preserved. `end` is searched after `start`, so
+/// one marker may delimit both ends.
+///
+/// # Examples
+///
+/// ```rust
+/// let readme = "# Demo\n<!-- tree -->\nstale\n<!-- /tree -->\n";
+/// let updated = tree::inject(readme, ".\n└── src/\n", "<!-- tree -->", "<!-- /tree -->")?;
+/// assert_eq!(
+///     updated,
+///     "# Demo\n<!-- tree -->\n```text\n.\n└── src/\n```\n<!-- /tree -->\n"
+/// );
+/// # Ok::<(), tree::TreeError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns [`TreeError::Other`] when either marker is missing.
+pub fn inject(document: &str, tree: &str, start: &str, end: &str) -> Result<String, TreeError> {
+    Ok(doc::inject_block(document, tree, start, end)?)
+}
+
+/// Create `root/.tree_ignore` listing exactly `patterns`.
+///
+/// Used after asking the user which [`suggest_ignores`] results to keep; an
+/// empty list still creates the file, so the question is not repeated.
+///
+/// # Errors
+///
+/// Returns an error when the root is invalid, the file already exists or it
+/// cannot be written.
+pub fn write_ignore_file<S: AsRef<str>>(root: &Path, patterns: &[S]) -> Result<(), TreeError> {
+    validate_root(root)?;
+    tree_printer::create_ignore_file(root, &suggest::ignore_file_contents(patterns))
+        .map_err(TreeError::from)
 }
 
 /// Remove every `.tree_ignore` file below the specified root directory.
@@ -314,7 +1110,7 @@ pub fn print_with_options<W: std::io::Write>(
 /// - Internal operations encounter unexpected errors ([`TreeError::Other`])
 pub fn clear(root: &Path) -> Result<u64, TreeError> {
     validate_root(root)?;
-    tree_printer::clear_ignore_files_count(root).map_err(TreeError::Other)
+    tree_printer::clear_ignore_files_count(root).map_err(TreeError::from)
 }
 
 /// Validates that a path exists and is a directory.
@@ -0,0 +1,115 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Guards against runaway traversals.
+//!
+//! Directories reached through symbolic links are descended into, so a link
+//! pointing back at one of its ancestors would be followed until the stack
+//! runs out. [`Limits`] caps the depth, the path length and the number of
+//! symlinked directories on the way to any entry; the walk stops with
+//! [`crate::TreeError::LimitExceeded`] naming the directory that tripped
+//! the guard instead.
+
+use serde::{Deserialize, Serialize};
+use std::{fmt, path::Path};
+
+/// Bounds checked before every directory is listed.
+///
+/// The defaults are far beyond any real project but well within the stack
+/// and the operating system's path limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    /// Deepest level listed; entries directly under the root are at depth 1
+    /// (default 512).
+    pub max_depth: usize,
+    /// Longest directory path, in bytes, that is listed (default 4096).
+    pub max_path_len: usize,
+    /// Most symlinked directories followed on the way from the root to any
+    /// entry (default 20). This must stay below the number of links the
+    /// kernel resolves in one path (40 on Linux, 32 on macOS): beyond that
+    /// a looping link no longer resolves to a directory, and the loop would
+    /// end quietly in a dangling link instead of being reported.
+    pub max_symlink_hops: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_depth: 512,
+            max_path_len: 4096,
+            max_symlink_hops: 20,
+        }
+    }
+}
+
+impl Limits {
+    /// Check `dir` before listing it: its children are at `depth`, and
+    /// `hops` symlinked directories were followed to reach it.
+    ///
+    /// # Errors
+    /// Returns the first limit `dir` exceeds.
+    pub fn check(&self, dir: &Path, depth: usize, hops: usize) -> Result<(), LimitExceeded> {
+        let exceeded = |limit, max| {
+            Err(LimitExceeded {
+                limit,
+                max,
+                path: dir.display().to_string(),
+            })
+        };
+        if depth > self.max_depth {
+            return exceeded(Limit::Depth, self.max_depth);
+        }
+        if dir.as_os_str().len() > self.max_path_len {
+            return exceeded(Limit::PathLength, self.max_path_len);
+        }
+        if hops > self.max_symlink_hops {
+            return exceeded(Limit::SymlinkHops, self.max_symlink_hops);
+        }
+        Ok(())
+    }
+}
+
+/// The guard of [`Limits`] that stopped a walk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// [`Limits::max_depth`].
+    Depth,
+    /// [`Limits::max_path_len`].
+    PathLength,
+    /// [`Limits::max_symlink_hops`].
+    SymlinkHops,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Depth => "depth",
+            Self::PathLength => "path length",
+            Self::SymlinkHops => "symlink hop",
+        })
+    }
+}
+
+/// A directory that was not listed because it exceeds one of the [`Limits`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitExceeded {
+    /// The guard that tripped.
+    pub limit: Limit,
+    /// Its configured maximum.
+    pub max: usize,
+    /// Display form of the offending directory.
+    pub path: String,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} limit of {} exceeded at `{}`",
+            self.limit, self.max, self.path
+        )
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
@@ -0,0 +1,105 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Counters describing the work done by a scan.
+//!
+//! Attach a shared [`ScanMetrics`] with
+//! [`TreeOptions::metrics`](crate::TreeOptions::metrics) and read it after
+//! rendering to see how much walking the ignore rules and the parallel
+//! walker saved.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Thread‑safe counters filled in while a tree is walked.
+#[derive(Debug, Default)]
+pub struct ScanMetrics {
+    directories: AtomicU64,
+    entries: AtomicU64,
+    stat_calls: AtomicU64,
+    stat_timeouts: AtomicU64,
+    retries: AtomicU64,
+}
+
+impl ScanMetrics {
+    /// Directories whose contents were listed.
+    pub fn directories(&self) -> u64 {
+        self.directories.load(Ordering::Relaxed)
+    }
+
+    /// Entries that passed every filter.
+    pub fn entries(&self) -> u64 {
+        self.entries.load(Ordering::Relaxed)
+    }
+
+    /// `stat`‑family calls issued by this crate (the walker's own
+    /// directory reads are not included).
+    pub fn stat_calls(&self) -> u64 {
+        self.stat_calls.load(Ordering::Relaxed)
+    }
+
+    /// Metadata lookups abandoned after
+    /// [`TreeOptions::stat_timeout`](crate::TreeOptions::stat_timeout).
+    pub fn stat_timeouts(&self) -> u64 {
+        self.stat_timeouts.load(Ordering::Relaxed)
+    }
+
+    /// Listings and lookups tried again after a transient error; see
+    /// [`TreeOptions::retry`](crate::TreeOptions::retry).
+    pub fn retries(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    /// One‑line summary for a walk that took `elapsed`, e.g.
+    /// `1204 entries in 12.3 ms (97886 entries/s), 38 directories, 1210 stat calls`,
+    /// followed by the numbers of timed‑out lookups and of retries when
+    /// there were any.
+    #[must_use]
+    pub fn summary(&self, elapsed: Duration) -> String {
+        let secs = elapsed.as_secs_f64();
+        #[allow(clippy::cast_precision_loss)] // display only
+        let rate = if secs > 0.0 {
+            self.entries() as f64 / secs
+        } else {
+            0.0
+        };
+        let timeouts = match self.stat_timeouts() {
+            0 => String::new(),
+            n => format!(" ({n} timed out)"),
+        };
+        let retries = match self.retries() {
+            0 => String::new(),
+            n => format!(", {n} retries"),
+        };
+        format!(
+            "{} entries in {:.1} ms ({rate:.0} entries/s), {} directories, {} stat calls{timeouts}{retries}",
+            self.entries(),
+            secs * 1000.0,
+            self.directories(),
+            self.stat_calls()
+        )
+    }
+
+    /// Record one listed directory holding `entries` accepted entries.
+    pub(crate) fn record_listing(&self, entries: usize) {
+        self.directories.fetch_add(1, Ordering::Relaxed);
+        self.entries.fetch_add(entries as u64, Ordering::Relaxed);
+    }
+
+    /// Record one retry after a transient error.
+    pub(crate) fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record one metadata lookup that timed out.
+    pub(crate) fn record_timeout(&self) {
+        self.stat_timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record `count` metadata lookups.
+    pub(crate) fn record_stats(&self, count: usize) {
+        self.stat_calls.fetch_add(count as u64, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,146 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Locale‑aware formatting of counts and sizes.
+//!
+//! `tree --numeric-locale de --count-only` prints `1.234 directories,
+//! 56.789 files` and `--du` sizes such as `4,0K`. Only the digit grouping
+//! and decimal separators change; unit letters and words stay as they are.
+//! The few conventions below cover most locales, and `auto` picks one from
+//! `LC_ALL`, `LC_NUMERIC` or `LANG` the way the C library does.
+
+use crate::du::human_size;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// Digit grouping and decimal separator conventions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NumericLocale {
+    /// No grouping, `.` decimal: `1234567.8` (the C and POSIX locales).
+    #[default]
+    C,
+    /// `,` grouping, `.` decimal: `1,234,567.8` (English, Chinese,
+    /// Japanese, Korean).
+    En,
+    /// `.` grouping, `,` decimal: `1.234.567,8` (German, Dutch, Italian,
+    /// Spanish, Portuguese, Danish, Turkish, Indonesian).
+    De,
+    /// Narrow no‑break space grouping, `,` decimal: `1 234 567,8` (French,
+    /// Russian, Polish, Czech, Swedish, Norwegian, Finnish, Ukrainian).
+    Fr,
+    /// `'` grouping, `.` decimal: `1'234'567.8` (Switzerland).
+    Ch,
+}
+
+impl NumericLocale {
+    /// Convention of the locale in effect, from the first of `LC_ALL`,
+    /// `LC_NUMERIC` and `LANG` that is set; [`NumericLocale::C`] when none
+    /// is or the locale is not known.
+    #[must_use]
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()))
+            .and_then(|name| Self::from_locale_name(&name))
+            .unwrap_or_default()
+    }
+
+    /// Convention of a POSIX locale name such as `de_DE.UTF-8` or `fr`;
+    /// `None` for an unknown language.
+    #[must_use]
+    pub fn from_locale_name(name: &str) -> Option<Self> {
+        let name = name.split(['.', '@']).next().unwrap_or(name);
+        let mut parts = name.split(['_', '-']);
+        let language = parts.next().unwrap_or_default().to_ascii_lowercase();
+        if parts
+            .next()
+            .is_some_and(|region| region.eq_ignore_ascii_case("CH"))
+        {
+            return Some(Self::Ch);
+        }
+        Some(match language.as_str() {
+            "c" | "posix" => Self::C,
+            "en" | "zh" | "ja" | "ko" | "th" | "he" | "hi" => Self::En,
+            "de" | "nl" | "it" | "es" | "pt" | "da" | "tr" | "id" | "el" | "ro" => Self::De,
+            "fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "nb" | "nn" | "no" | "fi" | "uk" | "hu"
+            | "bg" => Self::Fr,
+            _ => return None,
+        })
+    }
+
+    /// Separator between groups of three digits, if any.
+    #[must_use]
+    pub const fn grouping(self) -> Option<char> {
+        match self {
+            Self::C => None,
+            Self::En => Some(','),
+            Self::De => Some('.'),
+            Self::Fr => Some('\u{202f}'),
+            Self::Ch => Some('\''),
+        }
+    }
+
+    /// Decimal separator.
+    #[must_use]
+    pub const fn decimal(self) -> char {
+        match self {
+            Self::C | Self::En | Self::Ch => '.',
+            Self::De | Self::Fr => ',',
+        }
+    }
+
+    /// `n` with its digits grouped, e.g. `1,234,567`.
+    #[must_use]
+    pub fn count(self, n: u64) -> String {
+        let digits = n.to_string();
+        let Some(separator) = self.grouping() else {
+            return digits;
+        };
+        let mut out = String::with_capacity(digits.len() + digits.len() / 3 * 3);
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                out.push(separator);
+            }
+            out.push(digit);
+        }
+        out
+    }
+
+    /// [`human_size`] of `bytes` with this decimal separator, e.g. `4,0K`.
+    #[must_use]
+    pub fn size(self, bytes: u64) -> String {
+        let size = human_size(bytes);
+        match self.decimal() {
+            '.' => size,
+            decimal => size.replace('.', decimal.encode_utf8(&mut [0; 4])),
+        }
+    }
+}
+
+/// Error returned for an unknown `--numeric-locale` value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumericLocaleParseError(String);
+
+impl fmt::Display for NumericLocaleParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown numeric locale `{}` (expected auto, c, en, de, fr, ch or a locale name such as de_DE.UTF-8)",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for NumericLocaleParseError {}
+
+impl FromStr for NumericLocale {
+    type Err = NumericLocaleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(Self::from_env());
+        }
+        Self::from_locale_name(s).ok_or_else(|| NumericLocaleParseError(s.to_owned()))
+    }
+}
@@ -0,0 +1,1040 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Rendering options shared by every public entry point.
+
+use crate::{
+    annotator::Annotator,
+    cache::DecorationCache,
+    cancel::{CancelToken, Cancelled},
+    deadline,
+    diagnostics::Diagnostic,
+    entry::EntryInfo,
+    filter::{Candidate, FilterExpr},
+    heat::Buckets,
+    limits::Limits,
+    metrics::ScanMetrics,
+    numfmt::NumericLocale,
+    owner::OwnerFilter,
+    platform::{
+        access,
+        volume::{MetadataProvider, MetadataSupport, MountTable},
+    },
+    redact::PathRedaction,
+    render_core::{neutralize_bidi, Charset},
+    scan::EntryKind,
+    theme::Theme,
+    throttle::Throttle,
+    timefmt::TimeStyle,
+    tree_printer::io_reason,
+    unicode::{compare_names, sort_key},
+};
+use ignore::DirEntry;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    ffi::{OsStr, OsString},
+    fmt,
+    fs::{self, Metadata},
+    io,
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+/// Predicate deciding whether an entry is shown.
+pub type FilterFn = dyn Fn(&EntryInfo<'_>) -> bool + Send + Sync;
+
+/// Hook returning an optional annotation rendered next to an entry.
+pub type DecorateFn = dyn Fn(&EntryInfo<'_>) -> Option<String> + Send + Sync;
+
+/// Sink receiving informational [`Diagnostic`] messages.
+pub type DiagnosticFn = dyn Fn(&Diagnostic) + Send + Sync;
+
+/// Output format produced by [`crate::render`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// Unicode box‑drawing tree (the classic `tree` look).
+    #[default]
+    Text,
+    /// JSON document describing the scanned [`crate::TreeNode`], indented
+    /// or minified per [`TreeOptions::pretty`].
+    Json,
+    /// `HASH  relative/path` lines in the format of coreutils' `sha256sum`.
+    ///
+    /// Only regular files are listed, and always regardless of
+    /// [`TreeOptions::show_files`].
+    Sha256sum,
+    /// Rust source: a `FixtureEntry` enum and a nested `TREE` constant,
+    /// for snapshotting a layout into unit tests.
+    RustFixture,
+    /// TypeScript source: a `FixtureEntry` type and a nested `tree`
+    /// constant, for snapshotting a layout into unit tests.
+    TsFixture,
+    /// Indented lines naming each entry's kind in words, such as
+    /// `folder src, 3 items`, for screen readers.
+    PlainIndent,
+    /// One JSON [`crate::WatchEvent`] per line, streamed while watching a
+    /// directory; a tree on its own has no events to write.
+    Ndjson,
+}
+
+impl OutputFormat {
+    /// Every format, in declaration order.
+    pub const ALL: [Self; 7] = [
+        Self::Text,
+        Self::Json,
+        Self::Sha256sum,
+        Self::RustFixture,
+        Self::TsFixture,
+        Self::PlainIndent,
+        Self::Ndjson,
+    ];
+
+    /// Name of the format as accepted by `--format` and serde.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Json => "json",
+            Self::Sha256sum => "sha256sum",
+            Self::RustFixture => "rust-fixture",
+            Self::TsFixture => "ts-fixture",
+            Self::PlainIndent => "plain-indent",
+            Self::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// How entry paths that are not valid UTF‑8 are represented in structured
+/// outputs such as JSON.
+///
+/// Paths that are valid UTF‑8 are emitted unchanged by every variant except
+/// [`PathEncoding::Escape`], which doubles backslashes to stay unambiguous.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PathEncoding {
+    /// Replace invalid sequences with `U+FFFD`. Readable, but not reversible.
+    Lossy,
+    /// Keep a lossy `path` for display and add the exact bytes, Base64
+    /// encoded, in a separate `path_base64` field.
+    #[default]
+    Base64,
+    /// Escape invalid bytes as `\xNN` and backslashes as `\\` inside the
+    /// `path` itself, so a single string field round‑trips every byte.
+    Escape,
+}
+
+/// How directories and other entries are grouped among siblings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Grouping {
+    /// Directories before files and symlinks (the classic layout).
+    #[default]
+    DirsFirst,
+    /// Files and symlinks before directories, as some documentation styles
+    /// prefer.
+    FilesFirst,
+    /// No grouping: all siblings interleaved by name.
+    Mixed,
+}
+
+/// How rendered output is grouped before it reaches the caller's writer.
+///
+/// Renderers always write as they walk, so memory use does not grow with
+/// the size of the output under any policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BufPolicy {
+    /// Hand every write straight to the writer, which may buffer on its own
+    /// (standard output is line‑buffered).
+    #[default]
+    Unbuffered,
+    /// Write and flush complete lines, for interactive or streaming readers.
+    Line,
+    /// Collect output into chunks of this many bytes, for slow sinks where
+    /// fewer, larger writes are cheaper.
+    Chunks(usize),
+}
+
+/// Where the metadata columns of text output (security context,
+/// permissions, size, time) go on each line.
+///
+/// Either way every column is right‑aligned to its widest cell among the
+/// siblings of a directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColumnPlacement {
+    /// Before the name, like `ls -l`: `[ 4.0K]  [2025-01-02 13:04]  main.rs`.
+    #[default]
+    BeforeName,
+    /// After the name, starting at this display column (or two spaces after
+    /// the name when it is longer), so names stay lined up with the guides.
+    AfterName(usize),
+}
+
+/// Ordering of the children of each directory.
+///
+/// Applied while listing, so every output format — text, JSON, checksum
+/// manifests — orders children identically. Within a group, children are
+/// ordered by name (case‑sensitive unless [`SortOptions::ignore_case`],
+/// comparing the canonical decomposition so composed and decomposed
+/// spellings of a name sort together); see [`SortOptions::compare_names`].
+///
+/// The order is total: every tie falls back to a stricter comparison and
+/// finally to the raw bytes of the names, which differ for any two
+/// siblings. Output order therefore never depends on the order in which
+/// the operating system enumerates a directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct SortOptions {
+    /// Grouping of directories relative to other entries.
+    pub grouping: Grouping,
+    /// Order names case‑insensitively, e.g. `apple`, `Banana`, `cherry`.
+    pub ignore_case: bool,
+}
+
+impl SortOptions {
+    /// Sort options with the given grouping.
+    #[must_use]
+    pub const fn grouped(grouping: Grouping) -> Self {
+        Self {
+            grouping,
+            ignore_case: false,
+        }
+    }
+
+    /// These options with case‑insensitive name ordering switched on or off.
+    #[must_use]
+    pub const fn ignoring_case(mut self, ignore_case: bool) -> Self {
+        self.ignore_case = ignore_case;
+        self
+    }
+
+    /// Order two sibling names.
+    ///
+    /// Names compare by their canonical decomposition, case‑folded when
+    /// [`Self::ignore_case`] is set. Every tie is broken so the order is
+    /// total and stable across runs: names equal when folded fall back to
+    /// the case‑sensitive order (`README.md` before `Readme.md`, as upper
+    /// case sorts first), and names equal after decomposition to their raw
+    /// bytes.
+    #[must_use]
+    pub fn compare_names(self, a: &OsStr, b: &OsStr) -> Ordering {
+        if !self.ignore_case {
+            return compare_names(a, b);
+        }
+        sort_key(a, true)
+            .cmp(&sort_key(b, true))
+            .then_with(|| compare_names(a, b))
+    }
+}
+
+/// Order in which the text renderer visits entries.
+///
+/// Without [`TreeOptions::max_lines`] both orders print the same tree; with a
+/// line limit the order decides which entries make the cut.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Traversal {
+    /// Descend into each directory before moving on to its next sibling, so a
+    /// line limit shows the first part of the tree in full.
+    #[default]
+    DepthFirst,
+    /// Visit the tree level by level, so a line limit shows an overview of
+    /// the top levels of an enormous tree.
+    BreadthFirst,
+}
+
+/// Writability an entry must have to be shown; see [`TreeOptions::access`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AccessFilter {
+    /// Only entries the current process may write.
+    Writable,
+    /// Only entries the current process may not write.
+    ReadOnly,
+}
+
+/// How `.tree_ignore` patterns are compared with entry names.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CaseSensitivity {
+    /// Probe the filesystem holding the root and follow it.
+    #[default]
+    Auto,
+    /// Names must match exactly.
+    Sensitive,
+    /// Names match regardless of letter case, so `Target` and `target`
+    /// are the same pattern.
+    Insensitive,
+}
+
+/// Options controlling what gets rendered and how.
+///
+/// The default value reproduces [`crate::print`]: files and directories are
+/// shown and the output is the Unicode text tree.
+///
+/// ```rust
+/// use tree::{OutputFormat, TreeOptions};
+///
+/// let options = TreeOptions {
+///     format: OutputFormat::Json,
+///     ..TreeOptions::default()
+/// };
+/// assert!(options.show_files);
+/// ```
+///
+/// The same options can be built by chaining a `with_<field>` setter per
+/// public field; `Option` fields take the value itself, and the closure
+/// hooks keep their own methods such as [`Self::filter`]:
+///
+/// ```rust
+/// use tree::{OutputFormat, TreeOptions};
+///
+/// let options = TreeOptions::default()
+///     .with_format(OutputFormat::Json)
+///     .with_pretty(true)
+///     .with_max_lines(200)
+///     .on_diagnostic(|message| eprintln!("tree: {message}"));
+/// assert_eq!(options.format, OutputFormat::Json);
+/// assert_eq!(options.max_lines, Some(200));
+/// ```
+///
+/// The plain settings (everything except hooks, the theme and the metrics
+/// sink) implement serde, so applications can persist them in their own
+/// configuration format; missing keys take their default:
+///
+/// ```rust
+/// use tree::{Grouping, OutputFormat, TreeOptions};
+///
+/// let options: TreeOptions =
+///     serde_json::from_str(r#"{"format": "json", "sort": {"grouping": "files-first"}}"#)?;
+/// assert_eq!(options.format, OutputFormat::Json);
+/// assert_eq!(options.sort.grouping, Grouping::FilesFirst);
+/// assert!(options.show_files);
+/// # Ok::<(), serde_json::Error>(())
+/// ```
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+#[allow(clippy::struct_excessive_bools)] // independent on/off switches
+pub struct TreeOptions {
+    /// Include files (`true`) or render directories only (`false`).
+    pub show_files: bool,
+    /// Output format written by [`crate::render`].
+    pub format: OutputFormat,
+    /// Representation of non‑UTF‑8 paths in structured outputs.
+    pub path_encoding: PathEncoding,
+    /// User predicate applied after the ignore rules; see [`Self::filter`].
+    #[serde(skip)]
+    pub filter: Option<Arc<FilterFn>>,
+    /// User annotation hook; see [`Self::decorate`].
+    #[serde(skip)]
+    pub decorate: Option<Arc<DecorateFn>>,
+    /// Annotators composed after [`Self::decorate`]; see [`Self::annotator`].
+    #[serde(skip)]
+    pub annotators: Vec<Arc<dyn Annotator>>,
+    /// Rewrites of the root and entry paths shown, for sharing output
+    /// without revealing user or project names.
+    #[serde(skip)]
+    pub redaction: PathRedaction,
+    /// Colour theme for the text output; `None` renders without colour.
+    #[serde(skip)]
+    pub theme: Option<Theme>,
+    /// Wrap entry names in OSC 8 `file://` hyperlinks in the text output.
+    ///
+    /// Only enable this when writing to a terminal; the escape sequences are
+    /// noise in files and pipes.
+    pub hyperlinks: bool,
+    /// Print bidirectional control characters in names as they are. By
+    /// default text outputs show them as `\u{202e}` style escapes, so a name
+    /// cannot reorder how the rest of its line is displayed.
+    pub keep_bidi: bool,
+    /// Annotate each directory with a description read from the file of this
+    /// name inside it: the first heading of a Markdown file such as
+    /// `README.md`, or the first non‑empty line of anything else.
+    #[serde(with = "os_string")]
+    pub annotate_from: Option<OsString>,
+    /// Worker threads used for scanning and hashing; `0` (the default) uses
+    /// one per available core.
+    ///
+    /// Output is identical for every value. `1` runs everything on the
+    /// calling thread, which gives reproducible timings for benchmarks.
+    pub threads: usize,
+    /// Show entries that could not be read with an error note — `[error: …]`
+    /// in text, an `error` field in JSON — instead of silently omitting the
+    /// note. On by default.
+    pub show_errors: bool,
+    /// Entry order used by the text output when it is cut short by
+    /// [`Self::max_lines`].
+    pub traversal: Traversal,
+    /// Maximum number of entry lines in the text output (the root line and
+    /// the closing `…` note are not counted); `None` means unlimited.
+    pub max_lines: Option<usize>,
+    /// Precede the text output with a header recording the absolute root,
+    /// its device, the scan time (UTC), the active filters and the totals.
+    ///
+    /// The totals need a full scan before rendering starts.
+    pub report_header: bool,
+    /// Glyphs used for the branches of the text tree.
+    #[serde(with = "CharsetDef")]
+    pub charset: Charset,
+    /// Ordering of siblings in every output format.
+    pub sort: SortOptions,
+    /// Append the number of files directly inside each directory to its
+    /// text line, e.g. `src/ (12 files)`.
+    ///
+    /// Meant for the directories‑only view (`show_files == false`), which
+    /// would otherwise hide how much each directory holds. The count comes
+    /// from the same listing used for rendering; no extra pass is made.
+    pub file_counts: bool,
+    /// Append a rollup of the extensions of the files directly inside each
+    /// directory to its text line, most common first, e.g.
+    /// `src/ [rs:12 md:3 toml:1]`.
+    ///
+    /// Like [`Self::file_counts`] it is meant for the directories‑only view
+    /// and comes from the listing used for rendering. At most five
+    /// extensions are named, followed by `+N` for the rest; files without
+    /// an extension count as `(none)`.
+    pub extension_rollup: bool,
+    /// Show the Merkle digest of every directory, root included, as
+    /// `[merkle 3f2a…]`: the SHA‑256 over the names, types and digests of
+    /// the entries shown below it, so equal digests mean identical
+    /// subtrees. Costs a read of every file.
+    pub hash_dirs: bool,
+    /// Colour the guide lines by depth with the [`Theme::levels`] palette.
+    /// Has no effect without a [`Self::theme`].
+    pub level_colors: bool,
+    /// Colour entry names by how long ago they were modified, with the
+    /// [`Theme::ages`] palette spread over these buckets. Has no effect
+    /// without a [`Self::theme`].
+    pub age_colors: Option<Buckets<Duration>>,
+    /// Colour file names by size in bytes with the [`Theme::sizes`]
+    /// palette spread over these buckets. [`Self::age_colors`] takes
+    /// precedence when both are set. Has no effect without a
+    /// [`Self::theme`].
+    pub size_colors: Option<Buckets<u64>>,
+    /// Do not descend into directories holding more than this many entries
+    /// (counted recursively, ignore files not applied); their text line
+    /// reads `node_modules/ … skipped (48,201 entries)` instead.
+    ///
+    /// Guards interactive use against walking dependency or cache folders
+    /// that no ignore file covers. `None` descends everywhere.
+    pub auto_skip_over: Option<usize>,
+    /// When the root has no `.tree_ignore`, create one listing only the
+    /// well‑known bulky directories found directly under it (see
+    /// [`crate::suggest_ignores`]) instead of the full default template.
+    pub suggest_ignores: bool,
+    /// Create `.tree_ignore` in a root that has none (default `true`). When
+    /// `false`, the default patterns apply without anything being written.
+    pub create_ignore_file: bool,
+    /// Honour generic `.ignore` files, as `fd` and ripgrep do (default
+    /// `true`).
+    pub dot_ignore: bool,
+    /// Also honour the tool‑specific `.fdignore` and `.rgignore` files, so
+    /// the tree matches what `fd` and ripgrep see in the same directory.
+    pub tool_ignores: bool,
+    /// Hide entries marked `export-ignore` in `.gitattributes`, showing
+    /// what `git archive` would include.
+    pub export_ignore: bool,
+    /// Letter‑case handling of `.tree_ignore` patterns; by default this
+    /// follows the case sensitivity of the root's filesystem.
+    pub case_sensitivity: CaseSensitivity,
+    /// Indent structured output (JSON, including the export archive's
+    /// `tree.json`); `false` writes it minified (default `true`). The `tree`
+    /// binary indents only with `--pretty` or when stdout is a terminal.
+    pub pretty: bool,
+    /// Show at most this many entries per directory in text output — the
+    /// first ones after sorting — followed by a `+k more` line.
+    pub sample: Option<usize>,
+    /// Counters updated while walking; share an `Arc` and read it back after
+    /// rendering.
+    #[serde(skip)]
+    pub metrics: Option<Arc<ScanMetrics>>,
+    /// File hashes and decoration notes kept between renders, recomputed
+    /// only for files whose modification time or size changed; share an
+    /// `Arc` across the renders of a watch loop.
+    #[serde(skip)]
+    pub decoration_cache: Option<Arc<DecorationCache>>,
+    /// Prefix each text line with the entry's type and permission bits, like
+    /// `ls -l` (`[drwxr-xr-x]`), with a trailing `+` when the entry has an
+    /// ACL beyond those bits.
+    pub permissions: bool,
+    /// Append the names of each entry's extended attributes to its text
+    /// line, e.g. `[xattrs: user.origin]`.
+    pub xattrs: bool,
+    /// Prefix each text line with the entry's `SELinux` security context,
+    /// like `ls -Z` (Linux only; `?` when an entry has none).
+    pub security_context: bool,
+    /// Prefix every text line with the entry's modification time in this
+    /// style, e.g. `[2025-01-02 13:04]  main.rs`; `None` shows no times.
+    pub time_style: Option<TimeStyle>,
+    /// Digit grouping and decimal separator of the counts and sizes in
+    /// summaries and size cells, e.g. `1.234 files` and `4,0K`; `None`
+    /// keeps each output's usual plain digits.
+    pub numeric_locale: Option<NumericLocale>,
+    /// Prefix every text line with its disk usage like `du -h`: allocated
+    /// blocks, hard links counted once, and for directories everything on
+    /// disk below them (ignored entries included, as `du` would).
+    pub disk_usage: bool,
+    /// Count a directory reached under several paths, as through bind
+    /// mounts, only once in [`Self::disk_usage`] totals and in
+    /// [`crate::count`] (default `true`).
+    pub dedupe: bool,
+    /// Placement of the metadata columns enabled above.
+    pub columns: ColumnPlacement,
+    /// Grouping of rendered output before it reaches the writer.
+    pub buffering: BufPolicy,
+    /// Depth, path length and symlink guards checked before each directory
+    /// is listed, in every output format.
+    pub limits: Limits,
+    /// Show files, symlinks and special files only when they have these
+    /// owners; directories are always shown. `None` shows every owner.
+    pub owner: Option<OwnerFilter>,
+    /// Show files, symlinks and special files only when the current process
+    /// may (or may not) write them; directories are always shown. `None`
+    /// shows everything.
+    pub access: Option<AccessFilter>,
+    /// Show files, symlinks and special files only when this expression
+    /// holds for them; directories are always shown. Applied together with
+    /// [`Self::owner`] and [`Self::access`]; see [`FilterExpr`] for the
+    /// full semantics. `None` shows everything.
+    pub filter_expr: Option<FilterExpr>,
+    /// Give up on an entry's metadata lookup after this long, noting the
+    /// entry as `timed out` instead of waiting on a hung network mount.
+    /// `None` (the default) waits as long as the lookup takes.
+    pub stat_timeout: Option<Duration>,
+    /// Retry a directory listing or metadata lookup up to this many times
+    /// when it fails with a transient error (would block, interrupted,
+    /// timed out), waiting 50 ms before the first retry and twice as long
+    /// before each further one. `0` (the default) never retries.
+    pub retry: u32,
+    /// Limit directory listings and metadata lookups, or bytes read while
+    /// hashing, to a [`Rate`] shared by every worker thread, so scans of
+    /// shared storage leave bandwidth for others. `None` (the default)
+    /// runs at full speed.
+    #[serde(skip)]
+    pub throttle: Option<Arc<Throttle>>,
+    /// Stop the walk once this token is cancelled, failing with
+    /// [`crate::TreeError::Cancelled`] after the lines already written.
+    #[serde(skip)]
+    pub cancel: Option<CancelToken>,
+    /// Decides per root which of the [`Self::permissions`] and
+    /// [`Self::time_style`] columns its filesystem can fill; the others show
+    /// `-`. `None` asks the [`MountTable`].
+    #[serde(skip)]
+    pub metadata_provider: Option<Arc<dyn MetadataProvider>>,
+    /// Receiver of informational messages; see [`Self::on_diagnostic`].
+    #[serde(skip)]
+    pub diagnostics: Option<Arc<DiagnosticFn>>,
+}
+
+/// Chainable `#[must_use]` setters, each documented as setting its field.
+macro_rules! setters {
+    ($($setter:ident($field:ident): $ty:ty;)*) => {$(
+        #[doc = concat!("Set [`Self::", stringify!($field), "`].")]
+        #[must_use]
+        #[allow(clippy::missing_const_for_fn)] // const only for some field types
+        pub fn $setter(mut self, $field: $ty) -> Self {
+            self.$field = $field;
+            self
+        }
+    )*};
+}
+
+/// Like `setters!`, for `Option` fields, which are set to `Some`.
+macro_rules! optional_setters {
+    ($($setter:ident($field:ident): $ty:ty;)*) => {$(
+        #[doc = concat!("Set [`Self::", stringify!($field), "`] to `Some(", stringify!($field), ")`.")]
+        #[must_use]
+        #[allow(clippy::missing_const_for_fn)] // const only for some field types
+        pub fn $setter(mut self, $field: $ty) -> Self {
+            self.$field = Some($field);
+            self
+        }
+    )*};
+}
+
+impl TreeOptions {
+    setters! {
+    with_show_files(show_files): bool;
+    with_format(format): OutputFormat;
+    with_path_encoding(path_encoding): PathEncoding;
+    with_redaction(redaction): PathRedaction;
+    with_hyperlinks(hyperlinks): bool;
+    with_keep_bidi(keep_bidi): bool;
+    with_threads(threads): usize;
+    with_show_errors(show_errors): bool;
+    with_traversal(traversal): Traversal;
+    with_report_header(report_header): bool;
+    with_charset(charset): Charset;
+    with_sort(sort): SortOptions;
+    with_file_counts(file_counts): bool;
+    with_extension_rollup(extension_rollup): bool;
+    with_hash_dirs(hash_dirs): bool;
+    with_level_colors(level_colors): bool;
+    with_suggest_ignores(suggest_ignores): bool;
+    with_create_ignore_file(create_ignore_file): bool;
+    with_dot_ignore(dot_ignore): bool;
+    with_tool_ignores(tool_ignores): bool;
+    with_export_ignore(export_ignore): bool;
+    with_case_sensitivity(case_sensitivity): CaseSensitivity;
+    with_pretty(pretty): bool;
+    with_permissions(permissions): bool;
+    with_xattrs(xattrs): bool;
+    with_security_context(security_context): bool;
+    with_disk_usage(disk_usage): bool;
+    with_dedupe(dedupe): bool;
+    with_columns(columns): ColumnPlacement;
+    with_buffering(buffering): BufPolicy;
+    with_limits(limits): Limits;
+    with_retry(retry): u32;
+    }
+
+    optional_setters! {
+    with_theme(theme): Theme;
+    with_annotate_from(annotate_from): OsString;
+    with_max_lines(max_lines): usize;
+    with_age_colors(age_colors): Buckets<Duration>;
+    with_size_colors(size_colors): Buckets<u64>;
+    with_auto_skip_over(auto_skip_over): usize;
+    with_sample(sample): usize;
+    with_metrics(metrics): Arc<ScanMetrics>;
+    with_decoration_cache(decoration_cache): Arc<DecorationCache>;
+    with_time_style(time_style): TimeStyle;
+    with_numeric_locale(numeric_locale): NumericLocale;
+    with_owner(owner): OwnerFilter;
+    with_access(access): AccessFilter;
+    with_filter_expr(filter_expr): FilterExpr;
+    with_stat_timeout(stat_timeout): Duration;
+    with_throttle(throttle): Arc<Throttle>;
+    with_cancel(cancel): CancelToken;
+    with_metadata_provider(metadata_provider): Arc<dyn MetadataProvider>;
+    }
+
+    /// Hide every entry for which `predicate` returns `false`.
+    ///
+    /// The predicate runs after `.gitignore` and `.tree_ignore` filtering.
+    /// Rejected directories are not descended into. Calling this again
+    /// replaces the previous predicate.
+    ///
+    /// ```rust
+    /// use tree::{EntryKind, TreeOptions};
+    ///
+    /// // Hide log files.
+    /// let options = TreeOptions::default().filter(|entry| {
+    ///     entry.kind != EntryKind::File || entry.path.extension().map_or(true, |e| e != "log")
+    /// });
+    /// # let _ = options;
+    /// ```
+    #[must_use]
+    pub fn filter<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&EntryInfo<'_>) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Annotate entries with the text returned by `hook`.
+    ///
+    /// The text output appends the annotation after the entry name; JSON
+    /// output stores it in the node's `decoration` field. Calling this again
+    /// replaces the previous hook.
+    ///
+    /// ```rust
+    /// use tree::TreeOptions;
+    ///
+    /// let options = TreeOptions::default().decorate(|entry| {
+    ///     (entry.name == "target").then(|| "build output".to_owned())
+    /// });
+    /// # let _ = options;
+    /// ```
+    #[must_use]
+    pub fn decorate<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&EntryInfo<'_>) -> Option<String> + Send + Sync + 'static,
+    {
+        self.decorate = Some(Arc::new(hook));
+        self
+    }
+
+    /// Register `annotator`, adding its notes after those of the annotators
+    /// registered before it and of the [`Self::decorate`] hook.
+    ///
+    /// Notes are separated by two spaces and appear wherever decorations
+    /// do: after the entry name in text output and joined into the
+    /// `decoration` field of JSON output.
+    ///
+    /// ```rust
+    /// use tree::{Annotation, FileSize, TreeOptions};
+    ///
+    /// let options = TreeOptions::default()
+    ///     .annotator(FileSize)
+    ///     .annotator(|entry: &tree::EntryInfo<'_>| {
+    ///         (entry.depth == 1).then(|| Annotation::new("top level"))
+    ///     });
+    /// # let _ = options;
+    /// ```
+    #[must_use]
+    pub fn annotator<A: Annotator + 'static>(mut self, annotator: A) -> Self {
+        self.annotators.push(Arc::new(annotator));
+        self
+    }
+
+    /// Send informational messages — such as the creation of a default
+    /// `.tree_ignore` — to `sink` instead of discarding them.
+    ///
+    /// Messages are never written to the render writer, so they cannot mix
+    /// with the tree output. Calling this again replaces the previous sink.
+    ///
+    /// ```rust
+    /// use tree::TreeOptions;
+    ///
+    /// let options = TreeOptions::default().on_diagnostic(|message| eprintln!("tree: {message}"));
+    /// # let _ = options;
+    /// ```
+    #[must_use]
+    pub fn on_diagnostic<F>(mut self, sink: F) -> Self
+    where
+        F: Fn(&Diagnostic) + Send + Sync + 'static,
+    {
+        self.diagnostics = Some(Arc::new(sink));
+        self
+    }
+
+    /// Count `count` metadata lookups in [`Self::metrics`], if attached,
+    /// and pace them per [`Self::throttle`].
+    pub(crate) fn record_stats(&self, count: usize) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_stats(count);
+        }
+        if let Some(throttle) = &self.throttle {
+            throttle.ops(count as u64);
+        }
+    }
+
+    /// Count a listed directory in [`Self::metrics`] and [`Self::cancel`],
+    /// if attached, and pace it per [`Self::throttle`].
+    pub(crate) fn record_listing(&self, entries: usize) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_listing(entries);
+        }
+        if let Some(throttle) = &self.throttle {
+            throttle.ops(1);
+        }
+        if let Some(cancel) = &self.cancel {
+            cancel.record(entries);
+        }
+    }
+
+    /// Fail once [`Self::cancel`] has been cancelled.
+    pub(crate) fn check_cancelled(&self) -> Result<(), Cancelled> {
+        self.cancel.as_ref().map_or(Ok(()), CancelToken::check)
+    }
+
+    /// `name` as printed in text outputs: with bidirectional controls
+    /// escaped unless [`Self::keep_bidi`] is set.
+    pub(crate) fn shown_name<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        if self.keep_bidi {
+            Cow::Borrowed(name)
+        } else {
+            neutralize_bidi(name)
+        }
+    }
+
+    /// What the filesystem holding `root` records, asked only when a
+    /// permissions or time column is shown.
+    pub(crate) fn metadata_support(&self, root: &Path) -> MetadataSupport {
+        if !self.permissions && self.time_style.is_none() {
+            return MetadataSupport::FULL;
+        }
+        self.metadata_provider.as_ref().map_or_else(
+            || MountTable.support(root),
+            |provider| provider.support(root),
+        )
+    }
+
+    /// Hand `message` to the diagnostics sink, if any.
+    pub(crate) fn diagnose(&self, message: &Diagnostic) {
+        if let Some(sink) = &self.diagnostics {
+            sink(message);
+        }
+    }
+
+    /// Metadata of `entry` without following symlinks, bounded by
+    /// [`Self::stat_timeout`] and counted in [`Self::metrics`].
+    /// Transient failures are retried per [`Self::retry`].
+    pub(crate) fn stat(&self, entry: &DirEntry) -> Result<Metadata, ignore::Error> {
+        let mut attempt = 0;
+        loop {
+            match self.stat_once(entry.path()) {
+                Err(err) if self.retry_after(entry.path(), attempt, &err) => attempt += 1,
+                result => return result.map_err(ignore::Error::from),
+            }
+        }
+    }
+
+    /// One metadata lookup of `path` for [`Self::stat`].
+    fn stat_once(&self, path: &Path) -> io::Result<Metadata> {
+        self.record_stats(1);
+        let Some(timeout) = self.stat_timeout else {
+            return fs::symlink_metadata(path);
+        };
+        deadline::symlink_metadata(path, timeout).inspect_err(|err| {
+            if err.kind() == io::ErrorKind::TimedOut {
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_timeout();
+                }
+            }
+        })
+    }
+
+    /// Whether to try `path` again after `err` ended try number
+    /// `attempt` (from 0); sleeps for the backoff first when so.
+    pub(crate) fn retry_after(&self, path: &Path, attempt: u32, err: &io::Error) -> bool {
+        let transient = matches!(
+            err.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted | io::ErrorKind::TimedOut
+        );
+        if !transient || attempt >= self.retry {
+            return false;
+        }
+        if let Some(metrics) = &self.metrics {
+            metrics.record_retry();
+        }
+        self.diagnose(&Diagnostic::Retried(
+            path.to_path_buf(),
+            err.kind().to_string(),
+            attempt + 1,
+        ));
+        std::thread::sleep(Duration::from_millis(50) * 2u32.saturating_pow(attempt));
+        true
+    }
+
+    /// Whether the user filter (if any) accepts `entry`.
+    pub(crate) fn accepts(&self, entry: &EntryInfo<'_>) -> bool {
+        self.filter.as_ref().map_or(true, |f| f(entry))
+    }
+
+    /// Whether the [`Self::owner`], [`Self::access`] and
+    /// [`Self::filter_expr`] filters (if any) accept the entry of `kind` at
+    /// `path`, reading its metadata at most once.
+    pub(crate) fn admits(&self, path: &Path, kind: EntryKind) -> bool {
+        if kind == EntryKind::Directory
+            || (self.owner.is_none() && self.access.is_none() && self.filter_expr.is_none())
+        {
+            return true;
+        }
+        let entry = Candidate::new(path, kind, Some(self));
+        self.owner.map_or(true, |owner| {
+            entry.metadata().is_some_and(|meta| owner.matches(meta))
+        }) && self.access.map_or(true, |access| {
+            entry
+                .metadata()
+                .is_some_and(|meta| access::writable(meta) == (access == AccessFilter::Writable))
+        }) && self
+            .filter_expr
+            .as_ref()
+            .map_or(true, |expr| expr.eval(&entry))
+    }
+
+    /// Short description of `err` for the output, or `None` when error
+    /// notes are disabled.
+    pub(crate) fn error_note(&self, err: &ignore::Error) -> Option<String> {
+        self.show_errors.then(|| io_reason(err))
+    }
+
+    /// [`Self::decoration`] of the file `dir_entry`, through
+    /// [`Self::decoration_cache`] when there is one.
+    pub(crate) fn cached_decoration(
+        &self,
+        dir_entry: &DirEntry,
+        entry: &EntryInfo<'_>,
+    ) -> Option<String> {
+        let cached = self
+            .decoration_cache
+            .as_ref()
+            .filter(|_| entry.kind != EntryKind::Directory)
+            .and_then(|cache| Some((cache, self.stat(dir_entry).ok()?)));
+        match cached {
+            Some((cache, meta)) => cache.note(entry.path, &meta, || self.decoration(entry)),
+            None => self.decoration(entry),
+        }
+    }
+
+    /// Annotations produced by the user hook and the registered annotators
+    /// for `entry`, joined in that order.
+    pub(crate) fn decoration(&self, entry: &EntryInfo<'_>) -> Option<String> {
+        let mut notes = self.decorate.as_ref().and_then(|d| d(entry));
+        for annotation in self.annotators.iter().filter_map(|a| a.annotate(entry)) {
+            match &mut notes {
+                Some(text) => {
+                    text.push_str("  ");
+                    text.push_str(&annotation.text);
+                }
+                None => notes = Some(annotation.text),
+            }
+        }
+        notes
+    }
+}
+
+impl fmt::Debug for TreeOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TreeOptions")
+            .field("show_files", &self.show_files)
+            .field("format", &self.format)
+            .field("path_encoding", &self.path_encoding)
+            .field("filter", &self.filter.as_ref().map(|_| "<fn>"))
+            .field("decorate", &self.decorate.as_ref().map(|_| "<fn>"))
+            .field("annotators", &self.annotators.len())
+            .field("redaction", &self.redaction)
+            .field("theme", &self.theme)
+            .field("hyperlinks", &self.hyperlinks)
+            .field("keep_bidi", &self.keep_bidi)
+            .field("annotate_from", &self.annotate_from)
+            .field("threads", &self.threads)
+            .field("show_errors", &self.show_errors)
+            .field("traversal", &self.traversal)
+            .field("max_lines", &self.max_lines)
+            .field("report_header", &self.report_header)
+            .field("charset", &self.charset)
+            .field("sort", &self.sort)
+            .field("file_counts", &self.file_counts)
+            .field("extension_rollup", &self.extension_rollup)
+            .field("hash_dirs", &self.hash_dirs)
+            .field("level_colors", &self.level_colors)
+            .field("age_colors", &self.age_colors)
+            .field("size_colors", &self.size_colors)
+            .field("auto_skip_over", &self.auto_skip_over)
+            .field("suggest_ignores", &self.suggest_ignores)
+            .field("create_ignore_file", &self.create_ignore_file)
+            .field("dot_ignore", &self.dot_ignore)
+            .field("tool_ignores", &self.tool_ignores)
+            .field("export_ignore", &self.export_ignore)
+            .field("case_sensitivity", &self.case_sensitivity)
+            .field("pretty", &self.pretty)
+            .field("sample", &self.sample)
+            .field("permissions", &self.permissions)
+            .field("xattrs", &self.xattrs)
+            .field("security_context", &self.security_context)
+            .field("metrics", &self.metrics)
+            .field("decoration_cache", &self.decoration_cache)
+            .field("time_style", &self.time_style)
+            .field("numeric_locale", &self.numeric_locale)
+            .field("disk_usage", &self.disk_usage)
+            .field("dedupe", &self.dedupe)
+            .field("columns", &self.columns)
+            .field("buffering", &self.buffering)
+            .field("limits", &self.limits)
+            .field("owner", &self.owner)
+            .field("filter_expr", &self.filter_expr)
+            .field("access", &self.access)
+            .field("stat_timeout", &self.stat_timeout)
+            .field("retry", &self.retry)
+            .field("throttle", &self.throttle)
+            .field("cancel", &self.cancel)
+            .field(
+                "metadata_provider",
+                &self.metadata_provider.as_ref().map(|_| "<provider>"),
+            )
+            .field("diagnostics", &self.diagnostics.as_ref().map(|_| "<fn>"))
+            .finish()
+    }
+}
+
+impl Default for TreeOptions {
+    fn default() -> Self {
+        Self {
+            show_files: true,
+            format: OutputFormat::default(),
+            path_encoding: PathEncoding::default(),
+            filter: None,
+            decorate: None,
+            annotators: Vec::new(),
+            redaction: PathRedaction::default(),
+            theme: None,
+            hyperlinks: false,
+            keep_bidi: false,
+            annotate_from: None,
+            threads: 0,
+            show_errors: true,
+            traversal: Traversal::default(),
+            max_lines: None,
+            report_header: false,
+            charset: Charset::default(),
+            sort: SortOptions::default(),
+            file_counts: false,
+            extension_rollup: false,
+            hash_dirs: false,
+            level_colors: false,
+            age_colors: None,
+            size_colors: None,
+            auto_skip_over: None,
+            suggest_ignores: false,
+            create_ignore_file: true,
+            dot_ignore: true,
+            tool_ignores: false,
+            export_ignore: false,
+            case_sensitivity: CaseSensitivity::Auto,
+            pretty: true,
+            sample: None,
+            permissions: false,
+            xattrs: false,
+            security_context: false,
+            metrics: None,
+            decoration_cache: None,
+            time_style: None,
+            numeric_locale: None,
+            disk_usage: false,
+            dedupe: true,
+            columns: ColumnPlacement::BeforeName,
+            buffering: BufPolicy::Unbuffered,
+            limits: Limits::default(),
+            owner: None,
+            filter_expr: None,
+            access: None,
+            stat_timeout: None,
+            retry: 0,
+            throttle: None,
+            cancel: None,
+            metadata_provider: None,
+            diagnostics: None,
+        }
+    }
+}
+
+/// Serde mirror of the dependency‑free [`Charset`].
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Charset", rename_all = "kebab-case")]
+enum CharsetDef {
+    Unicode,
+    Ascii,
+}
+
+/// Serde for optional file names as plain (lossy UTF‑8) strings.
+mod os_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::ffi::OsString;
+
+    #[allow(clippy::ref_option)] // signature required by `#[serde(with)]`
+    pub fn serialize<S: Serializer>(
+        value: &Option<OsString>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(name) => serializer.serialize_some(&*name.to_string_lossy()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<OsString>, D::Error> {
+        Ok(Option::<String>::deserialize(deserializer)?.map(OsString::from))
+    }
+}
@@ -0,0 +1,124 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Buffering between the renderers and the caller's writer.
+//!
+//! Every renderer writes line by line as it walks, so output never piles up
+//! in memory; [`TreeOptions::buffering`] only decides how those lines are
+//! grouped before they reach the writer. A slow sink — a socket, a pager
+//! reading from a pipe — then applies backpressure one bounded buffer at a
+//! time.
+//!
+//! [`MultiWriter`] sends the same output to several writers, such as a
+//! terminal and a log file.
+//!
+//! [`TreeOptions::buffering`]: crate::TreeOptions::buffering
+
+use crate::options::BufPolicy;
+use std::{
+    fmt,
+    io::{self, BufWriter, LineWriter, Write},
+};
+
+/// Writer applying a [`BufPolicy`] in front of the caller's writer.
+pub enum Buffered<'w, W: Write> {
+    /// Writes go straight through.
+    Direct(&'w mut W),
+    /// Complete lines are written and the writer flushed after each.
+    Line(LineWriter<&'w mut W>),
+    /// Writes are collected into chunks of a fixed size.
+    Chunks(BufWriter<&'w mut W>),
+}
+
+impl<'w, W: Write> Buffered<'w, W> {
+    /// Wrap `writer` according to `policy`.
+    pub fn new(writer: &'w mut W, policy: BufPolicy) -> Self {
+        match policy {
+            BufPolicy::Unbuffered => Self::Direct(writer),
+            BufPolicy::Line => Self::Line(LineWriter::new(writer)),
+            BufPolicy::Chunks(size) => Self::Chunks(BufWriter::with_capacity(size, writer)),
+        }
+    }
+}
+
+impl<W: Write> Write for Buffered<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Direct(writer) => writer.write(buf),
+            Self::Line(writer) => {
+                let written = writer.write(buf)?;
+                // `LineWriter` hands complete lines over but does not flush.
+                if buf[..written].contains(&b'\n') {
+                    writer.get_mut().flush()?;
+                }
+                Ok(written)
+            }
+            Self::Chunks(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Direct(writer) => writer.flush(),
+            Self::Line(writer) => writer.flush(),
+            Self::Chunks(writer) => writer.flush(),
+        }
+    }
+}
+
+/// Writer copying everything written to it into each of its sinks, like
+/// `tee`.
+///
+/// Every write reaches all sinks in full before the next one starts, so the
+/// sinks hold the same bytes up to the first error, which is returned.
+///
+/// ```rust
+/// use tree::MultiWriter;
+/// use std::io::Write;
+///
+/// let (mut first, mut second) = (Vec::new(), Vec::new());
+/// let mut out = MultiWriter::new().with(&mut first).with(&mut second);
+/// writeln!(out, "src/").unwrap();
+/// drop(out);
+/// assert_eq!(first, second);
+/// ```
+#[derive(Default)]
+pub struct MultiWriter<'a> {
+    sinks: Vec<Box<dyn Write + 'a>>,
+}
+
+impl<'a> MultiWriter<'a> {
+    /// Writer without sinks; output is discarded until one is added.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `sink` after the existing sinks.
+    #[must_use]
+    pub fn with(mut self, sink: impl Write + 'a) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+}
+
+impl fmt::Debug for MultiWriter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultiWriter")
+            .field("sinks", &self.sinks.len())
+            .finish()
+    }
+}
+
+impl Write for MultiWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for sink in &mut self.sinks {
+            sink.write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.sinks.iter_mut().try_for_each(Write::flush)
+    }
+}
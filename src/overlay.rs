@@ -0,0 +1,108 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Union of several directory trees, composed like overlayfs layers.
+//!
+//! `tree overlay base/ app/ config/` stacks the roots in the order given,
+//! the first at the bottom. A directory present in several layers shows the
+//! merged contents of all of them, while a file (or any other non‑directory)
+//! hides whatever lower layers hold at the same path, directories included.
+//! Every entry is annotated with the layers it is visible from, and with the
+//! layers it shadows, so the composition of a layered image or mount can be
+//! debugged from a single listing.
+
+use crate::{
+    options::SortOptions,
+    paths::sort_nodes,
+    scan::{EntryKind, TreeNode},
+};
+use std::collections::BTreeMap;
+
+/// Merge `layers`, bottom first, into one tree named `name`.
+///
+/// Layers are numbered from 1 in the annotations, which are stored in each
+/// node's `decoration` ahead of any decoration the layer already carried:
+/// `[1 2]` for an entry visible from layers 1 and 2, `[3] shadows 1` for
+/// one in layer 3 that hides the entry of layer 1.
+#[must_use]
+pub fn merge_layers(name: &str, layers: &[TreeNode], sort: SortOptions) -> TreeNode {
+    let roots: Vec<(usize, &TreeNode)> = layers.iter().enumerate().collect();
+    TreeNode {
+        name: name.to_owned(),
+        path: String::new(),
+        path_base64: None,
+        kind: EntryKind::Directory,
+        size: None,
+        description: None,
+        decoration: None,
+        error: None,
+        truncated: None,
+        children: merge_children(&roots, sort),
+    }
+}
+
+/// Merged children of the same directory in several layers, bottom first.
+fn merge_children(dirs: &[(usize, &TreeNode)], sort: SortOptions) -> Vec<TreeNode> {
+    let mut by_path: BTreeMap<&str, Vec<(usize, &TreeNode)>> = BTreeMap::new();
+    for &(layer, dir) in dirs {
+        for child in &dir.children {
+            let key = child.path_base64.as_deref().unwrap_or(&child.path);
+            by_path.entry(key).or_default().push((layer, child));
+        }
+    }
+
+    let mut nodes: Vec<TreeNode> = by_path
+        .into_values()
+        .filter_map(|stack| {
+            let &(_, top) = stack.last()?;
+            // Directories merge downwards until a lower non-directory ends
+            // the run; anything else is opaque.
+            let visible = if top.kind == EntryKind::Directory {
+                stack
+                    .iter()
+                    .rev()
+                    .take_while(|(_, node)| node.kind == EntryKind::Directory)
+                    .count()
+            } else {
+                1
+            };
+            let (shadowed, visible) = stack.split_at(stack.len() - visible);
+            let mut note = format!("[{}]", layer_list(visible));
+            if !shadowed.is_empty() {
+                note.push_str(" shadows ");
+                note.push_str(&layer_list(shadowed));
+            }
+            if let Some(decoration) = &top.decoration {
+                note.push_str("  ");
+                note.push_str(decoration);
+            }
+            Some(TreeNode {
+                name: top.name.clone(),
+                path: top.path.clone(),
+                path_base64: top.path_base64.clone(),
+                kind: top.kind,
+                size: top.size,
+                description: top.description.clone(),
+                decoration: Some(note),
+                error: top.error.clone(),
+                truncated: None,
+                children: if top.kind == EntryKind::Directory {
+                    merge_children(visible, sort)
+                } else {
+                    Vec::new()
+                },
+            })
+        })
+        .collect();
+    sort_nodes(&mut nodes, sort);
+    nodes
+}
+
+/// Space‑separated 1‑based numbers of `layers`.
+fn layer_list(layers: &[(usize, &TreeNode)]) -> String {
+    layers
+        .iter()
+        .map(|(layer, _)| (layer + 1).to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
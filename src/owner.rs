@@ -0,0 +1,146 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Filtering by owning user and group.
+//!
+//! `tree --user alice --owner-group www-data /srv/shared` audits a shared
+//! directory: files, symlinks and special files are shown only when they
+//! belong to the given principals, while directories are always shown so
+//! the matches keep their place in the tree. Names are resolved once, when
+//! the filter is built, from `/etc/passwd` and `/etc/group`; both databases
+//! are read at most once per process.
+//!
+//! Ownership is a Unix notion. Elsewhere no entry has a known owner, so an
+//! owner filter hides every non‑directory.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, fs::Metadata, sync::OnceLock};
+
+/// Owners a non‑directory entry must have to be shown; `None` accepts any.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OwnerFilter {
+    /// Required owning user id.
+    pub uid: Option<u32>,
+    /// Required owning group id.
+    pub gid: Option<u32>,
+}
+
+impl OwnerFilter {
+    /// Filter for the user and group given by name or numeric id.
+    ///
+    /// # Errors
+    /// Returns an error naming the first principal that is neither a number
+    /// nor a known name.
+    pub fn resolve(user: Option<&str>, group: Option<&str>) -> Result<Self, UnknownPrincipal> {
+        Ok(Self {
+            uid: user.map(|name| lookup(name, Principal::User)).transpose()?,
+            gid: group
+                .map(|name| lookup(name, Principal::Group))
+                .transpose()?,
+        })
+    }
+
+    /// Whether an entry with `metadata` passes the filter.
+    #[must_use]
+    pub fn matches(&self, metadata: &Metadata) -> bool {
+        let Some((uid, gid)) = owner_ids(metadata) else {
+            return false;
+        };
+        self.uid.map_or(true, |want| want == uid) && self.gid.map_or(true, |want| want == gid)
+    }
+}
+
+impl fmt::Display for OwnerFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.uid, self.gid) {
+            (Some(uid), Some(gid)) => write!(f, "owner {uid}:{gid}"),
+            (Some(uid), None) => write!(f, "owner {uid}"),
+            (None, Some(gid)) => write!(f, "group {gid}"),
+            (None, None) => f.write_str("any owner"),
+        }
+    }
+}
+
+/// Kind of principal named in an [`OwnerFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Principal {
+    /// A user account, from `/etc/passwd`.
+    User,
+    /// A group, from `/etc/group`.
+    Group,
+}
+
+impl fmt::Display for Principal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::User => "user",
+            Self::Group => "group",
+        })
+    }
+}
+
+/// A user or group name that could not be resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownPrincipal {
+    /// Whether a user or a group was looked up.
+    pub kind: Principal,
+    /// The name as given.
+    pub name: String,
+}
+
+impl fmt::Display for UnknownPrincipal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown {} `{}`", self.kind, self.name)
+    }
+}
+
+impl std::error::Error for UnknownPrincipal {}
+
+/// Id of the principal `name`, which may also be given as a number.
+fn lookup(name: &str, kind: Principal) -> Result<u32, UnknownPrincipal> {
+    static USERS: OnceLock<HashMap<String, u32>> = OnceLock::new();
+    static GROUPS: OnceLock<HashMap<String, u32>> = OnceLock::new();
+
+    if let Ok(id) = name.parse() {
+        return Ok(id);
+    }
+    let names = match kind {
+        Principal::User => USERS.get_or_init(|| read_database("/etc/passwd")),
+        Principal::Group => GROUPS.get_or_init(|| read_database("/etc/group")),
+    };
+    names.get(name).copied().ok_or_else(|| UnknownPrincipal {
+        kind,
+        name: name.to_owned(),
+    })
+}
+
+/// Name → id map of a `passwd` or `group` style file, whose lines start
+/// with `name:password:id:`; empty when the file cannot be read.
+fn read_database(path: &str) -> HashMap<String, u32> {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return HashMap::new();
+    };
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((name.to_owned(), id))
+        })
+        .collect()
+}
+
+/// Owning user and group ids of an entry.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_wraps)] // `None` on other platforms
+fn owner_ids(metadata: &Metadata) -> Option<(u32, u32)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.uid(), metadata.gid()))
+}
+
+/// Ownership is not exposed outside Unix.
+#[cfg(not(unix))]
+const fn owner_ids(_metadata: &Metadata) -> Option<(u32, u32)> {
+    None
+}
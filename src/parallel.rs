@@ -0,0 +1,65 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Bounded worker pool for the scanner and the hasher.
+//!
+//! Work items are claimed from a shared counter by at most
+//! [`TreeOptions::threads`](crate::TreeOptions::threads) scoped threads, and
+//! results are put back in input order, so output never depends on the
+//! thread count.
+
+use std::{
+    num::NonZeroUsize,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+/// Number of worker threads for a requested count, `0` meaning one per core.
+pub fn effective_threads(requested: usize) -> usize {
+    if requested == 0 {
+        thread::available_parallelism().map_or(1, NonZeroUsize::get)
+    } else {
+        requested
+    }
+}
+
+/// Apply `f` to every item using up to `threads` workers and return the
+/// results in input order. A single worker runs inline on the caller's thread.
+pub fn map_ordered<T, R, F>(items: &[T], threads: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = effective_threads(threads).min(items.len());
+    if workers <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<R>> = std::iter::repeat_with(|| None).take(items.len()).collect();
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else { break };
+                        done.push((index, f(item)));
+                    }
+                    done
+                })
+            })
+            .collect();
+        for handle in handles {
+            let done = handle
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+            for (index, result) in done {
+                results[index] = Some(result);
+            }
+        }
+    });
+    results.into_iter().flatten().collect()
+}
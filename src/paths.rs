@@ -0,0 +1,201 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Trees built from path lists instead of a directory walk.
+//!
+//! `tree --paths-from -` renders whatever set of paths it is handed — the
+//! output of `git ls-files`, `find -print0` or a build manifest — without
+//! reading the filesystem. Directories are implied by the paths below them;
+//! an entry written with a trailing `/` is a directory even when empty.
+
+use crate::{
+    options::{Grouping, SortOptions, TreeOptions},
+    render_core::Layout,
+    scan::{EntryKind, TreeNode},
+};
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+/// Split a path list on NUL bytes if it contains any, else on newlines.
+///
+/// Empty entries are dropped and a trailing `\r` is removed from
+/// newline‑separated entries.
+#[must_use]
+pub fn parse_path_list(input: &str) -> Vec<&str> {
+    let entries: Vec<&str> = if input.contains('\0') {
+        input.split('\0').collect()
+    } else {
+        input
+            .lines()
+            .map(|line| line.strip_suffix('\r').unwrap_or(line))
+            .collect()
+    };
+    entries.into_iter().filter(|p| !p.is_empty()).collect()
+}
+
+/// Intermediate directory node, keyed by name for fast lookup.
+#[derive(Default)]
+struct Branch {
+    is_dir: bool,
+    children: BTreeMap<String, Self>,
+}
+
+/// Build a [`TreeNode`] named `name` that contains exactly `paths` plus the
+/// directories leading to them.
+///
+/// Paths are `/`‑separated and relative to the root; `.` components,
+/// leading `./` and repeated separators are ignored. Siblings are ordered
+/// like a directory walk: grouped per `sort`, then by name.
+pub fn tree_from_paths<I, S>(name: &str, paths: I, sort: SortOptions) -> TreeNode
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut root = Branch {
+        is_dir: true,
+        ..Branch::default()
+    };
+    for path in paths {
+        let path = path.as_ref();
+        let mut node = &mut root;
+        for part in path.split('/').filter(|p| !p.is_empty() && *p != ".") {
+            node.is_dir = true;
+            node = node.children.entry(part.to_owned()).or_default();
+        }
+        if path.ends_with('/') {
+            node.is_dir = true;
+        }
+    }
+    TreeNode {
+        name: name.to_owned(),
+        path: String::new(),
+        path_base64: None,
+        kind: EntryKind::Directory,
+        size: None,
+        description: None,
+        decoration: None,
+        error: None,
+        truncated: None,
+        children: into_nodes(root.children, "", sort),
+    }
+}
+
+/// Convert `children` of the directory at `parent` into sorted nodes.
+fn into_nodes(
+    children: BTreeMap<String, Branch>,
+    parent: &str,
+    sort: SortOptions,
+) -> Vec<TreeNode> {
+    let mut nodes: Vec<TreeNode> = children
+        .into_iter()
+        .map(|(name, branch)| {
+            let path = if parent.is_empty() {
+                name.clone()
+            } else {
+                format!("{parent}/{name}")
+            };
+            let children = into_nodes(branch.children, &path, sort);
+            TreeNode {
+                name,
+                path,
+                path_base64: None,
+                kind: if branch.is_dir {
+                    EntryKind::Directory
+                } else {
+                    EntryKind::File
+                },
+                size: None,
+                description: None,
+                decoration: None,
+                error: None,
+                truncated: None,
+                children,
+            }
+        })
+        .collect();
+    sort_nodes(&mut nodes, sort);
+    nodes
+}
+
+/// Order sibling `nodes` like a directory walk: grouped per `sort`, then by
+/// name.
+pub fn sort_nodes(nodes: &mut [TreeNode], sort: SortOptions) {
+    let group = |kind: EntryKind| {
+        let is_dir = kind == EntryKind::Directory;
+        match sort.grouping {
+            Grouping::DirsFirst => !is_dir,
+            Grouping::FilesFirst => is_dir,
+            Grouping::Mixed => false,
+        }
+    };
+    nodes.sort_by(|a, b| {
+        group(a.kind)
+            .cmp(&group(b.kind))
+            .then_with(|| sort.compare_names(a.name.as_ref(), b.name.as_ref()))
+    });
+}
+
+/// Write `tree` as classic text: the root name, then one line per entry
+/// with its description and decoration, as the directory printer shows them.
+///
+/// # Errors
+/// Returns an error when the writer fails.
+pub fn write_text<W: Write>(
+    tree: &TreeNode,
+    writer: &mut W,
+    options: &TreeOptions,
+) -> io::Result<()> {
+    writeln!(writer, "{}", options.shown_name(&tree.name))?;
+    let mut layout = Layout::new(options.charset);
+    write_children(
+        &tree.children,
+        tree.truncated,
+        1,
+        &mut layout,
+        options,
+        writer,
+    )
+}
+
+/// Write `children` at `depth` and everything below them, then a `+N more`
+/// line for the `omitted` entries of a size‑bounded tree.
+fn write_children<W: Write>(
+    children: &[TreeNode],
+    omitted: Option<usize>,
+    depth: usize,
+    layout: &mut Layout,
+    options: &TreeOptions,
+    writer: &mut W,
+) -> io::Result<()> {
+    for (i, child) in children.iter().enumerate() {
+        let last = i + 1 == children.len() && omitted.is_none();
+        let connector = layout.connector_str(depth, last);
+        let indicator = child.kind.indicator();
+        let mut notes = String::new();
+        if let Some(description) = &child.description {
+            notes.push_str("  # ");
+            notes.push_str(description);
+        }
+        if let Some(decoration) = &child.decoration {
+            notes.push_str("  ");
+            notes.push_str(decoration);
+        }
+        let name = options.shown_name(&child.name);
+        writeln!(writer, "{connector}{name}{indicator}{notes}")?;
+        write_children(
+            &child.children,
+            child.truncated,
+            depth + 1,
+            layout,
+            options,
+            writer,
+        )?;
+    }
+    if let Some(more) = omitted {
+        let connector = layout.connector_str(depth, true);
+        writeln!(writer, "{connector}+{more} more")?;
+    }
+    Ok(())
+}
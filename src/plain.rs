@@ -0,0 +1,79 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Screen‑reader friendly plain text.
+//!
+//! `--format plain-indent` prints one entry per line, indented by two spaces
+//! per level, with the kind spelled out as a word (`folder src, 3 items`)
+//! instead of box‑drawing guides, and never any colour. Screen readers read
+//! it as sentences, and documentation converters that mangle Unicode art
+//! pass it through unchanged.
+
+use crate::{
+    options::TreeOptions,
+    scan::{EntryKind, TreeNode},
+};
+use std::io::{self, Write};
+
+/// Write `tree` as indented `folder`/`file` lines.
+///
+/// # Errors
+/// Returns an error when the writer fails.
+pub fn write_plain_indent<W: Write>(
+    tree: &TreeNode,
+    writer: &mut W,
+    options: &TreeOptions,
+) -> io::Result<()> {
+    write_node(tree, 0, options, writer)
+}
+
+/// Write the line of `node` at `level`, then its children one level deeper.
+fn write_node<W: Write>(
+    node: &TreeNode,
+    level: usize,
+    options: &TreeOptions,
+    writer: &mut W,
+) -> io::Result<()> {
+    let indent = "  ".repeat(level);
+    let name = options.shown_name(&node.name);
+    write!(writer, "{indent}{} {name}", kind_word(node.kind))?;
+    if node.kind == EntryKind::Directory {
+        let items = node.children.len() + node.truncated.unwrap_or(0);
+        match items {
+            0 => write!(writer, ", empty")?,
+            1 => write!(writer, ", 1 item")?,
+            n => write!(writer, ", {n} items")?,
+        }
+    }
+    if let Some(error) = &node.error {
+        write!(writer, ", unreadable: {error}")?;
+    }
+    if let Some(description) = &node.description {
+        write!(writer, ", {description}")?;
+    }
+    if let Some(decoration) = &node.decoration {
+        write!(writer, ", {decoration}")?;
+    }
+    writeln!(writer)?;
+    for child in &node.children {
+        write_node(child, level + 1, options, writer)?;
+    }
+    match node.truncated {
+        Some(1) => writeln!(writer, "{indent}  1 more item not shown"),
+        Some(n) if n > 0 => writeln!(writer, "{indent}  {n} more items not shown"),
+        _ => Ok(()),
+    }
+}
+
+/// Spoken name of an entry kind.
+const fn kind_word(kind: EntryKind) -> &'static str {
+    match kind {
+        EntryKind::Directory => "folder",
+        EntryKind::File => "file",
+        EntryKind::Symlink => "link",
+        EntryKind::Fifo => "pipe",
+        EntryKind::Socket => "socket",
+        EntryKind::BlockDevice => "block device",
+        EntryKind::CharDevice => "character device",
+    }
+}
@@ -0,0 +1,14 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Operating‑system specific entry metadata and scheduling.
+//!
+//! Each submodule offers one portable interface and degrades to "nothing to
+//! show" on platforms that lack the feature, so callers never need `cfg`.
+
+pub mod access;
+pub mod file_type;
+pub mod mode;
+pub mod priority;
+pub mod volume;
+pub mod xattr;
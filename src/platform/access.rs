@@ -0,0 +1,92 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Whether the current process may read or write an entry.
+//!
+//! The answer comes from the mode bits checked against the process's
+//! effective user and groups, as the kernel does without ACLs; `root` may
+//! read and write everything. On Linux the identity is read once from
+//! `/proc/self/status`. Elsewhere it is unknown, and an entry counts as
+//! readable and writable when anyone may read or write it.
+
+use std::fs::Metadata;
+
+/// Effective user id and the ids of every group of the process.
+#[cfg(target_os = "linux")]
+struct Identity {
+    uid: u32,
+    groups: Vec<u32>,
+}
+
+/// Identity of the current process, read on first use.
+#[cfg(target_os = "linux")]
+fn identity() -> Option<&'static Identity> {
+    static IDENTITY: std::sync::OnceLock<Option<Identity>> = std::sync::OnceLock::new();
+    IDENTITY
+        .get_or_init(|| {
+            let status = std::fs::read_to_string("/proc/self/status").ok()?;
+            let field = |name: &str| {
+                status
+                    .lines()
+                    .find_map(|line| line.strip_prefix(name))
+                    .map(|rest| rest.split_whitespace().filter_map(|id| id.parse().ok()))
+            };
+            // `Uid:` and `Gid:` list the real, effective, saved and
+            // filesystem ids in that order.
+            let uid = field("Uid:")?.nth(1)?;
+            let mut groups: Vec<u32> = field("Groups:").into_iter().flatten().collect();
+            groups.push(field("Gid:")?.nth(1)?);
+            Some(Identity { uid, groups })
+        })
+        .as_ref()
+}
+
+/// Whether the permission bits of `meta` grant `bit` (4 read, 2 write) to
+/// the current process.
+#[cfg(unix)]
+fn granted(meta: &Metadata, bit: u32) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let mode = meta.mode();
+    #[cfg(target_os = "linux")]
+    if let Some(me) = identity() {
+        return if me.uid == 0 {
+            true
+        } else if me.uid == meta.uid() {
+            mode & (bit << 6) != 0
+        } else if me.groups.contains(&meta.gid()) {
+            mode & (bit << 3) != 0
+        } else {
+            mode & bit != 0
+        };
+    }
+    mode & (bit | bit << 3 | bit << 6) != 0
+}
+
+/// Whether the current process may read the entry.
+#[cfg(unix)]
+#[must_use]
+pub fn readable(meta: &Metadata) -> bool {
+    granted(meta, 4)
+}
+
+/// Whether the current process may write the entry.
+#[cfg(unix)]
+#[must_use]
+pub fn writable(meta: &Metadata) -> bool {
+    granted(meta, 2)
+}
+
+/// Without Unix mode bits every entry that could be listed is readable.
+#[cfg(not(unix))]
+#[must_use]
+pub const fn readable(_meta: &Metadata) -> bool {
+    true
+}
+
+/// Without Unix mode bits only the read‑only flag is known.
+#[cfg(not(unix))]
+#[must_use]
+pub fn writable(meta: &Metadata) -> bool {
+    !meta.permissions().readonly()
+}
@@ -0,0 +1,34 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! FIFOs, sockets and device files.
+
+use crate::scan::EntryKind;
+use std::fs::FileType;
+
+/// The special [`EntryKind`] of `file_type`, or `None` for directories,
+/// regular files and symlinks.
+#[cfg(unix)]
+#[must_use]
+pub fn special_kind(file_type: FileType) -> Option<EntryKind> {
+    use std::os::unix::fs::FileTypeExt;
+
+    if file_type.is_fifo() {
+        Some(EntryKind::Fifo)
+    } else if file_type.is_socket() {
+        Some(EntryKind::Socket)
+    } else if file_type.is_block_device() {
+        Some(EntryKind::BlockDevice)
+    } else if file_type.is_char_device() {
+        Some(EntryKind::CharDevice)
+    } else {
+        None
+    }
+}
+
+/// Other platforms have no special files.
+#[cfg(not(unix))]
+#[must_use]
+pub const fn special_kind(_file_type: FileType) -> Option<EntryKind> {
+    None
+}
@@ -0,0 +1,71 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! `ls -l` style permission strings.
+
+use std::fs::Metadata;
+
+/// Type character and permission bits of an entry, e.g. `drwxr-xr-x`,
+/// including setuid, setgid and sticky bits.
+#[cfg(unix)]
+#[must_use]
+pub fn permissions(meta: &Metadata) -> String {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    let file_type = meta.file_type();
+    let kind = if file_type.is_dir() {
+        'd'
+    } else if file_type.is_symlink() {
+        'l'
+    } else if file_type.is_block_device() {
+        'b'
+    } else if file_type.is_char_device() {
+        'c'
+    } else if file_type.is_fifo() {
+        'p'
+    } else if file_type.is_socket() {
+        's'
+    } else {
+        '-'
+    };
+    let mode = meta.permissions().mode();
+    let bit = |mask: u32, c: char| if mode & mask == 0 { '-' } else { c };
+    // Execute position, with the special bit shown as `s`/`t` (`S`/`T`
+    // when set without execute permission).
+    let exec = |exec_mask: u32, special_mask: u32, set: char| match (
+        mode & exec_mask != 0,
+        mode & special_mask != 0,
+    ) {
+        (true, true) => set,
+        (false, true) => set.to_ascii_uppercase(),
+        (true, false) => 'x',
+        (false, false) => '-',
+    };
+    [
+        kind,
+        bit(0o400, 'r'),
+        bit(0o200, 'w'),
+        exec(0o100, 0o4000, 's'),
+        bit(0o040, 'r'),
+        bit(0o020, 'w'),
+        exec(0o010, 0o2000, 's'),
+        bit(0o004, 'r'),
+        bit(0o002, 'w'),
+        exec(0o001, 0o1000, 't'),
+    ]
+    .iter()
+    .collect()
+}
+
+/// Without Unix mode bits only the type and the read‑only flag are known.
+#[cfg(not(unix))]
+#[must_use]
+pub fn permissions(meta: &Metadata) -> String {
+    let kind = if meta.is_dir() { 'd' } else { '-' };
+    let write = if meta.permissions().readonly() {
+        '-'
+    } else {
+        'w'
+    };
+    format!("{kind}r{write}-r{write}-r{write}-")
+}
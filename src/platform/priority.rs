@@ -0,0 +1,59 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Background scheduling priority for scans on busy servers.
+//!
+//! On Linux the process is moved to the idle I/O scheduling class, whose
+//! disk requests are served only when nobody else wants the disk, and to
+//! the lowest CPU priority (`nice 19`). Other Unix systems only lower the
+//! CPU priority; elsewhere the request is unsupported. The change is made
+//! through `ionice` and `renice`, which apply to the calling thread; every
+//! thread started afterwards inherits it, so lower the priority before the
+//! scan starts its workers.
+
+use std::{
+    io,
+    process::{Command, Stdio},
+};
+
+/// Move the current process to idle I/O and lowest CPU priority.
+///
+/// # Errors
+/// Returns an error when a scheduling tool is missing or fails, or when the
+/// platform has no such priorities.
+#[cfg(unix)]
+pub fn idle_io() -> io::Result<()> {
+    let pid = std::process::id().to_string();
+    #[cfg(target_os = "linux")]
+    run("ionice", &["-c", "3", "-p", &pid])?;
+    run("renice", &["-n", "19", "-p", &pid])
+}
+
+/// Scheduling classes are a Unix notion.
+///
+/// # Errors
+/// Always returns [`io::ErrorKind::Unsupported`].
+#[cfg(not(unix))]
+pub fn idle_io() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "idle I/O priority is not supported on this platform",
+    ))
+}
+
+/// Run `tool` with `args` silently, failing unless it succeeds.
+#[cfg(unix)]
+fn run(tool: &str, args: &[&str]) -> io::Result<()> {
+    let status = Command::new(tool)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|err| io::Error::new(err.kind(), format!("running {tool}: {err}")))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("{tool} failed ({status})")))
+    }
+}
@@ -0,0 +1,145 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! What the filesystem holding a root can record.
+//!
+//! FAT and exFAT volumes have no Unix permission bits: the kernel reports
+//! the mount's `umask` for every entry, so `rwx` columns would be made up.
+//! Some virtual and network filesystems report no modification times at
+//! all, which surface as the Unix epoch. Each root is checked once and the
+//! columns it cannot fill show `-` instead of such bogus values.
+
+use std::{fs, path::Path, time::SystemTime};
+
+/// Which metadata columns hold real values below a root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetadataSupport {
+    /// Unix permission bits are stored per entry.
+    pub permissions: bool,
+    /// Modification times are stored per entry.
+    pub times: bool,
+}
+
+impl MetadataSupport {
+    /// Everything is recorded, as on native Unix and Windows filesystems.
+    pub const FULL: Self = Self {
+        permissions: true,
+        times: true,
+    };
+
+    /// Support of a filesystem by its mount type, such as `vfat` or `ext4`.
+    #[must_use]
+    pub fn of_filesystem(fs_type: &str) -> Self {
+        let fat = matches!(
+            fs_type.to_ascii_lowercase().as_str(),
+            "vfat" | "msdos" | "fat" | "fat32" | "exfat" | "fuse.exfat" | "umsdos"
+        );
+        Self {
+            permissions: !fat,
+            ..Self::FULL
+        }
+    }
+}
+
+impl Default for MetadataSupport {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+/// Source of the [`MetadataSupport`] of a root.
+///
+/// Closures of the form `Fn(&Path) -> MetadataSupport` implement the trait,
+/// which lets tests pretend a directory lives on any filesystem.
+pub trait MetadataProvider: Send + Sync {
+    /// What the filesystem holding `root` records.
+    fn support(&self, root: &Path) -> MetadataSupport;
+}
+
+impl<F> MetadataProvider for F
+where
+    F: Fn(&Path) -> MetadataSupport + Send + Sync,
+{
+    fn support(&self, root: &Path) -> MetadataSupport {
+        self(root)
+    }
+}
+
+/// The default provider: the mount table's filesystem type for permissions
+/// and the root's own modification time for times.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MountTable;
+
+impl MetadataProvider for MountTable {
+    fn support(&self, root: &Path) -> MetadataSupport {
+        let support = filesystem_type(root).map_or(MetadataSupport::FULL, |fs_type| {
+            MetadataSupport::of_filesystem(&fs_type)
+        });
+        let timed = fs::metadata(root)
+            .and_then(|meta| meta.modified())
+            .is_ok_and(|mtime| mtime != SystemTime::UNIX_EPOCH);
+        MetadataSupport {
+            times: support.times && timed,
+            ..support
+        }
+    }
+}
+
+/// Mount type of the filesystem holding `path`, from the deepest matching
+/// entry of `/proc/self/mounts`; `None` when unknown.
+#[cfg(target_os = "linux")]
+#[must_use]
+pub fn filesystem_type(path: &Path) -> Option<String> {
+    let path = fs::canonicalize(path).ok()?;
+    let mounts = fs::read_to_string("/proc/self/mounts").ok()?;
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            let mount_point = unescape(fields.nth(1)?);
+            let fs_type = fields.next()?;
+            path.starts_with(&mount_point)
+                .then(|| (mount_point.len(), fs_type.to_owned()))
+        })
+        // Later mounts shadow earlier ones on the same point.
+        .fold(
+            None,
+            |best: Option<(usize, String)>, (len, fs_type)| match best {
+                Some((best_len, _)) if best_len > len => best,
+                _ => Some((len, fs_type)),
+            },
+        )
+        .map(|(_, fs_type)| fs_type)
+}
+
+/// Mount types are only read from the Linux mount table.
+#[cfg(not(target_os = "linux"))]
+#[must_use]
+pub fn filesystem_type(_path: &Path) -> Option<String> {
+    None
+}
+
+/// Undo the octal escapes (`\040` for a space) of a mount table field.
+#[cfg(target_os = "linux")]
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let code = bytes.get(i + 1..i + 4).and_then(|digits| {
+            let digits = std::str::from_utf8(digits).ok()?;
+            u8::from_str_radix(digits, 8).ok()
+        });
+        match (bytes[i], code) {
+            (b'\\', Some(byte)) => {
+                out.push(byte);
+                i += 4;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
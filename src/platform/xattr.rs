@@ -0,0 +1,71 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Extended attributes and security contexts.
+//!
+//! Symbolic links are never followed: the attributes shown belong to the
+//! entry itself, as with `getfattr -h`. Entries whose attributes cannot be
+//! read (unsupported filesystem, permissions) simply report none.
+
+use std::path::Path;
+
+/// Attribute holding the `SELinux` security context on Linux.
+#[cfg(target_os = "linux")]
+const SELINUX: &str = "security.selinux";
+
+/// Attributes holding POSIX ACLs on Linux; a file carries them only when
+/// its ACL grants more than the mode bits express.
+#[cfg(target_os = "linux")]
+const POSIX_ACLS: [&str; 2] = ["system.posix_acl_access", "system.posix_acl_default"];
+
+/// Names of the extended attributes of `path`, sorted.
+#[cfg(unix)]
+#[must_use]
+pub fn names(path: &Path) -> Vec<String> {
+    let mut names: Vec<String> = ::xattr::list(path)
+        .map(|list| list.map(|n| n.to_string_lossy().into_owned()).collect())
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Extended attributes are not supported on this platform.
+#[cfg(not(unix))]
+#[must_use]
+pub fn names(_path: &Path) -> Vec<String> {
+    Vec::new()
+}
+
+/// `SELinux` security context of `path`, e.g.
+/// `unconfined_u:object_r:user_home_t:s0`, if it has one.
+#[cfg(target_os = "linux")]
+#[must_use]
+pub fn security_context(path: &Path) -> Option<String> {
+    let value = ::xattr::get(path, SELINUX).ok()??;
+    let value = value.strip_suffix(b"\0").unwrap_or(&value);
+    Some(String::from_utf8_lossy(value).into_owned())
+}
+
+/// Security contexts are only read on Linux.
+#[cfg(not(target_os = "linux"))]
+#[must_use]
+pub const fn security_context(_path: &Path) -> Option<String> {
+    None
+}
+
+/// Whether `path` has an ACL beyond its basic mode bits — the entries
+/// `ls -l` marks with `+`.
+#[cfg(target_os = "linux")]
+#[must_use]
+pub fn has_acl(path: &Path) -> bool {
+    POSIX_ACLS
+        .iter()
+        .any(|name| ::xattr::get(path, name).is_ok_and(|v| v.is_some()))
+}
+
+/// ACLs are only detected on Linux, where they are stored as attributes.
+#[cfg(not(target_os = "linux"))]
+#[must_use]
+pub const fn has_acl(_path: &Path) -> bool {
+    false
+}
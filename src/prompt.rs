@@ -0,0 +1,242 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Trees trimmed to fit a prompt.
+//!
+//! `tree --budget-tokens 2000` lists as much of a project as fits in about
+//! 2000 tokens of a language‑model prompt. Every entry is scored — shallow
+//! entries, source directories and recently modified files first — and
+//! entries are taken best first, each together with its ancestors, for as
+//! long as the rendered text stays within the budget. Directories that lost
+//! entries end with a `+N more` line, so the summary of what was left out is
+//! part of the listing.
+//!
+//! Sizes are counted in bytes of the text rendering with ASCII guides, four
+//! per level of indentation, and a line is reserved for the `+N more` note
+//! of every directory shown.
+
+use crate::scan::{EntryKind, TreeNode};
+use std::{
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+/// Bytes per token assumed when a budget is given in tokens, the usual
+/// rule of thumb for English text and code.
+pub const BYTES_PER_TOKEN: usize = 4;
+
+/// Bytes reserved for a `+N more` line besides its indentation.
+const MORE_LINE: usize = 12;
+
+/// Size limit of a budgeted tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptBudget {
+    /// Approximate number of prompt tokens.
+    Tokens(usize),
+    /// Number of bytes.
+    Bytes(usize),
+}
+
+impl PromptBudget {
+    /// The budget in bytes.
+    #[must_use]
+    pub const fn bytes(self) -> usize {
+        match self {
+            Self::Tokens(tokens) => tokens.saturating_mul(BYTES_PER_TOKEN),
+            Self::Bytes(bytes) => bytes,
+        }
+    }
+}
+
+/// An entry being considered for a budgeted tree.
+#[derive(Debug, Clone, Copy)]
+pub struct Candidate<'a> {
+    /// The entry itself; its children are not considered part of it.
+    pub node: &'a TreeNode,
+    /// Level below the root, 1 for the root's children.
+    pub depth: usize,
+    /// Time since the entry was last modified, when it could be read.
+    pub age: Option<Duration>,
+}
+
+/// Weights of the default scoring; entries with higher scores are kept
+/// first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Priorities {
+    /// Points lost per level below the root (default 1).
+    pub depth: f64,
+    /// Points for entries in or named after one of
+    /// [`Priorities::source_dirs`] (default 2).
+    pub source: f64,
+    /// Points for a file modified just now, halving with every
+    /// [`Priorities::half_life`] of age (default 3).
+    pub recency: f64,
+    /// Age at which the recency bonus halves (default one week).
+    pub half_life: Duration,
+    /// Directory names that hold source code.
+    pub source_dirs: Vec<String>,
+}
+
+impl Default for Priorities {
+    fn default() -> Self {
+        Self {
+            depth: 1.0,
+            source: 2.0,
+            recency: 3.0,
+            half_life: Duration::from_secs(7 * 24 * 60 * 60),
+            source_dirs: ["src", "lib", "app", "cmd", "pkg", "include"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+}
+
+impl Priorities {
+    /// Score of `candidate` under these weights.
+    #[must_use]
+    pub fn score(&self, candidate: &Candidate<'_>) -> f64 {
+        let depth = f64::from(u32::try_from(candidate.depth).unwrap_or(u32::MAX));
+        let mut score = -self.depth * depth;
+        if candidate
+            .node
+            .path
+            .split('/')
+            .any(|part| self.source_dirs.iter().any(|dir| dir == part))
+        {
+            score += self.source;
+        }
+        if let (Some(age), EntryKind::File) = (candidate.age, candidate.node.kind) {
+            let half_lives = age.as_secs_f64() / self.half_life.as_secs_f64().max(1.0);
+            score += self.recency * 0.5_f64.powf(half_lives);
+        }
+        score
+    }
+}
+
+/// One entry of the tree in pre‑order.
+struct Flat<'a> {
+    node: &'a TreeNode,
+    parent: Option<usize>,
+    depth: usize,
+}
+
+/// Copy of `tree` holding the entries that fit in `max_bytes`, chosen by
+/// `score`, with [`TreeNode::truncated`] set on every directory that lost
+/// children.
+///
+/// Modification times are read from the entries' paths below `root`; pass
+/// a tree whose paths are not redacted or escaped, or recency is ignored.
+#[must_use]
+pub fn fit_to_budget(
+    tree: &TreeNode,
+    root: &Path,
+    max_bytes: usize,
+    score: impl Fn(&Candidate<'_>) -> f64,
+) -> TreeNode {
+    let mut entries = Vec::new();
+    flatten(&tree.children, None, 1, &mut entries);
+
+    let now = SystemTime::now();
+    let scores: Vec<f64> = entries
+        .iter()
+        .map(|entry| {
+            let age = std::fs::symlink_metadata(root.join(&entry.node.path))
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok());
+            score(&Candidate {
+                node: entry.node,
+                depth: entry.depth,
+                age,
+            })
+        })
+        .collect();
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+
+    let mut kept = vec![false; entries.len()];
+    let mut used = tree.name.len() + 1 + MORE_LINE;
+    for idx in order {
+        // The entry comes with whichever of its ancestors are not shown yet.
+        let mut missing = Vec::new();
+        let mut next = Some(idx);
+        while let Some(i) = next.filter(|&i| !kept[i]) {
+            missing.push(i);
+            next = entries[i].parent;
+        }
+        let cost: usize = missing.iter().map(|&i| cost(&entries[i])).sum();
+        if used + cost <= max_bytes {
+            used += cost;
+            for i in missing {
+                kept[i] = true;
+            }
+        }
+    }
+
+    let (children, truncated) = keep(&tree.children, &kept, &mut 0);
+    shallow(tree, children, truncated)
+}
+
+/// Append `nodes` and everything below them to `out` in pre‑order.
+fn flatten<'a>(
+    nodes: &'a [TreeNode],
+    parent: Option<usize>,
+    depth: usize,
+    out: &mut Vec<Flat<'a>>,
+) {
+    for node in nodes {
+        let idx = out.len();
+        out.push(Flat {
+            node,
+            parent,
+            depth,
+        });
+        flatten(&node.children, Some(idx), depth + 1, out);
+    }
+}
+
+/// Bytes the line of `entry` takes, plus the `+N more` line reserved
+/// below a directory.
+fn cost(entry: &Flat<'_>) -> usize {
+    let node = entry.node;
+    let notes = node.description.as_ref().map_or(0, |d| d.len() + 4)
+        + node.decoration.as_ref().map_or(0, |d| d.len() + 2);
+    let line = 4 * entry.depth + node.name.len() + 1 + notes + 1;
+    if node.kind == EntryKind::Directory {
+        line + 4 * (entry.depth + 1) + MORE_LINE
+    } else {
+        line
+    }
+}
+
+/// The kept ones of `nodes`, whose first pre‑order index is `*next`, and
+/// the number left out.
+fn keep(nodes: &[TreeNode], kept: &[bool], next: &mut usize) -> (Vec<TreeNode>, Option<usize>) {
+    let mut children = Vec::new();
+    for node in nodes {
+        let idx = *next;
+        *next += 1;
+        let (grandchildren, truncated) = keep(&node.children, kept, next);
+        if kept[idx] {
+            children.push(shallow(node, grandchildren, truncated));
+        }
+    }
+    let omitted = nodes.len() - children.len();
+    (children, (omitted > 0).then_some(omitted))
+}
+
+/// `node` without its subtree, given new `children` and `truncated` count.
+fn shallow(node: &TreeNode, children: Vec<TreeNode>, truncated: Option<usize>) -> TreeNode {
+    TreeNode {
+        name: node.name.clone(),
+        path: node.path.clone(),
+        path_base64: node.path_base64.clone(),
+        kind: node.kind,
+        size: node.size,
+        description: node.description.clone(),
+        decoration: node.decoration.clone(),
+        error: node.error.clone(),
+        truncated,
+        children,
+    }
+}
@@ -0,0 +1,140 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Rewriting of displayed paths for sharing trees publicly.
+//!
+//! `--strip-prefix /home/alice` turns the root line `/home/alice/src/app`
+//! into `src/app`, and `--redact 'acme-[a-z]+=<internal>'` replaces every
+//! match in the root and in each entry's name and path. The rewrite is
+//! applied wherever a path is shown — text, JSON, checksum lists and
+//! fixtures — but never to the paths the walk itself opens, nor to the
+//! targets of terminal hyperlinks.
+
+use crate::scan::TreeNode;
+use regex::Regex;
+use std::{
+    borrow::Cow,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Rewrites applied to every path shown in the output.
+#[derive(Debug, Clone, Default)]
+pub struct PathRedaction {
+    /// Leading directories removed from the root line; a root equal to the
+    /// prefix is shown as `.`.
+    pub strip_prefix: Option<PathBuf>,
+    /// Substitutions applied in order to the root line and to every entry's
+    /// name and relative path.
+    pub rules: Vec<Redaction>,
+}
+
+impl PathRedaction {
+    /// Whether nothing is rewritten.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.strip_prefix.is_none() && self.rules.is_empty()
+    }
+
+    /// `text` with every rule applied, borrowed when no rule matched.
+    #[must_use]
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        self.rules
+            .iter()
+            .fold(Cow::Borrowed(text), |text, rule| match text {
+                Cow::Borrowed(text) => rule.pattern.replace_all(text, &rule.replacement),
+                Cow::Owned(text) => Cow::Owned(
+                    rule.pattern
+                        .replace_all(&text, &rule.replacement)
+                        .into_owned(),
+                ),
+            })
+    }
+
+    /// Display form of the root directory `root`.
+    #[must_use]
+    pub fn root(&self, root: &Path) -> String {
+        let shown = match self.strip_prefix.as_deref().map(|p| root.strip_prefix(p)) {
+            Some(Ok(rest)) if rest.as_os_str().is_empty() => Path::new("."),
+            Some(Ok(rest)) => rest,
+            _ => root,
+        };
+        let shown = shown.display().to_string();
+        self.apply(&shown).into_owned()
+    }
+
+    /// Entry name or relative path `text` with the rules applied, together
+    /// with its lossless Base64 form, which is dropped when a rule matched
+    /// because it would reveal the original.
+    pub(crate) fn entry(&self, text: String, base64: Option<String>) -> (String, Option<String>) {
+        match self.apply(&text) {
+            Cow::Borrowed(_) => (text, base64),
+            Cow::Owned(redacted) => (redacted, None),
+        }
+    }
+
+    /// Copy of `tree` with the root line and every entry rewritten.
+    pub(crate) fn tree(&self, tree: &TreeNode) -> TreeNode {
+        let mut tree = tree.clone();
+        tree.name = self.root(Path::new(&tree.name));
+        let mut stack: Vec<&mut TreeNode> = tree.children.iter_mut().collect();
+        while let Some(node) = stack.pop() {
+            node.name = self.apply(&node.name).into_owned();
+            let path = std::mem::take(&mut node.path);
+            (node.path, node.path_base64) = self.entry(path, node.path_base64.take());
+            stack.extend(node.children.iter_mut());
+        }
+        tree
+    }
+}
+
+/// One `REGEX=REPLACEMENT` substitution.
+///
+/// The replacement may refer to capture groups as `$1` or `$name`.
+#[derive(Debug, Clone)]
+pub struct Redaction {
+    /// Pattern searched for.
+    pattern: Regex,
+    /// Text each match is replaced with.
+    replacement: String,
+}
+
+impl Redaction {
+    /// Substitution of `replacement` for every match of `pattern`.
+    ///
+    /// # Errors
+    /// Returns an error when `pattern` is not a valid regular expression.
+    pub fn new(pattern: &str, replacement: impl Into<String>) -> Result<Self, RedactionParseError> {
+        let pattern =
+            Regex::new(pattern).map_err(|e| RedactionParseError(format!("`{pattern}`: {e}")))?;
+        Ok(Self {
+            pattern,
+            replacement: replacement.into(),
+        })
+    }
+}
+
+/// Error returned for a malformed [`Redaction`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedactionParseError(String);
+
+impl fmt::Display for RedactionParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid redaction {}", self.0)
+    }
+}
+
+impl std::error::Error for RedactionParseError {}
+
+impl FromStr for Redaction {
+    type Err = RedactionParseError;
+
+    /// Parse `REGEX=REPLACEMENT`, split at the last `=`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pattern, replacement) = s
+            .rsplit_once('=')
+            .ok_or_else(|| RedactionParseError(format!("`{s}` (expected REGEX=REPLACEMENT)")))?;
+        Self::new(pattern, replacement)
+    }
+}
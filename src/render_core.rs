@@ -0,0 +1,477 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Dependency‑free tree layout.
+//!
+//! Everything here is pure string building over `core` and `alloc`: no
+//! filesystem, no I/O, no third‑party crates. Given entries in pre‑order as
+//! `(name, depth, is_last, is_dir)` it computes the connectors and prefixes
+//! of the classic `tree` look, so the layout can be reused for any
+//! hierarchy — and unit‑tested without touching a disk.
+//!
+//! ```rust
+//! use tree::render_core::{render_lines, Charset};
+//!
+//! let rows = [("src", 1, false, true), ("lib.rs", 2, true, false), ("README.md", 1, true, false)];
+//! let lines: Vec<String> = render_lines(rows, Charset::Unicode).collect();
+//! assert_eq!(lines, ["├── src/", "│   └── lib.rs", "└── README.md"]);
+//! ```
+
+use std::borrow::Cow;
+
+/// Glyphs used to draw the branches of the text tree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Charset {
+    /// Unicode box drawing: `├── `, `└── `, `│   `.
+    #[default]
+    Unicode,
+    /// Plain ASCII for terminals without Unicode: `|-- `, `` `-- ``, `|   `.
+    Ascii,
+}
+
+impl Charset {
+    /// Connector before a child that has later siblings.
+    #[must_use]
+    pub const fn branch(self) -> &'static str {
+        match self {
+            Self::Unicode => "├── ",
+            Self::Ascii => "|-- ",
+        }
+    }
+
+    /// Connector before the last child of a directory.
+    #[must_use]
+    pub const fn last_branch(self) -> &'static str {
+        match self {
+            Self::Unicode => "└── ",
+            Self::Ascii => "`-- ",
+        }
+    }
+
+    /// Prefix segment below an ancestor that has later siblings.
+    #[must_use]
+    pub const fn vertical(self) -> &'static str {
+        match self {
+            Self::Unicode => "│   ",
+            Self::Ascii => "|   ",
+        }
+    }
+
+    /// Prefix segment below an ancestor that was the last of its siblings.
+    #[must_use]
+    pub const fn blank(self) -> &'static str {
+        "    "
+    }
+
+    /// Marker for elided output.
+    #[must_use]
+    pub const fn ellipsis(self) -> &'static str {
+        match self {
+            Self::Unicode => "…",
+            Self::Ascii => "...",
+        }
+    }
+}
+
+/// Incremental layout state: remembers which ancestors still have siblings
+/// to come, so each entry's prefix can be computed on the fly.
+///
+/// The guide segments of the open levels live in one reusable buffer that
+/// is truncated and extended as the walk moves, so [`Layout::connector_str`]
+/// does not allocate once the buffer has grown to the deepest line.
+#[derive(Debug, Clone, Default)]
+pub struct Layout {
+    charset: Charset,
+    /// For each open ancestor level, whether more siblings follow it.
+    open: Vec<bool>,
+    /// Guide segments of the open levels, followed by the last connector.
+    line: String,
+    /// Byte offset in `line` where the segment of each drawn level ends.
+    ends: Vec<usize>,
+}
+
+impl Layout {
+    /// Start a layout drawn with `charset`.
+    #[must_use]
+    pub const fn new(charset: Charset) -> Self {
+        Self {
+            charset,
+            open: Vec::new(),
+            line: String::new(),
+            ends: Vec::new(),
+        }
+    }
+
+    /// Prefix plus connector for the next entry in pre‑order.
+    ///
+    /// `depth` is 1 for children of the root; `is_last` tells whether the
+    /// entry is the last of its siblings.
+    pub fn connector(&mut self, depth: usize, is_last: bool) -> String {
+        self.connector_str(depth, is_last).to_owned()
+    }
+
+    /// Like [`Layout::connector`], but borrows the line from the layout's
+    /// buffer instead of allocating a new one.
+    ///
+    /// Only the levels that changed since the previous entry are redrawn;
+    /// the borrow ends before the next entry is laid out.
+    pub fn connector_str(&mut self, depth: usize, is_last: bool) -> &str {
+        let parents = depth.saturating_sub(1);
+        self.open.resize(parents, false);
+        self.ends.truncate(parents);
+        self.line.truncate(self.ends.last().copied().unwrap_or(0));
+        for level in self.ends.len()..parents {
+            self.line.push_str(self.segment(self.open[level]));
+            self.ends.push(self.line.len());
+        }
+        self.line.push_str(if is_last {
+            self.charset.last_branch()
+        } else {
+            self.charset.branch()
+        });
+        self.open.push(!is_last);
+        &self.line
+    }
+
+    /// Like [`Layout::connector`], but passes every segment through `paint`
+    /// together with its level (0 for the guides of the root's children), so
+    /// callers can style the guide lines per depth.
+    pub fn connector_with<F>(&mut self, depth: usize, is_last: bool, paint: F) -> String
+    where
+        F: Fn(usize, &'static str) -> String,
+    {
+        let parents = depth.saturating_sub(1);
+        self.open.resize(parents, false);
+        self.ends.truncate(parents);
+        let mut line = String::with_capacity(4 * depth);
+        for (level, &more) in self.open.iter().enumerate() {
+            line.push_str(&paint(level, self.segment(more)));
+        }
+        let connector = if is_last {
+            self.charset.last_branch()
+        } else {
+            self.charset.branch()
+        };
+        line.push_str(&paint(self.open.len(), connector));
+        self.open.push(!is_last);
+        line
+    }
+
+    /// Guide segment below an ancestor, by whether more siblings follow it.
+    const fn segment(&self, more: bool) -> &'static str {
+        if more {
+            self.charset.vertical()
+        } else {
+            self.charset.blank()
+        }
+    }
+}
+
+/// Lay out pre‑order `(name, depth, is_last, is_dir)` rows as tree lines.
+///
+/// Directory names get a trailing `/`. Lines carry no newline.
+pub fn render_lines<N, I>(rows: I, charset: Charset) -> impl Iterator<Item = String>
+where
+    N: AsRef<str>,
+    I: IntoIterator<Item = (N, usize, bool, bool)>,
+{
+    let mut layout = Layout::new(charset);
+    rows.into_iter().map(move |(name, depth, is_last, is_dir)| {
+        let mut line = String::from(layout.connector_str(depth, is_last));
+        line.push_str(name.as_ref());
+        if is_dir {
+            line.push('/');
+        }
+        line
+    })
+}
+
+/// Metadata columns (sizes, times, modes, …) for the entries of one
+/// directory, each right‑aligned to its widest cell.
+///
+/// Measure every row first, then format each one; cells are bracketed and
+/// separated by two spaces.
+///
+/// ```rust
+/// use tree::render_core::Columns;
+///
+/// let rows = [["4.0K", "2 days ago"], ["12M", "now"]];
+/// let columns = Columns::measure(&rows);
+/// assert_eq!(columns.format(&rows[0]), "[4.0K]  [2 days ago]");
+/// assert_eq!(columns.format(&rows[1]), "[ 12M]  [       now]");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Columns {
+    widths: Vec<usize>,
+}
+
+impl Columns {
+    /// Widths fitting the widest cell of each column across `rows`.
+    pub fn measure<I, R, S>(rows: I) -> Self
+    where
+        I: IntoIterator<Item = R>,
+        R: AsRef<[S]>,
+        S: AsRef<str>,
+    {
+        let mut widths: Vec<usize> = Vec::new();
+        for row in rows {
+            for (i, cell) in row.as_ref().iter().enumerate() {
+                let width = display_width(cell.as_ref());
+                match widths.get_mut(i) {
+                    Some(max) => *max = (*max).max(width),
+                    None => widths.push(width),
+                }
+            }
+        }
+        Self { widths }
+    }
+
+    /// `row` as bracketed, right‑aligned cells; empty when there are no
+    /// columns.
+    #[must_use]
+    pub fn format<S: AsRef<str>>(&self, row: &[S]) -> String {
+        let mut line = String::new();
+        for (i, cell) in row.iter().enumerate() {
+            let cell = cell.as_ref();
+            let width = self.widths.get(i).copied().unwrap_or_default();
+            if i > 0 {
+                line.push_str("  ");
+            }
+            line.push('[');
+            line.extend(core::iter::repeat(' ').take(width.saturating_sub(display_width(cell))));
+            line.push_str(cell);
+            line.push(']');
+        }
+        line
+    }
+}
+
+/// Terminal columns taken by `text`: 2 for East Asian wide and fullwidth
+/// characters and emoji, 0 for combining marks and zero‑width formatting
+/// characters, 1 for everything else.
+///
+/// Escape sequences — colours and hyperlinks — take no columns.
+///
+/// Use it instead of `str::len` or `chars().count()` when padding columns
+/// that follow names, so CJK and emoji names keep the layout aligned. The
+/// tables cover the common wide and zero‑width blocks rather than every
+/// code point of the Unicode width property.
+///
+/// ```rust
+/// use tree::display_width;
+///
+/// assert_eq!(display_width("src"), 3);
+/// assert_eq!(display_width("文档"), 4);
+/// assert_eq!(display_width("cafe\u{301}"), 4);
+/// assert_eq!(display_width("🚀"), 2);
+/// ```
+#[must_use]
+pub fn display_width(text: &str) -> usize {
+    let mut chars = text.chars();
+    let mut width = 0;
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            width += char_width(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI, e.g. SGR colours: up to the final byte in `@`..=`~`.
+            Some('[') => {
+                chars.find(|c| ('@'..='~').contains(c));
+            }
+            // OSC, e.g. hyperlinks: up to BEL or the `ESC \` terminator.
+            Some(']') => {
+                chars.find(|&c| c == '\x07' || c == '\\');
+            }
+            _ => {}
+        }
+    }
+    width
+}
+
+/// Columns taken by one character; see [`display_width`].
+fn char_width(c: char) -> usize {
+    let in_table = |table: &[(char, char)]| {
+        table
+            .binary_search_by(|&(start, end)| {
+                if end < c {
+                    core::cmp::Ordering::Less
+                } else if start > c {
+                    core::cmp::Ordering::Greater
+                } else {
+                    core::cmp::Ordering::Equal
+                }
+            })
+            .is_ok()
+    };
+    if c.is_ascii() {
+        1
+    } else if in_table(ZERO_WIDTH) {
+        0
+    } else if in_table(WIDE) {
+        2
+    } else {
+        1
+    }
+}
+
+/// `text` with every bidirectional control character replaced by its
+/// visible `\u{…}` escape.
+///
+/// Overrides such as U+202E make a terminal draw the rest of the line right
+/// to left, so `invoice\u{202e}fdp.exe` shows as `invoiceexe.pdf`. Names
+/// printed for people pass through here unless
+/// [`TreeOptions::keep_bidi`](crate::TreeOptions::keep_bidi) is set.
+///
+/// ```rust
+/// use tree::neutralize_bidi;
+///
+/// assert_eq!(neutralize_bidi("invoice\u{202e}fdp.exe"), "invoice\\u{202e}fdp.exe");
+/// assert_eq!(neutralize_bidi("שלום.txt"), "שלום.txt");
+/// ```
+#[must_use]
+pub fn neutralize_bidi(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(is_bidi_control) {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        if is_bidi_control(c) {
+            out.extend(c.escape_unicode());
+        } else {
+            out.push(c);
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Embedding, override, isolate and mark characters that change the
+/// direction of the text around them.
+const fn is_bidi_control(c: char) -> bool {
+    matches!(
+        c,
+        '\u{061c}' | '\u{200e}' | '\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}'
+    )
+}
+
+/// Combining marks and zero‑width formatting characters, sorted.
+const ZERO_WIDTH: &[(char, char)] = &[
+    ('\u{300}', '\u{36f}'),
+    ('\u{483}', '\u{489}'),
+    ('\u{591}', '\u{5bd}'),
+    ('\u{610}', '\u{61a}'),
+    ('\u{64b}', '\u{65f}'),
+    ('\u{900}', '\u{902}'),
+    ('\u{93c}', '\u{93c}'),
+    ('\u{941}', '\u{948}'),
+    ('\u{94d}', '\u{94d}'),
+    ('\u{e31}', '\u{e31}'),
+    ('\u{e34}', '\u{e3a}'),
+    ('\u{e47}', '\u{e4e}'),
+    ('\u{1ab0}', '\u{1aff}'),
+    ('\u{1dc0}', '\u{1dff}'),
+    ('\u{200b}', '\u{200f}'),
+    ('\u{202a}', '\u{202e}'),
+    ('\u{2060}', '\u{2064}'),
+    ('\u{20d0}', '\u{20ff}'),
+    ('\u{302a}', '\u{302d}'),
+    ('\u{3099}', '\u{309a}'),
+    ('\u{fe00}', '\u{fe0f}'),
+    ('\u{fe20}', '\u{fe2f}'),
+    ('\u{feff}', '\u{feff}'),
+    ('\u{1f3fb}', '\u{1f3ff}'),
+    ('\u{e0000}', '\u{e0fff}'),
+];
+
+/// East Asian wide and fullwidth characters and emoji, sorted.
+const WIDE: &[(char, char)] = &[
+    ('\u{1100}', '\u{115f}'),
+    ('\u{231a}', '\u{231b}'),
+    ('\u{2329}', '\u{232a}'),
+    ('\u{23e9}', '\u{23ec}'),
+    ('\u{23f0}', '\u{23f0}'),
+    ('\u{23f3}', '\u{23f3}'),
+    ('\u{25fd}', '\u{25fe}'),
+    ('\u{2614}', '\u{2615}'),
+    ('\u{2648}', '\u{2653}'),
+    ('\u{267f}', '\u{267f}'),
+    ('\u{2693}', '\u{2693}'),
+    ('\u{26a1}', '\u{26a1}'),
+    ('\u{26aa}', '\u{26ab}'),
+    ('\u{26bd}', '\u{26be}'),
+    ('\u{26c4}', '\u{26c5}'),
+    ('\u{26ce}', '\u{26ce}'),
+    ('\u{26d4}', '\u{26d4}'),
+    ('\u{26ea}', '\u{26ea}'),
+    ('\u{26f2}', '\u{26f3}'),
+    ('\u{26f5}', '\u{26f5}'),
+    ('\u{26fa}', '\u{26fa}'),
+    ('\u{26fd}', '\u{26fd}'),
+    ('\u{2705}', '\u{2705}'),
+    ('\u{270a}', '\u{270b}'),
+    ('\u{2728}', '\u{2728}'),
+    ('\u{274c}', '\u{274c}'),
+    ('\u{274e}', '\u{274e}'),
+    ('\u{2753}', '\u{2755}'),
+    ('\u{2757}', '\u{2757}'),
+    ('\u{2795}', '\u{2797}'),
+    ('\u{27b0}', '\u{27b0}'),
+    ('\u{27bf}', '\u{27bf}'),
+    ('\u{2b1b}', '\u{2b1c}'),
+    ('\u{2b50}', '\u{2b50}'),
+    ('\u{2b55}', '\u{2b55}'),
+    ('\u{2e80}', '\u{3029}'),
+    ('\u{302e}', '\u{303e}'),
+    ('\u{3041}', '\u{3098}'),
+    ('\u{309b}', '\u{4dbf}'),
+    ('\u{4e00}', '\u{a4cf}'),
+    ('\u{a960}', '\u{a97f}'),
+    ('\u{ac00}', '\u{d7a3}'),
+    ('\u{f900}', '\u{faff}'),
+    ('\u{fe10}', '\u{fe19}'),
+    ('\u{fe30}', '\u{fe6f}'),
+    ('\u{ff00}', '\u{ff60}'),
+    ('\u{ffe0}', '\u{ffe6}'),
+    ('\u{16fe0}', '\u{16fe4}'),
+    ('\u{17000}', '\u{18cff}'),
+    ('\u{1b000}', '\u{1b2ff}'),
+    ('\u{1f004}', '\u{1f004}'),
+    ('\u{1f0cf}', '\u{1f0cf}'),
+    ('\u{1f18e}', '\u{1f18e}'),
+    ('\u{1f191}', '\u{1f19a}'),
+    ('\u{1f200}', '\u{1f2ff}'),
+    ('\u{1f300}', '\u{1f320}'),
+    ('\u{1f32d}', '\u{1f335}'),
+    ('\u{1f337}', '\u{1f37c}'),
+    ('\u{1f37e}', '\u{1f393}'),
+    ('\u{1f3a0}', '\u{1f3ca}'),
+    ('\u{1f3cf}', '\u{1f3d3}'),
+    ('\u{1f3e0}', '\u{1f3f0}'),
+    ('\u{1f3f4}', '\u{1f3f4}'),
+    ('\u{1f3f8}', '\u{1f3fa}'),
+    ('\u{1f400}', '\u{1f43e}'),
+    ('\u{1f440}', '\u{1f440}'),
+    ('\u{1f442}', '\u{1f4fc}'),
+    ('\u{1f4ff}', '\u{1f53d}'),
+    ('\u{1f54b}', '\u{1f54e}'),
+    ('\u{1f550}', '\u{1f567}'),
+    ('\u{1f57a}', '\u{1f57a}'),
+    ('\u{1f595}', '\u{1f596}'),
+    ('\u{1f5a4}', '\u{1f5a4}'),
+    ('\u{1f5fb}', '\u{1f64f}'),
+    ('\u{1f680}', '\u{1f6c5}'),
+    ('\u{1f6cc}', '\u{1f6cc}'),
+    ('\u{1f6d0}', '\u{1f6d2}'),
+    ('\u{1f6d5}', '\u{1f6d7}'),
+    ('\u{1f6dc}', '\u{1f6df}'),
+    ('\u{1f6eb}', '\u{1f6ec}'),
+    ('\u{1f6f4}', '\u{1f6fc}'),
+    ('\u{1f7e0}', '\u{1f7eb}'),
+    ('\u{1f7f0}', '\u{1f7f0}'),
+    ('\u{1f90c}', '\u{1f93a}'),
+    ('\u{1f93c}', '\u{1f945}'),
+    ('\u{1f947}', '\u{1f9ff}'),
+    ('\u{1fa70}', '\u{1faff}'),
+    ('\u{20000}', '\u{2fffd}'),
+    ('\u{30000}', '\u{3fffd}'),
+];
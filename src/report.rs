@@ -0,0 +1,106 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Report header printed above the text tree with `--report-header`.
+//!
+//! The header records where and when the tree was taken, which filters were
+//! in effect and what the tree contains, so a saved listing can stand on its
+//! own as evidence or as a ticket attachment.
+
+use crate::{
+    options::{AccessFilter, Traversal, TreeOptions},
+    scan::scan_tree,
+    timefmt::utc_timestamp,
+    tree_printer::{load_ignore_set, TOOL_IGNORE_FILES},
+};
+use anyhow::Result;
+use std::{fs, io::Write, path::Path, time::SystemTime};
+
+/// Write the header block for `root`, followed by a blank line.
+///
+/// # Errors
+/// Returns an error when the tree cannot be scanned or the writer fails.
+pub fn write_header<W: Write>(root: &Path, options: &TreeOptions, writer: &mut W) -> Result<()> {
+    let absolute = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    // Count files even when only directories are displayed.
+    let counting = TreeOptions {
+        show_files: true,
+        ..options.clone()
+    };
+    let stats = scan_tree(root, &counting)?.stats();
+
+    writeln!(writer, "root:     {}", options.redaction.root(&absolute))?;
+    if let Some(device) = device_id(root) {
+        writeln!(writer, "device:   {device}")?;
+    }
+    writeln!(writer, "scanned:  {}", utc_timestamp(SystemTime::now()))?;
+    writeln!(writer, "filters:  {}", filter_summary(root, options)?)?;
+    let count = |n| options.numeric_locale.unwrap_or_default().count(n);
+    writeln!(
+        writer,
+        "totals:   {} directories, {} files, {} symlinks, {} bytes",
+        count(stats.directories),
+        count(stats.files),
+        count(stats.symlinks),
+        count(stats.bytes)
+    )?;
+    if stats.errors > 0 {
+        writeln!(writer, "errors:   {}", count(stats.errors))?;
+    }
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Comma‑separated list of the filters applied to the listing.
+fn filter_summary(root: &Path, options: &TreeOptions) -> Result<String> {
+    let patterns = load_ignore_set(root, options)?.len();
+    let mut filters = vec![".gitignore".to_owned()];
+    if options.dot_ignore {
+        filters.push(".ignore".to_owned());
+    }
+    if options.tool_ignores {
+        filters.extend(TOOL_IGNORE_FILES.map(ToOwned::to_owned));
+    }
+    if options.export_ignore {
+        filters.push(".gitattributes export-ignore".to_owned());
+    }
+    filters.push(format!(".tree_ignore ({patterns} patterns)"));
+    if !options.show_files {
+        filters.push("directories only".to_owned());
+    }
+    if let Some(owner) = &options.owner {
+        filters.push(owner.to_string());
+    }
+    match options.access {
+        Some(AccessFilter::Writable) => filters.push("writable only".to_owned()),
+        Some(AccessFilter::ReadOnly) => filters.push("read-only only".to_owned()),
+        None => {}
+    }
+    if let Some(expr) = &options.filter_expr {
+        filters.push(format!("filter {expr}"));
+    }
+    if options.filter.is_some() {
+        filters.push("custom filter".to_owned());
+    }
+    if let Some(limit) = options.max_lines {
+        let order = match options.traversal {
+            Traversal::DepthFirst => "depth-first",
+            Traversal::BreadthFirst => "breadth-first",
+        };
+        filters.push(format!("first {limit} lines ({order})"));
+    }
+    Ok(filters.join(", "))
+}
+
+/// Identifier of the filesystem device holding `path`.
+#[cfg(unix)]
+fn device_id(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|m| m.dev())
+}
+
+/// Device identifiers are not exposed outside Unix.
+#[cfg(not(unix))]
+const fn device_id(_path: &Path) -> Option<u64> {
+    None
+}
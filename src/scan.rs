@@ -0,0 +1,592 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! In‑memory scan of a directory tree.
+//!
+//! The streaming printer never materialises the tree, which is ideal for the
+//! default text output. Structured outputs (JSON, CSV statistics, export
+//! archives) need the whole hierarchy at once, so this module walks the same
+//! filtered view — `.gitignore` plus `.tree_ignore` — into a [`TreeNode`].
+
+use crate::{
+    annotate::directory_description,
+    du::inode_id,
+    entry::EntryInfo,
+    options::{PathEncoding, TreeOptions},
+    parallel::map_ordered,
+    tree_printer::{collect_children, load_ignore_set, IgnoreSet},
+    TreeError,
+};
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use ignore::DirEntry;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt::Write as _,
+    fs,
+    path::Path,
+    sync::Mutex,
+    vec,
+};
+
+/// Kind of filesystem entry recorded in a [`TreeNode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    /// A directory (or a symbolic link resolving to one).
+    Directory,
+    /// A regular file.
+    File,
+    /// A symbolic link that does not resolve to a directory.
+    Symlink,
+    /// A named pipe. Opening one can block, so it is never read.
+    Fifo,
+    /// A Unix domain socket.
+    Socket,
+    /// A block device.
+    #[serde(rename = "block-device")]
+    BlockDevice,
+    /// A character device.
+    #[serde(rename = "char-device")]
+    CharDevice,
+}
+
+impl EntryKind {
+    /// Whether this is a FIFO, socket or device file: classified from the
+    /// directory entry alone and never opened, hashed or previewed.
+    #[must_use]
+    pub const fn is_special(self) -> bool {
+        matches!(
+            self,
+            Self::Fifo | Self::Socket | Self::BlockDevice | Self::CharDevice
+        )
+    }
+
+    /// Marker written after the name in text output: `/` for directories,
+    /// `|` for FIFOs and `=` for sockets as `ls -F` writes them, a note for
+    /// devices, nothing for files and symlinks.
+    #[must_use]
+    pub const fn indicator(self) -> &'static str {
+        match self {
+            Self::Directory => "/",
+            Self::File | Self::Symlink => "",
+            Self::Fifo => "|",
+            Self::Socket => "=",
+            Self::BlockDevice => " [block device]",
+            Self::CharDevice => " [char device]",
+        }
+    }
+}
+
+/// One entry of a scanned directory tree.
+///
+/// The root node carries the root path as its `name` and an empty `path`;
+/// every other node stores its path relative to the root using `/` as the
+/// separator so the serialised form is identical on every platform.
+///
+/// How names that are not valid UTF‑8 end up in `name` and `path` depends on
+/// [`TreeOptions::path_encoding`]; with [`PathEncoding::Base64`] the exact
+/// bytes are preserved in `path_base64`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreeNode {
+    /// File name of the entry (the displayed root path for the root node).
+    pub name: String,
+    /// Path relative to the scanned root, `/`‑separated.
+    pub path: String,
+    /// Base64 of the raw relative path bytes, present only when the path is
+    /// not valid UTF‑8 and [`PathEncoding::Base64`] is selected.
+    ///
+    /// The bytes are the platform's `OsStr` encoding: the raw bytes on Unix
+    /// and WTF‑8 on Windows.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_base64: Option<String>,
+    /// What kind of entry this is.
+    #[serde(rename = "type")]
+    pub kind: EntryKind,
+    /// Size in bytes for files; `None` for directories and symlinks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Directory description from [`TreeOptions::annotate_from`], if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Annotation from the [`TreeOptions::decorate`] hook, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decoration: Option<String>,
+    /// Why the entry could not be read (e.g. `permission denied`), if it
+    /// could not; see [`TreeOptions::show_errors`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Number of children left out of `children` by a size‑bounded scan
+    /// such as [`crate::render_json_limited`]; `None` when none were.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncated: Option<usize>,
+    /// Children in display order, as chosen by [`TreeOptions::sort`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Self>,
+}
+
+/// Aggregate counters over a scanned tree (the root itself is not counted).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TreeStats {
+    /// Number of directories below the root.
+    pub directories: u64,
+    /// Number of regular files.
+    pub files: u64,
+    /// Number of symbolic links that are not directories.
+    pub symlinks: u64,
+    /// Number of FIFOs, sockets and device files.
+    pub special: u64,
+    /// Sum of all file sizes in bytes.
+    pub bytes: u64,
+    /// Number of entries that could not be read.
+    pub errors: u64,
+}
+
+impl TreeNode {
+    /// Compute aggregate statistics for everything below this node.
+    #[must_use]
+    pub fn stats(&self) -> TreeStats {
+        let mut stats = TreeStats::default();
+        let mut pending = vec![self];
+        while let Some(node) = pending.pop() {
+            for child in &node.children {
+                match child.kind {
+                    EntryKind::Directory => stats.directories += 1,
+                    EntryKind::File => stats.files += 1,
+                    EntryKind::Symlink => stats.symlinks += 1,
+                    _ => stats.special += 1,
+                }
+                stats.bytes += child.size.unwrap_or(0);
+                stats.errors += u64::from(child.error.is_some());
+                pending.push(child);
+            }
+        }
+        stats
+    }
+}
+
+impl TreeStats {
+    /// Add the counters of `other` to these.
+    fn add(&mut self, other: Self) {
+        self.directories += other.directories;
+        self.files += other.files;
+        self.symlinks += other.symlinks;
+        self.special += other.special;
+        self.bytes += other.bytes;
+        self.errors += other.errors;
+    }
+}
+
+/// Scan `root` into a [`TreeNode`] using the same filtering as the printer.
+///
+/// # Errors
+/// Returns an error when the `.tree_ignore` file cannot be created or read,
+/// or when a directory exceeds [`TreeOptions::limits`].
+pub fn scan_tree(root: &Path, options: &TreeOptions) -> Result<TreeNode> {
+    let ignore_set = load_ignore_set(root, options)?;
+    let listing = collect_children(root, &ignore_set, options, 1);
+    Ok(TreeNode {
+        name: options.redaction.root(root),
+        path: String::new(),
+        path_base64: None,
+        kind: EntryKind::Directory,
+        size: None,
+        description: None,
+        decoration: None,
+        error: listing.error,
+        truncated: None,
+        children: scan_children(&listing.entries, &ignore_set, options)?,
+    })
+}
+
+/// Scan `root` like [`scan_tree`], keeping at most `max_entries` entries
+/// below the root.
+///
+/// Levels are filled breadth‑first, so a huge tree keeps its top levels and
+/// loses its deepest ones. Every directory that lost children records how
+/// many in [`TreeNode::truncated`]; directories beyond the budget are still
+/// listed once for that count but never descended into.
+///
+/// # Errors
+/// Returns an error when the `.tree_ignore` file cannot be created or read,
+/// or when a directory exceeds [`TreeOptions::limits`].
+pub fn scan_limited(root: &Path, max_entries: usize, options: &TreeOptions) -> Result<TreeNode> {
+    let ignore_set = load_ignore_set(root, options)?;
+    let listing = collect_children(root, &ignore_set, options, 1);
+    let mut tree = TreeNode {
+        name: options.redaction.root(root),
+        path: String::new(),
+        path_base64: None,
+        kind: EntryKind::Directory,
+        size: None,
+        description: None,
+        decoration: None,
+        error: listing.error,
+        truncated: None,
+        children: Vec::new(),
+    };
+
+    // Kept entries in breadth-first order with their parent's slot, `None`
+    // standing for the root; a parent always precedes its children.
+    let mut slots: Vec<(TreeNode, Option<usize>)> = Vec::new();
+    let mut queue = VecDeque::from([(None::<usize>, Vec::new(), listing.entries, 1, 0)]);
+    let mut left = max_entries;
+    while let Some((parent, rel, entries, depth, hops)) = queue.pop_front() {
+        let visible: Vec<_> = entries
+            .into_iter()
+            .filter(|(_, kind)| options.show_files || *kind == EntryKind::Directory)
+            .collect();
+        let kept = visible.len().min(left);
+        left -= kept;
+        if kept < visible.len() {
+            let dir = parent.map_or(&mut tree, |slot| &mut slots[slot].0);
+            dir.truncated = Some(visible.len() - kept);
+        }
+        for (child, kind) in visible.into_iter().take(kept) {
+            let (mut node, child_rel) = entry_node(&child, kind, &rel, options, depth);
+            if kind == EntryKind::Directory {
+                let hops = hops + usize::from(child.path_is_symlink());
+                options.limits.check(child.path(), depth + 1, hops)?;
+                options.check_cancelled()?;
+                let listing = collect_children(child.path(), &ignore_set, options, depth + 1);
+                node.error = listing.error;
+                queue.push_back((
+                    Some(slots.len()),
+                    child_rel,
+                    listing.entries,
+                    depth + 1,
+                    hops,
+                ));
+            }
+            slots.push((node, parent));
+        }
+    }
+
+    // Popping from the back completes every subtree before it is attached;
+    // children arrive last first and are put back in order.
+    while let Some((mut node, parent)) = slots.pop() {
+        node.children.reverse();
+        parent
+            .map_or(&mut tree, |slot| &mut slots[slot].0)
+            .children
+            .push(node);
+    }
+    tree.children.reverse();
+    Ok(tree)
+}
+
+/// Count the entries [`scan_tree`] would return for `root` without building
+/// any nodes: no names, paths or decorations are formatted, so totals of a
+/// large tree come much faster than from `scan_tree(..).stats()`.
+///
+/// With [`TreeOptions::dedupe`] a directory met again under another path,
+/// as through a bind mount, is counted only the first time.
+///
+/// # Errors
+/// Returns an error when the `.tree_ignore` file cannot be created or read,
+/// or when a directory exceeds [`TreeOptions::limits`].
+pub fn count_tree(root: &Path, options: &TreeOptions) -> Result<TreeStats> {
+    let ignore_set = load_ignore_set(root, options)?;
+    let listing = collect_children(root, &ignore_set, options, 1);
+    let seen = Mutex::new(HashSet::new());
+    first_visit(root, &seen);
+    let walk = CountWalk {
+        ignore_set: &ignore_set,
+        options,
+        seen: options.dedupe.then_some(&seen),
+    };
+    let mut stats = TreeStats::default();
+    for nested in map_ordered(&listing.entries, options.threads, |(child, kind)| {
+        count_entry(child, *kind, &walk)
+    }) {
+        stats.add(nested?);
+    }
+    Ok(stats)
+}
+
+/// What every step of [`count_tree`] shares.
+struct CountWalk<'a> {
+    ignore_set: &'a IgnoreSet,
+    options: &'a TreeOptions,
+    /// Identities of the directories counted so far, when deduplicating.
+    seen: Option<&'a Mutex<HashSet<(u64, u64)>>>,
+}
+
+/// Record the directory at `path` in `seen`; `false` when it was already
+/// there. Directories that cannot be identified always count as new.
+fn first_visit(path: &Path, seen: &Mutex<HashSet<(u64, u64)>>) -> bool {
+    let Some(id) = fs::metadata(path).ok().as_ref().and_then(inode_id) else {
+        return true;
+    };
+    seen.lock().map_or(true, |mut seen| seen.insert(id))
+}
+
+/// Totals for one child of the root and everything below it. The root's
+/// children are counted by up to [`TreeOptions::threads`] workers, like
+/// [`scan_children`].
+fn count_entry(
+    child: &DirEntry,
+    kind: EntryKind,
+    walk: &CountWalk<'_>,
+) -> Result<TreeStats, TreeError> {
+    let mut stats = TreeStats::default();
+    // Listings still being counted, innermost last.
+    let mut open = Vec::new();
+    open.extend(count_one(child, kind, walk, 1, 0, &mut stats)?);
+    while let Some((entries, depth, hops)) = open.last_mut() {
+        let (depth, hops) = (*depth, *hops);
+        match entries.next() {
+            Some((child, kind)) => {
+                open.extend(count_one(&child, kind, walk, depth, hops, &mut stats)?);
+            }
+            None => {
+                open.pop();
+            }
+        }
+    }
+    Ok(stats)
+}
+
+/// A listing still being counted, with the depth and symlink hops of its
+/// entries.
+type Counting = (vec::IntoIter<(DirEntry, EntryKind)>, usize, usize);
+
+/// Add one entry at `depth` to `stats`; for a directory, also return its
+/// listing with the depth and symlink hops of its entries.
+fn count_one(
+    child: &DirEntry,
+    kind: EntryKind,
+    walk: &CountWalk<'_>,
+    depth: usize,
+    hops: usize,
+    stats: &mut TreeStats,
+) -> Result<Option<Counting>, TreeError> {
+    let options = walk.options;
+    match kind {
+        EntryKind::Directory => {
+            if walk
+                .seen
+                .is_some_and(|seen| !first_visit(child.path(), seen))
+            {
+                return Ok(None);
+            }
+            let hops = hops + usize::from(child.path_is_symlink());
+            options.limits.check(child.path(), depth + 1, hops)?;
+            options.check_cancelled()?;
+            let listing = collect_children(child.path(), walk.ignore_set, options, depth + 1);
+            stats.directories += 1;
+            stats.errors += u64::from(listing.error.is_some());
+            return Ok(Some((listing.entries.into_iter(), depth + 1, hops)));
+        }
+        _ if !options.show_files => {}
+        EntryKind::File => {
+            let (size, error) = file_size(child, options);
+            stats.files += 1;
+            stats.bytes += size.unwrap_or(0);
+            stats.errors += u64::from(error.is_some());
+        }
+        EntryKind::Symlink => stats.symlinks += 1,
+        _ => stats.special += 1,
+    }
+    Ok(None)
+}
+
+/// Build the nodes for the root's `children`.
+///
+/// The root's subtrees are scanned by up to [`TreeOptions::threads`]
+/// workers; each subtree is then walked by the worker that claimed it.
+fn scan_children(
+    children: &[(DirEntry, EntryKind)],
+    ignore_set: &IgnoreSet,
+    options: &TreeOptions,
+) -> Result<Vec<TreeNode>, TreeError> {
+    map_ordered(children, options.threads, |(child, kind)| {
+        scan_entry(child, *kind, ignore_set, options)
+    })
+    .into_iter()
+    .filter_map(Result::transpose)
+    .collect()
+}
+
+/// Build the node for one child of the root and everything below it;
+/// `None` for files when only directories are shown.
+///
+/// The directories being filled are kept on the heap, so the depth of the
+/// tree is bounded by [`TreeOptions::limits`] rather than the stack.
+fn scan_entry(
+    child: &DirEntry,
+    kind: EntryKind,
+    ignore_set: &IgnoreSet,
+    options: &TreeOptions,
+) -> Result<Option<TreeNode>, TreeError> {
+    if kind != EntryKind::Directory && !options.show_files {
+        return Ok(None);
+    }
+    let (node, rel) = entry_node(child, kind, &[], options, 1);
+    if kind != EntryKind::Directory {
+        return Ok(Some(node));
+    }
+    let mut open = vec![Scanning::open(child, node, rel, 1, 0, ignore_set, options)?];
+    while let Some(top) = open.last_mut() {
+        let Some((child, kind)) = top.entries.next() else {
+            let Some(done) = open.pop() else { break };
+            match open.last_mut() {
+                Some(parent) => parent.node.children.push(done.node),
+                None => return Ok(Some(done.node)),
+            }
+            continue;
+        };
+        if kind != EntryKind::Directory && !options.show_files {
+            continue;
+        }
+        let (node, rel) = entry_node(&child, kind, &top.rel, options, top.depth);
+        if kind == EntryKind::Directory {
+            let (depth, hops) = (top.depth, top.hops);
+            let dir = Scanning::open(&child, node, rel, depth, hops, ignore_set, options)?;
+            open.push(dir);
+        } else {
+            top.node.children.push(node);
+        }
+    }
+    Ok(None)
+}
+
+/// A directory being filled by [`scan_entry`].
+struct Scanning {
+    node: TreeNode,
+    /// Raw path of the directory relative to the root.
+    rel: Vec<u8>,
+    /// Depth of the children.
+    depth: usize,
+    /// Symlinked directories followed to reach the children.
+    hops: usize,
+    /// Children still to scan.
+    entries: vec::IntoIter<(DirEntry, EntryKind)>,
+}
+
+impl Scanning {
+    /// Check and list the directory `child` at `depth`, reached through
+    /// `hops` symlinked directories, whose node is `node`.
+    fn open(
+        child: &DirEntry,
+        mut node: TreeNode,
+        rel: Vec<u8>,
+        depth: usize,
+        hops: usize,
+        ignore_set: &IgnoreSet,
+        options: &TreeOptions,
+    ) -> Result<Self, TreeError> {
+        let hops = hops + usize::from(child.path_is_symlink());
+        options.limits.check(child.path(), depth + 1, hops)?;
+        options.check_cancelled()?;
+        let listing = collect_children(child.path(), ignore_set, options, depth + 1);
+        node.error = listing.error;
+        Ok(Self {
+            node,
+            rel,
+            depth: depth + 1,
+            hops,
+            entries: listing.entries.into_iter(),
+        })
+    }
+}
+
+/// The node for one child of a directory whose raw relative path is `rel`,
+/// without children, and the raw relative path of the child itself.
+pub fn entry_node(
+    child: &DirEntry,
+    kind: EntryKind,
+    rel: &[u8],
+    options: &TreeOptions,
+    depth: usize,
+) -> (TreeNode, Vec<u8>) {
+    let path = child.path();
+    let raw_name = child.file_name().as_encoded_bytes();
+    let mut child_rel = rel.to_vec();
+    if !child_rel.is_empty() {
+        child_rel.push(b'/');
+    }
+    child_rel.extend_from_slice(raw_name);
+
+    let (name, _) = display_path(raw_name, options);
+    let (rel_path, path_base64) = display_path(&child_rel, options);
+    let decoration = options.decoration(&EntryInfo {
+        path,
+        name: child.file_name(),
+        kind,
+        depth,
+    });
+    let (size, error) = match kind {
+        EntryKind::File => file_size(child, options),
+        _ => (None, None),
+    };
+    let description = match (kind, &options.annotate_from) {
+        (EntryKind::Directory, Some(file)) => directory_description(path, file),
+        _ => None,
+    };
+    let node = TreeNode {
+        name,
+        path: rel_path,
+        path_base64,
+        kind,
+        size,
+        description,
+        decoration,
+        error,
+        truncated: None,
+        children: Vec::new(),
+    };
+    (node, child_rel)
+}
+
+/// Size of a file entry, or the error note when it cannot be stat'ed.
+pub fn file_size(entry: &DirEntry, options: &TreeOptions) -> (Option<u64>, Option<String>) {
+    match options.stat(entry) {
+        Ok(metadata) => (Some(metadata.len()), None),
+        Err(err) => (None, options.error_note(&err)),
+    }
+}
+
+/// Turn raw path bytes into the display string plus the optional lossless
+/// Base64 side field, according to `encoding`.
+pub fn encode_path(raw: &[u8], encoding: PathEncoding) -> (String, Option<String>) {
+    match (std::str::from_utf8(raw), encoding) {
+        (Ok(text), PathEncoding::Escape) => (text.replace('\\', "\\\\"), None),
+        (Ok(text), _) => (text.to_owned(), None),
+        (Err(_), PathEncoding::Lossy) => (String::from_utf8_lossy(raw).into_owned(), None),
+        (Err(_), PathEncoding::Base64) => (
+            String::from_utf8_lossy(raw).into_owned(),
+            Some(STANDARD.encode(raw)),
+        ),
+        (Err(_), PathEncoding::Escape) => (escape_bytes(raw), None),
+    }
+}
+
+/// [`encode_path`] followed by the [`TreeOptions::redaction`] rules.
+pub fn display_path(raw: &[u8], options: &TreeOptions) -> (String, Option<String>) {
+    let (text, base64) = encode_path(raw, options.path_encoding);
+    options.redaction.entry(text, base64)
+}
+
+/// Escape `raw` so that every byte survives: backslashes are doubled and
+/// bytes that are not part of valid UTF‑8 become `\xNN`.
+fn escape_bytes(mut raw: &[u8]) -> String {
+    let mut out = String::with_capacity(raw.len());
+    while !raw.is_empty() {
+        let (valid, invalid) = match std::str::from_utf8(raw) {
+            Ok(text) => (text, 0),
+            Err(e) => (
+                std::str::from_utf8(&raw[..e.valid_up_to()]).unwrap_or_default(),
+                e.error_len().unwrap_or_else(|| raw.len() - e.valid_up_to()),
+            ),
+        };
+        out.push_str(&valid.replace('\\', "\\\\"));
+        let end = valid.len() + invalid;
+        for byte in &raw[valid.len()..end] {
+            let _ = write!(out, "\\x{byte:02X}");
+        }
+        raw = &raw[end..];
+    }
+    out
+}
@@ -0,0 +1,65 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Depth and fan‑out statistics of a scanned tree.
+//!
+//! `tree --shape-stats` answers "how deep and how wide is this?" before a
+//! depth limit is picked: how many entries sit at each depth, how many
+//! children a directory holds on average, and which path reaches deepest.
+//! Pathological layouts — one enormous flat directory, a chain nested a
+//! hundred levels down — stand out at a glance.
+
+use crate::scan::{EntryKind, TreeNode};
+
+/// Shape of a tree, computed by [`ShapeStats::of`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShapeStats {
+    /// Entries per depth: index 0 counts the root's children (depth 1).
+    pub per_depth: Vec<u64>,
+    /// Directories, the root included.
+    pub directories: u64,
+    /// Mean number of children per directory, the root included.
+    pub average_fanout: f64,
+    /// Path of the first entry at the greatest depth, relative to the root;
+    /// empty for an empty tree.
+    pub deepest_path: String,
+}
+
+impl ShapeStats {
+    /// Measure everything below `tree`.
+    #[must_use]
+    pub fn of(tree: &TreeNode) -> Self {
+        let mut stats = Self {
+            directories: 1,
+            ..Self::default()
+        };
+        stats.visit(&tree.children, 1);
+        let entries: u64 = stats.per_depth.iter().sum();
+        #[allow(clippy::cast_precision_loss)] // display only
+        {
+            stats.average_fanout = entries as f64 / stats.directories as f64;
+        }
+        stats
+    }
+
+    /// Deepest level holding an entry; 0 for an empty tree.
+    #[must_use]
+    pub fn max_depth(&self) -> usize {
+        self.per_depth.len()
+    }
+
+    /// Count `children` at `depth` and everything below them.
+    fn visit(&mut self, children: &[TreeNode], depth: usize) {
+        for child in children {
+            if self.per_depth.len() < depth {
+                self.per_depth.push(0);
+                self.deepest_path.clone_from(&child.path);
+            }
+            self.per_depth[depth - 1] += 1;
+            if child.kind == EntryKind::Directory {
+                self.directories += 1;
+                self.visit(&child.children, depth + 1);
+            }
+        }
+    }
+}
@@ -0,0 +1,626 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Compact binary snapshots of a directory tree.
+//!
+//! A snapshot records every entry's raw name, type, size and modification
+//! time, and optionally the SHA‑256 of every file together with the Merkle
+//! digest of every directory. Taking a new snapshot from a previous one
+//! re‑hashes only files whose size or mtime changed, so repeated snapshots
+//! on CI cost little more than a directory walk, and comparing two hashed
+//! snapshots skips every subtree whose directory digest is unchanged.
+//!
+//! # Format (version 2)
+//!
+//! All integers are little‑endian.
+//!
+//! | Field        | Type        | Notes                                   |
+//! |--------------|-------------|-----------------------------------------|
+//! | magic        | `[u8; 8]`   | `TREESNAP`                              |
+//! | version      | `u16`       | `2` with bit 1 of the flags, else `1`;  |
+//! |              |             | readers reject newer versions           |
+//! | flags        | `u16`       | bit 0: file hashes present              |
+//! |              |             | bit 1: directory digests present        |
+//! | reserved     | `u32`       | `0`                                     |
+//! | entry count  | `u64`       | including the root                      |
+//! | entries      | …           | pre‑order, root first                   |
+//!
+//! Each entry is: kind `u8` (0 directory, 1 file, 2 symlink), name length
+//! `u32` and the raw name bytes (empty for the root), size `u64`, mtime
+//! seconds `i64` and nanoseconds `u32` relative to the Unix epoch, child
+//! count `u32`, and — for files when bit 0 of the flags is set — the 32‑byte
+//! SHA‑256 digest, or — for directories, the root included, when bit 1 is
+//! set — the 32‑byte Merkle digest of its children, as `--hash-dirs` shows.
+//!
+//! A snapshot also serves as a recording of a scan: [`Snapshot::to_tree`]
+//! rebuilds the [`TreeNode`] it was taken from, so `tree --record` scans a
+//! slow filesystem once and `tree render` re‑renders the recording in any
+//! format later without touching the disk.
+
+use crate::{
+    diff::{Change, ChangeKind},
+    hash::{kind_code, link_digest, merkle_digest, sha256_digest},
+    options::{PathEncoding, TreeOptions},
+    scan::{encode_path, EntryKind, TreeNode},
+    tree_printer::{collect_children, load_ignore_set, IgnoreSet},
+    TreeError,
+};
+use anyhow::{anyhow, bail, Context};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Read, Write},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// File signature at the start of every snapshot.
+const MAGIC: &[u8; 8] = b"TREESNAP";
+/// Newest format version, written when directories carry digests.
+const VERSION: u16 = 2;
+/// Flag bit: file entries carry a SHA‑256 digest.
+const FLAG_HASHES: u16 = 1;
+/// Flag bit: directory entries carry a Merkle digest.
+const FLAG_DIR_HASHES: u16 = 2;
+/// Deepest nesting read back; far beyond real trees, yet shallow enough for
+/// the recursive rendering, diffing and dropping of the entries.
+const MAX_DEPTH: usize = 1024;
+
+/// A captured directory tree; see the [module docs](self) for the format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Whether file entries carry content hashes; directories then carry
+    /// Merkle digests too, unless the snapshot predates them.
+    pub hashes: bool,
+    /// The root directory; its name is empty.
+    pub root: SnapshotEntry,
+}
+
+/// One entry of a [`Snapshot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotEntry {
+    /// Raw name bytes (the platform's `OsStr` encoding).
+    pub name: Vec<u8>,
+    /// Entry type.
+    pub kind: EntryKind,
+    /// Size in bytes as reported by the filesystem.
+    pub size: u64,
+    /// Last modification time.
+    pub mtime: SystemTime,
+    /// SHA‑256 of the contents of a file, or Merkle digest of a
+    /// directory, in snapshots taken with hashes.
+    pub hash: Option<[u8; 32]>,
+    /// Children in display order.
+    pub children: Vec<Self>,
+}
+
+impl Snapshot {
+    /// Capture `root` with the filtering of `options`, hashing every file
+    /// when `hashes` is set.
+    ///
+    /// # Errors
+    /// Returns an error when the root cannot be read or a file cannot be
+    /// hashed.
+    pub fn capture(root: &Path, options: &TreeOptions, hashes: bool) -> Result<Self, TreeError> {
+        Self::walk(root, options, hashes, None)
+    }
+
+    /// Capture `root` again, reusing the hash of every file whose size and
+    /// mtime are unchanged since `self` was taken.
+    ///
+    /// # Errors
+    /// Returns an error when the root cannot be read or a file cannot be
+    /// hashed.
+    pub fn update(&self, root: &Path, options: &TreeOptions) -> Result<Self, TreeError> {
+        Self::walk(root, options, self.hashes, Some(&self.root))
+    }
+
+    /// Shared implementation of [`Self::capture`] and [`Self::update`].
+    fn walk(
+        root: &Path,
+        options: &TreeOptions,
+        hashes: bool,
+        previous: Option<&SnapshotEntry>,
+    ) -> Result<Self, TreeError> {
+        let ignore_set = load_ignore_set(root, options)?;
+        let metadata = fs::metadata(root)?;
+        let walker = Walker {
+            ignore_set: &ignore_set,
+            options,
+            hashes,
+        };
+        let (children, hash) = walker.children(root, 1, 0, previous)?;
+        Ok(Self {
+            hashes,
+            root: SnapshotEntry {
+                name: Vec::new(),
+                kind: EntryKind::Directory,
+                size: metadata.len(),
+                mtime: metadata.modified().unwrap_or(UNIX_EPOCH),
+                hash,
+                children,
+            },
+        })
+    }
+
+    /// Number of entries below the root.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        count(&self.root) - 1
+    }
+
+    /// Whether the snapshot holds no entries below the root.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.root.children.is_empty()
+    }
+
+    /// Serialise the snapshot in the binary format.
+    ///
+    /// # Errors
+    /// Returns an error when the writer fails.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), TreeError> {
+        let dir_hashes = self.root.hash.is_some();
+        let flags = match (self.hashes, dir_hashes) {
+            (true, true) => FLAG_HASHES | FLAG_DIR_HASHES,
+            (true, false) => FLAG_HASHES,
+            (false, _) => 0,
+        };
+        // Version 1 readers do not know directory digests.
+        let version: u16 = if dir_hashes { VERSION } else { 1 };
+        writer.write_all(MAGIC)?;
+        writer.write_all(&version.to_le_bytes())?;
+        writer.write_all(&flags.to_le_bytes())?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(&(count(&self.root) as u64).to_le_bytes())?;
+
+        let mut stack = vec![&self.root];
+        while let Some(entry) = stack.pop() {
+            write_entry(writer, entry, flags)?;
+            stack.extend(entry.children.iter().rev());
+        }
+        Ok(())
+    }
+
+    /// Parse a snapshot written by [`Self::write_to`].
+    ///
+    /// # Errors
+    /// Returns [`TreeError::Other`] for a bad signature, an unsupported
+    /// version, truncated data or entries nested more than 1024 levels
+    /// deep, and [`TreeError::Io`] when reading fails.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, TreeError> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(anyhow!("not a tree snapshot").into());
+        }
+        let version = u16::from_le_bytes(read_array(reader)?);
+        if version > VERSION {
+            return Err(
+                anyhow!("snapshot version {version} is newer than supported ({VERSION})").into(),
+            );
+        }
+        let flags = u16::from_le_bytes(read_array(reader)?);
+        let _reserved: [u8; 4] = read_array(reader)?;
+        let total = u64::from_le_bytes(read_array(reader)?);
+        let mut remaining = total;
+        let root =
+            read_entries(reader, flags, &mut remaining).context("reading snapshot entries")?;
+        if remaining != 0 {
+            return Err(anyhow!("snapshot declares {total} entries but holds fewer").into());
+        }
+        Ok(Self {
+            hashes: flags & FLAG_HASHES != 0,
+            root,
+        })
+    }
+
+    /// Write the snapshot to the file at `path`.
+    ///
+    /// # Errors
+    /// Returns an error when the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<(), TreeError> {
+        let mut writer = std::io::BufWriter::new(fs::File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read a snapshot from the file at `path`.
+    ///
+    /// # Errors
+    /// Returns an error when the file cannot be read or is not a snapshot.
+    pub fn load(path: &Path) -> Result<Self, TreeError> {
+        let mut reader = std::io::BufReader::new(fs::File::open(path)?);
+        Self::read_from(&mut reader)
+    }
+
+    /// Rebuild the scanned tree with the root named `name`, ready for
+    /// [`crate::render_node`]; names and paths are encoded per `encoding`.
+    ///
+    /// Files carry their recorded size. Descriptions, decorations and error
+    /// notes are not part of a snapshot and are left empty.
+    #[must_use]
+    pub fn to_tree(&self, name: &str, encoding: PathEncoding) -> TreeNode {
+        TreeNode {
+            name: name.to_owned(),
+            path: String::new(),
+            path_base64: None,
+            kind: EntryKind::Directory,
+            size: None,
+            description: None,
+            decoration: None,
+            error: None,
+            truncated: None,
+            children: tree_children(&self.root, &[], encoding),
+        }
+    }
+
+    /// Compare `self` (expected) with `newer` (actual).
+    ///
+    /// Entries differ when their type or size changed or, when both
+    /// snapshots carry hashes, when a file's content hash changed. A
+    /// directory whose Merkle digest is the same in both is not descended
+    /// into.
+    #[must_use]
+    pub fn diff(&self, newer: &Self) -> Vec<Change> {
+        let mut changes = Vec::new();
+        diff_children(&self.root, &newer.root, &[], &mut changes);
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        changes
+    }
+}
+
+/// Directory walk shared by capture and update.
+struct Walker<'a> {
+    ignore_set: &'a IgnoreSet,
+    options: &'a TreeOptions,
+    hashes: bool,
+}
+
+impl Walker<'_> {
+    /// Entries for the children of `dir`, reached through `hops` symlinked
+    /// directories, reusing hashes from `previous`, and the Merkle digest of
+    /// `dir` when hashing.
+    fn children(
+        &self,
+        dir: &Path,
+        depth: usize,
+        hops: usize,
+        previous: Option<&SnapshotEntry>,
+    ) -> Result<(Vec<SnapshotEntry>, Option<[u8; 32]>), TreeError> {
+        let known: HashMap<&[u8], &SnapshotEntry> = previous
+            .map(|p| p.children.iter().map(|c| (c.name.as_slice(), c)).collect())
+            .unwrap_or_default();
+
+        let mut entries = Vec::new();
+        let mut digests = Vec::new();
+        for (child, kind) in collect_children(dir, self.ignore_set, self.options, depth).entries {
+            if kind != EntryKind::Directory && !self.options.show_files {
+                continue;
+            }
+            let name = child.file_name().as_encoded_bytes().to_vec();
+            let before = known.get(name.as_slice()).copied();
+            let metadata = match kind {
+                EntryKind::Directory => fs::metadata(child.path()),
+                _ => fs::symlink_metadata(child.path()),
+            }?;
+            let size = metadata.len();
+            let mtime = metadata.modified().unwrap_or(UNIX_EPOCH);
+
+            let hash = if kind == EntryKind::File && self.hashes {
+                match before {
+                    Some(b)
+                        if b.kind == kind
+                            && b.size == size
+                            && b.mtime == mtime
+                            && b.hash.is_some() =>
+                    {
+                        b.hash
+                    }
+                    _ => Some(sha256_digest(
+                        child.path(),
+                        self.options.throttle.as_deref(),
+                    )?),
+                }
+            } else {
+                None
+            };
+            let (children, hash) = if kind == EntryKind::Directory {
+                let hops = hops + usize::from(child.path_is_symlink());
+                self.options.limits.check(child.path(), depth + 1, hops)?;
+                self.options.check_cancelled()?;
+                self.children(
+                    child.path(),
+                    depth + 1,
+                    hops,
+                    before.filter(|b| b.kind == kind),
+                )?
+            } else {
+                (Vec::new(), hash)
+            };
+            if self.hashes {
+                let digest = match kind {
+                    EntryKind::Symlink => link_digest(child.path()),
+                    _ => hash,
+                };
+                digests.push((name.clone(), kind, digest.unwrap_or_default()));
+            }
+            entries.push(SnapshotEntry {
+                name,
+                kind,
+                size,
+                mtime,
+                hash,
+                children,
+            });
+        }
+        let hash = self.hashes.then(|| merkle_digest(digests));
+        Ok((entries, hash))
+    }
+}
+
+/// Nodes for the children of `entry`, whose raw relative path is `rel`.
+fn tree_children(entry: &SnapshotEntry, rel: &[u8], encoding: PathEncoding) -> Vec<TreeNode> {
+    entry
+        .children
+        .iter()
+        .map(|child| {
+            let mut child_rel = rel.to_vec();
+            if !child_rel.is_empty() {
+                child_rel.push(b'/');
+            }
+            child_rel.extend_from_slice(&child.name);
+            let (name, _) = encode_path(&child.name, encoding);
+            let (path, path_base64) = encode_path(&child_rel, encoding);
+            TreeNode {
+                name,
+                path,
+                path_base64,
+                kind: child.kind,
+                size: (child.kind == EntryKind::File).then_some(child.size),
+                description: None,
+                decoration: None,
+                error: None,
+                truncated: None,
+                children: tree_children(child, &child_rel, encoding),
+            }
+        })
+        .collect()
+}
+
+/// Number of entries in the subtree rooted at `entry`, itself included.
+fn count(entry: &SnapshotEntry) -> usize {
+    1 + entry.children.iter().map(count).sum::<usize>()
+}
+
+/// Write a single entry header (children follow separately).
+fn write_entry<W: Write>(
+    writer: &mut W,
+    entry: &SnapshotEntry,
+    flags: u16,
+) -> Result<(), TreeError> {
+    let kind = kind_code(entry.kind);
+    let name_len = u32::try_from(entry.name.len()).context("name too long")?;
+    let children = u32::try_from(entry.children.len()).context("too many children")?;
+    let (secs, nanos) = match entry.mtime.duration_since(UNIX_EPOCH) {
+        Ok(d) => (
+            i64::try_from(d.as_secs()).unwrap_or(i64::MAX),
+            d.subsec_nanos(),
+        ),
+        Err(e) => {
+            let d = e.duration();
+            (
+                -i64::try_from(d.as_secs()).unwrap_or(i64::MAX),
+                d.subsec_nanos(),
+            )
+        }
+    };
+
+    writer.write_all(&[kind])?;
+    writer.write_all(&name_len.to_le_bytes())?;
+    writer.write_all(&entry.name)?;
+    writer.write_all(&entry.size.to_le_bytes())?;
+    writer.write_all(&secs.to_le_bytes())?;
+    writer.write_all(&nanos.to_le_bytes())?;
+    writer.write_all(&children.to_le_bytes())?;
+    if carries_hash(entry.kind, flags) {
+        writer.write_all(&entry.hash.unwrap_or_default())?;
+    }
+    Ok(())
+}
+
+/// Read the root entry and all entries below it, which follow in
+/// depth-first order.
+///
+/// The nesting comes from the file, so it is tracked on an explicit stack
+/// rather than by recursion.
+fn read_entries<R: Read>(
+    reader: &mut R,
+    flags: u16,
+    remaining: &mut u64,
+) -> anyhow::Result<SnapshotEntry> {
+    // Directories still expecting children, with how many are left.
+    let mut open: Vec<(SnapshotEntry, u32)> = Vec::new();
+    loop {
+        let (mut done, pending) = read_entry(reader, flags, remaining)?;
+        if pending > 0 {
+            if open.len() == MAX_DEPTH {
+                bail!("entries nested more than {MAX_DEPTH} levels deep");
+            }
+            open.push((done, pending));
+            continue;
+        }
+        // Hand the finished entry to its parent, closing every directory
+        // it was the last child of.
+        loop {
+            match open.pop() {
+                None => return Ok(done),
+                Some((mut parent, pending)) => {
+                    parent.children.push(done);
+                    if pending > 1 {
+                        open.push((parent, pending - 1));
+                        break;
+                    }
+                    done = parent;
+                }
+            }
+        }
+    }
+}
+
+/// Read one entry header, returning the entry without children and the
+/// number of children that follow it.
+fn read_entry<R: Read>(
+    reader: &mut R,
+    flags: u16,
+    remaining: &mut u64,
+) -> anyhow::Result<(SnapshotEntry, u32)> {
+    if *remaining == 0 {
+        bail!("more entries than declared");
+    }
+    *remaining -= 1;
+
+    let kind = match read_array::<1, _>(reader)?[0] {
+        0 => EntryKind::Directory,
+        1 => EntryKind::File,
+        2 => EntryKind::Symlink,
+        3 => EntryKind::Fifo,
+        4 => EntryKind::Socket,
+        5 => EntryKind::BlockDevice,
+        6 => EntryKind::CharDevice,
+        other => bail!("unknown entry kind {other}"),
+    };
+    let name_len = u32::from_le_bytes(read_array(reader)?) as usize;
+    let mut name = Vec::new();
+    reader.take(name_len as u64).read_to_end(&mut name)?;
+    if name.len() != name_len {
+        bail!("truncated entry name");
+    }
+    let size = u64::from_le_bytes(read_array(reader)?);
+    let secs = i64::from_le_bytes(read_array(reader)?);
+    let nanos = u32::from_le_bytes(read_array(reader)?);
+    let child_count = u32::from_le_bytes(read_array(reader)?);
+    let hash = if carries_hash(kind, flags) {
+        Some(read_array(reader)?)
+    } else {
+        None
+    };
+
+    let offset = Duration::new(secs.unsigned_abs(), nanos);
+    let mtime = if secs >= 0 {
+        UNIX_EPOCH + offset
+    } else {
+        UNIX_EPOCH - offset
+    };
+    let entry = SnapshotEntry {
+        name,
+        kind,
+        size,
+        mtime,
+        hash,
+        children: Vec::new(),
+    };
+    Ok((entry, child_count))
+}
+
+/// Whether an entry of `kind` is followed by a digest under `flags`.
+const fn carries_hash(kind: EntryKind, flags: u16) -> bool {
+    match kind {
+        EntryKind::File => flags & FLAG_HASHES != 0,
+        EntryKind::Directory => flags & FLAG_DIR_HASHES != 0,
+        _ => false,
+    }
+}
+
+/// Read exactly `N` bytes.
+fn read_array<const N: usize, R: Read>(reader: &mut R) -> std::io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Append the differences between the children of `before` and `after`,
+/// whose raw relative path is `rel`, to `changes`; directories with equal
+/// Merkle digests are compared but not descended into.
+fn diff_children(
+    before: &SnapshotEntry,
+    after: &SnapshotEntry,
+    rel: &[u8],
+    changes: &mut Vec<Change>,
+) {
+    let new: HashMap<&[u8], &SnapshotEntry> = after
+        .children
+        .iter()
+        .map(|c| (c.name.as_slice(), c))
+        .collect();
+    for old in &before.children {
+        let path = join(rel, &old.name);
+        let Some(&new) = new.get(old.name.as_slice()) else {
+            every(old, &path, ChangeKind::Removed, changes);
+            continue;
+        };
+        let detail = if old.kind != new.kind {
+            Some("type changed")
+        } else if old.size != new.size {
+            Some("size changed")
+        } else if old.kind == EntryKind::File
+            && old.hash.is_some()
+            && new.hash.is_some()
+            && old.hash != new.hash
+        {
+            Some("content changed")
+        } else {
+            None
+        };
+        if detail.is_some() {
+            changes.push(change(ChangeKind::Modified, &path, detail));
+        }
+        let unchanged =
+            old.kind == EntryKind::Directory && old.hash.is_some() && old.hash == new.hash;
+        if !unchanged {
+            diff_children(old, new, &path, changes);
+        }
+    }
+    let old: HashMap<&[u8], &SnapshotEntry> = before
+        .children
+        .iter()
+        .map(|c| (c.name.as_slice(), c))
+        .collect();
+    for entry in after
+        .children
+        .iter()
+        .filter(|c| !old.contains_key(c.name.as_slice()))
+    {
+        every(entry, &join(rel, &entry.name), ChangeKind::Added, changes);
+    }
+}
+
+/// Append a change of `kind` for `entry`, at `path`, and everything below it.
+fn every(entry: &SnapshotEntry, path: &[u8], kind: ChangeKind, changes: &mut Vec<Change>) {
+    changes.push(change(kind, path, None));
+    for child in &entry.children {
+        every(child, &join(path, &child.name), kind, changes);
+    }
+}
+
+/// `rel/name`, or `name` at the root.
+fn join(rel: &[u8], name: &[u8]) -> Vec<u8> {
+    if rel.is_empty() {
+        return name.to_vec();
+    }
+    let mut path = Vec::with_capacity(rel.len() + 1 + name.len());
+    path.extend_from_slice(rel);
+    path.push(b'/');
+    path.extend_from_slice(name);
+    path
+}
+
+/// Build a [`Change`] for a raw path.
+fn change(kind: ChangeKind, path: &[u8], detail: Option<&str>) -> Change {
+    Change {
+        kind,
+        path: String::from_utf8_lossy(path).into_owned(),
+        detail: detail.map(ToOwned::to_owned),
+    }
+}
@@ -0,0 +1,213 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Streaming JSON serializer.
+//!
+//! Produces exactly the document `serde_json::to_writer_pretty` (or, when
+//! minified, `serde_json::to_writer`) would write for the scanned
+//! [`TreeNode`](crate::TreeNode), but emits it while walking:
+//! an explicit stack holds one frame per open directory, so memory is bounded
+//! by depth × directory width instead of the size of the whole tree. Each
+//! node's own fields go through the `TreeNode` serde impl, so the two forms
+//! cannot drift apart.
+
+use crate::{
+    emitter::Emitter,
+    options::TreeOptions,
+    scan::{entry_node, EntryKind, TreeNode},
+    tree_printer::{collect_children, load_ignore_set, IgnoreSet},
+};
+use anyhow::Result;
+use ignore::DirEntry;
+use std::{io::Write, path::Path, vec::IntoIter};
+
+/// A directory whose children are still being written.
+struct Frame {
+    /// Remaining children, already filtered and sorted.
+    children: IntoIter<(DirEntry, EntryKind)>,
+    /// Raw relative path of the directory.
+    rel: Vec<u8>,
+    /// Depth of the children (1 for the root's).
+    depth: usize,
+    /// Symlinked directories followed to reach the children.
+    hops: usize,
+    /// Whether no child has been written yet.
+    first: bool,
+}
+
+/// Write the JSON document for `root` to `writer`, pretty or minified as
+/// [`TreeOptions::pretty`] says, without a trailing newline.
+///
+/// # Errors
+/// Returns an error when the `.tree_ignore` file cannot be created or read,
+/// a directory exceeds [`TreeOptions::limits`], or the writer fails.
+pub fn write_json<W: Write>(root: &Path, options: &TreeOptions, writer: &mut W) -> Result<()> {
+    let mut out = Emitter::new(writer, options.pretty);
+    write_tree(root, options, &mut out, 0)
+}
+
+/// Write one document holding the trees of several roots,
+/// `{"roots": [...]}`, without a trailing newline.
+///
+/// # Errors
+/// Returns an error when a `.tree_ignore` file cannot be created or read,
+/// or the writer fails.
+pub fn write_json_roots<W: Write, P: AsRef<Path>>(
+    roots: &[P],
+    options: &TreeOptions,
+    writer: &mut W,
+) -> Result<()> {
+    let mut out = Emitter::new(writer, options.pretty);
+    out.token("{")?;
+    out.line(1)?;
+    out.key("roots")?;
+    out.token("[")?;
+    for (index, root) in roots.iter().enumerate() {
+        if index > 0 {
+            out.token(",")?;
+        }
+        out.line(2)?;
+        write_tree(root.as_ref(), options, &mut out, 2)?;
+    }
+    if !roots.is_empty() {
+        out.line(1)?;
+    }
+    out.token("]")?;
+    out.line(0)?;
+    out.token("}")?;
+    Ok(())
+}
+
+/// Write the node of `root` and everything below it, with the root object
+/// at indent level `base`.
+fn write_tree<W: Write>(
+    root: &Path,
+    options: &TreeOptions,
+    out: &mut Emitter<'_, W>,
+    base: usize,
+) -> Result<()> {
+    let ignore_set = load_ignore_set(root, options)?;
+    let (children, error) = visible_children(root, &ignore_set, options, 1);
+    let node = TreeNode {
+        name: options.redaction.root(root),
+        path: String::new(),
+        path_base64: None,
+        kind: EntryKind::Directory,
+        size: None,
+        description: None,
+        decoration: None,
+        error,
+        truncated: None,
+        children: Vec::new(),
+    };
+    write_open(out, base, &node, !children.is_empty())?;
+    if children.is_empty() {
+        return Ok(());
+    }
+
+    let mut stack = vec![Frame {
+        children: children.into_iter(),
+        rel: Vec::new(),
+        depth: 1,
+        hops: 0,
+        first: true,
+    }];
+    loop {
+        // Children of the frame at stack height `h` sit at indent level
+        // `base + 2h`.
+        let level = base + 2 * stack.len();
+        let Some(frame) = stack.last_mut() else { break };
+        let Some((child, kind)) = frame.children.next() else {
+            stack.pop();
+            out.line(level - 1)?;
+            out.token("]")?;
+            out.line(level - 2)?;
+            out.token("}")?;
+            continue;
+        };
+        if !std::mem::replace(&mut frame.first, false) {
+            out.token(",")?;
+        }
+        out.line(level)?;
+
+        let (depth, hops) = (frame.depth, frame.hops);
+        let (mut node, child_rel) = entry_node(&child, kind, &frame.rel, options, depth);
+        if kind == EntryKind::Directory {
+            let path = child.path();
+            let hops = hops + usize::from(child.path_is_symlink());
+            options.limits.check(path, depth + 1, hops)?;
+            options.check_cancelled()?;
+            let (grandchildren, error) = visible_children(path, &ignore_set, options, depth + 1);
+            node.error = error;
+            write_open(out, level, &node, !grandchildren.is_empty())?;
+            if !grandchildren.is_empty() {
+                stack.push(Frame {
+                    children: grandchildren.into_iter(),
+                    rel: child_rel,
+                    depth: depth + 1,
+                    hops,
+                    first: true,
+                });
+            }
+        } else {
+            write_open(out, level, &node, false)?;
+        }
+    }
+    Ok(())
+}
+
+/// Children of `dir` that end up in the document: everything the printer
+/// would list, minus non‑directories when only directories are shown —
+/// plus the error hit while reading `dir`, if any.
+fn visible_children(
+    dir: &Path,
+    ignore_set: &IgnoreSet,
+    options: &TreeOptions,
+    depth: usize,
+) -> (Vec<(DirEntry, EntryKind)>, Option<String>) {
+    let mut listing = collect_children(dir, ignore_set, options, depth);
+    if !options.show_files {
+        listing
+            .entries
+            .retain(|(_, kind)| *kind == EntryKind::Directory);
+    }
+    (listing.entries, listing.error)
+}
+
+/// Write a childless `node`'s opening brace and fields as its serde impl
+/// lays them out; the node sits at indent `level` and the caller has
+/// already positioned the output there.
+///
+/// With `has_children` the `children` array is opened and left for the
+/// caller to close; otherwise the node is closed immediately.
+fn write_open<W: Write>(
+    out: &mut Emitter<'_, W>,
+    level: usize,
+    node: &TreeNode,
+    has_children: bool,
+) -> Result<()> {
+    let text = if out.pretty() {
+        serde_json::to_string_pretty(node)?
+    } else {
+        serde_json::to_string(node)?
+    };
+    // Everything but the closing brace, its lines re-indented to `level`;
+    // JSON escapes line breaks inside strings, so each line is one field.
+    let fields = text.strip_suffix('}').unwrap_or(&text).trim_end();
+    for (index, line) in fields.split('\n').enumerate() {
+        if index > 0 {
+            out.line(level)?;
+        }
+        out.token(line)?;
+    }
+    if has_children {
+        out.token(",")?;
+        out.line(level + 1)?;
+        out.key("children")?;
+        out.token("[")?;
+    } else {
+        out.line(level)?;
+        out.token("}")?;
+    }
+    Ok(())
+}
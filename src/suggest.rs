@@ -0,0 +1,73 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! First‑run ignore suggestions.
+//!
+//! Rather than writing the whole default template, a first run can look for
+//! well‑known bulky directories directly under the root and put only those
+//! in the new `.tree_ignore`.
+
+use crate::tree_printer::{count_entries, group_thousands};
+use std::{fmt, path::Path};
+
+/// Directory names worth suggesting when they are large.
+const WELL_KNOWN: &[&str] = &[
+    ".git",
+    "node_modules",
+    "target",
+    "vendor",
+    ".venv",
+    "__pycache__",
+    "dist",
+    "build",
+];
+
+/// Entry count above which a well‑known directory is suggested.
+pub const SUGGEST_THRESHOLD: usize = 1000;
+
+/// A directory proposed for `.tree_ignore`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoreSuggestion {
+    /// Directory name, used as the ignore pattern.
+    pub name: String,
+    /// Entries below it, counted recursively.
+    pub entries: usize,
+}
+
+impl fmt::Display for IgnoreSuggestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} entries)",
+            self.name,
+            group_thousands(self.entries)
+        )
+    }
+}
+
+/// Well‑known directories directly under `root` holding more than
+/// [`SUGGEST_THRESHOLD`] entries, in a fixed order.
+#[must_use]
+pub fn suggest_ignores(root: &Path) -> Vec<IgnoreSuggestion> {
+    WELL_KNOWN
+        .iter()
+        .filter(|name| root.join(name).is_dir())
+        .map(|name| IgnoreSuggestion {
+            name: (*name).to_owned(),
+            entries: count_entries(&root.join(name)),
+        })
+        .filter(|s| s.entries > SUGGEST_THRESHOLD)
+        .collect()
+}
+
+/// `.tree_ignore` contents listing exactly `patterns`.
+pub fn ignore_file_contents<S: AsRef<str>>(patterns: &[S]) -> String {
+    let mut contents = String::from(
+        "# Tree ignore patterns configuration file\n# Add one pattern per line (exact name matches only)\n\n",
+    );
+    for pattern in patterns {
+        contents.push_str(pattern.as_ref());
+        contents.push('\n');
+    }
+    contents
+}
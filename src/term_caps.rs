@@ -0,0 +1,63 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Terminal capability detection.
+//!
+//! Every feature that emits escape sequences or non‑ASCII glyphs — colour
+//! themes, hyperlinks, box‑drawing characters — asks [`TermCaps`] instead of
+//! inspecting the environment itself, so the conventions below are honoured
+//! consistently:
+//!
+//! * `NO_COLOR` (non‑empty) disables colour;
+//! * `CLICOLOR_FORCE` (non‑empty, not `0`) enables colour even when not
+//!   writing to a terminal;
+//! * `CLICOLOR=0` disables colour on terminals;
+//! * `TERM=dumb` disables colour, hyperlinks and Unicode box drawing on a
+//!   terminal; redirected output keeps Unicode since `TERM` describes the
+//!   terminal, not the file or pipe being written.
+
+use std::{env, ffi::OsString};
+
+/// What the output stream can render.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TermCaps {
+    /// ANSI colours and text attributes (bold, underline, …).
+    pub color: bool,
+    /// OSC 8 hyperlinks.
+    pub hyperlinks: bool,
+    /// Unicode glyphs such as box‑drawing characters.
+    pub unicode: bool,
+}
+
+impl TermCaps {
+    /// Detect the capabilities of an output stream from the process
+    /// environment; `is_terminal` tells whether the stream is a terminal.
+    #[must_use]
+    pub fn detect(is_terminal: bool) -> Self {
+        Self::from_env(is_terminal, |name| env::var_os(name))
+    }
+
+    /// Like [`TermCaps::detect`], but reading variables through `var`.
+    #[must_use]
+    pub fn from_env<F>(is_terminal: bool, var: F) -> Self
+    where
+        F: Fn(&str) -> Option<OsString>,
+    {
+        let set = |name: &str| var(name).filter(|v| !v.is_empty());
+        let dumb = set("TERM").is_some_and(|t| t == "dumb");
+
+        let color = if set("NO_COLOR").is_some() {
+            false
+        } else if set("CLICOLOR_FORCE").is_some_and(|v| v != "0") {
+            true
+        } else {
+            is_terminal && !dumb && set("CLICOLOR").map_or(true, |v| v != "0")
+        };
+
+        Self {
+            color,
+            hyperlinks: is_terminal && !dumb,
+            unicode: !(is_terminal && dumb),
+        }
+    }
+}
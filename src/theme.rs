@@ -0,0 +1,304 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Colour themes for the text renderer.
+//!
+//! A [`Theme`] maps each entry role to a [`Style`], which is nothing more than
+//! a pre‑computed ANSI SGR sequence. Styles are written as short specs such as
+//! `"bold blue"`, `"208"` or `"underline #ff8800"` so they can live in the
+//! configuration file.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{borrow::Cow, fmt, fmt::Write as _, str::FromStr};
+
+/// Terminal text style: a set of attributes plus an optional foreground colour.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Style {
+    /// SGR parameters joined by `;` (empty for the plain style).
+    sgr: String,
+}
+
+impl Style {
+    /// The style that leaves text untouched.
+    #[must_use]
+    pub const fn plain() -> Self {
+        Self { sgr: String::new() }
+    }
+
+    /// Whether this style changes nothing.
+    #[must_use]
+    pub fn is_plain(&self) -> bool {
+        self.sgr.is_empty()
+    }
+
+    /// Wrap `text` in this style's escape sequences.
+    #[must_use]
+    pub fn paint<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.is_plain() {
+            Cow::Borrowed(text)
+        } else {
+            Cow::Owned(format!("\x1b[{}m{text}\x1b[0m", self.sgr))
+        }
+    }
+}
+
+/// Error returned when a style spec cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StyleParseError(String);
+
+impl fmt::Display for StyleParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid style word `{}`", self.0)
+    }
+}
+
+impl std::error::Error for StyleParseError {}
+
+impl FromStr for Style {
+    type Err = StyleParseError;
+
+    /// Parse a whitespace‑separated style spec.
+    ///
+    /// Accepted words are the attributes `bold`, `dim`, `italic`,
+    /// `underline` and `reverse`; the colours `black`, `red`, `green`,
+    /// `yellow`, `blue`, `magenta`, `cyan`, `white` and their `bright-`
+    /// variants; a 256‑colour index (`0`–`255`); or a `#rrggbb` true colour.
+    /// `plain` (or an empty spec) yields the plain style.
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut codes = Vec::new();
+        for word in spec.split_whitespace() {
+            let lower = word.to_ascii_lowercase();
+            let code = match lower.as_str() {
+                "plain" | "none" => continue,
+                "bold" => "1".to_owned(),
+                "dim" => "2".to_owned(),
+                "italic" => "3".to_owned(),
+                "underline" => "4".to_owned(),
+                "reverse" => "7".to_owned(),
+                other => colour_code(other).ok_or_else(|| StyleParseError(word.to_owned()))?,
+            };
+            codes.push(code);
+        }
+        Ok(Self {
+            sgr: codes.join(";"),
+        })
+    }
+}
+
+impl fmt::Display for Style {
+    /// Write the canonical spec, which parses back to the same style
+    /// (`plain` for the plain style).
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_plain() {
+            return f.write_str("plain");
+        }
+        let mut words = Vec::new();
+        let mut codes = self.sgr.split(';');
+        while let Some(code) = codes.next() {
+            let word = match code {
+                "1" => "bold".to_owned(),
+                "2" => "dim".to_owned(),
+                "3" => "italic".to_owned(),
+                "4" => "underline".to_owned(),
+                "7" => "reverse".to_owned(),
+                "38" => match codes.next() {
+                    Some("5") => codes.next().unwrap_or_default().to_owned(),
+                    Some("2") => codes.by_ref().take(3).fold("#".to_owned(), |mut hex, c| {
+                        let _ = write!(hex, "{:02x}", c.parse::<u8>().unwrap_or_default());
+                        hex
+                    }),
+                    _ => continue,
+                },
+                other => match other.parse::<usize>() {
+                    Ok(n @ 30..=37) => COLOUR_NAMES[n - 30].to_owned(),
+                    Ok(n @ 90..=97) => format!("bright-{}", COLOUR_NAMES[n - 90]),
+                    _ => continue,
+                },
+            };
+            words.push(word);
+        }
+        f.write_str(&words.join(" "))
+    }
+}
+
+impl Serialize for Style {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Style {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let spec = String::deserialize(deserializer)?;
+        spec.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// The eight basic colour words, in SGR order.
+const COLOUR_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// SGR foreground code for a colour word, if it is one.
+fn colour_code(word: &str) -> Option<String> {
+    if let Some(idx) = COLOUR_NAMES.iter().position(|n| *n == word) {
+        return Some((30 + idx).to_string());
+    }
+    if let Some(idx) = word
+        .strip_prefix("bright-")
+        .and_then(|w| COLOUR_NAMES.iter().position(|n| *n == w))
+    {
+        return Some((90 + idx).to_string());
+    }
+    if let Ok(index) = word.parse::<u8>() {
+        return Some(format!("38;5;{index}"));
+    }
+    let hex = word.strip_prefix('#').filter(|h| h.len() == 6)?;
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(format!(
+        "38;2;{};{};{}",
+        channel(0)?,
+        channel(2)?,
+        channel(4)?
+    ))
+}
+
+/// Styles for every entry role the renderer distinguishes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Theme {
+    /// Directory names.
+    pub directory: Style,
+    /// Regular, non‑executable files.
+    pub file: Style,
+    /// Symbolic links.
+    pub symlink: Style,
+    /// Files with any execute bit set (Unix only).
+    pub executable: Style,
+    /// Entries that are shown although an ignore rule matched them.
+    pub ignored: Style,
+    /// Emphasis for matches and other highlighted entries.
+    pub highlight: Style,
+    /// Palette for the tree guide lines with `--level-colors`, cycled by
+    /// depth; empty leaves the guides plain.
+    pub levels: Vec<Style>,
+    /// Palette for entry names with `--age-colors`, newest first; spread
+    /// evenly over the age buckets, and empty leaves names in their role
+    /// style.
+    pub ages: Vec<Style>,
+    /// Palette for file names with `--size-colors`, smallest first; spread
+    /// like [`Self::ages`].
+    pub sizes: Vec<Style>,
+}
+
+impl Theme {
+    /// Names accepted by [`Theme::builtin`].
+    pub const BUILTIN: [&'static str; 3] = ["default", "high-contrast", "monochrome-bold"];
+
+    /// Look up one of the built‑in themes by name.
+    #[must_use]
+    pub fn builtin(name: &str) -> Option<Self> {
+        let (specs, levels, ages, sizes): ([&str; 6], &[&str], &[&str], &[&str]) = match name {
+            "default" => (
+                ["bold blue", "", "cyan", "bold green", "dim", "bold yellow"],
+                &["blue", "magenta", "cyan", "green", "yellow", "red"],
+                &["bold bright-green", "bright-green", "green", "", "dim"],
+                &["dim", "", "yellow", "bold yellow", "bold red"],
+            ),
+            "high-contrast" => (
+                [
+                    "bold bright-cyan",
+                    "bright-white",
+                    "bold bright-magenta",
+                    "bold bright-green",
+                    "white",
+                    "reverse bold bright-yellow",
+                ],
+                &[
+                    "bright-cyan",
+                    "bright-magenta",
+                    "bright-yellow",
+                    "bright-green",
+                ],
+                &[
+                    "reverse bold bright-yellow",
+                    "bold bright-yellow",
+                    "bright-white",
+                    "white",
+                ],
+                &[
+                    "white",
+                    "bright-white",
+                    "bold bright-yellow",
+                    "reverse bold bright-red",
+                ],
+            ),
+            "monochrome-bold" => (
+                ["bold", "", "italic", "bold underline", "dim", "reverse"],
+                &["", "dim"],
+                &["bold", "", "dim"],
+                &["dim", "", "bold", "reverse"],
+            ),
+            _ => return None,
+        };
+        let palette = |specs: &[&str]| {
+            specs
+                .iter()
+                .map(|s| s.parse().unwrap_or_default())
+                .collect()
+        };
+        Some(Self {
+            levels: palette(levels),
+            ages: palette(ages),
+            sizes: palette(sizes),
+            ..Self::from_specs(specs)
+        })
+    }
+
+    /// Style of the guide lines at `level` (0 for the root's children).
+    #[must_use]
+    pub fn level(&self, level: usize) -> Style {
+        if self.levels.is_empty() {
+            Style::plain()
+        } else {
+            self.levels[level % self.levels.len()].clone()
+        }
+    }
+
+    /// Style of a name in age bucket `bucket` of `buckets`, 0 being the
+    /// newest; `None` without an age palette.
+    #[must_use]
+    pub fn age(&self, bucket: usize, buckets: usize) -> Option<&Style> {
+        heat(&self.ages, bucket, buckets)
+    }
+
+    /// Style of a file name in size bucket `bucket` of `buckets`, 0 being
+    /// the smallest; `None` without a size palette.
+    #[must_use]
+    pub fn size(&self, bucket: usize, buckets: usize) -> Option<&Style> {
+        heat(&self.sizes, bucket, buckets)
+    }
+
+    /// Build a theme from six known‑good specs, in field order.
+    fn from_specs(specs: [&str; 6]) -> Self {
+        let [directory, file, symlink, executable, ignored, highlight] =
+            specs.map(|s| s.parse().unwrap_or_default());
+        Self {
+            directory,
+            file,
+            symlink,
+            executable,
+            ignored,
+            highlight,
+            levels: Vec::new(),
+            ages: Vec::new(),
+            sizes: Vec::new(),
+        }
+    }
+}
+
+/// Entry of `palette` for bucket `bucket` of `buckets`, spreading the
+/// palette evenly over the buckets.
+fn heat(palette: &[Style], bucket: usize, buckets: usize) -> Option<&Style> {
+    let idx = bucket.min(buckets.saturating_sub(1)) * palette.len() / buckets.max(1);
+    palette.get(idx)
+}
@@ -0,0 +1,183 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Rate‑limited I/O for scans of shared storage.
+//!
+//! A background manifest job walking a NAS export can saturate the
+//! appliance for everyone else. With
+//! [`TreeOptions::throttle`](crate::TreeOptions::throttle) set, every
+//! directory listing and metadata lookup (for a [`Rate::Ops`] limit) or
+//! every byte read while hashing (for a [`Rate::Bytes`] limit) draws from
+//! one token bucket shared by all worker threads. The bucket holds one
+//! second's worth of tokens, so short bursts run at full speed and a
+//! sustained scan settles at the configured rate.
+
+use crate::du::{human_size, parse_size};
+use std::{
+    fmt,
+    io::{self, Read},
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// A throughput limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rate {
+    /// Directory listings, metadata lookups and file opens per second.
+    Ops(u64),
+    /// Bytes read from files per second.
+    Bytes(u64),
+}
+
+impl fmt::Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ops(ops) => write!(f, "{ops} ops/s"),
+            Self::Bytes(bytes) => write!(f, "{}/s", human_size(*bytes)),
+        }
+    }
+}
+
+/// Error returned when a rate cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateParseError(String);
+
+impl fmt::Display for RateParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid rate `{}` (expected operations such as 200 or 200/s, or a size such as 10MB/s)",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for RateParseError {}
+
+impl FromStr for Rate {
+    type Err = RateParseError;
+
+    /// Parse `200`, `200/s` or `200ops/s` as operations and `10MB/s` or
+    /// `512K` as bytes per second; zero is rejected.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim();
+        let text = text.strip_suffix("/s").unwrap_or(text);
+        let rate = match text.strip_suffix("ops") {
+            Some(ops) => ops.trim().parse().ok().map(Self::Ops),
+            None if text.bytes().all(|b| b.is_ascii_digit()) => text.parse().ok().map(Self::Ops),
+            None => parse_size(text).map(Self::Bytes),
+        };
+        rate.filter(|rate| !matches!(rate, Self::Ops(0) | Self::Bytes(0)))
+            .ok_or_else(|| RateParseError(s.to_owned()))
+    }
+}
+
+/// Token bucket enforcing a [`Rate`]; share one behind an `Arc` between
+/// every thread of a scan.
+#[derive(Debug)]
+pub struct Throttle {
+    rate: Rate,
+    bucket: Mutex<Bucket>,
+}
+
+/// Tokens available and when they were last topped up.
+#[derive(Debug)]
+struct Bucket {
+    /// Negative while callers are waiting off a debt.
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl Throttle {
+    /// A full bucket for `rate`.
+    #[must_use]
+    pub fn new(rate: Rate) -> Self {
+        Self {
+            rate,
+            bucket: Mutex::new(Bucket {
+                tokens: per_second(rate),
+                refilled: Instant::now(),
+            }),
+        }
+    }
+
+    /// The enforced limit.
+    #[must_use]
+    pub const fn rate(&self) -> Rate {
+        self.rate
+    }
+
+    /// Account for `count` operations, sleeping as long as needed under an
+    /// [`Rate::Ops`] limit.
+    pub fn ops(&self, count: u64) {
+        if matches!(self.rate, Rate::Ops(_)) {
+            self.take(count);
+        }
+    }
+
+    /// Account for `count` bytes read, sleeping as long as needed under a
+    /// [`Rate::Bytes`] limit.
+    pub fn bytes(&self, count: u64) {
+        if matches!(self.rate, Rate::Bytes(_)) {
+            self.take(count);
+        }
+    }
+
+    /// Draw `cost` tokens, going into debt when there are too few, and
+    /// sleep until the debt would be repaid.
+    #[allow(clippy::cast_precision_loss)] // rates far below 2^52
+    fn take(&self, cost: u64) {
+        if cost == 0 {
+            return;
+        }
+        let rate = per_second(self.rate);
+        let debt = {
+            let Ok(mut bucket) = self.bucket.lock() else {
+                return;
+            };
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+            bucket.tokens = elapsed.mul_add(rate, bucket.tokens).min(rate) - cost as f64;
+            bucket.refilled = now;
+            -bucket.tokens
+        };
+        if debt > 0.0 {
+            std::thread::sleep(Duration::from_secs_f64(debt / rate));
+        }
+    }
+}
+
+/// Tokens added per second, which is also the bucket's capacity.
+#[allow(clippy::cast_precision_loss)] // rates far below 2^52
+const fn per_second(rate: Rate) -> f64 {
+    match rate {
+        Rate::Ops(n) | Rate::Bytes(n) => n as f64,
+    }
+}
+
+/// Reader charging the bytes it reads to a [`Throttle`], if any.
+pub struct Throttled<'a, R> {
+    inner: R,
+    throttle: Option<&'a Throttle>,
+}
+
+impl<'a, R> Throttled<'a, R> {
+    /// Wrap `inner`, whose opening counts as one operation.
+    pub fn new(inner: R, throttle: Option<&'a Throttle>) -> Self {
+        if let Some(throttle) = throttle {
+            throttle.ops(1);
+        }
+        Self { inner, throttle }
+    }
+}
+
+impl<R: Read> Read for Throttled<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if let Some(throttle) = self.throttle {
+            throttle.bytes(read as u64);
+        }
+        Ok(read)
+    }
+}
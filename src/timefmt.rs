@@ -0,0 +1,266 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Timestamp formatting shared by every output that shows times.
+//!
+//! Styles follow `ls --time-style`. Times are rendered in UTC: the standard
+//! library offers no portable access to the local time zone.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    fmt::{self, Write as _},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// How timestamps are written, selected with `--time-style`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TimeStyle {
+    /// `01-02 13:04` for times within six months, `2024-01-02 ` otherwise
+    /// (both eleven columns wide).
+    Iso,
+    /// `2025-01-02 13:04`.
+    #[default]
+    LongIso,
+    /// `2025-01-02 13:04:05.000000000 +0000`.
+    FullIso,
+    /// Humanised distance from now: `just now`, `3 days ago`, `in 2 hours`.
+    Relative,
+    /// `strftime`‑style pattern (written `+FORMAT` on the command line).
+    ///
+    /// Supports `%Y %y %m %d %e %H %M %S %j %b %B %a %A %s %%`; other
+    /// sequences are copied verbatim.
+    Custom(String),
+}
+
+/// Error returned for an unknown time style name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeStyleParseError(String);
+
+impl fmt::Display for TimeStyleParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown time style `{}` (expected iso, long-iso, full-iso, relative or +FORMAT)",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for TimeStyleParseError {}
+
+impl FromStr for TimeStyle {
+    type Err = TimeStyleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "iso" => Ok(Self::Iso),
+            "long-iso" => Ok(Self::LongIso),
+            "full-iso" => Ok(Self::FullIso),
+            "relative" => Ok(Self::Relative),
+            _ => s
+                .strip_prefix('+')
+                .map(|format| Self::Custom(format.to_owned()))
+                .ok_or_else(|| TimeStyleParseError(s.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for TimeStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Iso => f.write_str("iso"),
+            Self::LongIso => f.write_str("long-iso"),
+            Self::FullIso => f.write_str("full-iso"),
+            Self::Relative => f.write_str("relative"),
+            Self::Custom(format) => write!(f, "+{format}"),
+        }
+    }
+}
+
+impl Serialize for TimeStyle {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeStyle {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Write `time` in `style`; `now` anchors the relative and `iso` styles.
+#[must_use]
+pub fn format_time(time: SystemTime, style: &TimeStyle, now: SystemTime) -> String {
+    let at = Civil::from(time);
+    match style {
+        TimeStyle::Iso => {
+            let recent = now
+                .duration_since(time)
+                .map_or(true, |age| age.as_secs() < SIX_MONTHS);
+            if recent {
+                at.format("%m-%d %H:%M")
+            } else {
+                at.format("%Y-%m-%d ")
+            }
+        }
+        TimeStyle::LongIso => at.format("%Y-%m-%d %H:%M"),
+        TimeStyle::FullIso => format!("{}.{:09} +0000", at.format("%Y-%m-%d %H:%M:%S"), at.nanos),
+        TimeStyle::Relative => relative(time, now),
+        TimeStyle::Custom(format) => at.format(format),
+    }
+}
+
+/// Format `time` as an RFC 3339 UTC timestamp with second precision.
+#[must_use]
+pub fn utc_timestamp(time: SystemTime) -> String {
+    Civil::from(time).format("%Y-%m-%dT%H:%M:%SZ")
+}
+
+/// Seconds in the six months `ls` treats as "recent".
+const SIX_MONTHS: u64 = 183 * 86_400;
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+/// `3 days ago` / `in 2 hours` / `just now`.
+fn relative(time: SystemTime, now: SystemTime) -> String {
+    let (secs, future) = match now.duration_since(time) {
+        Ok(age) => (age.as_secs(), false),
+        Err(ahead) => (ahead.duration().as_secs(), true),
+    };
+    let (count, unit) = match secs {
+        0..=59 => return "just now".to_owned(),
+        60..=3_599 => (secs / 60, "minute"),
+        3_600..=86_399 => (secs / 3_600, "hour"),
+        86_400..=2_591_999 => (secs / 86_400, "day"),
+        2_592_000..=31_535_999 => (secs / 2_592_000, "month"),
+        _ => (secs / 31_536_000, "year"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    if future {
+        format!("in {count} {unit}{plural}")
+    } else {
+        format!("{count} {unit}{plural} ago")
+    }
+}
+
+/// Broken‑down UTC time.
+struct Civil {
+    secs: u64,
+    year: u64,
+    month: u64,
+    day: u64,
+    hour: u64,
+    minute: u64,
+    second: u64,
+    nanos: u32,
+    yday: u64,
+    weekday: usize,
+}
+
+impl From<SystemTime> for Civil {
+    #[allow(clippy::cast_possible_truncation)] // weekday is 0–6
+    fn from(time: SystemTime) -> Self {
+        let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let secs = since.as_secs();
+        let (days, rem) = (secs / 86_400, secs % 86_400);
+        let (year, month, day) = civil_from_days(days);
+        let yday = days - days_from_civil(year) + 1;
+        Self {
+            secs,
+            year,
+            month,
+            day,
+            hour: rem / 3600,
+            minute: rem % 3600 / 60,
+            second: rem % 60,
+            nanos: since.subsec_nanos(),
+            yday,
+            // 1970‑01‑01 was a Thursday.
+            weekday: ((days + 4) % 7) as usize,
+        }
+    }
+}
+
+impl Civil {
+    /// Expand a `strftime`‑style `format`.
+    #[allow(clippy::cast_possible_truncation)] // month is 1–12
+    fn format(&self, format: &str) -> String {
+        let mut out = String::with_capacity(format.len() + 8);
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            let month = MONTHS[(self.month - 1) as usize];
+            let weekday = WEEKDAYS[self.weekday];
+            let _ = match chars.next() {
+                Some('Y') => write!(out, "{:04}", self.year),
+                Some('y') => write!(out, "{:02}", self.year % 100),
+                Some('m') => write!(out, "{:02}", self.month),
+                Some('d') => write!(out, "{:02}", self.day),
+                Some('e') => write!(out, "{:2}", self.day),
+                Some('H') => write!(out, "{:02}", self.hour),
+                Some('M') => write!(out, "{:02}", self.minute),
+                Some('S') => write!(out, "{:02}", self.second),
+                Some('j') => write!(out, "{:03}", self.yday),
+                Some('b') => write!(out, "{}", &month[..3]),
+                Some('B') => write!(out, "{month}"),
+                Some('a') => write!(out, "{}", &weekday[..3]),
+                Some('A') => write!(out, "{weekday}"),
+                Some('s') => write!(out, "{}", self.secs),
+                Some('%') | None => write!(out, "%"),
+                Some(other) => write!(out, "%{other}"),
+            };
+        }
+        out
+    }
+}
+
+/// Proleptic Gregorian date for a count of days since 1970‑01‑01
+/// (Howard Hinnant's `civil_from_days`).
+const fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as u64;
+    (year, month, day)
+}
+
+/// Days since 1970‑01‑01 of January 1st of `year` (1970 or later).
+const fn days_from_civil(year: u64) -> u64 {
+    let y = year - 1;
+    365 * (year - 1970) + (y / 4 - y / 100 + y / 400) - (1969 / 4 - 1969 / 100 + 1969 / 400)
+}
@@ -1,21 +1,41 @@
 // SPDX‑License‑Identifier: MIT
 // Copyright (c) 2025 Robert Nio
 
-//! Core tree printing and file‑management implementation (refactored).
+//! Core tree printing and `.tree_ignore` file management.
 //!
-//! This version actually honours `.gitignore` files via `ignore::WalkBuilder`,
-//! appends a trailing “/” to directory names, avoids O(n) pattern scans by
-//! using a `HashSet`, and removes repeated string allocations for prefixes.
-//!
-//! Public surface is unchanged.
+//! Directories are listed through `ignore::WalkBuilder`, so `.gitignore`
+//! rules apply, and `.tree_ignore` names are looked up in a `HashSet`.
+//! The text renderer walks with an explicit stack of frames instead of
+//! recursing, so deep trees are bounded by [`TreeOptions::limits`] rather
+//! than by the thread's stack.
 
+use crate::{
+    annotate::directory_description,
+    attributes::ExportIgnore,
+    diagnostics::Diagnostic,
+    du::DiskUsage,
+    entry::{entry_kind, needs_stat, EntryInfo},
+    hash::{to_hex, DirHashes},
+    hyperlink,
+    options::{CaseSensitivity, ColumnPlacement, Grouping, Traversal, TreeOptions},
+    platform::{mode, volume::MetadataSupport, xattr},
+    render_core::{display_width, Columns, Layout},
+    scan::EntryKind,
+    suggest::{ignore_file_contents, suggest_ignores},
+    theme::{Style, Theme},
+    timefmt::format_time,
+    unicode::nfd,
+};
 use anyhow::{Context, Result};
 use ignore::{DirEntry, WalkBuilder};
 use std::{
-    collections::HashSet,
+    borrow::Cow,
+    collections::{HashMap, HashSet, VecDeque},
+    ffi::OsStr,
     fs::{self, OpenOptions},
     io::{self, Write},
-    path::Path,
+    path::{Path, PathBuf},
+    time::SystemTime,
 };
 
 /* -------------------------------------------------------------------------- */
@@ -24,38 +44,233 @@ use std::{
 
 /// Print the directory tree rooted at `root` into `writer`.
 ///
-/// Behaviour is identical to the previous version, but now:
 /// * Respects `.gitignore`, `.ignore`, and global Git excludes.
 /// * Uses `.tree_ignore` patterns loaded **once** into a `HashSet`.
 /// * Appends “/” to directory names, in line with the docs.
-/// * Performs zero heap allocations during traversal other than the Vec that
-///   holds each directory’s immediate children.
+/// * Keeps the directories being rendered on a heap-allocated stack, each
+///   holding its listed children, so depth does not grow the call stack.
+/// * With [`TreeOptions::max_lines`] and breadth-first traversal, plans
+///   which entries to show level by level before printing anything.
+/// * Counts subtrees once to decide [`TreeOptions::auto_skip_over`].
+/// * Applies the user `filter` and `decorate` hooks from `options`.
 ///
 /// # Errors
 /// Returns an error when I/O fails at any point.
 pub fn print_directory_tree_to_writer<W: Write>(
     root: &Path,
     writer: &mut W,
-    show_files: bool,
+    options: &TreeOptions,
 ) -> Result<()> {
-    writeln!(writer, "{}", root.display()).context("failed to write root path")?;
+    let ignore_set = load_ignore_set(root, options)?;
+    let listing = collect_children(root, &ignore_set, options, 1);
+
+    let mut disk_usage = if options.dedupe {
+        DiskUsage::new()
+    } else {
+        DiskUsage::without_dedupe()
+    };
+    let root_size = if options.disk_usage {
+        let cell = [size_cell(disk_usage.total(root), options)];
+        Columns::measure([&cell]).format(&cell)
+    } else {
+        String::new()
+    };
+    let mut dir_hashes = DirHashes::new(&ignore_set, options);
+    let root_hash = if options.hash_dirs {
+        merkle_note(dir_hashes.digest(root, 1, 0)?)
+    } else {
+        String::new()
+    };
+    let root_name = options
+        .shown_name(&options.redaction.root(root))
+        .into_owned();
+    writeln!(
+        writer,
+        "{}{}{root_hash}",
+        line_head(options, "", &root_name, "", &root_size),
+        error_suffix(listing.error.as_deref())
+    )
+    .context("failed to write root path")?;
+
+    let mut walk = Walk {
+        disk_usage,
+        dir_hashes,
+        ignore_set: &ignore_set,
+        options,
+        planned: None,
+        layout: Layout::new(options.charset),
+        remaining: options.max_lines.unwrap_or(usize::MAX),
+        truncated: false,
+        now: SystemTime::now(),
+        support: options.metadata_support(root),
+        sizes: SubtreeSizes::default(),
+    };
+    let entries = match (options.traversal, options.max_lines) {
+        (Traversal::BreadthFirst, Some(limit)) => {
+            let (entries, planned, truncated) = plan_breadth_first(
+                root,
+                listing.entries,
+                &ignore_set,
+                options,
+                limit,
+                &mut walk.sizes,
+            );
+            walk.planned = Some(planned);
+            walk.truncated = truncated;
+            entries
+        }
+        _ => listing.entries,
+    };
+    render_tree(&mut walk, entries, writer, 1, 0)?;
+
+    if walk.truncated {
+        if let Some(limit) = options.max_lines {
+            let ellipsis = options.charset.ellipsis();
+            writeln!(writer, "{ellipsis} (output limited to {limit} lines)")
+                .context("failed to write truncation note")?;
+        }
+    }
+    Ok(())
+}
+
+/// Names listed in `.tree_ignore`, matched exactly or case‑insensitively.
+///
+/// Patterns and names are compared in NFD form, so a pattern typed on one
+/// system matches a name stored composed or decomposed on another.
+#[derive(Debug, Default)]
+pub struct IgnoreSet {
+    /// Patterns in NFD form, lower‑cased when matching is case‑insensitive.
+    patterns: HashSet<String>,
+    case_insensitive: bool,
+}
+
+impl IgnoreSet {
+    /// Build a set from raw patterns; case‑insensitive sets fold
+    /// duplicates that differ only in case.
+    pub fn new(patterns: impl IntoIterator<Item = String>, case_insensitive: bool) -> Self {
+        let patterns = patterns
+            .into_iter()
+            .map(|p| {
+                let p = nfd(&p).into_owned();
+                if case_insensitive {
+                    p.to_lowercase()
+                } else {
+                    p
+                }
+            })
+            .collect();
+        Self {
+            patterns,
+            case_insensitive,
+        }
+    }
+
+    /// Whether an entry called `name` is ignored.
+    pub fn matches(&self, name: &OsStr) -> bool {
+        let name = name.to_string_lossy();
+        let name = nfd(&name);
+        if self.case_insensitive {
+            self.patterns.contains(&name.to_lowercase())
+        } else {
+            self.patterns.contains(name.as_ref())
+        }
+    }
+
+    /// Number of distinct patterns.
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+}
 
+/// Create the default `.tree_ignore` below `root` when missing and load its
+/// patterns into an [`IgnoreSet`] for constant-time lookups.
+///
+/// Creating the file is reported through the diagnostics sink of `options`.
+///
+/// # Errors
+/// Returns an error when the ignore file cannot be created or read.
+pub fn load_ignore_set(root: &Path, options: &TreeOptions) -> Result<IgnoreSet> {
     // Lazily create `.tree_ignore` if it is missing.
-    if !root.join(".tree_ignore").exists() {
-        create_default_ignore_file(root)?;
+    let path = root.join(".tree_ignore");
+    let missing = !path.exists();
+    if missing && options.create_ignore_file {
+        if options.suggest_ignores {
+            let patterns: Vec<String> = suggest_ignores(root).into_iter().map(|s| s.name).collect();
+            create_ignore_file(root, &ignore_file_contents(&patterns))?;
+            options.diagnose(&Diagnostic::SuggestedIgnoreFile(path, patterns));
+        } else {
+            create_ignore_file(root, DEFAULT_IGNORE)?;
+            options.diagnose(&Diagnostic::CreatedIgnoreFile(path));
+        }
     }
 
-    let ignore_set = HashSet::<String>::from_iter(read_ignore_patterns(root)?);
+    let case_insensitive = match options.case_sensitivity {
+        CaseSensitivity::Auto => is_case_insensitive(root),
+        CaseSensitivity::Sensitive => false,
+        CaseSensitivity::Insensitive => true,
+    };
+    let patterns = if missing && !options.create_ignore_file {
+        parse_ignore_patterns(DEFAULT_IGNORE)
+    } else {
+        read_ignore_patterns(root)?
+    };
+    Ok(IgnoreSet::new(patterns, case_insensitive))
+}
 
-    render_tree(root, "", writer, &ignore_set, show_files)?;
+/// Whether the filesystem holding `root` ignores letter case.
+///
+/// Looks for an entry of `root` whose name changes when its case is
+/// flipped and checks whether the flipped name leads to the same file.
+/// Without such an entry the platform default is assumed.
+fn is_case_insensitive(root: &Path) -> bool {
+    let entries = fs::read_dir(root).into_iter().flatten().flatten();
+    for entry in entries {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        let flipped: String = name
+            .chars()
+            .map(|c| {
+                if c.is_uppercase() {
+                    c.to_ascii_lowercase()
+                } else {
+                    c.to_ascii_uppercase()
+                }
+            })
+            .collect();
+        if flipped == name {
+            continue;
+        }
+        return match (
+            fs::symlink_metadata(entry.path()),
+            fs::symlink_metadata(root.join(&flipped)),
+        ) {
+            (Ok(original), Ok(other)) => same_file(&original, &other),
+            _ => false,
+        };
+    }
+    cfg!(any(windows, target_os = "macos"))
+}
 
-    Ok(())
+/// Whether two metadata records describe the same file.
+#[cfg(unix)]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+/// File identities are not portable; a flipped name that resolves is
+/// taken as the same file.
+#[cfg(not(unix))]
+const fn same_file(_a: &fs::Metadata, _b: &fs::Metadata) -> bool {
+    true
 }
 
 /// Remove every `.tree_ignore` file below `root` and return the count.
 ///
-/// The function itself is unchanged except for a micro‑optimisation that
-/// avoids a second metadata call.
+/// File types come from the directory listing, so no extra metadata call
+/// is made per entry.
 pub fn clear_ignore_files_count(root: &Path) -> Result<u64> {
     let mut removed = 0u64;
 
@@ -115,8 +330,8 @@ vendor
 Thumbs.db
 ";
 
-/// Create a starter ignore file (no overwrite).
-fn create_default_ignore_file(dir: &Path) -> Result<()> {
+/// Create `.tree_ignore` in `dir` with `contents` (no overwrite).
+pub fn create_ignore_file(dir: &Path, contents: &str) -> Result<()> {
     let path = dir.join(".tree_ignore");
     let file = OpenOptions::new()
         .create_new(true) // fail if the user already created one
@@ -124,8 +339,8 @@ fn create_default_ignore_file(dir: &Path) -> Result<()> {
         .open(&path)
         .with_context(|| format!("creating {}", path.display()))?;
     io::BufWriter::new(file)
-        .write_all(DEFAULT_IGNORE.as_bytes())
-        .with_context(|| format!("writing defaults to {}", path.display()))
+        .write_all(contents.as_bytes())
+        .with_context(|| format!("writing {}", path.display()))
 }
 
 /// Load ignore patterns into a `Vec`, stripping comments and blanks.
@@ -136,64 +351,833 @@ fn read_ignore_patterns(dir: &Path) -> Result<Vec<String>> {
     }
     let content =
         fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
-    Ok(content
+    Ok(parse_ignore_patterns(&content))
+}
+
+/// Patterns of an ignore file's `content`, without comments and blanks.
+fn parse_ignore_patterns(content: &str) -> Vec<String> {
+    content
         .lines()
         .map(str::trim)
         .filter(|l| !l.is_empty() && !l.starts_with('#'))
         .map(ToOwned::to_owned)
-        .collect())
+        .collect()
 }
 
 /* -------------------------------------------------------------------------- */
 /* Rendering                                                                  */
 /* -------------------------------------------------------------------------- */
 
-/// Recursive pretty printer using `ignore::WalkBuilder` for Git integration.
+/// State shared by one text rendering pass.
+struct Walk<'a> {
+    ignore_set: &'a IgnoreSet,
+    options: &'a TreeOptions,
+    /// Listings chosen up front by [`plan_breadth_first`]; `None` lists each
+    /// directory when it is reached.
+    planned: Option<HashMap<PathBuf, Listing>>,
+    /// Connector and prefix state.
+    layout: Layout,
+    /// Entry lines that may still be written.
+    remaining: usize,
+    /// Whether entries were left out because of [`TreeOptions::max_lines`].
+    truncated: bool,
+    /// Reference point for relative times.
+    now: SystemTime,
+    /// Directory totals for [`TreeOptions::disk_usage`].
+    disk_usage: DiskUsage,
+    /// Directory digests for [`TreeOptions::hash_dirs`].
+    dir_hashes: DirHashes<'a>,
+    /// Columns the root's filesystem can fill.
+    support: MetadataSupport,
+    /// Subtree sizes for [`TreeOptions::auto_skip_over`].
+    sizes: SubtreeSizes,
+}
+
+impl Walk<'_> {
+    /// Entry name with its style, link and the size and time columns.
+    fn label(&self, child: &DirEntry, kind: EntryKind) -> String {
+        let options = self.options;
+        let path = child.path();
+        let raw_name = child.file_name().to_string_lossy();
+        let raw_name = match options.redaction.apply(&raw_name) {
+            Cow::Borrowed(_) => raw_name,
+            Cow::Owned(redacted) => Cow::Owned(redacted),
+        };
+        let raw_name = match options.shown_name(&raw_name) {
+            Cow::Borrowed(_) => raw_name,
+            Cow::Owned(escaped) => Cow::Owned(escaped),
+        };
+        let mut name = match &options.theme {
+            Some(theme) => self
+                .age_style(theme, child)
+                .or_else(|| self.size_style(theme, child, kind))
+                .unwrap_or_else(|| style_for(theme, kind, child, options))
+                .paint(&raw_name),
+            None => raw_name,
+        };
+        if options.hyperlinks {
+            name = hyperlink::link(&hyperlink::file_url(path), &name).into();
+        }
+        if options.xattrs {
+            let names = xattr::names(path);
+            if !names.is_empty() {
+                name = format!("{name} [xattrs: {}]", names.join(", ")).into();
+            }
+        }
+        name.into_owned()
+    }
+
+    /// Heat‑map style of `child` for [`TreeOptions::age_colors`], when
+    /// enabled and its modification time can be read.
+    fn age_style<'t>(&self, theme: &'t Theme, child: &DirEntry) -> Option<&'t Style> {
+        let buckets = self.options.age_colors.as_ref()?;
+        let modified = self.options.stat(child).ok()?.modified().ok()?;
+        let age = self.now.duration_since(modified).unwrap_or_default();
+        theme.age(buckets.bucket(&age), buckets.len())
+    }
+
+    /// Heat‑map style of a file `child` for [`TreeOptions::size_colors`],
+    /// when enabled and its size can be read.
+    fn size_style<'t>(
+        &self,
+        theme: &'t Theme,
+        child: &DirEntry,
+        kind: EntryKind,
+    ) -> Option<&'t Style> {
+        let buckets = self.options.size_colors.as_ref()?;
+        if kind != EntryKind::File {
+            return None;
+        }
+        let size = self.options.stat(child).ok()?.len();
+        theme.size(buckets.bucket(&size), buckets.len())
+    }
+
+    /// Metadata cells of `child` in display order: security context,
+    /// permissions, size, modification time. Empty when none are enabled.
+    fn metadata(&mut self, child: &DirEntry, kind: EntryKind) -> Vec<String> {
+        let options = self.options;
+        let path = child.path();
+        let mut cells = Vec::new();
+        if options.security_context {
+            let context = xattr::security_context(path);
+            cells.push(context.unwrap_or_else(|| "?".to_owned()));
+        }
+        if options.permissions && !self.support.permissions {
+            cells.push("-".to_owned());
+        } else if options.permissions {
+            let mode = options
+                .stat(child)
+                .map_or_else(|_| "?".to_owned(), |m| mode::permissions(&m));
+            let acl = if xattr::has_acl(path) { "+" } else { "" };
+            cells.push(format!("{mode}{acl}"));
+        }
+        if options.disk_usage {
+            let bytes = if kind == EntryKind::Directory {
+                self.disk_usage.total(path)
+            } else {
+                DiskUsage::own(path)
+            };
+            cells.push(size_cell(bytes, options));
+        }
+        if let Some(style) = options.time_style.as_ref().filter(|_| self.support.times) {
+            let stamp = options
+                .stat(child)
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .map_or_else(
+                    || "?".to_owned(),
+                    |mtime| format_time(mtime, style, self.now),
+                );
+            cells.push(stamp);
+        } else if options.time_style.is_some() {
+            cells.push("-".to_owned());
+        }
+        cells
+    }
+
+    /// Connector for the next line, with guides coloured by level when
+    /// [`TreeOptions::level_colors`] is on.
+    ///
+    /// Uncoloured guides are borrowed from the layout's reusable buffer.
+    fn connector(&mut self, depth: usize, is_last: bool) -> Cow<'_, str> {
+        match (&self.options.theme, self.options.level_colors) {
+            // Blank segments stay unpainted: colour is invisible on spaces.
+            (Some(theme), true) => Cow::Owned(self.layout.connector_with(
+                depth,
+                is_last,
+                |level, segment| {
+                    if segment.trim().is_empty() {
+                        segment.to_owned()
+                    } else {
+                        theme.level(level).paint(segment).into_owned()
+                    }
+                },
+            )),
+            _ => Cow::Borrowed(self.layout.connector_str(depth, is_last)),
+        }
+    }
+
+    /// Children of `dir` to render, plus its read error.
+    fn listing(&mut self, dir: &Path, depth: usize) -> Listing {
+        match &mut self.planned {
+            None => collect_children(dir, self.ignore_set, self.options, depth),
+            Some(planned) => planned.remove(dir).unwrap_or_default(),
+        }
+    }
+}
+
+/// One directory on the rendering stack: its children, measured and cut
+/// to [`TreeOptions::sample`], and the next one to visit.
+struct Frame {
+    children: Vec<(DirEntry, EntryKind)>,
+    /// Metadata cells per child; empty for hidden children.
+    metadata: Vec<Vec<String>>,
+    columns: Columns,
+    /// Index of the child that closes the directory, if no `+k more` does.
+    last_shown: Option<usize>,
+    /// Shown children dropped by sampling.
+    more: usize,
+    next: usize,
+    /// Depth of the children (1 for the root's).
+    depth: usize,
+    /// Symlinked directories followed to reach the children.
+    hops: usize,
+}
+
+impl Frame {
+    /// Prepare `children` of a directory for rendering.
+    fn new(
+        walk: &mut Walk<'_>,
+        mut children: Vec<(DirEntry, EntryKind)>,
+        depth: usize,
+        hops: usize,
+    ) -> Self {
+        let options = walk.options;
+        let shown = |kind: EntryKind| options.show_files || kind == EntryKind::Directory;
+        let (kept, more) = sample(&children, options.sample, shown);
+        children.truncate(kept.len());
+        // With a `+k more` line, that line closes the directory instead.
+        let last_shown = if more > 0 {
+            None
+        } else {
+            children.iter().rposition(|(_, kind)| shown(*kind))
+        };
+        // Measure the whole directory up front so its columns line up.
+        let metadata: Vec<Vec<String>> = children
+            .iter()
+            .map(|(child, kind)| {
+                if shown(*kind) {
+                    walk.metadata(child, *kind)
+                } else {
+                    Vec::new()
+                }
+            })
+            .collect();
+        let columns = Columns::measure(&metadata);
+        Self {
+            children,
+            metadata,
+            columns,
+            last_shown,
+            more,
+            next: 0,
+            depth,
+            hops,
+        }
+    }
+}
+
+/// Pretty printer over the already collected `children` of a directory,
+/// using `ignore::WalkBuilder` for Git integration below it.
+///
+/// Directories still being rendered are kept on an explicit stack rather
+/// than the call stack, so the depth of the tree is bounded only by
+/// [`TreeOptions::limits`]. `hops` counts the symlinked directories
+/// followed to reach `children`.
 fn render_tree<W: Write>(
-    dir: &Path,
-    prefix: &str,
+    walk: &mut Walk<'_>,
+    children: Vec<(DirEntry, EntryKind)>,
     writer: &mut W,
-    ignore_set: &HashSet<String>,
-    show_files: bool,
+    depth: usize,
+    hops: usize,
 ) -> Result<()> {
-    let children = collect_children(dir, ignore_set);
+    let options = walk.options;
+    let shown = |kind: EntryKind| options.show_files || kind == EntryKind::Directory;
+    let mut stack = vec![Frame::new(walk, children, depth, hops)];
+    while let Some(frame) = stack.last_mut() {
+        let next = frame.children[frame.next..]
+            .iter()
+            .position(|(_, kind)| shown(*kind))
+            .map(|offset| frame.next + offset);
+        let Some(idx) = next else {
+            let (depth, more) = (frame.depth, frame.more);
+            stack.pop();
+            if more > 0 {
+                if walk.remaining == 0 {
+                    walk.truncated = true;
+                    return Ok(());
+                }
+                walk.remaining -= 1;
+                let connector = walk.connector(depth, true);
+                writeln!(writer, "{connector}+{more} more")
+                    .context("failed to write sample note")?;
+            }
+            continue;
+        };
+        frame.next = idx + 1;
+        if walk.remaining == 0 {
+            walk.truncated = true;
+            return Ok(());
+        }
+        walk.remaining -= 1;
+        if let Some(below) = render_entry(walk, frame, idx, writer)? {
+            stack.push(below);
+        }
+    }
+    Ok(())
+}
 
-    for (idx, child) in children.iter().enumerate() {
-        let is_last = idx + 1 == children.len();
-        let connector = if is_last { "└── " } else { "├── " };
-        let path = child.path();
-        let name = child.file_name().to_string_lossy();
+/// Write the line of child `idx` of `frame`; for a directory that is
+/// descended into, return the frame of its children.
+fn render_entry<W: Write>(
+    walk: &mut Walk<'_>,
+    frame: &Frame,
+    idx: usize,
+    writer: &mut W,
+) -> Result<Option<Frame>> {
+    let options = walk.options;
+    options.check_cancelled()?;
+    let (child, kind) = &frame.children[idx];
+    let depth = frame.depth;
+    let is_last = Some(idx) == frame.last_shown;
+    let path = child.path();
+    let name = walk.label(child, *kind);
+    let cells = frame.columns.format(&frame.metadata[idx]);
+    let mut decoration = String::new();
+    if let (EntryKind::Directory, Some(file)) = (kind, &options.annotate_from) {
+        if let Some(description) = directory_description(path, file) {
+            decoration.push_str("  # ");
+            decoration.push_str(&description);
+        }
+    }
+    let hops = frame.hops + usize::from(child.path_is_symlink());
+    if *kind == EntryKind::Directory && options.hash_dirs {
+        let digest = walk.dir_hashes.digest(path, depth + 1, hops)?;
+        decoration.push_str(&merkle_note(digest));
+    }
+    if let Some(extra) = options.cached_decoration(
+        child,
+        &EntryInfo {
+            path,
+            name: child.file_name(),
+            kind: *kind,
+            depth,
+        },
+    ) {
+        decoration.push_str("  ");
+        decoration.push_str(&extra);
+    }
 
-        if path.is_dir() {
-            writeln!(writer, "{prefix}{connector}{name}/").context("failed to write directory")?;
-            let new_prefix = format!("{prefix}{}", if is_last { "    " } else { "│   " });
-            render_tree(path, &new_prefix, writer, ignore_set, show_files)?;
-        } else if show_files {
-            writeln!(writer, "{prefix}{connector}{name}").context("failed to write file")?;
+    if *kind != EntryKind::Directory {
+        let connector = walk.connector(depth, is_last);
+        let head = line_head(options, &connector, &name, kind.indicator(), &cells);
+        writeln!(writer, "{head}{decoration}").context("failed to write file")?;
+        return Ok(None);
+    }
+    if let Some(total) = walk.sizes.oversized(path, options) {
+        let ellipsis = options.charset.ellipsis();
+        let total = options.numeric_locale.map_or_else(
+            || group_thousands(total),
+            |locale| locale.count(u64::try_from(total).unwrap_or(u64::MAX)),
+        );
+        let connector = walk.connector(depth, is_last);
+        let head = line_head(options, &connector, &name, "/", &cells);
+        writeln!(
+            writer,
+            "{head} {ellipsis} skipped ({total} entries){decoration}"
+        )
+        .context("failed to write directory")?;
+        return Ok(None);
+    }
+    // List the directory first so a read error can annotate its line.
+    options.limits.check(path, depth + 1, hops)?;
+    options.check_cancelled()?;
+    let listing = walk.listing(path, depth + 1);
+    let error = error_suffix(listing.error.as_deref());
+    let count = if options.file_counts {
+        match listing.files {
+            1 => " (1 file)".to_owned(),
+            n => format!(" ({n} files)"),
         }
+    } else {
+        String::new()
+    };
+    let rollup = if options.extension_rollup {
+        extension_rollup(&listing.entries)
+    } else {
+        String::new()
+    };
+    let connector = walk.connector(depth, is_last);
+    let head = line_head(options, &connector, &name, "/", &cells);
+    writeln!(writer, "{head}{count}{rollup}{error}{decoration}")
+        .context("failed to write directory")?;
+    Ok(Some(Frame::new(walk, listing.entries, depth + 1, hops)))
+}
+
+/// Cut `children` after the first `limit` shown entries and count how many
+/// shown entries were dropped.
+fn sample<F: Fn(EntryKind) -> bool>(
+    children: &[(DirEntry, EntryKind)],
+    limit: Option<usize>,
+    shown: F,
+) -> (&[(DirEntry, EntryKind)], usize) {
+    let Some(limit) = limit else {
+        return (children, 0);
+    };
+    let mut visible = children
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, kind))| shown(*kind));
+    match visible.nth(limit) {
+        Some((cut, _)) => (&children[..cut], 1 + visible.count()),
+        None => (children, 0),
     }
-    Ok(())
 }
 
-/// Collect immediate children of `dir` honouring Git and `.tree_ignore`.
-fn collect_children(dir: &Path, ignore_set: &HashSet<String>) -> Vec<DirEntry> {
-    let mut children: Vec<DirEntry> = WalkBuilder::new(dir)
+/// Choose the first `limit` entries in breadth‑first order, level by level.
+///
+/// Returns the root's selected children, the trimmed listing of every other
+/// expanded directory keyed by path, and whether anything was left out.
+fn plan_breadth_first(
+    root: &Path,
+    root_entries: Vec<(DirEntry, EntryKind)>,
+    ignore_set: &IgnoreSet,
+    options: &TreeOptions,
+    limit: usize,
+    sizes: &mut SubtreeSizes,
+) -> (Vec<(DirEntry, EntryKind)>, HashMap<PathBuf, Listing>, bool) {
+    let mut left = limit;
+    let mut truncated = false;
+    let mut planned = HashMap::new();
+    let root_listing = Listing {
+        entries: root_entries,
+        ..Listing::default()
+    };
+    let mut queue = VecDeque::from([(root.to_path_buf(), root_listing, 1)]);
+
+    while let Some((dir, mut listing, depth)) = queue.pop_front() {
+        if left == 0 {
+            truncated |= listing
+                .entries
+                .iter()
+                .any(|(_, kind)| options.show_files || *kind == EntryKind::Directory);
+            if truncated {
+                break;
+            }
+            continue;
+        }
+        truncated |= take_lines(&mut listing.entries, &mut left, options.show_files);
+        for (child, kind) in &listing.entries {
+            if *kind == EntryKind::Directory && sizes.oversized(child.path(), options).is_none() {
+                let path = child.path();
+                let sub = collect_children(path, ignore_set, options, depth + 1);
+                queue.push_back((path.to_path_buf(), sub, depth + 1));
+            }
+        }
+        planned.insert(dir, listing);
+    }
+
+    let root_entries = planned.remove(root).unwrap_or_default().entries;
+    (root_entries, planned, truncated)
+}
+
+/// Keep as many leading `entries` as fit in `left` printed lines (files do
+/// not print when `show_files` is off) and report whether any were cut.
+fn take_lines(
+    entries: &mut Vec<(DirEntry, EntryKind)>,
+    left: &mut usize,
+    show_files: bool,
+) -> bool {
+    for (keep, (_, kind)) in entries.iter().enumerate() {
+        if show_files || *kind == EntryKind::Directory {
+            if *left == 0 {
+                entries.truncate(keep);
+                return true;
+            }
+            *left -= 1;
+        }
+    }
+    false
+}
+
+/// Entry counts of the directories below a root, for deciding
+/// [`TreeOptions::auto_skip_over`] without walking any subtree twice.
+///
+/// Counting stops as soon as a directory is known to exceed the limit, and
+/// every subdirectory counted to the end along the way is remembered, so
+/// descending into a directory that fits finds all its children's counts
+/// already known. Only a directory that is skipped is counted in full, once,
+/// for its `skipped (N entries)` label.
+#[derive(Debug, Default)]
+struct SubtreeSizes {
+    /// Exact entry counts, all within the limit.
+    known: HashMap<PathBuf, usize>,
+}
+
+/// A directory being counted by [`SubtreeSizes::fits`].
+struct Counting {
+    path: PathBuf,
+    /// Entries counted so far, including finished subdirectories.
+    total: usize,
+    /// Subdirectories still to count.
+    pending: Vec<PathBuf>,
+}
+
+impl SubtreeSizes {
+    /// Total entry count of `dir` when it exceeds
+    /// [`TreeOptions::auto_skip_over`].
+    fn oversized(&mut self, dir: &Path, options: &TreeOptions) -> Option<usize> {
+        let limit = options.auto_skip_over?;
+        if self.fits(dir, limit, options) {
+            return None;
+        }
+        Some(count_entries(dir))
+    }
+
+    /// Whether `dir` holds at most `limit` entries, counting no further
+    /// than needed to tell.
+    fn fits(&mut self, dir: &Path, limit: usize, options: &TreeOptions) -> bool {
+        if self.known.contains_key(dir) {
+            return true;
+        }
+        // Entries counted in every open directory, all of them inside `dir`.
+        let mut counted = 0;
+        let mut open = vec![Self::open(dir, options, limit, &mut counted)];
+        while counted <= limit {
+            let Some(top) = open.last_mut() else {
+                return true;
+            };
+            if let Some(sub) = top.pending.pop() {
+                match self.known.get(&sub) {
+                    Some(&total) => {
+                        top.total += total;
+                        counted += total;
+                    }
+                    None => open.push(Self::open(&sub, options, limit, &mut counted)),
+                }
+                continue;
+            }
+            let done = open.pop().map(|done| {
+                self.known.insert(done.path, done.total);
+                done.total
+            });
+            if let (Some(total), Some(parent)) = (done, open.last_mut()) {
+                parent.total += total;
+            }
+        }
+        false
+    }
+
+    /// List `dir` for counting, stopping once `counted` exceeds `limit`.
+    /// Unreadable directories count as empty; symlinks are not followed.
+    fn open(dir: &Path, options: &TreeOptions, limit: usize, counted: &mut usize) -> Counting {
+        let mut counting = Counting {
+            path: dir.to_path_buf(),
+            total: 0,
+            pending: Vec::new(),
+        };
+        options.record_listing(0);
+        for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            counting.total += 1;
+            *counted += 1;
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                counting.pending.push(entry.path());
+            }
+            if *counted > limit {
+                break;
+            }
+        }
+        counting
+    }
+}
+
+/// Number of entries below `dir`, recursively, without following symlinks.
+/// Unreadable directories count as empty.
+pub fn count_entries(dir: &Path) -> usize {
+    let mut total = 0;
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            total += 1;
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                pending.push(entry.path());
+            }
+        }
+    }
+    total
+}
+
+/// `48201` → `48,201`.
+pub fn group_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
+
+/// Path an ignore error is about, if it names one.
+fn error_path(err: &ignore::Error) -> Option<&Path> {
+    match err {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            error_path(err)
+        }
+        _ => None,
+    }
+}
+
+/// Short reason for an ignore error: the I/O kind when there is one.
+pub fn io_reason(err: &ignore::Error) -> String {
+    err.io_error()
+        .map_or_else(|| err.to_string(), |io| io.kind().to_string())
+}
+
+/// Size cell (`  12K`) for [`TreeOptions::disk_usage`].
+fn size_cell(bytes: u64, options: &TreeOptions) -> String {
+    format!(
+        "{:>5}",
+        options.numeric_locale.unwrap_or_default().size(bytes)
+    )
+}
+
+/// Connector, name with `suffix` and metadata `cells` of a text line, placed
+/// per [`TreeOptions::columns`].
+fn line_head(
+    options: &TreeOptions,
+    connector: &str,
+    name: &str,
+    suffix: &str,
+    cells: &str,
+) -> String {
+    if cells.is_empty() {
+        return format!("{connector}{name}{suffix}");
+    }
+    match options.columns {
+        ColumnPlacement::BeforeName => format!("{connector}{cells}  {name}{suffix}"),
+        ColumnPlacement::AfterName(column) => {
+            let head = format!("{connector}{name}{suffix}");
+            let gap = column.saturating_sub(display_width(&head)).max(2);
+            format!("{head}{:gap$}{cells}", "")
+        }
+    }
+}
+
+/// ` [rs:12 md:3]` rollup of the file extensions among `entries` for
+/// [`TreeOptions::extension_rollup`]; empty when there are no files.
+fn extension_rollup(entries: &[(DirEntry, EntryKind)]) -> String {
+    const SHOWN: usize = 5;
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for (entry, _) in entries
+        .iter()
+        .filter(|(_, kind)| *kind != EntryKind::Directory)
+    {
+        let ext = Path::new(entry.file_name()).extension().map_or_else(
+            || "(none)".to_owned(),
+            |ext| ext.to_string_lossy().to_lowercase(),
+        );
+        *counts.entry(ext).or_default() += 1;
+    }
+    if counts.is_empty() {
+        return String::new();
+    }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+    let mut badges: Vec<String> = counts
+        .iter()
+        .take(SHOWN)
+        .map(|(ext, count)| format!("{ext}:{count}"))
+        .collect();
+    if counts.len() > SHOWN {
+        badges.push(format!("+{}", counts.len() - SHOWN));
+    }
+    format!(" [{}]", badges.join(" "))
+}
+
+/// `  [merkle 3f2a…]` note for [`TreeOptions::hash_dirs`], showing the first
+/// 16 hex digits of `digest`.
+fn merkle_note(digest: Option<[u8; 32]>) -> String {
+    digest.map_or_else(
+        || "  [merkle unreadable]".to_owned(),
+        |digest| format!("  [merkle {}]", &to_hex(&digest)[..16]),
+    )
+}
+
+/// ` [error: …]` annotation for an entry that could not be read, if any.
+fn error_suffix(error: Option<&str>) -> String {
+    error.map_or_else(String::new, |e| format!(" [error: {e}]"))
+}
+
+/// Pick the theme style for an entry, checking the execute bits of files.
+fn style_for<'t>(
+    theme: &'t Theme,
+    kind: EntryKind,
+    entry: &DirEntry,
+    options: &TreeOptions,
+) -> &'t Style {
+    match kind {
+        EntryKind::Directory => &theme.directory,
+        EntryKind::Symlink => &theme.symlink,
+        EntryKind::File if is_executable(entry, options) => &theme.executable,
+        _ => &theme.file,
+    }
+}
+
+/// Whether any execute bit is set on the entry.
+#[cfg(unix)]
+fn is_executable(entry: &DirEntry, options: &TreeOptions) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    options
+        .stat(entry)
+        .is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+}
+
+/// Execute bits do not exist outside Unix.
+#[cfg(not(unix))]
+const fn is_executable(_entry: &DirEntry, _options: &TreeOptions) -> bool {
+    false
+}
+
+/// Immediate children of a directory, plus the first error hit while
+/// reading it.
+#[derive(Debug, Default)]
+pub struct Listing {
+    /// Children that passed every filter, in display order.
+    pub entries: Vec<(DirEntry, EntryKind)>,
+    /// Short description of the read error (e.g. `permission denied`), or
+    /// `None` when the directory was read cleanly or
+    /// [`TreeOptions::show_errors`] is off.
+    pub error: Option<String>,
+    /// Number of non‑directory children, counted before any trimming.
+    pub files: usize,
+}
+
+/// Ignore files of other tools honoured with [`TreeOptions::tool_ignores`].
+pub const TOOL_IGNORE_FILES: [&str; 2] = [".fdignore", ".rgignore"];
+
+/// Collect immediate children of `dir` honouring Git, `.ignore`,
+/// `.tree_ignore` and the user filter, paired with their kind.
+///
+/// `depth` is the depth of the children themselves (1 for the root's).
+/// Read errors are recorded in [`Listing::error`] instead of being dropped.
+pub fn collect_children(
+    dir: &Path,
+    ignore_set: &IgnoreSet,
+    options: &TreeOptions,
+    depth: usize,
+) -> Listing {
+    let mut error = None;
+    let mut children = Vec::new();
+    let mut builder = WalkBuilder::new(dir);
+    builder
         .max_depth(Some(1))
         .hidden(false)
         .git_ignore(true)
         .git_exclude(true)
-        .parents(true)
-        .build()
-        .filter_map(std::result::Result::ok)
-        .filter(|e| e.depth() == 1) // skip the directory itself
-        .filter(|e| !ignore_set.contains(&e.file_name().to_string_lossy().to_string()))
+        .ignore(options.dot_ignore)
+        .parents(true);
+    if options.tool_ignores {
+        for name in TOOL_IGNORE_FILES {
+            builder.add_custom_ignore_filename(name);
+        }
+    }
+    let mut attempt = 0;
+    let errors = loop {
+        let mut errors = Vec::new();
+        for result in builder.build() {
+            match result {
+                // Depth 0 is the directory itself.
+                Ok(e) if e.depth() == 1 => children.push(e),
+                Ok(_) => {}
+                Err(err) => errors.push(err),
+            }
+        }
+        let retried = errors
+            .iter()
+            .filter_map(ignore::Error::io_error)
+            .any(|io| options.retry_after(dir, attempt, io));
+        if !retried {
+            break errors;
+        }
+        attempt += 1;
+        children.clear();
+    };
+    for err in errors {
+        options.diagnose(&Diagnostic::Unreadable(
+            error_path(&err).unwrap_or(dir).to_path_buf(),
+            io_reason(&err),
+        ));
+        if error.is_none() {
+            error = options.error_note(&err);
+        }
+    }
+
+    children.retain(|e| !ignore_set.matches(e.file_name()));
+    if options.export_ignore {
+        let export_ignore = ExportIgnore::for_dir(dir);
+        children.retain(|e| {
+            let is_dir = e.file_type().is_some_and(|t| t.is_dir());
+            !export_ignore.matches(e.path(), is_dir)
+        });
+    }
+    // `entry_kind` only stats symlinks and entries of unknown type.
+    options.record_stats(children.iter().filter(|e| needs_stat(e)).count());
+    let mut children: Vec<(DirEntry, EntryKind)> = children
+        .into_iter()
+        .map(|e| {
+            let kind = entry_kind(&e);
+            (e, kind)
+        })
+        .filter(|(e, kind)| {
+            options.admits(e.path(), *kind)
+                && options.accepts(&EntryInfo {
+                    path: e.path(),
+                    name: e.file_name(),
+                    kind: *kind,
+                    depth,
+                })
+        })
         .collect();
 
-    // Sort: dirs first, then files, then case‑sensitive name.
-    children.sort_by(|a, b| match (a.path().is_dir(), b.path().is_dir()) {
-        (true, false) => std::cmp::Ordering::Less,
-        (false, true) => std::cmp::Ordering::Greater,
-        _ => a.file_name().cmp(b.file_name()),
+    // Sort: group per `options.sort`, then name.
+    let group = |kind: EntryKind| {
+        let is_dir = kind == EntryKind::Directory;
+        match options.sort.grouping {
+            Grouping::DirsFirst => !is_dir,
+            Grouping::FilesFirst => is_dir,
+            Grouping::Mixed => false,
+        }
+    };
+    children.sort_by(|(a, a_kind), (b, b_kind)| {
+        group(*a_kind)
+            .cmp(&group(*b_kind))
+            .then_with(|| options.sort.compare_names(a.file_name(), b.file_name()))
     });
-    children
+    let files = children
+        .iter()
+        .filter(|(_, kind)| *kind != EntryKind::Directory)
+        .count();
+    options.record_listing(children.len());
+    Listing {
+        entries: children,
+        error,
+        files,
+    }
 }
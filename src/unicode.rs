@@ -0,0 +1,322 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Canonical decomposition (NFD) for file names.
+//!
+//! macOS stores names decomposed while most other systems keep whatever
+//! bytes they are given, so `café` can exist in two forms that look — and on
+//! some filesystems are — identical. Full Unicode normalization needs large
+//! tables; this module covers the scripts file names overwhelmingly use:
+//! Latin (including Vietnamese), Greek and Cyrillic, generated from Unicode
+//! 14.0.0. Other characters pass through unchanged.
+
+use std::{borrow::Cow, cmp::Ordering, ffi::OsStr};
+
+/// Order file names by their canonical decomposition, so `café` sorts the
+/// same whether it is stored composed or decomposed; names equal after
+/// decomposition fall back to their raw bytes.
+///
+/// Every name, UTF‑8 or not, is compared through the same [`sort_key`], so
+/// the order is total and consistent: sorting never depends on the order
+/// the names came in.
+pub fn compare_names(a: &OsStr, b: &OsStr) -> Ordering {
+    sort_key(a, false)
+        .cmp(&sort_key(b, false))
+        .then_with(|| a.cmp(b))
+}
+
+/// Bytes that order `name`: the canonical decomposition of its UTF‑8 runs,
+/// lower‑cased with `fold`, with any bytes that are not UTF‑8 kept as is.
+///
+/// Comparing non‑UTF‑8 names byte‑wise and the others decomposed would not
+/// be transitive (`é` < `f` < `\xC0` < `é`), letting enumeration order leak
+/// into sorted output.
+pub fn sort_key(name: &OsStr, fold: bool) -> Cow<'_, [u8]> {
+    let mut rest = name.as_encoded_bytes();
+    if !fold && rest.is_ascii() {
+        return Cow::Borrowed(rest);
+    }
+    let mut key = Vec::with_capacity(rest.len());
+    while !rest.is_empty() {
+        let (valid, invalid) = match std::str::from_utf8(rest) {
+            Ok(valid) => (valid, 0),
+            Err(err) => {
+                let valid = std::str::from_utf8(&rest[..err.valid_up_to()]).unwrap_or_default();
+                (valid, err.error_len().unwrap_or(rest.len() - valid.len()))
+            }
+        };
+        let decomposed = nfd(valid);
+        if fold {
+            key.extend_from_slice(decomposed.to_lowercase().as_bytes());
+        } else {
+            key.extend_from_slice(decomposed.as_bytes());
+        }
+        let end = valid.len() + invalid;
+        key.extend_from_slice(&rest[valid.len()..end]);
+        rest = &rest[end..];
+    }
+    Cow::Owned(key)
+}
+
+/// Canonical decomposition of `name`, with combining marks in canonical
+/// order. ASCII names are returned as is without allocating.
+pub fn nfd(name: &str) -> Cow<'_, str> {
+    if name.is_ascii() {
+        return Cow::Borrowed(name);
+    }
+    let mut chars: Vec<char> = Vec::with_capacity(name.len());
+    for c in name.chars() {
+        match DECOMPOSITIONS.binary_search_by_key(&c, |&(composed, _)| composed) {
+            Ok(at) => chars.extend(DECOMPOSITIONS[at].1.chars()),
+            Err(_) => chars.push(c),
+        }
+    }
+    // Canonical ordering: stable‑sort each run of combining marks by class.
+    let mut start = 0;
+    while start < chars.len() {
+        if combining_class(chars[start]) == 0 {
+            start += 1;
+            continue;
+        }
+        let end = chars[start..]
+            .iter()
+            .position(|&c| combining_class(c) == 0)
+            .map_or(chars.len(), |len| start + len);
+        chars[start..end].sort_by_key(|&c| combining_class(c));
+        start = end;
+    }
+    Cow::Owned(chars.into_iter().collect())
+}
+
+/// Canonical combining class of `c`: 0 for base characters, 230 for most
+/// marks above, and the specific class of the other marks covered here.
+fn combining_class(c: char) -> u8 {
+    if !('\u{300}'..='\u{36f}').contains(&c) && !('\u{483}'..='\u{487}').contains(&c) {
+        return 0;
+    }
+    COMBINING_CLASSES
+        .binary_search_by_key(&c, |&(mark, _)| mark)
+        .map_or(230, |at| COMBINING_CLASSES[at].1)
+}
+
+/// Precomposed characters and their full canonical decompositions, sorted.
+#[rustfmt::skip]
+const DECOMPOSITIONS: &[(char, &str)] = &[
+    ('\u{c0}', "A\u{300}"), ('\u{c1}', "A\u{301}"), ('\u{c2}', "A\u{302}"), ('\u{c3}', "A\u{303}"),
+    ('\u{c4}', "A\u{308}"), ('\u{c5}', "A\u{30a}"), ('\u{c7}', "C\u{327}"), ('\u{c8}', "E\u{300}"),
+    ('\u{c9}', "E\u{301}"), ('\u{ca}', "E\u{302}"), ('\u{cb}', "E\u{308}"), ('\u{cc}', "I\u{300}"),
+    ('\u{cd}', "I\u{301}"), ('\u{ce}', "I\u{302}"), ('\u{cf}', "I\u{308}"), ('\u{d1}', "N\u{303}"),
+    ('\u{d2}', "O\u{300}"), ('\u{d3}', "O\u{301}"), ('\u{d4}', "O\u{302}"), ('\u{d5}', "O\u{303}"),
+    ('\u{d6}', "O\u{308}"), ('\u{d9}', "U\u{300}"), ('\u{da}', "U\u{301}"), ('\u{db}', "U\u{302}"),
+    ('\u{dc}', "U\u{308}"), ('\u{dd}', "Y\u{301}"), ('\u{e0}', "a\u{300}"), ('\u{e1}', "a\u{301}"),
+    ('\u{e2}', "a\u{302}"), ('\u{e3}', "a\u{303}"), ('\u{e4}', "a\u{308}"), ('\u{e5}', "a\u{30a}"),
+    ('\u{e7}', "c\u{327}"), ('\u{e8}', "e\u{300}"), ('\u{e9}', "e\u{301}"), ('\u{ea}', "e\u{302}"),
+    ('\u{eb}', "e\u{308}"), ('\u{ec}', "i\u{300}"), ('\u{ed}', "i\u{301}"), ('\u{ee}', "i\u{302}"),
+    ('\u{ef}', "i\u{308}"), ('\u{f1}', "n\u{303}"), ('\u{f2}', "o\u{300}"), ('\u{f3}', "o\u{301}"),
+    ('\u{f4}', "o\u{302}"), ('\u{f5}', "o\u{303}"), ('\u{f6}', "o\u{308}"), ('\u{f9}', "u\u{300}"),
+    ('\u{fa}', "u\u{301}"), ('\u{fb}', "u\u{302}"), ('\u{fc}', "u\u{308}"), ('\u{fd}', "y\u{301}"),
+    ('\u{ff}', "y\u{308}"), ('\u{100}', "A\u{304}"), ('\u{101}', "a\u{304}"), ('\u{102}', "A\u{306}"),
+    ('\u{103}', "a\u{306}"), ('\u{104}', "A\u{328}"), ('\u{105}', "a\u{328}"), ('\u{106}', "C\u{301}"),
+    ('\u{107}', "c\u{301}"), ('\u{108}', "C\u{302}"), ('\u{109}', "c\u{302}"), ('\u{10a}', "C\u{307}"),
+    ('\u{10b}', "c\u{307}"), ('\u{10c}', "C\u{30c}"), ('\u{10d}', "c\u{30c}"), ('\u{10e}', "D\u{30c}"),
+    ('\u{10f}', "d\u{30c}"), ('\u{112}', "E\u{304}"), ('\u{113}', "e\u{304}"), ('\u{114}', "E\u{306}"),
+    ('\u{115}', "e\u{306}"), ('\u{116}', "E\u{307}"), ('\u{117}', "e\u{307}"), ('\u{118}', "E\u{328}"),
+    ('\u{119}', "e\u{328}"), ('\u{11a}', "E\u{30c}"), ('\u{11b}', "e\u{30c}"), ('\u{11c}', "G\u{302}"),
+    ('\u{11d}', "g\u{302}"), ('\u{11e}', "G\u{306}"), ('\u{11f}', "g\u{306}"), ('\u{120}', "G\u{307}"),
+    ('\u{121}', "g\u{307}"), ('\u{122}', "G\u{327}"), ('\u{123}', "g\u{327}"), ('\u{124}', "H\u{302}"),
+    ('\u{125}', "h\u{302}"), ('\u{128}', "I\u{303}"), ('\u{129}', "i\u{303}"), ('\u{12a}', "I\u{304}"),
+    ('\u{12b}', "i\u{304}"), ('\u{12c}', "I\u{306}"), ('\u{12d}', "i\u{306}"), ('\u{12e}', "I\u{328}"),
+    ('\u{12f}', "i\u{328}"), ('\u{130}', "I\u{307}"), ('\u{134}', "J\u{302}"), ('\u{135}', "j\u{302}"),
+    ('\u{136}', "K\u{327}"), ('\u{137}', "k\u{327}"), ('\u{139}', "L\u{301}"), ('\u{13a}', "l\u{301}"),
+    ('\u{13b}', "L\u{327}"), ('\u{13c}', "l\u{327}"), ('\u{13d}', "L\u{30c}"), ('\u{13e}', "l\u{30c}"),
+    ('\u{143}', "N\u{301}"), ('\u{144}', "n\u{301}"), ('\u{145}', "N\u{327}"), ('\u{146}', "n\u{327}"),
+    ('\u{147}', "N\u{30c}"), ('\u{148}', "n\u{30c}"), ('\u{14c}', "O\u{304}"), ('\u{14d}', "o\u{304}"),
+    ('\u{14e}', "O\u{306}"), ('\u{14f}', "o\u{306}"), ('\u{150}', "O\u{30b}"), ('\u{151}', "o\u{30b}"),
+    ('\u{154}', "R\u{301}"), ('\u{155}', "r\u{301}"), ('\u{156}', "R\u{327}"), ('\u{157}', "r\u{327}"),
+    ('\u{158}', "R\u{30c}"), ('\u{159}', "r\u{30c}"), ('\u{15a}', "S\u{301}"), ('\u{15b}', "s\u{301}"),
+    ('\u{15c}', "S\u{302}"), ('\u{15d}', "s\u{302}"), ('\u{15e}', "S\u{327}"), ('\u{15f}', "s\u{327}"),
+    ('\u{160}', "S\u{30c}"), ('\u{161}', "s\u{30c}"), ('\u{162}', "T\u{327}"), ('\u{163}', "t\u{327}"),
+    ('\u{164}', "T\u{30c}"), ('\u{165}', "t\u{30c}"), ('\u{168}', "U\u{303}"), ('\u{169}', "u\u{303}"),
+    ('\u{16a}', "U\u{304}"), ('\u{16b}', "u\u{304}"), ('\u{16c}', "U\u{306}"), ('\u{16d}', "u\u{306}"),
+    ('\u{16e}', "U\u{30a}"), ('\u{16f}', "u\u{30a}"), ('\u{170}', "U\u{30b}"), ('\u{171}', "u\u{30b}"),
+    ('\u{172}', "U\u{328}"), ('\u{173}', "u\u{328}"), ('\u{174}', "W\u{302}"), ('\u{175}', "w\u{302}"),
+    ('\u{176}', "Y\u{302}"), ('\u{177}', "y\u{302}"), ('\u{178}', "Y\u{308}"), ('\u{179}', "Z\u{301}"),
+    ('\u{17a}', "z\u{301}"), ('\u{17b}', "Z\u{307}"), ('\u{17c}', "z\u{307}"), ('\u{17d}', "Z\u{30c}"),
+    ('\u{17e}', "z\u{30c}"), ('\u{1a0}', "O\u{31b}"), ('\u{1a1}', "o\u{31b}"), ('\u{1af}', "U\u{31b}"),
+    ('\u{1b0}', "u\u{31b}"), ('\u{1cd}', "A\u{30c}"), ('\u{1ce}', "a\u{30c}"), ('\u{1cf}', "I\u{30c}"),
+    ('\u{1d0}', "i\u{30c}"), ('\u{1d1}', "O\u{30c}"), ('\u{1d2}', "o\u{30c}"), ('\u{1d3}', "U\u{30c}"),
+    ('\u{1d4}', "u\u{30c}"), ('\u{1d5}', "U\u{308}\u{304}"), ('\u{1d6}', "u\u{308}\u{304}"), ('\u{1d7}', "U\u{308}\u{301}"),
+    ('\u{1d8}', "u\u{308}\u{301}"), ('\u{1d9}', "U\u{308}\u{30c}"), ('\u{1da}', "u\u{308}\u{30c}"), ('\u{1db}', "U\u{308}\u{300}"),
+    ('\u{1dc}', "u\u{308}\u{300}"), ('\u{1de}', "A\u{308}\u{304}"), ('\u{1df}', "a\u{308}\u{304}"), ('\u{1e0}', "A\u{307}\u{304}"),
+    ('\u{1e1}', "a\u{307}\u{304}"), ('\u{1e2}', "\u{c6}\u{304}"), ('\u{1e3}', "\u{e6}\u{304}"), ('\u{1e6}', "G\u{30c}"),
+    ('\u{1e7}', "g\u{30c}"), ('\u{1e8}', "K\u{30c}"), ('\u{1e9}', "k\u{30c}"), ('\u{1ea}', "O\u{328}"),
+    ('\u{1eb}', "o\u{328}"), ('\u{1ec}', "O\u{328}\u{304}"), ('\u{1ed}', "o\u{328}\u{304}"), ('\u{1ee}', "\u{1b7}\u{30c}"),
+    ('\u{1ef}', "\u{292}\u{30c}"), ('\u{1f0}', "j\u{30c}"), ('\u{1f4}', "G\u{301}"), ('\u{1f5}', "g\u{301}"),
+    ('\u{1f8}', "N\u{300}"), ('\u{1f9}', "n\u{300}"), ('\u{1fa}', "A\u{30a}\u{301}"), ('\u{1fb}', "a\u{30a}\u{301}"),
+    ('\u{1fc}', "\u{c6}\u{301}"), ('\u{1fd}', "\u{e6}\u{301}"), ('\u{1fe}', "\u{d8}\u{301}"), ('\u{1ff}', "\u{f8}\u{301}"),
+    ('\u{200}', "A\u{30f}"), ('\u{201}', "a\u{30f}"), ('\u{202}', "A\u{311}"), ('\u{203}', "a\u{311}"),
+    ('\u{204}', "E\u{30f}"), ('\u{205}', "e\u{30f}"), ('\u{206}', "E\u{311}"), ('\u{207}', "e\u{311}"),
+    ('\u{208}', "I\u{30f}"), ('\u{209}', "i\u{30f}"), ('\u{20a}', "I\u{311}"), ('\u{20b}', "i\u{311}"),
+    ('\u{20c}', "O\u{30f}"), ('\u{20d}', "o\u{30f}"), ('\u{20e}', "O\u{311}"), ('\u{20f}', "o\u{311}"),
+    ('\u{210}', "R\u{30f}"), ('\u{211}', "r\u{30f}"), ('\u{212}', "R\u{311}"), ('\u{213}', "r\u{311}"),
+    ('\u{214}', "U\u{30f}"), ('\u{215}', "u\u{30f}"), ('\u{216}', "U\u{311}"), ('\u{217}', "u\u{311}"),
+    ('\u{218}', "S\u{326}"), ('\u{219}', "s\u{326}"), ('\u{21a}', "T\u{326}"), ('\u{21b}', "t\u{326}"),
+    ('\u{21e}', "H\u{30c}"), ('\u{21f}', "h\u{30c}"), ('\u{226}', "A\u{307}"), ('\u{227}', "a\u{307}"),
+    ('\u{228}', "E\u{327}"), ('\u{229}', "e\u{327}"), ('\u{22a}', "O\u{308}\u{304}"), ('\u{22b}', "o\u{308}\u{304}"),
+    ('\u{22c}', "O\u{303}\u{304}"), ('\u{22d}', "o\u{303}\u{304}"), ('\u{22e}', "O\u{307}"), ('\u{22f}', "o\u{307}"),
+    ('\u{230}', "O\u{307}\u{304}"), ('\u{231}', "o\u{307}\u{304}"), ('\u{232}', "Y\u{304}"), ('\u{233}', "y\u{304}"),
+    ('\u{374}', "\u{2b9}"), ('\u{37e}', ";"), ('\u{385}', "\u{a8}\u{301}"), ('\u{386}', "\u{391}\u{301}"),
+    ('\u{387}', "\u{b7}"), ('\u{388}', "\u{395}\u{301}"), ('\u{389}', "\u{397}\u{301}"), ('\u{38a}', "\u{399}\u{301}"),
+    ('\u{38c}', "\u{39f}\u{301}"), ('\u{38e}', "\u{3a5}\u{301}"), ('\u{38f}', "\u{3a9}\u{301}"), ('\u{390}', "\u{3b9}\u{308}\u{301}"),
+    ('\u{3aa}', "\u{399}\u{308}"), ('\u{3ab}', "\u{3a5}\u{308}"), ('\u{3ac}', "\u{3b1}\u{301}"), ('\u{3ad}', "\u{3b5}\u{301}"),
+    ('\u{3ae}', "\u{3b7}\u{301}"), ('\u{3af}', "\u{3b9}\u{301}"), ('\u{3b0}', "\u{3c5}\u{308}\u{301}"), ('\u{3ca}', "\u{3b9}\u{308}"),
+    ('\u{3cb}', "\u{3c5}\u{308}"), ('\u{3cc}', "\u{3bf}\u{301}"), ('\u{3cd}', "\u{3c5}\u{301}"), ('\u{3ce}', "\u{3c9}\u{301}"),
+    ('\u{3d3}', "\u{3d2}\u{301}"), ('\u{3d4}', "\u{3d2}\u{308}"), ('\u{400}', "\u{415}\u{300}"), ('\u{401}', "\u{415}\u{308}"),
+    ('\u{403}', "\u{413}\u{301}"), ('\u{407}', "\u{406}\u{308}"), ('\u{40c}', "\u{41a}\u{301}"), ('\u{40d}', "\u{418}\u{300}"),
+    ('\u{40e}', "\u{423}\u{306}"), ('\u{419}', "\u{418}\u{306}"), ('\u{439}', "\u{438}\u{306}"), ('\u{450}', "\u{435}\u{300}"),
+    ('\u{451}', "\u{435}\u{308}"), ('\u{453}', "\u{433}\u{301}"), ('\u{457}', "\u{456}\u{308}"), ('\u{45c}', "\u{43a}\u{301}"),
+    ('\u{45d}', "\u{438}\u{300}"), ('\u{45e}', "\u{443}\u{306}"), ('\u{476}', "\u{474}\u{30f}"), ('\u{477}', "\u{475}\u{30f}"),
+    ('\u{4c1}', "\u{416}\u{306}"), ('\u{4c2}', "\u{436}\u{306}"), ('\u{4d0}', "\u{410}\u{306}"), ('\u{4d1}', "\u{430}\u{306}"),
+    ('\u{4d2}', "\u{410}\u{308}"), ('\u{4d3}', "\u{430}\u{308}"), ('\u{4d6}', "\u{415}\u{306}"), ('\u{4d7}', "\u{435}\u{306}"),
+    ('\u{4da}', "\u{4d8}\u{308}"), ('\u{4db}', "\u{4d9}\u{308}"), ('\u{4dc}', "\u{416}\u{308}"), ('\u{4dd}', "\u{436}\u{308}"),
+    ('\u{4de}', "\u{417}\u{308}"), ('\u{4df}', "\u{437}\u{308}"), ('\u{4e2}', "\u{418}\u{304}"), ('\u{4e3}', "\u{438}\u{304}"),
+    ('\u{4e4}', "\u{418}\u{308}"), ('\u{4e5}', "\u{438}\u{308}"), ('\u{4e6}', "\u{41e}\u{308}"), ('\u{4e7}', "\u{43e}\u{308}"),
+    ('\u{4ea}', "\u{4e8}\u{308}"), ('\u{4eb}', "\u{4e9}\u{308}"), ('\u{4ec}', "\u{42d}\u{308}"), ('\u{4ed}', "\u{44d}\u{308}"),
+    ('\u{4ee}', "\u{423}\u{304}"), ('\u{4ef}', "\u{443}\u{304}"), ('\u{4f0}', "\u{423}\u{308}"), ('\u{4f1}', "\u{443}\u{308}"),
+    ('\u{4f2}', "\u{423}\u{30b}"), ('\u{4f3}', "\u{443}\u{30b}"), ('\u{4f4}', "\u{427}\u{308}"), ('\u{4f5}', "\u{447}\u{308}"),
+    ('\u{4f8}', "\u{42b}\u{308}"), ('\u{4f9}', "\u{44b}\u{308}"), ('\u{1e00}', "A\u{325}"), ('\u{1e01}', "a\u{325}"),
+    ('\u{1e02}', "B\u{307}"), ('\u{1e03}', "b\u{307}"), ('\u{1e04}', "B\u{323}"), ('\u{1e05}', "b\u{323}"),
+    ('\u{1e06}', "B\u{331}"), ('\u{1e07}', "b\u{331}"), ('\u{1e08}', "C\u{327}\u{301}"), ('\u{1e09}', "c\u{327}\u{301}"),
+    ('\u{1e0a}', "D\u{307}"), ('\u{1e0b}', "d\u{307}"), ('\u{1e0c}', "D\u{323}"), ('\u{1e0d}', "d\u{323}"),
+    ('\u{1e0e}', "D\u{331}"), ('\u{1e0f}', "d\u{331}"), ('\u{1e10}', "D\u{327}"), ('\u{1e11}', "d\u{327}"),
+    ('\u{1e12}', "D\u{32d}"), ('\u{1e13}', "d\u{32d}"), ('\u{1e14}', "E\u{304}\u{300}"), ('\u{1e15}', "e\u{304}\u{300}"),
+    ('\u{1e16}', "E\u{304}\u{301}"), ('\u{1e17}', "e\u{304}\u{301}"), ('\u{1e18}', "E\u{32d}"), ('\u{1e19}', "e\u{32d}"),
+    ('\u{1e1a}', "E\u{330}"), ('\u{1e1b}', "e\u{330}"), ('\u{1e1c}', "E\u{327}\u{306}"), ('\u{1e1d}', "e\u{327}\u{306}"),
+    ('\u{1e1e}', "F\u{307}"), ('\u{1e1f}', "f\u{307}"), ('\u{1e20}', "G\u{304}"), ('\u{1e21}', "g\u{304}"),
+    ('\u{1e22}', "H\u{307}"), ('\u{1e23}', "h\u{307}"), ('\u{1e24}', "H\u{323}"), ('\u{1e25}', "h\u{323}"),
+    ('\u{1e26}', "H\u{308}"), ('\u{1e27}', "h\u{308}"), ('\u{1e28}', "H\u{327}"), ('\u{1e29}', "h\u{327}"),
+    ('\u{1e2a}', "H\u{32e}"), ('\u{1e2b}', "h\u{32e}"), ('\u{1e2c}', "I\u{330}"), ('\u{1e2d}', "i\u{330}"),
+    ('\u{1e2e}', "I\u{308}\u{301}"), ('\u{1e2f}', "i\u{308}\u{301}"), ('\u{1e30}', "K\u{301}"), ('\u{1e31}', "k\u{301}"),
+    ('\u{1e32}', "K\u{323}"), ('\u{1e33}', "k\u{323}"), ('\u{1e34}', "K\u{331}"), ('\u{1e35}', "k\u{331}"),
+    ('\u{1e36}', "L\u{323}"), ('\u{1e37}', "l\u{323}"), ('\u{1e38}', "L\u{323}\u{304}"), ('\u{1e39}', "l\u{323}\u{304}"),
+    ('\u{1e3a}', "L\u{331}"), ('\u{1e3b}', "l\u{331}"), ('\u{1e3c}', "L\u{32d}"), ('\u{1e3d}', "l\u{32d}"),
+    ('\u{1e3e}', "M\u{301}"), ('\u{1e3f}', "m\u{301}"), ('\u{1e40}', "M\u{307}"), ('\u{1e41}', "m\u{307}"),
+    ('\u{1e42}', "M\u{323}"), ('\u{1e43}', "m\u{323}"), ('\u{1e44}', "N\u{307}"), ('\u{1e45}', "n\u{307}"),
+    ('\u{1e46}', "N\u{323}"), ('\u{1e47}', "n\u{323}"), ('\u{1e48}', "N\u{331}"), ('\u{1e49}', "n\u{331}"),
+    ('\u{1e4a}', "N\u{32d}"), ('\u{1e4b}', "n\u{32d}"), ('\u{1e4c}', "O\u{303}\u{301}"), ('\u{1e4d}', "o\u{303}\u{301}"),
+    ('\u{1e4e}', "O\u{303}\u{308}"), ('\u{1e4f}', "o\u{303}\u{308}"), ('\u{1e50}', "O\u{304}\u{300}"), ('\u{1e51}', "o\u{304}\u{300}"),
+    ('\u{1e52}', "O\u{304}\u{301}"), ('\u{1e53}', "o\u{304}\u{301}"), ('\u{1e54}', "P\u{301}"), ('\u{1e55}', "p\u{301}"),
+    ('\u{1e56}', "P\u{307}"), ('\u{1e57}', "p\u{307}"), ('\u{1e58}', "R\u{307}"), ('\u{1e59}', "r\u{307}"),
+    ('\u{1e5a}', "R\u{323}"), ('\u{1e5b}', "r\u{323}"), ('\u{1e5c}', "R\u{323}\u{304}"), ('\u{1e5d}', "r\u{323}\u{304}"),
+    ('\u{1e5e}', "R\u{331}"), ('\u{1e5f}', "r\u{331}"), ('\u{1e60}', "S\u{307}"), ('\u{1e61}', "s\u{307}"),
+    ('\u{1e62}', "S\u{323}"), ('\u{1e63}', "s\u{323}"), ('\u{1e64}', "S\u{301}\u{307}"), ('\u{1e65}', "s\u{301}\u{307}"),
+    ('\u{1e66}', "S\u{30c}\u{307}"), ('\u{1e67}', "s\u{30c}\u{307}"), ('\u{1e68}', "S\u{323}\u{307}"), ('\u{1e69}', "s\u{323}\u{307}"),
+    ('\u{1e6a}', "T\u{307}"), ('\u{1e6b}', "t\u{307}"), ('\u{1e6c}', "T\u{323}"), ('\u{1e6d}', "t\u{323}"),
+    ('\u{1e6e}', "T\u{331}"), ('\u{1e6f}', "t\u{331}"), ('\u{1e70}', "T\u{32d}"), ('\u{1e71}', "t\u{32d}"),
+    ('\u{1e72}', "U\u{324}"), ('\u{1e73}', "u\u{324}"), ('\u{1e74}', "U\u{330}"), ('\u{1e75}', "u\u{330}"),
+    ('\u{1e76}', "U\u{32d}"), ('\u{1e77}', "u\u{32d}"), ('\u{1e78}', "U\u{303}\u{301}"), ('\u{1e79}', "u\u{303}\u{301}"),
+    ('\u{1e7a}', "U\u{304}\u{308}"), ('\u{1e7b}', "u\u{304}\u{308}"), ('\u{1e7c}', "V\u{303}"), ('\u{1e7d}', "v\u{303}"),
+    ('\u{1e7e}', "V\u{323}"), ('\u{1e7f}', "v\u{323}"), ('\u{1e80}', "W\u{300}"), ('\u{1e81}', "w\u{300}"),
+    ('\u{1e82}', "W\u{301}"), ('\u{1e83}', "w\u{301}"), ('\u{1e84}', "W\u{308}"), ('\u{1e85}', "w\u{308}"),
+    ('\u{1e86}', "W\u{307}"), ('\u{1e87}', "w\u{307}"), ('\u{1e88}', "W\u{323}"), ('\u{1e89}', "w\u{323}"),
+    ('\u{1e8a}', "X\u{307}"), ('\u{1e8b}', "x\u{307}"), ('\u{1e8c}', "X\u{308}"), ('\u{1e8d}', "x\u{308}"),
+    ('\u{1e8e}', "Y\u{307}"), ('\u{1e8f}', "y\u{307}"), ('\u{1e90}', "Z\u{302}"), ('\u{1e91}', "z\u{302}"),
+    ('\u{1e92}', "Z\u{323}"), ('\u{1e93}', "z\u{323}"), ('\u{1e94}', "Z\u{331}"), ('\u{1e95}', "z\u{331}"),
+    ('\u{1e96}', "h\u{331}"), ('\u{1e97}', "t\u{308}"), ('\u{1e98}', "w\u{30a}"), ('\u{1e99}', "y\u{30a}"),
+    ('\u{1e9b}', "\u{17f}\u{307}"), ('\u{1ea0}', "A\u{323}"), ('\u{1ea1}', "a\u{323}"), ('\u{1ea2}', "A\u{309}"),
+    ('\u{1ea3}', "a\u{309}"), ('\u{1ea4}', "A\u{302}\u{301}"), ('\u{1ea5}', "a\u{302}\u{301}"), ('\u{1ea6}', "A\u{302}\u{300}"),
+    ('\u{1ea7}', "a\u{302}\u{300}"), ('\u{1ea8}', "A\u{302}\u{309}"), ('\u{1ea9}', "a\u{302}\u{309}"), ('\u{1eaa}', "A\u{302}\u{303}"),
+    ('\u{1eab}', "a\u{302}\u{303}"), ('\u{1eac}', "A\u{323}\u{302}"), ('\u{1ead}', "a\u{323}\u{302}"), ('\u{1eae}', "A\u{306}\u{301}"),
+    ('\u{1eaf}', "a\u{306}\u{301}"), ('\u{1eb0}', "A\u{306}\u{300}"), ('\u{1eb1}', "a\u{306}\u{300}"), ('\u{1eb2}', "A\u{306}\u{309}"),
+    ('\u{1eb3}', "a\u{306}\u{309}"), ('\u{1eb4}', "A\u{306}\u{303}"), ('\u{1eb5}', "a\u{306}\u{303}"), ('\u{1eb6}', "A\u{323}\u{306}"),
+    ('\u{1eb7}', "a\u{323}\u{306}"), ('\u{1eb8}', "E\u{323}"), ('\u{1eb9}', "e\u{323}"), ('\u{1eba}', "E\u{309}"),
+    ('\u{1ebb}', "e\u{309}"), ('\u{1ebc}', "E\u{303}"), ('\u{1ebd}', "e\u{303}"), ('\u{1ebe}', "E\u{302}\u{301}"),
+    ('\u{1ebf}', "e\u{302}\u{301}"), ('\u{1ec0}', "E\u{302}\u{300}"), ('\u{1ec1}', "e\u{302}\u{300}"), ('\u{1ec2}', "E\u{302}\u{309}"),
+    ('\u{1ec3}', "e\u{302}\u{309}"), ('\u{1ec4}', "E\u{302}\u{303}"), ('\u{1ec5}', "e\u{302}\u{303}"), ('\u{1ec6}', "E\u{323}\u{302}"),
+    ('\u{1ec7}', "e\u{323}\u{302}"), ('\u{1ec8}', "I\u{309}"), ('\u{1ec9}', "i\u{309}"), ('\u{1eca}', "I\u{323}"),
+    ('\u{1ecb}', "i\u{323}"), ('\u{1ecc}', "O\u{323}"), ('\u{1ecd}', "o\u{323}"), ('\u{1ece}', "O\u{309}"),
+    ('\u{1ecf}', "o\u{309}"), ('\u{1ed0}', "O\u{302}\u{301}"), ('\u{1ed1}', "o\u{302}\u{301}"), ('\u{1ed2}', "O\u{302}\u{300}"),
+    ('\u{1ed3}', "o\u{302}\u{300}"), ('\u{1ed4}', "O\u{302}\u{309}"), ('\u{1ed5}', "o\u{302}\u{309}"), ('\u{1ed6}', "O\u{302}\u{303}"),
+    ('\u{1ed7}', "o\u{302}\u{303}"), ('\u{1ed8}', "O\u{323}\u{302}"), ('\u{1ed9}', "o\u{323}\u{302}"), ('\u{1eda}', "O\u{31b}\u{301}"),
+    ('\u{1edb}', "o\u{31b}\u{301}"), ('\u{1edc}', "O\u{31b}\u{300}"), ('\u{1edd}', "o\u{31b}\u{300}"), ('\u{1ede}', "O\u{31b}\u{309}"),
+    ('\u{1edf}', "o\u{31b}\u{309}"), ('\u{1ee0}', "O\u{31b}\u{303}"), ('\u{1ee1}', "o\u{31b}\u{303}"), ('\u{1ee2}', "O\u{31b}\u{323}"),
+    ('\u{1ee3}', "o\u{31b}\u{323}"), ('\u{1ee4}', "U\u{323}"), ('\u{1ee5}', "u\u{323}"), ('\u{1ee6}', "U\u{309}"),
+    ('\u{1ee7}', "u\u{309}"), ('\u{1ee8}', "U\u{31b}\u{301}"), ('\u{1ee9}', "u\u{31b}\u{301}"), ('\u{1eea}', "U\u{31b}\u{300}"),
+    ('\u{1eeb}', "u\u{31b}\u{300}"), ('\u{1eec}', "U\u{31b}\u{309}"), ('\u{1eed}', "u\u{31b}\u{309}"), ('\u{1eee}', "U\u{31b}\u{303}"),
+    ('\u{1eef}', "u\u{31b}\u{303}"), ('\u{1ef0}', "U\u{31b}\u{323}"), ('\u{1ef1}', "u\u{31b}\u{323}"), ('\u{1ef2}', "Y\u{300}"),
+    ('\u{1ef3}', "y\u{300}"), ('\u{1ef4}', "Y\u{323}"), ('\u{1ef5}', "y\u{323}"), ('\u{1ef6}', "Y\u{309}"),
+    ('\u{1ef7}', "y\u{309}"), ('\u{1ef8}', "Y\u{303}"), ('\u{1ef9}', "y\u{303}"), ('\u{1f00}', "\u{3b1}\u{313}"),
+    ('\u{1f01}', "\u{3b1}\u{314}"), ('\u{1f02}', "\u{3b1}\u{313}\u{300}"), ('\u{1f03}', "\u{3b1}\u{314}\u{300}"), ('\u{1f04}', "\u{3b1}\u{313}\u{301}"),
+    ('\u{1f05}', "\u{3b1}\u{314}\u{301}"), ('\u{1f06}', "\u{3b1}\u{313}\u{342}"), ('\u{1f07}', "\u{3b1}\u{314}\u{342}"), ('\u{1f08}', "\u{391}\u{313}"),
+    ('\u{1f09}', "\u{391}\u{314}"), ('\u{1f0a}', "\u{391}\u{313}\u{300}"), ('\u{1f0b}', "\u{391}\u{314}\u{300}"), ('\u{1f0c}', "\u{391}\u{313}\u{301}"),
+    ('\u{1f0d}', "\u{391}\u{314}\u{301}"), ('\u{1f0e}', "\u{391}\u{313}\u{342}"), ('\u{1f0f}', "\u{391}\u{314}\u{342}"), ('\u{1f10}', "\u{3b5}\u{313}"),
+    ('\u{1f11}', "\u{3b5}\u{314}"), ('\u{1f12}', "\u{3b5}\u{313}\u{300}"), ('\u{1f13}', "\u{3b5}\u{314}\u{300}"), ('\u{1f14}', "\u{3b5}\u{313}\u{301}"),
+    ('\u{1f15}', "\u{3b5}\u{314}\u{301}"), ('\u{1f18}', "\u{395}\u{313}"), ('\u{1f19}', "\u{395}\u{314}"), ('\u{1f1a}', "\u{395}\u{313}\u{300}"),
+    ('\u{1f1b}', "\u{395}\u{314}\u{300}"), ('\u{1f1c}', "\u{395}\u{313}\u{301}"), ('\u{1f1d}', "\u{395}\u{314}\u{301}"), ('\u{1f20}', "\u{3b7}\u{313}"),
+    ('\u{1f21}', "\u{3b7}\u{314}"), ('\u{1f22}', "\u{3b7}\u{313}\u{300}"), ('\u{1f23}', "\u{3b7}\u{314}\u{300}"), ('\u{1f24}', "\u{3b7}\u{313}\u{301}"),
+    ('\u{1f25}', "\u{3b7}\u{314}\u{301}"), ('\u{1f26}', "\u{3b7}\u{313}\u{342}"), ('\u{1f27}', "\u{3b7}\u{314}\u{342}"), ('\u{1f28}', "\u{397}\u{313}"),
+    ('\u{1f29}', "\u{397}\u{314}"), ('\u{1f2a}', "\u{397}\u{313}\u{300}"), ('\u{1f2b}', "\u{397}\u{314}\u{300}"), ('\u{1f2c}', "\u{397}\u{313}\u{301}"),
+    ('\u{1f2d}', "\u{397}\u{314}\u{301}"), ('\u{1f2e}', "\u{397}\u{313}\u{342}"), ('\u{1f2f}', "\u{397}\u{314}\u{342}"), ('\u{1f30}', "\u{3b9}\u{313}"),
+    ('\u{1f31}', "\u{3b9}\u{314}"), ('\u{1f32}', "\u{3b9}\u{313}\u{300}"), ('\u{1f33}', "\u{3b9}\u{314}\u{300}"), ('\u{1f34}', "\u{3b9}\u{313}\u{301}"),
+    ('\u{1f35}', "\u{3b9}\u{314}\u{301}"), ('\u{1f36}', "\u{3b9}\u{313}\u{342}"), ('\u{1f37}', "\u{3b9}\u{314}\u{342}"), ('\u{1f38}', "\u{399}\u{313}"),
+    ('\u{1f39}', "\u{399}\u{314}"), ('\u{1f3a}', "\u{399}\u{313}\u{300}"), ('\u{1f3b}', "\u{399}\u{314}\u{300}"), ('\u{1f3c}', "\u{399}\u{313}\u{301}"),
+    ('\u{1f3d}', "\u{399}\u{314}\u{301}"), ('\u{1f3e}', "\u{399}\u{313}\u{342}"), ('\u{1f3f}', "\u{399}\u{314}\u{342}"), ('\u{1f40}', "\u{3bf}\u{313}"),
+    ('\u{1f41}', "\u{3bf}\u{314}"), ('\u{1f42}', "\u{3bf}\u{313}\u{300}"), ('\u{1f43}', "\u{3bf}\u{314}\u{300}"), ('\u{1f44}', "\u{3bf}\u{313}\u{301}"),
+    ('\u{1f45}', "\u{3bf}\u{314}\u{301}"), ('\u{1f48}', "\u{39f}\u{313}"), ('\u{1f49}', "\u{39f}\u{314}"), ('\u{1f4a}', "\u{39f}\u{313}\u{300}"),
+    ('\u{1f4b}', "\u{39f}\u{314}\u{300}"), ('\u{1f4c}', "\u{39f}\u{313}\u{301}"), ('\u{1f4d}', "\u{39f}\u{314}\u{301}"), ('\u{1f50}', "\u{3c5}\u{313}"),
+    ('\u{1f51}', "\u{3c5}\u{314}"), ('\u{1f52}', "\u{3c5}\u{313}\u{300}"), ('\u{1f53}', "\u{3c5}\u{314}\u{300}"), ('\u{1f54}', "\u{3c5}\u{313}\u{301}"),
+    ('\u{1f55}', "\u{3c5}\u{314}\u{301}"), ('\u{1f56}', "\u{3c5}\u{313}\u{342}"), ('\u{1f57}', "\u{3c5}\u{314}\u{342}"), ('\u{1f59}', "\u{3a5}\u{314}"),
+    ('\u{1f5b}', "\u{3a5}\u{314}\u{300}"), ('\u{1f5d}', "\u{3a5}\u{314}\u{301}"), ('\u{1f5f}', "\u{3a5}\u{314}\u{342}"), ('\u{1f60}', "\u{3c9}\u{313}"),
+    ('\u{1f61}', "\u{3c9}\u{314}"), ('\u{1f62}', "\u{3c9}\u{313}\u{300}"), ('\u{1f63}', "\u{3c9}\u{314}\u{300}"), ('\u{1f64}', "\u{3c9}\u{313}\u{301}"),
+    ('\u{1f65}', "\u{3c9}\u{314}\u{301}"), ('\u{1f66}', "\u{3c9}\u{313}\u{342}"), ('\u{1f67}', "\u{3c9}\u{314}\u{342}"), ('\u{1f68}', "\u{3a9}\u{313}"),
+    ('\u{1f69}', "\u{3a9}\u{314}"), ('\u{1f6a}', "\u{3a9}\u{313}\u{300}"), ('\u{1f6b}', "\u{3a9}\u{314}\u{300}"), ('\u{1f6c}', "\u{3a9}\u{313}\u{301}"),
+    ('\u{1f6d}', "\u{3a9}\u{314}\u{301}"), ('\u{1f6e}', "\u{3a9}\u{313}\u{342}"), ('\u{1f6f}', "\u{3a9}\u{314}\u{342}"), ('\u{1f70}', "\u{3b1}\u{300}"),
+    ('\u{1f71}', "\u{3b1}\u{301}"), ('\u{1f72}', "\u{3b5}\u{300}"), ('\u{1f73}', "\u{3b5}\u{301}"), ('\u{1f74}', "\u{3b7}\u{300}"),
+    ('\u{1f75}', "\u{3b7}\u{301}"), ('\u{1f76}', "\u{3b9}\u{300}"), ('\u{1f77}', "\u{3b9}\u{301}"), ('\u{1f78}', "\u{3bf}\u{300}"),
+    ('\u{1f79}', "\u{3bf}\u{301}"), ('\u{1f7a}', "\u{3c5}\u{300}"), ('\u{1f7b}', "\u{3c5}\u{301}"), ('\u{1f7c}', "\u{3c9}\u{300}"),
+    ('\u{1f7d}', "\u{3c9}\u{301}"), ('\u{1f80}', "\u{3b1}\u{313}\u{345}"), ('\u{1f81}', "\u{3b1}\u{314}\u{345}"), ('\u{1f82}', "\u{3b1}\u{313}\u{300}\u{345}"),
+    ('\u{1f83}', "\u{3b1}\u{314}\u{300}\u{345}"), ('\u{1f84}', "\u{3b1}\u{313}\u{301}\u{345}"), ('\u{1f85}', "\u{3b1}\u{314}\u{301}\u{345}"), ('\u{1f86}', "\u{3b1}\u{313}\u{342}\u{345}"),
+    ('\u{1f87}', "\u{3b1}\u{314}\u{342}\u{345}"), ('\u{1f88}', "\u{391}\u{313}\u{345}"), ('\u{1f89}', "\u{391}\u{314}\u{345}"), ('\u{1f8a}', "\u{391}\u{313}\u{300}\u{345}"),
+    ('\u{1f8b}', "\u{391}\u{314}\u{300}\u{345}"), ('\u{1f8c}', "\u{391}\u{313}\u{301}\u{345}"), ('\u{1f8d}', "\u{391}\u{314}\u{301}\u{345}"), ('\u{1f8e}', "\u{391}\u{313}\u{342}\u{345}"),
+    ('\u{1f8f}', "\u{391}\u{314}\u{342}\u{345}"), ('\u{1f90}', "\u{3b7}\u{313}\u{345}"), ('\u{1f91}', "\u{3b7}\u{314}\u{345}"), ('\u{1f92}', "\u{3b7}\u{313}\u{300}\u{345}"),
+    ('\u{1f93}', "\u{3b7}\u{314}\u{300}\u{345}"), ('\u{1f94}', "\u{3b7}\u{313}\u{301}\u{345}"), ('\u{1f95}', "\u{3b7}\u{314}\u{301}\u{345}"), ('\u{1f96}', "\u{3b7}\u{313}\u{342}\u{345}"),
+    ('\u{1f97}', "\u{3b7}\u{314}\u{342}\u{345}"), ('\u{1f98}', "\u{397}\u{313}\u{345}"), ('\u{1f99}', "\u{397}\u{314}\u{345}"), ('\u{1f9a}', "\u{397}\u{313}\u{300}\u{345}"),
+    ('\u{1f9b}', "\u{397}\u{314}\u{300}\u{345}"), ('\u{1f9c}', "\u{397}\u{313}\u{301}\u{345}"), ('\u{1f9d}', "\u{397}\u{314}\u{301}\u{345}"), ('\u{1f9e}', "\u{397}\u{313}\u{342}\u{345}"),
+    ('\u{1f9f}', "\u{397}\u{314}\u{342}\u{345}"), ('\u{1fa0}', "\u{3c9}\u{313}\u{345}"), ('\u{1fa1}', "\u{3c9}\u{314}\u{345}"), ('\u{1fa2}', "\u{3c9}\u{313}\u{300}\u{345}"),
+    ('\u{1fa3}', "\u{3c9}\u{314}\u{300}\u{345}"), ('\u{1fa4}', "\u{3c9}\u{313}\u{301}\u{345}"), ('\u{1fa5}', "\u{3c9}\u{314}\u{301}\u{345}"), ('\u{1fa6}', "\u{3c9}\u{313}\u{342}\u{345}"),
+    ('\u{1fa7}', "\u{3c9}\u{314}\u{342}\u{345}"), ('\u{1fa8}', "\u{3a9}\u{313}\u{345}"), ('\u{1fa9}', "\u{3a9}\u{314}\u{345}"), ('\u{1faa}', "\u{3a9}\u{313}\u{300}\u{345}"),
+    ('\u{1fab}', "\u{3a9}\u{314}\u{300}\u{345}"), ('\u{1fac}', "\u{3a9}\u{313}\u{301}\u{345}"), ('\u{1fad}', "\u{3a9}\u{314}\u{301}\u{345}"), ('\u{1fae}', "\u{3a9}\u{313}\u{342}\u{345}"),
+    ('\u{1faf}', "\u{3a9}\u{314}\u{342}\u{345}"), ('\u{1fb0}', "\u{3b1}\u{306}"), ('\u{1fb1}', "\u{3b1}\u{304}"), ('\u{1fb2}', "\u{3b1}\u{300}\u{345}"),
+    ('\u{1fb3}', "\u{3b1}\u{345}"), ('\u{1fb4}', "\u{3b1}\u{301}\u{345}"), ('\u{1fb6}', "\u{3b1}\u{342}"), ('\u{1fb7}', "\u{3b1}\u{342}\u{345}"),
+    ('\u{1fb8}', "\u{391}\u{306}"), ('\u{1fb9}', "\u{391}\u{304}"), ('\u{1fba}', "\u{391}\u{300}"), ('\u{1fbb}', "\u{391}\u{301}"),
+    ('\u{1fbc}', "\u{391}\u{345}"), ('\u{1fbe}', "\u{3b9}"), ('\u{1fc1}', "\u{a8}\u{342}"), ('\u{1fc2}', "\u{3b7}\u{300}\u{345}"),
+    ('\u{1fc3}', "\u{3b7}\u{345}"), ('\u{1fc4}', "\u{3b7}\u{301}\u{345}"), ('\u{1fc6}', "\u{3b7}\u{342}"), ('\u{1fc7}', "\u{3b7}\u{342}\u{345}"),
+    ('\u{1fc8}', "\u{395}\u{300}"), ('\u{1fc9}', "\u{395}\u{301}"), ('\u{1fca}', "\u{397}\u{300}"), ('\u{1fcb}', "\u{397}\u{301}"),
+    ('\u{1fcc}', "\u{397}\u{345}"), ('\u{1fcd}', "\u{1fbf}\u{300}"), ('\u{1fce}', "\u{1fbf}\u{301}"), ('\u{1fcf}', "\u{1fbf}\u{342}"),
+    ('\u{1fd0}', "\u{3b9}\u{306}"), ('\u{1fd1}', "\u{3b9}\u{304}"), ('\u{1fd2}', "\u{3b9}\u{308}\u{300}"), ('\u{1fd3}', "\u{3b9}\u{308}\u{301}"),
+    ('\u{1fd6}', "\u{3b9}\u{342}"), ('\u{1fd7}', "\u{3b9}\u{308}\u{342}"), ('\u{1fd8}', "\u{399}\u{306}"), ('\u{1fd9}', "\u{399}\u{304}"),
+    ('\u{1fda}', "\u{399}\u{300}"), ('\u{1fdb}', "\u{399}\u{301}"), ('\u{1fdd}', "\u{1ffe}\u{300}"), ('\u{1fde}', "\u{1ffe}\u{301}"),
+    ('\u{1fdf}', "\u{1ffe}\u{342}"), ('\u{1fe0}', "\u{3c5}\u{306}"), ('\u{1fe1}', "\u{3c5}\u{304}"), ('\u{1fe2}', "\u{3c5}\u{308}\u{300}"),
+    ('\u{1fe3}', "\u{3c5}\u{308}\u{301}"), ('\u{1fe4}', "\u{3c1}\u{313}"), ('\u{1fe5}', "\u{3c1}\u{314}"), ('\u{1fe6}', "\u{3c5}\u{342}"),
+    ('\u{1fe7}', "\u{3c5}\u{308}\u{342}"), ('\u{1fe8}', "\u{3a5}\u{306}"), ('\u{1fe9}', "\u{3a5}\u{304}"), ('\u{1fea}', "\u{3a5}\u{300}"),
+    ('\u{1feb}', "\u{3a5}\u{301}"), ('\u{1fec}', "\u{3a1}\u{314}"), ('\u{1fed}', "\u{a8}\u{300}"), ('\u{1fee}', "\u{a8}\u{301}"),
+    ('\u{1fef}', "`"), ('\u{1ff2}', "\u{3c9}\u{300}\u{345}"), ('\u{1ff3}', "\u{3c9}\u{345}"), ('\u{1ff4}', "\u{3c9}\u{301}\u{345}"),
+    ('\u{1ff6}', "\u{3c9}\u{342}"), ('\u{1ff7}', "\u{3c9}\u{342}\u{345}"), ('\u{1ff8}', "\u{39f}\u{300}"), ('\u{1ff9}', "\u{39f}\u{301}"),
+    ('\u{1ffa}', "\u{3a9}\u{300}"), ('\u{1ffb}', "\u{3a9}\u{301}"), ('\u{1ffc}', "\u{3a9}\u{345}"), ('\u{1ffd}', "\u{b4}"),
+];
+
+/// Marks of the covered ranges whose class is not 230, sorted.
+#[rustfmt::skip]
+const COMBINING_CLASSES: &[(char, u8)] = &[
+    ('\u{315}', 232), ('\u{316}', 220), ('\u{317}', 220), ('\u{318}', 220), ('\u{319}', 220), ('\u{31a}', 232),
+    ('\u{31b}', 216), ('\u{31c}', 220), ('\u{31d}', 220), ('\u{31e}', 220), ('\u{31f}', 220), ('\u{320}', 220),
+    ('\u{321}', 202), ('\u{322}', 202), ('\u{323}', 220), ('\u{324}', 220), ('\u{325}', 220), ('\u{326}', 220),
+    ('\u{327}', 202), ('\u{328}', 202), ('\u{329}', 220), ('\u{32a}', 220), ('\u{32b}', 220), ('\u{32c}', 220),
+    ('\u{32d}', 220), ('\u{32e}', 220), ('\u{32f}', 220), ('\u{330}', 220), ('\u{331}', 220), ('\u{332}', 220),
+    ('\u{333}', 220), ('\u{334}', 1), ('\u{335}', 1), ('\u{336}', 1), ('\u{337}', 1), ('\u{338}', 1),
+    ('\u{339}', 220), ('\u{33a}', 220), ('\u{33b}', 220), ('\u{33c}', 220), ('\u{345}', 240), ('\u{347}', 220),
+    ('\u{348}', 220), ('\u{349}', 220), ('\u{34d}', 220), ('\u{34e}', 220), ('\u{34f}', 0), ('\u{353}', 220),
+    ('\u{354}', 220), ('\u{355}', 220), ('\u{356}', 220), ('\u{358}', 232), ('\u{359}', 220), ('\u{35a}', 220),
+    ('\u{35c}', 233), ('\u{35d}', 234), ('\u{35e}', 234), ('\u{35f}', 233), ('\u{360}', 234), ('\u{361}', 234),
+    ('\u{362}', 233),
+];
@@ -0,0 +1,388 @@
+// SPDX‑License‑Identifier: MIT
+// Copyright (c) 2025 Robert Nio
+
+//! Polling watch mode.
+//!
+//! `tree --watch` prints the tree and then re‑scans it at a fixed interval,
+//! comparing the type, size and modification time of every entry with the
+//! previous scan. Each difference becomes a [`WatchEvent`]: the tree is
+//! printed again, and with `--log-changes` the events are appended to a
+//! file as JSON lines, one per event. Polling behaves the same on every
+//! platform and filesystem, network mounts included, at the cost of one
+//! metadata walk per interval. The walk applies the same ignore rules as
+//! the printed tree, so nothing under `target/` or `node_modules/` is
+//! scanned or reported, and [`Watcher::include`] narrows the events
+//! further (`--watch-include`).
+//!
+//! A build rewrites hundreds of files within a second or two. A
+//! [`Debouncer`] holds events back until the tree has been quiet for a
+//! while (`--debounce`) so the burst causes one re‑render, and with
+//! `--batch` reduces it to the net change of every path.
+//!
+//! `--notify-cmd` hands every released batch to a shell command, expanded
+//! by [`notify_command`], to trigger builds or desktop notifications.
+
+use crate::{
+    options::TreeOptions,
+    scan::EntryKind,
+    snapshot::{Snapshot, SnapshotEntry},
+    timefmt::utc_timestamp,
+    TreeError,
+};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::{Serialize, Serializer};
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    fmt,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// What happened to an entry between two scans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    /// The entry appeared.
+    Created,
+    /// The entry disappeared.
+    Removed,
+    /// The entry's type, size or modification time changed.
+    Modified,
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Created => "created",
+            Self::Removed => "removed",
+            Self::Modified => "modified",
+        })
+    }
+}
+
+/// One change seen by a [`Watcher`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WatchEvent {
+    /// When the change was noticed, written as an RFC 3339 UTC timestamp.
+    #[serde(serialize_with = "serialize_time")]
+    pub time: SystemTime,
+    /// What happened.
+    #[serde(rename = "event")]
+    pub kind: EventKind,
+    /// Path relative to the watched root, `/`‑separated.
+    pub path: String,
+}
+
+/// Serialize a time with [`utc_timestamp`].
+fn serialize_time<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&utc_timestamp(*time))
+}
+
+/// Anything that reports batches of [`WatchEvent`]s when asked.
+pub trait EventSource {
+    /// Events since the previous call, in path order; empty when nothing
+    /// happened.
+    ///
+    /// # Errors
+    /// Returns an error when the source cannot be read any more.
+    fn poll(&mut self) -> Result<Vec<WatchEvent>, TreeError>;
+}
+
+/// Re‑scans a directory and reports what changed since the previous scan.
+#[derive(Debug)]
+pub struct Watcher {
+    root: PathBuf,
+    options: TreeOptions,
+    last: Snapshot,
+    /// Globs an event's path must match; `None` reports everything.
+    include: Option<Gitignore>,
+}
+
+impl Watcher {
+    /// Take the first scan of `root`, filtered per `options`.
+    ///
+    /// # Errors
+    /// Returns an error when `root` cannot be scanned.
+    pub fn new(root: &Path, options: &TreeOptions) -> Result<Self, TreeError> {
+        Ok(Self {
+            root: root.to_path_buf(),
+            options: options.clone(),
+            last: Snapshot::capture(root, options, false)?,
+            include: None,
+        })
+    }
+
+    /// Report only changes to entries matching one of `globs`, or inside a
+    /// matching directory. Globs use `.gitignore` syntax relative to the
+    /// root: `*.rs` matches at any depth, `src/` a directory, `/docs` only
+    /// at the top.
+    ///
+    /// # Errors
+    /// Returns an error naming the first invalid glob.
+    pub fn include<S: AsRef<str>>(mut self, globs: &[S]) -> Result<Self, TreeError> {
+        let mut builder = GitignoreBuilder::new(&self.root);
+        for glob in globs {
+            builder
+                .add_line(None, glob.as_ref())
+                .map_err(|err| anyhow::anyhow!("invalid --watch-include glob: {err}"))?;
+        }
+        let matcher = builder
+            .build()
+            .map_err(|err| anyhow::anyhow!("invalid --watch-include glob: {err}"))?;
+        self.include = Some(matcher);
+        Ok(self)
+    }
+
+    /// Scan again and return the changes since the previous scan, in path
+    /// order; empty when nothing changed.
+    ///
+    /// # Errors
+    /// Returns an error when the root can no longer be scanned.
+    pub fn poll(&mut self) -> Result<Vec<WatchEvent>, TreeError> {
+        let next = Snapshot::capture(&self.root, &self.options, false)?;
+        let events = diff(&self.last, &next, SystemTime::now())
+            .into_iter()
+            .filter(|(event, kind)| {
+                self.include.as_ref().map_or(true, |include| {
+                    let is_dir = *kind == EntryKind::Directory;
+                    include
+                        .matched_path_or_any_parents(&event.path, is_dir)
+                        .is_ignore()
+                })
+            })
+            .map(|(event, _)| event)
+            .collect();
+        self.last = next;
+        Ok(events)
+    }
+}
+
+impl EventSource for Watcher {
+    fn poll(&mut self) -> Result<Vec<WatchEvent>, TreeError> {
+        Self::poll(self)
+    }
+}
+
+/// Holds events back until their source has been quiet for a while.
+#[derive(Debug, Clone)]
+pub struct Debouncer {
+    quiet: Duration,
+    batch: bool,
+    pending: Vec<WatchEvent>,
+    /// When the latest pending event arrived.
+    last: Option<Instant>,
+}
+
+impl Debouncer {
+    /// Release events once none arrived for `quiet`; with `batch`, reduce
+    /// them to the net change of each path first (see [`coalesce`]). A zero
+    /// `quiet` releases every batch at once.
+    #[must_use]
+    pub const fn new(quiet: Duration, batch: bool) -> Self {
+        Self {
+            quiet,
+            batch,
+            pending: Vec::new(),
+            last: None,
+        }
+    }
+
+    /// Queue `events` that arrived at `now`.
+    pub fn push(&mut self, events: Vec<WatchEvent>, now: Instant) {
+        if !events.is_empty() {
+            self.pending.extend(events);
+            self.last = Some(now);
+        }
+    }
+
+    /// The queued events if the source has been quiet since `now - quiet`;
+    /// `None` while a burst may still be going on or nothing is queued.
+    pub fn take(&mut self, now: Instant) -> Option<Vec<WatchEvent>> {
+        let last = self.last?;
+        if now.saturating_duration_since(last) < self.quiet {
+            return None;
+        }
+        self.last = None;
+        let events = std::mem::take(&mut self.pending);
+        let events = if self.batch { coalesce(events) } else { events };
+        // A burst that cancels out, such as a temporary file, is no change.
+        (!events.is_empty()).then_some(events)
+    }
+
+    /// Poll `source` at `now` and return whatever is ready per
+    /// [`Self::take`].
+    ///
+    /// # Errors
+    /// Returns the error of `source`.
+    pub fn drive<S: EventSource>(
+        &mut self,
+        source: &mut S,
+        now: Instant,
+    ) -> Result<Option<Vec<WatchEvent>>, TreeError> {
+        self.push(source.poll()?, now);
+        Ok(self.take(now))
+    }
+}
+
+/// Reduce `events`, oldest first, to the net change of each path.
+///
+/// Created then removed is nothing, created then modified is created,
+/// removed then created is modified, and modified then removed is removed.
+/// The result is in path order, stamped with each path's latest time.
+#[must_use]
+pub fn coalesce(events: Vec<WatchEvent>) -> Vec<WatchEvent> {
+    let mut net: BTreeMap<String, (Option<EventKind>, SystemTime)> = BTreeMap::new();
+    for event in events {
+        match net.entry(event.path) {
+            Entry::Vacant(slot) => {
+                slot.insert((Some(event.kind), event.time));
+            }
+            Entry::Occupied(mut slot) => {
+                let (kind, time) = slot.get_mut();
+                *kind = merge(*kind, event.kind);
+                *time = event.time;
+            }
+        }
+    }
+    net.into_iter()
+        .filter_map(|(path, (kind, time))| {
+            Some(WatchEvent {
+                time,
+                kind: kind?,
+                path,
+            })
+        })
+        .collect()
+}
+
+/// Net change of a path whose net change so far is `before` (`None`: it
+/// did not exist before and does not now) and that then saw `next`.
+const fn merge(before: Option<EventKind>, next: EventKind) -> Option<EventKind> {
+    use EventKind::{Created, Modified, Removed};
+    match (before, next) {
+        (None | Some(Created), Removed) => None,
+        (None | Some(Created), Created | Modified) => Some(Created),
+        (Some(Removed | Modified), Removed) => Some(Removed),
+        (Some(Removed | Modified), Created | Modified) => Some(Modified),
+    }
+}
+
+/// Changes from `before` to `after`, stamped with `time`, in path order.
+///
+/// Entries below a created or removed directory are reported one by one.
+/// A directory is modified only when it changes type: its own size and
+/// time change with every entry created or removed in it, which is
+/// reported already.
+#[must_use]
+pub fn changes(before: &Snapshot, after: &Snapshot, time: SystemTime) -> Vec<WatchEvent> {
+    diff(before, after, time)
+        .into_iter()
+        .map(|(event, _)| event)
+        .collect()
+}
+
+/// [`changes`] with the type of each entry, as it is now or, when
+/// removed, as it was.
+fn diff(before: &Snapshot, after: &Snapshot, time: SystemTime) -> Vec<(WatchEvent, EntryKind)> {
+    let old = index(&before.root);
+    let new = index(&after.root);
+    let mut events: Vec<(WatchEvent, EntryKind)> = old
+        .iter()
+        .filter_map(|(path, was)| {
+            let (kind, entry) = match new.get(path) {
+                None => (EventKind::Removed, was.0),
+                Some(now) if now != was => (EventKind::Modified, now.0),
+                Some(_) => return None,
+            };
+            Some((path, kind, entry))
+        })
+        .chain(
+            new.iter()
+                .filter(|(path, _)| !old.contains_key(*path))
+                .map(|(path, now)| (path, EventKind::Created, now.0)),
+        )
+        .map(|(path, kind, entry)| {
+            let event = WatchEvent {
+                time,
+                kind,
+                path: path.clone(),
+            };
+            (event, entry)
+        })
+        .collect();
+    events.sort_by(|a, b| a.0.path.cmp(&b.0.path));
+    events
+}
+
+/// Type, size and modification time of every entry below `root`, by
+/// `/`‑joined path; directories count by type alone.
+fn index(root: &SnapshotEntry) -> BTreeMap<String, (EntryKind, u64, SystemTime)> {
+    let mut map = BTreeMap::new();
+    let mut stack: Vec<(String, &SnapshotEntry)> = root
+        .children
+        .iter()
+        .map(|c| (String::from_utf8_lossy(&c.name).into_owned(), c))
+        .collect();
+    while let Some((path, entry)) = stack.pop() {
+        for child in &entry.children {
+            let name = String::from_utf8_lossy(&child.name);
+            stack.push((format!("{path}/{name}"), child));
+        }
+        let state = if entry.kind == EntryKind::Directory {
+            (entry.kind, 0, UNIX_EPOCH)
+        } else {
+            (entry.kind, entry.size, entry.mtime)
+        };
+        map.insert(path, state);
+    }
+    map
+}
+
+/// Expand the `--notify-cmd` `template` for `events` seen below `root`.
+///
+/// `{path}` becomes the changed paths and `{root}` the watched root, each
+/// quoted for the platform shell and separated by spaces; `{count}` becomes
+/// the number of events and `{event}` the kind of the first one. Other text,
+/// including anything substituted, is kept as is.
+#[must_use]
+pub fn notify_command(template: &str, root: &Path, events: &[WatchEvent]) -> String {
+    let mut command = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        command.push_str(&rest[..open]);
+        rest = &rest[open + 1..];
+        let name = rest.find('}').map_or("", |close| &rest[..close]);
+        match name {
+            "path" => {
+                let paths: Vec<String> = events.iter().map(|e| shell_quote(&e.path)).collect();
+                command.push_str(&paths.join(" "));
+            }
+            "root" => command.push_str(&shell_quote(&root.to_string_lossy())),
+            "count" => command.push_str(&events.len().to_string()),
+            "event" => {
+                if let Some(event) = events.first() {
+                    command.push_str(&event.kind.to_string());
+                }
+            }
+            _ => {
+                command.push('{');
+                continue;
+            }
+        }
+        rest = &rest[name.len() + 1..];
+    }
+    command.push_str(rest);
+    command
+}
+
+/// `text` as one word for `sh`.
+#[cfg(not(windows))]
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// `text` as one word for `cmd.exe`.
+#[cfg(windows)]
+fn shell_quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
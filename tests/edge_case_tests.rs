@@ -354,3 +354,2446 @@ fn directory_file_sorting_order() {
         "Directory should come before file in output"
     );
 }
+
+/// `scan` builds the same filtered hierarchy as `print` and aggregates stats.
+#[test]
+fn scan_builds_tree_and_stats() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+
+    fs::write(root.join(".tree_ignore"), "skipped\n").unwrap();
+    fs::create_dir_all(root.join("a/b")).unwrap();
+    fs::create_dir(root.join("skipped")).unwrap();
+    fs::write(root.join("a/b/data.bin"), [0u8; 10]).unwrap();
+    fs::write(root.join("top.txt"), "abc").unwrap();
+
+    let tree = tree::scan(root, &tree::TreeOptions::default()).unwrap();
+    let names: Vec<_> = tree.children.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["a", ".tree_ignore", "top.txt"]);
+    assert_eq!(
+        tree.children[0].children[0].children[0].path,
+        "a/b/data.bin"
+    );
+
+    let stats = tree.stats();
+    assert_eq!(stats.directories, 2);
+    assert_eq!(stats.files, 3);
+    assert_eq!(stats.bytes, 10 + 3 + "skipped\n".len() as u64);
+
+    // The counting fast path agrees with the full scan.
+    let options = tree::TreeOptions::default();
+    assert_eq!(tree::count(root, &options).unwrap(), stats);
+    let dirs_only = tree::TreeOptions {
+        show_files: false,
+        ..options
+    };
+    let counted = tree::count(root, &dirs_only).unwrap();
+    assert_eq!(counted, tree::scan(root, &dirs_only).unwrap().stats());
+    assert_eq!((counted.directories, counted.files), (2, 0));
+}
+
+/// Non‑UTF‑8 names survive structured output: Base64 keeps the exact bytes
+/// next to a lossy display path, Escape encodes them inside `path` itself.
+#[cfg(unix)]
+#[test]
+fn non_utf8_paths_round_trip_in_structured_output() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+    use tree::{PathEncoding, TreeOptions};
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    fs::create_dir(root.join("data")).unwrap();
+    let raw = b"bad\xff\\.txt";
+    fs::write(root.join("data").join(OsStr::from_bytes(raw)), "x").unwrap();
+
+    let scan = |path_encoding| {
+        let options = TreeOptions {
+            path_encoding,
+            ..TreeOptions::default()
+        };
+        tree::scan(root, &options).unwrap().children[0].children[0].clone()
+    };
+
+    let base64 = scan(PathEncoding::Base64);
+    assert_eq!(base64.path, "data/bad\u{fffd}\\.txt");
+    assert_eq!(base64.path_base64.as_deref(), Some("ZGF0YS9iYWT/XC50eHQ="));
+
+    let escaped = scan(PathEncoding::Escape);
+    assert_eq!(escaped.path, "data/bad\\xFF\\\\.txt");
+    assert_eq!(escaped.path_base64, None);
+
+    let lossy = scan(PathEncoding::Lossy);
+    assert_eq!(lossy.path, base64.path);
+    assert_eq!(lossy.path_base64, None);
+}
+
+/// Entry kinds come from the directory listing; only symlinks are stat'ed,
+/// and one that points at a directory still renders as a directory.
+#[cfg(unix)]
+#[test]
+fn only_symlinks_cost_a_stat() {
+    use std::{os::unix::fs::symlink, sync::Arc};
+    use tree::{render, ScanMetrics, TreeOptions};
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir_all(root.join("real/inner")).unwrap();
+    fs::write(root.join("a.txt"), "").unwrap();
+    fs::write(root.join("real/b.txt"), "").unwrap();
+    symlink(root.join("real"), root.join("link")).unwrap();
+    symlink(root.join("a.txt"), root.join("alias")).unwrap();
+
+    let metrics = Arc::new(ScanMetrics::default());
+    let options = TreeOptions {
+        metrics: Some(Arc::clone(&metrics)),
+        ..TreeOptions::default()
+    };
+    let mut out = Vec::new();
+    render(root, &mut out, &options).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains("── link/\n"), "{text}");
+    assert!(text.contains("── alias\n"), "{text}");
+    assert_eq!(metrics.stat_calls(), 2);
+}
+
+/// `auto_skip_over` counts each directory below the root at most once per
+/// render, in both traversal orders, instead of recounting every subtree
+/// at every level.
+#[test]
+fn auto_skip_counts_each_directory_once() {
+    use std::sync::Arc;
+    use tree::{render, ScanMetrics, Traversal, TreeOptions};
+
+    const LEVELS: usize = 40;
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    let mut dir = root.to_path_buf();
+    for level in 0..LEVELS {
+        dir.push(format!("d{level}"));
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("file.txt"), "").unwrap();
+    }
+    fs::create_dir(root.join("big")).unwrap();
+    for i in 0..300 {
+        fs::write(root.join(format!("big/{i}.js")), "").unwrap();
+    }
+
+    for (traversal, max_lines) in [
+        (Traversal::DepthFirst, None),
+        (Traversal::BreadthFirst, Some(10_000)),
+    ] {
+        let metrics = Arc::new(ScanMetrics::default());
+        let options = TreeOptions {
+            auto_skip_over: Some(200),
+            traversal,
+            max_lines,
+            metrics: Some(Arc::clone(&metrics)),
+            ..TreeOptions::default()
+        };
+        let mut out = Vec::new();
+        render(root, &mut out, &options).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("big/ … skipped (300 entries)"), "{text}");
+        assert!(text.contains(&format!("d{}/", LEVELS - 1)), "{text}");
+        // The root and the chain are listed to render and counted once
+        // each; `big` is counted to the limit and once in full.
+        let directories = usize::try_from(metrics.directories()).unwrap();
+        assert!(
+            directories <= 2 * LEVELS + 3,
+            "{traversal:?}: {directories}"
+        );
+    }
+}
+
+/// A symlink back to an ancestor stops the walk with `LimitExceeded` naming
+/// the directory that tripped the guard, in every output format, instead
+/// of descending until the stack or the kernel gives up.
+#[cfg(unix)]
+#[test]
+fn traversal_limits_stop_runaway_walks() {
+    use std::os::unix::fs::symlink;
+    use tree::{render, Limit, Limits, OutputFormat, TreeError, TreeOptions};
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir_all(root.join("a/b")).unwrap();
+    symlink("..", root.join("a/up")).unwrap();
+
+    let run = |format, limits| {
+        let options = TreeOptions {
+            format,
+            limits,
+            ..TreeOptions::default()
+        };
+        render(root, &mut Vec::new(), &options)
+    };
+    let limited = |format, limits| match run(format, limits) {
+        Err(TreeError::LimitExceeded(err)) => Some(err),
+        _ => None,
+    };
+    let hops = Limits {
+        max_symlink_hops: 2,
+        ..Limits::default()
+    };
+    for format in [OutputFormat::Text, OutputFormat::Json] {
+        let err = limited(format, hops).expect("walk was not stopped");
+        assert_eq!(err.limit, Limit::SymlinkHops);
+        assert!(err.path.ends_with("a/up/a/up/a/up"), "{}", err.path);
+    }
+    let err = tree::scan(
+        root,
+        &TreeOptions {
+            limits: hops,
+            ..TreeOptions::default()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err.code(), "limit-exceeded");
+
+    let shallow = Limits {
+        max_depth: 1,
+        ..Limits::default()
+    };
+    let err = run(OutputFormat::Text, shallow).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "depth limit of 1 exceeded at `{}`",
+            root.join("a").display()
+        )
+    );
+    let short = Limits {
+        max_path_len: root.join("a").as_os_str().len(),
+        ..Limits::default()
+    };
+    let err = limited(OutputFormat::Text, short).expect("path length was not limited");
+    assert_eq!(err.limit, Limit::PathLength);
+}
+
+/// `hash_dirs` digests the whole tree before the root line is written, under
+/// the same limits and cancellation as the walk: both end the render with
+/// nothing printed rather than a digest of part of the tree.
+#[test]
+fn directory_hashes_respect_limits_and_cancellation() {
+    use std::os::unix::fs::symlink;
+    use tree::{render, CancelToken, Limit, Limits, TreeError, TreeOptions};
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir_all(root.join("a/b")).unwrap();
+    fs::write(root.join("a/b/c.txt"), "c").unwrap();
+    symlink("..", root.join("a/up")).unwrap();
+
+    let token = CancelToken::new();
+    let options = TreeOptions {
+        hash_dirs: true,
+        cancel: Some(token.clone()),
+        ..TreeOptions::default()
+    };
+    let limited = |options: &TreeOptions| {
+        let mut out = Vec::new();
+        let limit = match render(root, &mut out, options) {
+            Err(TreeError::LimitExceeded(err)) => Some(err.limit),
+            _ => None,
+        };
+        (limit, out)
+    };
+    assert_eq!(limited(&options), (Some(Limit::SymlinkHops), Vec::new()));
+
+    let shallow = TreeOptions {
+        limits: Limits {
+            max_depth: 1,
+            ..Limits::default()
+        },
+        ..options.clone()
+    };
+    assert_eq!(limited(&shallow), (Some(Limit::Depth), Vec::new()));
+
+    fs::remove_file(root.join("a/up")).unwrap();
+    token.cancel();
+    let mut out = Vec::new();
+    let err = render(root, &mut out, &options).unwrap_err();
+    assert!(matches!(err, TreeError::Cancelled(_)), "{err:?}");
+    assert!(out.is_empty());
+}
+
+/// The ways of walking a tree that once recursed per level: text and JSON
+/// output, `--du`, `--hash-dirs` and [`tree::count`].
+fn deep_walks(root: &std::path::Path, limits: tree::Limits) -> Vec<Result<usize, tree::TreeError>> {
+    use tree::{count, render, OutputFormat, TreeOptions};
+
+    let variants = [
+        (OutputFormat::Text, false, false),
+        (OutputFormat::Json, false, false),
+        (OutputFormat::Text, true, false),
+        (OutputFormat::Text, false, true),
+    ];
+    let mut results: Vec<_> = variants
+        .into_iter()
+        .map(|(format, disk_usage, hash_dirs)| {
+            let options = TreeOptions {
+                format,
+                disk_usage,
+                hash_dirs,
+                limits,
+                ..TreeOptions::default()
+            };
+            let mut out = Vec::new();
+            render(root, &mut out, &options).map(|()| String::from_utf8_lossy(&out).lines().count())
+        })
+        .collect();
+    let options = TreeOptions {
+        limits,
+        ..TreeOptions::default()
+    };
+    results.push(count(root, &options).map(|stats| usize::try_from(stats.directories).unwrap()));
+    results
+}
+
+/// Every walk keeps the directories it is inside of on the heap: a tree 200
+/// levels deep is walked on a thread whose 256 KiB stack would not hold one
+/// recursive call per level.
+#[test]
+fn deep_trees_render_without_recursion() {
+    use tree::{render, Limits, TreeOptions};
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    let levels = 200;
+    let deepest = root.join(vec!["d"; levels].join("/"));
+    fs::create_dir_all(&deepest).unwrap();
+    fs::write(deepest.join("leaf.txt"), "").unwrap();
+
+    let dir = root.to_path_buf();
+    let results = std::thread::Builder::new()
+        .stack_size(256 * 1024)
+        .spawn(move || deep_walks(&dir, Limits::default()))
+        .unwrap()
+        .join()
+        .unwrap();
+    let lines: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+    // Text lines, pretty JSON lines, `--du` and `--hash-dirs` text lines and
+    // directories counted.
+    assert_eq!([lines[0], lines[2], lines[3]], [levels + 2; 3]);
+    assert!(lines[1] > 2 * levels, "{lines:?}");
+    assert_eq!(lines[4], levels);
+
+    let mut out = Vec::new();
+    render(root, &mut out, &TreeOptions::default()).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    let last = text.lines().last().unwrap();
+    assert_eq!(last, format!("{}└── leaf.txt", "    ".repeat(levels)));
+}
+
+/// A tree 10 000 levels deep, far more than one recursive call per level
+/// would survive, is walked on the default stack until the depth limit
+/// stops it. Such a tree cannot be created by path, as the kernel refuses
+/// paths past `PATH_MAX`; it is built in pieces moved into one another.
+#[test]
+#[ignore = "builds a 10 000-level tree and walks 512 levels of it six times"]
+fn ten_thousand_levels_hit_the_depth_limit() {
+    use tree::{scan, Limit, Limits, TreeError, TreeOptions};
+
+    const LEVELS: usize = 10_000;
+    const PIECE: usize = 500;
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    let below = vec!["d"; PIECE - 1].join("/");
+    let piece = |n: usize| root.join(format!("piece{n}"));
+    for n in 0..LEVELS / PIECE {
+        fs::create_dir_all(piece(n).join(&below)).unwrap();
+        if n > 0 {
+            fs::rename(piece(n - 1), piece(n).join(&below).join("d")).unwrap();
+        }
+    }
+    fs::rename(piece(LEVELS / PIECE - 1), root.join("d")).unwrap();
+
+    let limited = |result| match result {
+        Err(TreeError::LimitExceeded(err)) => Some(err.limit),
+        _ => None,
+    };
+    for result in deep_walks(root, Limits::default()) {
+        assert_eq!(limited(result), Some(Limit::Depth));
+    }
+    let scanned = scan(root, &TreeOptions::default()).map(|_| 0);
+    assert_eq!(limited(scanned), Some(Limit::Depth));
+
+    // Removing the tree whole would also need paths past `PATH_MAX`: take
+    // it apart piece by piece.
+    let mut top = root.join("d");
+    for n in 0.. {
+        let rest = top.join(&below).join("d");
+        if !rest.exists() {
+            break;
+        }
+        fs::rename(&rest, piece(n)).unwrap();
+        fs::remove_dir_all(&top).unwrap();
+        top = piece(n);
+    }
+    fs::remove_dir_all(top).unwrap();
+}
+
+/// The `filter` hook hides entries (without descending into rejected
+/// directories) and `decorate` annotates them in text and JSON output.
+#[test]
+fn filter_and_decorate_hooks_apply_to_all_outputs() {
+    use tree::{render, EntryKind, OutputFormat, TreeOptions};
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    fs::create_dir_all(root.join("generated/deep")).unwrap();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src/main.rs"), "").unwrap();
+    fs::write(root.join("src/debug.log"), "").unwrap();
+
+    let options = TreeOptions::default()
+        .filter(|e| e.kind == EntryKind::Directory || e.name != "debug.log")
+        .decorate(|e| (e.name == "generated" && e.depth == 1).then(|| "[build]".to_owned()));
+
+    let mut out = Vec::new();
+    render(root, &mut out, &options).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains("generated/  [build]"));
+    assert!(text.contains("main.rs"));
+    assert!(!text.contains("debug.log"));
+
+    let options = TreeOptions {
+        format: OutputFormat::Json,
+        ..options.filter(|e| e.name != "generated")
+    };
+    let mut out = Vec::new();
+    render(root, &mut out, &options).unwrap();
+    let json = String::from_utf8(out).unwrap();
+    assert!(!json.contains("generated"));
+    assert!(!json.contains("deep"));
+}
+
+/// Registered annotators add their notes after the `decorate` hook's, in
+/// registration order, in text and JSON output alike.
+#[test]
+fn annotators_compose_after_decorate() {
+    use tree::{render, Annotation, EntryInfo, FileSize, OutputFormat, TreeOptions};
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    fs::write(root.join("data.bin"), vec![0u8; 2048]).unwrap();
+    fs::write(root.join("notes.txt"), "hi").unwrap();
+
+    let options = TreeOptions::default()
+        .decorate(|e| (e.name == "data.bin").then(|| "[hook]".to_owned()))
+        .annotator(FileSize)
+        .annotator(|e: &EntryInfo<'_>| (e.name == "notes.txt").then(|| Annotation::new("todo")));
+
+    let mut out = Vec::new();
+    render(root, &mut out, &options).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains("data.bin  [hook]  2.0K\n"), "{text}");
+    assert!(text.contains("notes.txt  2  todo\n"), "{text}");
+
+    let options = TreeOptions {
+        format: OutputFormat::Json,
+        ..options
+    };
+    let mut out = Vec::new();
+    render(root, &mut out, &options).unwrap();
+    let json = String::from_utf8(out).unwrap();
+    assert!(json.contains(r#""decoration": "[hook]  2.0K""#), "{json}");
+}
+
+/// With `hyperlinks` enabled every name is wrapped in an OSC 8 link to its
+/// percent-encoded absolute `file://` URL.
+#[test]
+fn hyperlinks_wrap_names_in_osc8() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    fs::write(root.join("my file.txt"), "").unwrap();
+
+    let options = tree::TreeOptions {
+        hyperlinks: true,
+        ..tree::TreeOptions::default()
+    };
+    let mut out = Vec::new();
+    tree::render(root, &mut out, &options).unwrap();
+    let text = String::from_utf8(out).unwrap();
+
+    assert!(text.contains("\u{1b}]8;;file://"));
+    assert!(text.contains("/my%20file.txt\u{1b}\\my file.txt\u{1b}]8;;\u{1b}\\"));
+}
+
+/// `content::is_binary` trusts known extensions and sniffs everything else
+/// for NUL bytes.
+#[test]
+fn test_content_is_binary() {
+    use tree::content::{is_binary, looks_binary};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join("notes"), "plain text\n").unwrap();
+    fs::write(root.join("blob"), b"ab\0cd").unwrap();
+    fs::write(root.join("empty"), "").unwrap();
+    fs::write(root.join("fake.PNG"), "not really an image").unwrap();
+    fs::write(root.join("data.rs"), b"\0").unwrap();
+
+    assert!(!is_binary(&root.join("notes")).unwrap());
+    assert!(is_binary(&root.join("blob")).unwrap());
+    assert!(!is_binary(&root.join("empty")).unwrap());
+    assert!(is_binary(&root.join("fake.PNG")).unwrap());
+    assert!(!is_binary(&root.join("data.rs")).unwrap());
+    assert!(is_binary(&root.join("missing")).is_err());
+    assert!(looks_binary(b"\x7fELF\0"));
+}
+
+/// Scanning and hashing produce identical results for every thread count.
+#[test]
+fn test_threads_do_not_change_output() {
+    use tree::{render, scan, OutputFormat, TreeOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    for dir in ["a", "b", "c", "d", "e"] {
+        fs::create_dir_all(root.join(dir).join("nested")).unwrap();
+        for file in ["1.txt", "2.txt", "nested/3.txt"] {
+            fs::write(root.join(dir).join(file), format!("{dir}/{file}")).unwrap();
+        }
+    }
+
+    let single = TreeOptions {
+        threads: 1,
+        ..TreeOptions::default()
+    };
+    let many = TreeOptions {
+        threads: 4,
+        ..TreeOptions::default()
+    };
+    assert_eq!(scan(root, &single).unwrap(), scan(root, &many).unwrap());
+
+    let sums = |options: TreeOptions| {
+        let mut out = Vec::new();
+        let options = TreeOptions {
+            format: OutputFormat::Sha256sum,
+            ..options
+        };
+        render(root, &mut out, &options).unwrap();
+        out
+    };
+    let sequential = sums(single);
+    assert_eq!(sequential, sums(many));
+    // 15 fixture files plus `.tree_ignore` itself.
+    assert_eq!(String::from_utf8(sequential).unwrap().lines().count(), 16);
+}
+
+/// The streamed JSON document is byte‑identical to serialising the scanned
+/// tree, including optional fields, every kind of entry and directories-only
+/// mode.
+#[test]
+fn test_streamed_json_matches_scan() {
+    use tree::{render, scan, OutputFormat, PathEncoding, TreeOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    fs::create_dir_all(root.join("docs/empty")).unwrap();
+    fs::create_dir_all(root.join("src/deep/er")).unwrap();
+    fs::write(root.join("docs/README.md"), "# Docs \"quoted\"\n").unwrap();
+    fs::write(root.join("src/deep/er/leaf.rs"), "fn main() {}").unwrap();
+    fs::write(root.join("tab\tname.txt"), "x").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::{ffi::OsStrExt, fs::symlink, net::UnixListener};
+        symlink("docs/README.md", root.join("link")).unwrap();
+        symlink("src", root.join("src-link")).unwrap();
+        UnixListener::bind(root.join("app.sock")).unwrap();
+        let raw = std::ffi::OsStr::from_bytes(b"caf\xC0.txt");
+        fs::write(root.join("docs").join(raw), "x").unwrap();
+    }
+
+    for show_files in [true, false] {
+        let options = TreeOptions {
+            show_files,
+            format: OutputFormat::Json,
+            path_encoding: PathEncoding::Base64,
+            annotate_from: Some("README.md".into()),
+            ..TreeOptions::default()
+        }
+        .decorate(|entry| (entry.name == "deep").then(|| "nested".to_owned()));
+
+        let mut streamed = Vec::new();
+        render(root, &mut streamed, &options).unwrap();
+        let expected = serde_json::to_string_pretty(&scan(root, &options).unwrap()).unwrap() + "\n";
+        assert_eq!(String::from_utf8(streamed).unwrap(), expected);
+
+        let options = TreeOptions {
+            pretty: false,
+            ..options
+        };
+        let mut minified = Vec::new();
+        render(root, &mut minified, &options).unwrap();
+        let expected = serde_json::to_string(&scan(root, &options).unwrap()).unwrap() + "\n";
+        assert_eq!(String::from_utf8(minified).unwrap(), expected);
+    }
+}
+
+/// Streaming a synthetic one‑million‑entry tree completes without building
+/// the tree in memory. Slow; run with `cargo test -- --ignored`.
+#[test]
+#[ignore = "creates one million files"]
+fn test_streamed_json_million_entries() {
+    use std::io::{BufRead, BufReader, BufWriter};
+    use tree::{render, OutputFormat, TreeOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().join("tree");
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    for dir in 0..1000 {
+        let dir = root.join(format!("d{dir:03}"));
+        fs::create_dir(&dir).unwrap();
+        for file in 0..999 {
+            fs::File::create(dir.join(format!("f{file:03}"))).unwrap();
+        }
+    }
+
+    let output = temp_dir.path().join("tree.json");
+    let options = TreeOptions {
+        format: OutputFormat::Json,
+        ..TreeOptions::default()
+    };
+    let mut writer = BufWriter::new(fs::File::create(&output).unwrap());
+    render(&root, &mut writer, &options).unwrap();
+    drop(writer);
+
+    let files = BufReader::new(fs::File::open(&output).unwrap())
+        .lines()
+        .filter(|line| line.as_ref().unwrap().trim() == "\"type\": \"file\",")
+        .count();
+    // 999 000 fixture files plus `.tree_ignore`; with the 1 000 directories
+    // that makes one million entries.
+    assert_eq!(files, 999_001);
+}
+
+/// `max_lines` cuts the text output; the traversal order decides which
+/// entries make the cut, and without a limit both orders agree.
+#[test]
+fn test_max_lines_and_traversal() {
+    use tree::{render, Traversal, TreeOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir_all(root.join("a/deep/deeper")).unwrap();
+    fs::create_dir_all(root.join("b")).unwrap();
+    fs::write(root.join("a/deep/deeper/leaf.txt"), "").unwrap();
+    fs::write(root.join("b/file.txt"), "").unwrap();
+    fs::write(root.join("top.txt"), "").unwrap();
+
+    let text = |traversal, max_lines| {
+        let options = TreeOptions {
+            traversal,
+            max_lines,
+            ..TreeOptions::default()
+        };
+        let mut out = Vec::new();
+        render(root, &mut out, &options).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        out.split_once('\n').unwrap().1.to_owned()
+    };
+
+    assert_eq!(
+        text(Traversal::DepthFirst, None),
+        text(Traversal::BreadthFirst, None)
+    );
+    assert_eq!(
+        text(Traversal::DepthFirst, Some(3)),
+        "├── a/\n│   └── deep/\n│       └── deeper/\n… (output limited to 3 lines)\n"
+    );
+    assert_eq!(
+        text(Traversal::BreadthFirst, Some(4)),
+        "├── a/\n│   └── deep/\n├── b/\n└── top.txt\n… (output limited to 4 lines)\n"
+    );
+    // A limit that fits the whole tree adds no note.
+    assert!(!text(Traversal::BreadthFirst, Some(7)).contains('…'));
+}
+
+/// `TermCaps` follows `NO_COLOR`, `CLICOLOR`, `CLICOLOR_FORCE` and
+/// `TERM=dumb`.
+#[test]
+fn test_term_caps_environment() {
+    use std::ffi::OsString;
+    use tree::TermCaps;
+
+    let caps = |tty: bool, vars: &[(&str, &str)]| {
+        let vars: Vec<(String, OsString)> = vars
+            .iter()
+            .map(|(k, v)| ((*k).to_owned(), OsString::from(v)))
+            .collect();
+        TermCaps::from_env(tty, move |name| {
+            vars.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone())
+        })
+    };
+
+    let plain_tty = caps(true, &[("TERM", "xterm-256color")]);
+    assert!(plain_tty.color && plain_tty.hyperlinks && plain_tty.unicode);
+
+    let piped = caps(false, &[]);
+    assert!(!piped.color && !piped.hyperlinks && piped.unicode);
+
+    assert!(!caps(true, &[("NO_COLOR", "1")]).color);
+    assert!(caps(true, &[("NO_COLOR", "")]).color);
+    assert!(!caps(true, &[("CLICOLOR", "0")]).color);
+    assert!(caps(false, &[("CLICOLOR_FORCE", "1")]).color);
+    assert!(!caps(false, &[("CLICOLOR_FORCE", "0")]).color);
+    assert!(!caps(false, &[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")]).color);
+
+    let dumb = caps(true, &[("TERM", "dumb")]);
+    assert!(!dumb.color && !dumb.hyperlinks && !dumb.unicode);
+    assert!(caps(false, &[("TERM", "dumb")]).unicode);
+}
+
+/// The grouping strategy orders text and JSON output identically.
+#[test]
+fn test_grouping_orders_every_format() {
+    use tree::{render, scan, Grouping, SortOptions, TreeOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir(root.join("b_dir")).unwrap();
+    fs::write(root.join("a.txt"), "").unwrap();
+    fs::write(root.join("c.txt"), "").unwrap();
+
+    for (grouping, expected) in [
+        (Grouping::DirsFirst, ["b_dir", "a.txt", "c.txt"]),
+        (Grouping::FilesFirst, ["a.txt", "c.txt", "b_dir"]),
+        (Grouping::Mixed, ["a.txt", "b_dir", "c.txt"]),
+    ] {
+        let options = TreeOptions {
+            sort: SortOptions::grouped(grouping),
+            ..TreeOptions::default()
+        };
+        let names: Vec<String> = scan(root, &options)
+            .unwrap()
+            .children
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(names, expected, "{grouping:?}");
+
+        let mut text = Vec::new();
+        render(root, &mut text, &options).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        let positions: Vec<usize> = expected.iter().map(|n| text.find(n).unwrap()).collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]), "{text}");
+    }
+}
+
+/// `render_core` lays out arbitrary pre‑order rows without any filesystem.
+#[test]
+fn test_render_core_layout() {
+    use tree::render_core::{render_lines, Charset, Layout};
+
+    let rows = [
+        ("a", 1, false, true),
+        ("b", 2, false, true),
+        ("c.txt", 3, true, false),
+        ("d.txt", 2, true, false),
+        ("e", 1, true, true),
+        ("f.txt", 2, true, false),
+    ];
+    let unicode: Vec<String> = render_lines(rows, Charset::Unicode).collect();
+    assert_eq!(
+        unicode,
+        [
+            "├── a/",
+            "│   ├── b/",
+            "│   │   └── c.txt",
+            "│   └── d.txt",
+            "└── e/",
+            "    └── f.txt",
+        ]
+    );
+    let ascii: Vec<String> = render_lines(rows, Charset::Ascii).collect();
+    assert_eq!(ascii[2], "|   |   `-- c.txt");
+
+    let mut layout = Layout::new(Charset::Unicode);
+    assert_eq!(layout.connector(1, true), "└── ");
+    assert_eq!(layout.connector(2, true), "    └── ");
+}
+
+/// The borrowed connector redraws only the levels that changed, matching
+/// the owned and painted forms line for line, without growing its buffer
+/// once the deepest line has been drawn.
+#[test]
+fn test_render_core_reuses_prefix_buffer() {
+    use tree::render_core::{Charset, Layout};
+
+    let steps = [
+        (1, false),
+        (2, false),
+        (3, false),
+        (4, true),
+        (3, true),
+        (2, true),
+        (3, true),
+        (1, false),
+        (4, true),
+        (1, true),
+    ];
+    let mut borrowed = Layout::new(Charset::Unicode);
+    let mut painted = Layout::new(Charset::Unicode);
+    let mut lines = Vec::new();
+    for (depth, is_last) in steps {
+        let line = borrowed.connector_str(depth, is_last).to_owned();
+        assert_eq!(
+            line,
+            painted.connector_with(depth, is_last, |_, s| s.to_owned())
+        );
+        lines.push(line);
+    }
+    assert_eq!(lines[3], "│   │   │   └── ");
+    assert_eq!(lines[6], "│       └── ");
+    assert_eq!(lines[8], "│           └── ");
+    assert_eq!(lines[9], "└── ");
+
+    let mut layout = Layout::new(Charset::Ascii);
+    let deepest = layout.connector_str(8, false).as_ptr();
+    for (depth, is_last) in steps.into_iter().cycle().take(100) {
+        let line = layout.connector_str(depth, is_last);
+        assert_eq!(line.as_ptr(), deepest, "buffer reallocated at {line:?}");
+    }
+}
+
+/// Directories-only output can still show how many files each directory
+/// holds, and the last visible directory gets the closing connector even
+/// when files follow it.
+#[test]
+fn test_dirs_only_file_counts() {
+    use tree::{render, TreeOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    fs::create_dir_all(root.join("src/empty")).unwrap();
+    fs::write(root.join("src/a.rs"), "").unwrap();
+    fs::write(root.join("src/b.rs"), "").unwrap();
+    fs::create_dir(root.join("docs")).unwrap();
+    fs::write(root.join("docs/index.md"), "").unwrap();
+
+    let options = TreeOptions {
+        show_files: false,
+        file_counts: true,
+        ..TreeOptions::default()
+    };
+    let mut out = Vec::new();
+    render(root, &mut out, &options).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(
+        out.split_once('\n').unwrap().1,
+        "├── docs/ (1 file)\n└── src/ (2 files)\n    └── empty/ (0 files)\n"
+    );
+}
+
+/// Extension rollups rank the extensions of each directory's files, cap the
+/// list at five and skip directories without files.
+#[test]
+fn test_dirs_only_extension_rollup() {
+    use tree::{render, TreeOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir_all(root.join("src/empty")).unwrap();
+    for name in ["a.rs", "b.rs", "C.RS", "mod.toml", "notes.md", "Makefile"] {
+        fs::write(root.join("src").join(name), "").unwrap();
+    }
+    fs::create_dir(root.join("assets")).unwrap();
+    for ext in ["png", "svg", "jpg", "gif", "ico", "webp", "bmp"] {
+        fs::write(root.join("assets").join(format!("x.{ext}")), "").unwrap();
+    }
+
+    let options = TreeOptions {
+        show_files: false,
+        extension_rollup: true,
+        ..TreeOptions::default()
+    };
+    let mut out = Vec::new();
+    render(root, &mut out, &options).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(
+        out.split_once('\n').unwrap().1,
+        "├── assets/ [bmp:1 gif:1 ico:1 jpg:1 png:1 +2]\n\
+         └── src/ [rs:3 (none):1 md:1 toml:1]\n    └── empty/\n"
+    );
+}
+
+/// Snapshots round-trip through the binary format, reuse hashes of files
+/// whose size and mtime are unchanged, and re-hash everything else.
+#[test]
+fn test_snapshot_roundtrip_and_incremental_update() {
+    use std::time::{Duration, SystemTime};
+    use tree::{ChangeKind, Snapshot, TreeOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src/lib.rs"), "aaaa").unwrap();
+    fs::write(root.join("README"), "readme").unwrap();
+    let stamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    fs::File::options()
+        .write(true)
+        .open(root.join("src/lib.rs"))
+        .unwrap()
+        .set_modified(stamp)
+        .unwrap();
+
+    let options = TreeOptions::default();
+    let first = Snapshot::capture(root, &options, true).unwrap();
+    assert_eq!(first.len(), 4);
+
+    let mut bytes = Vec::new();
+    first.write_to(&mut bytes).unwrap();
+    assert_eq!(&bytes[..8], b"TREESNAP");
+    assert_eq!(Snapshot::read_from(&mut bytes.as_slice()).unwrap(), first);
+
+    // Same size and mtime: the stale hash is reused, so nothing is reported.
+    fs::write(root.join("src/lib.rs"), "bbbb").unwrap();
+    let file = fs::File::options()
+        .write(true)
+        .open(root.join("src/lib.rs"))
+        .unwrap();
+    file.set_modified(stamp).unwrap();
+    let reused = first.update(root, &options).unwrap();
+    assert!(first.diff(&reused).is_empty());
+
+    // A new mtime forces a re-hash and the content change shows up.
+    file.set_modified(stamp + Duration::from_secs(1)).unwrap();
+    let rehashed = first.update(root, &options).unwrap();
+    let changes = first.diff(&rehashed);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].kind, ChangeKind::Modified);
+    assert_eq!(changes[0].path, "src/lib.rs");
+    assert_eq!(changes[0].detail.as_deref(), Some("content changed"));
+
+    bytes[8] = 99;
+    assert!(Snapshot::read_from(&mut bytes.as_slice()).is_err());
+}
+
+/// A corrupt snapshot declaring a very deep chain of directories is
+/// rejected instead of overflowing the stack.
+#[test]
+fn test_snapshot_rejects_deep_nesting() {
+    use tree::Snapshot;
+
+    const LEVELS: u64 = 100_000;
+    let mut bytes = b"TREESNAP".to_vec();
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&0u16.to_le_bytes());
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(&LEVELS.to_le_bytes());
+    for level in 0..LEVELS {
+        bytes.push(0);
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.push(b'd');
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        bytes.extend_from_slice(&0i64.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&u32::from(level + 1 < LEVELS).to_le_bytes());
+    }
+
+    let err = Snapshot::read_from(&mut bytes.as_slice()).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("nested more than 1024 levels deep"),
+        "{err:#}"
+    );
+}
+
+/// Directory Merkle digests agree for identical subtrees, differ once a
+/// file changes, and are stored in hashed snapshots.
+#[test]
+fn test_hash_dirs_merkle_digests() {
+    use tree::{render, Snapshot, TreeOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    for dir in ["a", "b", "c"] {
+        fs::create_dir_all(root.join(dir).join("sub")).unwrap();
+        fs::write(root.join(dir).join("sub/file.txt"), "same").unwrap();
+    }
+    fs::write(root.join("c/sub/file.txt"), "different").unwrap();
+
+    let options = TreeOptions {
+        show_files: false,
+        hash_dirs: true,
+        ..TreeOptions::default()
+    };
+    let mut out = Vec::new();
+    render(root, &mut out, &options).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let digest = |line: &str| line.split_once("[merkle ").unwrap().1.to_owned();
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 7, "{out}");
+    assert!(lines.iter().all(|line| line.contains("[merkle ")), "{out}");
+    assert_eq!(digest(lines[1]), digest(lines[3]));
+    assert_ne!(digest(lines[1]), digest(lines[5]));
+    assert_eq!(digest(lines[2]), digest(lines[4]));
+
+    let options = TreeOptions::default();
+    let first = Snapshot::capture(root, &options, true).unwrap();
+    assert!(first.root.hash.is_some());
+    let mut bytes = Vec::new();
+    first.write_to(&mut bytes).unwrap();
+    assert_eq!(&bytes[8..10], &2u16.to_le_bytes());
+    assert_eq!(Snapshot::read_from(&mut bytes.as_slice()).unwrap(), first);
+
+    fs::write(root.join("a/sub/file.txt"), "edit").unwrap();
+    let second = Snapshot::capture(root, &options, true).unwrap();
+    assert_ne!(first.root.hash, second.root.hash);
+    let changes = first.diff(&second);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].path, "a/sub/file.txt");
+
+    let plain = Snapshot::capture(root, &options, false).unwrap();
+    assert!(plain.root.hash.is_none());
+}
+
+/// `.ignore` files apply by default and can be turned off; `.fdignore` and
+/// `.rgignore` apply only when asked for.
+#[test]
+fn test_dot_ignore_and_tool_ignore_files() {
+    use tree::{render, TreeOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(
+        root.join(".tree_ignore"),
+        ".tree_ignore\n.ignore\n.fdignore\n.rgignore\n",
+    )
+    .unwrap();
+    fs::write(root.join(".ignore"), "a.log\n").unwrap();
+    fs::write(root.join(".fdignore"), "b.tmp\n").unwrap();
+    fs::write(root.join(".rgignore"), "c.bak\n").unwrap();
+    for name in ["a.log", "b.tmp", "c.bak", "d.rs"] {
+        fs::write(root.join(name), "").unwrap();
+    }
+
+    let listed = |options: &TreeOptions| {
+        let mut out = Vec::new();
+        render(root, &mut out, options).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        out.lines()
+            .skip(1)
+            .map(|l| l.rsplit(' ').next().unwrap().to_owned())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(listed(&TreeOptions::default()), ["b.tmp", "c.bak", "d.rs"]);
+    let all = TreeOptions {
+        dot_ignore: false,
+        ..TreeOptions::default()
+    };
+    assert_eq!(listed(&all), ["a.log", "b.tmp", "c.bak", "d.rs"]);
+    let tools = TreeOptions {
+        tool_ignores: true,
+        ..TreeOptions::default()
+    };
+    assert_eq!(listed(&tools), ["d.rs"]);
+}
+
+/// Pattern case follows the filesystem by default and can be forced either
+/// way; on a case-sensitive filesystem `Build` does not hide `build`.
+#[test]
+fn test_pattern_case_sensitivity() {
+    use tree::{render, CaseSensitivity, TreeOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\nBuild\n").unwrap();
+    fs::create_dir(root.join("build")).unwrap();
+    fs::write(root.join("main.rs"), "").unwrap();
+
+    let lines = |case_sensitivity| {
+        let options = TreeOptions {
+            case_sensitivity,
+            ..TreeOptions::default()
+        };
+        let mut out = Vec::new();
+        render(root, &mut out, &options).unwrap();
+        String::from_utf8(out).unwrap().lines().count() - 1
+    };
+
+    assert_eq!(lines(CaseSensitivity::Sensitive), 2);
+    assert_eq!(lines(CaseSensitivity::Insensitive), 1);
+    // Probing finds the same answer the platform gives for a flipped name.
+    let insensitive = root.join("MAIN.RS").exists();
+    assert_eq!(
+        lines(CaseSensitivity::Auto),
+        if insensitive { 1 } else { 2 }
+    );
+}
+
+/// Sampling keeps the first N shown entries of every directory and closes
+/// it with a `+k more` line.
+#[test]
+fn test_sample_per_directory() {
+    use tree::{render, TreeOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir(root.join("data")).unwrap();
+    for i in 0..5 {
+        fs::write(root.join(format!("data/{i}.csv")), "").unwrap();
+    }
+    fs::write(root.join("notes.txt"), "").unwrap();
+
+    let options = TreeOptions {
+        sample: Some(2),
+        ..TreeOptions::default()
+    };
+    let mut out = Vec::new();
+    render(root, &mut out, &options).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(
+        out.split_once('\n').unwrap().1,
+        "├── data/\n│   ├── 0.csv\n│   ├── 1.csv\n│   └── +3 more\n└── notes.txt\n"
+    );
+}
+
+/// The chainable setters build the same options as struct literals,
+/// wrapping `Option` fields in `Some`.
+#[test]
+fn test_options_builder_setters() {
+    use std::time::Duration;
+    use tree::{Charset, OutputFormat, TreeOptions};
+
+    let built = TreeOptions::default()
+        .with_show_files(false)
+        .with_format(OutputFormat::Json)
+        .with_charset(Charset::Ascii)
+        .with_threads(3)
+        .with_max_lines(10)
+        .with_stat_timeout(Duration::from_secs(2))
+        .with_create_ignore_file(false);
+    let literal = TreeOptions {
+        show_files: false,
+        format: OutputFormat::Json,
+        charset: Charset::Ascii,
+        threads: 3,
+        max_lines: Some(10),
+        stat_timeout: Some(Duration::from_secs(2)),
+        create_ignore_file: false,
+        ..TreeOptions::default()
+    };
+    assert_eq!(format!("{built:?}"), format!("{literal:?}"));
+}
+
+/// Plain settings survive a serde round trip; hooks are skipped and
+/// omitted keys fall back to the defaults.
+#[test]
+fn test_options_serde_round_trip() {
+    use tree::{CaseSensitivity, Charset, OutputFormat, Traversal, TreeOptions};
+
+    let options = TreeOptions {
+        show_files: false,
+        format: OutputFormat::RustFixture,
+        annotate_from: Some("README.md".into()),
+        traversal: Traversal::BreadthFirst,
+        max_lines: Some(40),
+        charset: Charset::Ascii,
+        case_sensitivity: CaseSensitivity::Insensitive,
+        ..TreeOptions::default()
+    }
+    .filter(|_| true);
+
+    let json = serde_json::to_value(&options).unwrap();
+    assert_eq!(json["format"], "rust-fixture");
+    assert_eq!(json["charset"], "ascii");
+    assert!(json.get("filter").is_none());
+
+    let back: TreeOptions = serde_json::from_value(json).unwrap();
+    assert_eq!(
+        format!("{back:?}"),
+        format!(
+            "{:?}",
+            TreeOptions {
+                filter: None,
+                ..options
+            }
+        )
+    );
+
+    let partial: TreeOptions = serde_json::from_str(r#"{"max_lines": 3}"#).unwrap();
+    assert_eq!(partial.max_lines, Some(3));
+    assert_eq!(
+        format!("{partial:?}"),
+        format!(
+            "{:?}",
+            TreeOptions {
+                max_lines: Some(3),
+                ..TreeOptions::default()
+            }
+        )
+    );
+}
+
+/// Capabilities list every output format under its CLI name and the
+/// crate version.
+#[test]
+fn test_capabilities_report() {
+    let caps = tree::capabilities();
+    assert_eq!(caps.version, env!("CARGO_PKG_VERSION"));
+    let names: Vec<_> = caps.formats.iter().map(|f| f.name()).collect();
+    assert_eq!(
+        names,
+        [
+            "text",
+            "json",
+            "sha256sum",
+            "rust-fixture",
+            "ts-fixture",
+            "plain-indent",
+            "ndjson"
+        ]
+    );
+    let json = serde_json::to_value(&caps).unwrap();
+    assert_eq!(json["formats"][3], "rust-fixture");
+    assert_eq!(
+        json["features"].as_array().unwrap().len(),
+        caps.features.len()
+    );
+}
+
+/// Every time style formats a fixed instant like `ls --time-style` (UTC).
+#[test]
+fn test_time_styles() {
+    use std::time::{Duration, SystemTime};
+    use tree::{format_time, TimeStyle};
+
+    // 2024-12-31 23:05:09.5 UTC, a Tuesday and day 366 of a leap year.
+    let at = SystemTime::UNIX_EPOCH + Duration::new(1_735_686_309, 500_000_000);
+    let soon = at + Duration::from_secs(3 * 86_400 + 7);
+    let later = at + Duration::from_secs(400 * 86_400);
+    let style = |s: &str| s.parse::<TimeStyle>().unwrap();
+
+    assert_eq!(
+        format_time(at, &style("long-iso"), soon),
+        "2024-12-31 23:05"
+    );
+    assert_eq!(format_time(at, &style("iso"), soon), "12-31 23:05");
+    assert_eq!(format_time(at, &style("iso"), later), "2024-12-31 ");
+    assert_eq!(
+        format_time(at, &style("full-iso"), soon),
+        "2024-12-31 23:05:09.500000000 +0000"
+    );
+    assert_eq!(format_time(at, &style("relative"), soon), "3 days ago");
+    assert_eq!(format_time(soon, &style("relative"), at), "in 3 days");
+    assert_eq!(format_time(at, &style("relative"), at), "just now");
+    assert_eq!(format_time(at, &style("relative"), later), "1 year ago");
+    assert_eq!(
+        format_time(at, &style("+%a %A %e %b %B %y %j %s %% %q"), soon),
+        "Tue Tuesday 31 Dec December 24 366 1735686309 % %q"
+    );
+    assert!("yesterday".parse::<TimeStyle>().is_err());
+    assert_eq!(style("+%Y").to_string(), "+%Y");
+}
+
+/// Disk usage counts allocated blocks (so sparse files stay small), counts
+/// hard-linked data once, and agrees with `du` where it is available.
+#[test]
+fn test_disk_usage_matches_du() {
+    use tree::{human_size, DiskUsage};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("a/b")).unwrap();
+    fs::write(root.join("a/data.bin"), vec![7u8; 256 * 1024]).unwrap();
+    fs::hard_link(root.join("a/data.bin"), root.join("a/b/link.bin")).unwrap();
+    fs::File::create(root.join("sparse.img"))
+        .unwrap()
+        .set_len(64 * 1024 * 1024)
+        .unwrap();
+
+    let total = DiskUsage::new().total(root);
+    assert!(total >= 256 * 1024, "{total}");
+    assert!(total < 2 * 256 * 1024, "hard link counted twice: {total}");
+
+    if let Ok(output) = std::process::Command::new("du")
+        .arg("-sB1")
+        .arg(root)
+        .output()
+    {
+        if output.status.success() {
+            let du: u64 = String::from_utf8(output.stdout)
+                .unwrap()
+                .split_whitespace()
+                .next()
+                .unwrap()
+                .parse()
+                .unwrap();
+            assert_eq!(total, du);
+        }
+    }
+
+    assert_eq!(human_size(0), "0");
+    assert_eq!(human_size(512), "512");
+    assert_eq!(human_size(4096), "4.0K");
+    assert_eq!(human_size(4097), "4.1K");
+    assert_eq!(human_size(10 * 1024), "10K");
+    assert_eq!(human_size(12 * 1024 * 1024 + 1), "13M");
+    assert_eq!(human_size(1024 * 1024), "1.0M");
+}
+
+/// Path lists become trees with implied parent directories, NUL or newline
+/// separated, sorted like a directory walk.
+#[test]
+fn tree_from_path_list() {
+    use tree::{
+        parse_path_list, render_node, tree_from_paths, EntryKind, Grouping, SortOptions,
+        TreeOptions,
+    };
+
+    assert_eq!(parse_path_list("a\r\n\nb/c\n"), ["a", "b/c"]);
+    assert_eq!(parse_path_list("a\nb\0c\0"), ["a\nb", "c"]);
+
+    let paths = ["./README.md", "src//main.rs", "src/lib.rs", "docs/", "src"];
+    let tree = tree_from_paths("repo", paths, SortOptions::default());
+    let names: Vec<_> = tree.children.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["docs", "src", "README.md"]);
+    assert_eq!(tree.children[0].kind, EntryKind::Directory);
+    assert_eq!(tree.children[1].children[1].path, "src/main.rs");
+    assert_eq!(tree.stats().files, 3);
+
+    let tree = tree_from_paths("repo", paths, SortOptions::grouped(Grouping::FilesFirst));
+    let mut out = Vec::new();
+    render_node(&tree, &mut out, &TreeOptions::default()).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "repo\n├── README.md\n├── docs/\n└── src/\n    ├── lib.rs\n    └── main.rs\n"
+    );
+}
+
+/// Sizes parse with binary units, and budgets report the largest
+/// contributors by the exceeded dimension.
+#[test]
+fn budget_check_and_size_parsing() {
+    use tree::{parse_size, scan, Budget, TreeOptions};
+
+    assert_eq!(parse_size("4096"), Some(4096));
+    assert_eq!(parse_size("500M"), Some(500 * 1024 * 1024));
+    assert_eq!(parse_size("1.5k"), Some(1536));
+    assert_eq!(parse_size("2GiB"), Some(2 << 30));
+    assert_eq!(parse_size("10KB"), Some(10 * 1024));
+    assert_eq!(parse_size(""), None);
+    assert_eq!(parse_size("M"), None);
+    assert_eq!(parse_size("12Q"), None);
+    assert_eq!(parse_size("-1"), None);
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir(root.join("assets")).unwrap();
+    fs::write(root.join("assets/logo.png"), vec![0; 5000]).unwrap();
+    fs::create_dir(root.join("src")).unwrap();
+    for name in ["a.rs", "b.rs", "c.rs"] {
+        fs::write(root.join("src").join(name), "fn x() {}").unwrap();
+    }
+    let tree = scan(root, &TreeOptions::default()).unwrap();
+
+    let report = Budget {
+        max_bytes: Some(4096),
+        max_files: None,
+    }
+    .check(&tree);
+    assert_eq!((report.bytes, report.files), (5027, 4));
+    assert!(report.over_bytes() && !report.over_files() && !report.is_within());
+    assert_eq!(report.contributors[0].path, "assets");
+
+    let report = Budget {
+        max_bytes: Some(1 << 20),
+        max_files: Some(3),
+    }
+    .check(&tree);
+    assert!(!report.over_bytes() && report.over_files());
+    assert_eq!(report.contributors[0].path, "src");
+    assert_eq!(report.contributors[0].files, 3);
+}
+
+/// Siblings colliding by case, by normalization form or by both are
+/// grouped per directory; equal names in different directories are fine.
+#[test]
+fn collisions_by_case_and_normalization() {
+    use tree::{collision_tree, find_collisions, tree_from_paths, CollisionKind, SortOptions};
+
+    let paths = [
+        "Makefile",
+        "makefile",
+        "docs/caf\u{e9}.md",
+        "docs/cafe\u{301}.md",
+        "docs/Viet/\u{1ec7}",
+        "docs/Viet/e\u{302}\u{323}",
+        "docs/Viet/E\u{323}\u{302}",
+        "src/makefile",
+        "src/lib.rs",
+    ];
+    let tree = tree_from_paths(".", paths, SortOptions::default());
+    let collisions = find_collisions(&tree);
+    let summary: Vec<_> = collisions
+        .iter()
+        .map(|c| (c.dir.as_str(), c.names.len(), c.kind))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("", 2, CollisionKind::Case),
+            ("docs", 2, CollisionKind::Normalization),
+            ("docs/Viet", 3, CollisionKind::CaseAndNormalization),
+        ]
+    );
+
+    let pruned = collision_tree(&tree, &collisions).unwrap();
+    let names: Vec<_> = pruned.children.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["docs", "Makefile", "makefile"]);
+    assert_eq!(pruned.children[0].children.len(), 3);
+    assert_eq!(
+        pruned.children[1].decoration.as_deref(),
+        Some("[collides: case]")
+    );
+
+    let clean = tree_from_paths(".", ["a/x", "b/x"], SortOptions::default());
+    assert!(find_collisions(&clean).is_empty());
+    assert!(collision_tree(&clean, &[]).is_none());
+}
+
+#[test]
+fn names_sort_and_match_in_canonical_form() {
+    use tree::{display_width, render, tree_from_paths, SortOptions, TreeOptions};
+
+    // Byte order would put the composed `é` after `cafz`.
+    let tree = tree_from_paths(".", ["cafz", "caf\u{e9}", "cafa"], SortOptions::default());
+    let names: Vec<_> = tree.children.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["cafa", "caf\u{e9}", "cafz"]);
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), "r\u{e9}sum\u{e9}\n").unwrap();
+    fs::write(root.join("re\u{301}sume\u{301}"), "").unwrap();
+    fs::write(root.join("cafz"), "").unwrap();
+    fs::write(root.join("caf\u{e9}"), "").unwrap();
+    let mut out = Vec::new();
+    render(root, &mut out, &TreeOptions::default()).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert!(!text.contains("sume"), "{text}");
+    assert!(text.find("caf\u{e9}").unwrap() < text.find("cafz").unwrap());
+
+    assert_eq!(display_width("main.rs"), 7);
+    assert_eq!(display_width("\u{6587}\u{6863}.md"), 7);
+    assert_eq!(display_width("re\u{301}sume\u{301}"), 6);
+    assert_eq!(display_width("\u{1f680}-launch"), 9);
+}
+
+#[test]
+fn shape_stats_histogram_and_deepest_path() {
+    use tree::{tree_from_paths, ShapeStats, SortOptions};
+
+    let paths = [
+        "README.md",
+        "src/lib.rs",
+        "src/a/b/deep.rs",
+        "src/a/c.rs",
+        "docs/",
+    ];
+    let shape = ShapeStats::of(&tree_from_paths(".", paths, SortOptions::default()));
+    assert_eq!(shape.per_depth, [3, 2, 2, 1]);
+    assert_eq!(shape.max_depth(), 4);
+    assert_eq!(shape.directories, 5);
+    assert!((shape.average_fanout - 8.0 / 5.0).abs() < f64::EPSILON);
+    assert_eq!(shape.deepest_path, "src/a/b/deep.rs");
+
+    let empty = ShapeStats::of(&tree_from_paths(".", [""; 0], SortOptions::default()));
+    assert_eq!((empty.max_depth(), empty.deepest_path.as_str()), (0, ""));
+}
+
+/// Writer recording the size of every write and the number of flushes.
+#[derive(Default)]
+struct RecordingWriter {
+    data: Vec<u8>,
+    writes: Vec<usize>,
+    flushes: usize,
+}
+
+impl std::io::Write for RecordingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.data.extend_from_slice(buf);
+        self.writes.push(buf.len());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.flushes += 1;
+        Ok(())
+    }
+}
+
+#[test]
+fn buffering_policies_shape_writes_not_output() {
+    use tree::{render, BufPolicy, TreeOptions};
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    for i in 0..20 {
+        fs::write(root.join(format!("file-{i:02}.txt")), "").unwrap();
+    }
+    let run = |buffering| {
+        let mut out = RecordingWriter::default();
+        let options = TreeOptions {
+            buffering,
+            ..TreeOptions::default()
+        };
+        render(root, &mut out, &options).unwrap();
+        out
+    };
+
+    let direct = run(BufPolicy::Unbuffered);
+    let lines = String::from_utf8_lossy(&direct.data).lines().count();
+    assert_eq!(lines, 22);
+
+    let line = run(BufPolicy::Line);
+    assert_eq!(line.data, direct.data);
+    assert!(line.flushes >= lines);
+
+    let chunks = run(BufPolicy::Chunks(64));
+    assert_eq!(chunks.data, direct.data);
+    assert!(chunks.writes.iter().all(|&len| len <= 64));
+    assert!(chunks.writes.len() < direct.writes.len());
+}
+
+#[test]
+fn case_insensitive_sort_breaks_ties_deterministically() {
+    use std::{cmp::Ordering, ffi::OsStr};
+    use tree::{render, tree_from_paths, Grouping, SortOptions, TreeOptions};
+
+    let sort = SortOptions::grouped(Grouping::Mixed).ignoring_case(true);
+    assert_eq!(
+        sort.compare_names(OsStr::new("README.md"), OsStr::new("Readme.md")),
+        Ordering::Less
+    );
+    assert_eq!(
+        sort.compare_names(OsStr::new("readme.md"), OsStr::new("Readme.md")),
+        Ordering::Greater
+    );
+    assert_eq!(
+        sort.compare_names(OsStr::new("apple"), OsStr::new("Banana")),
+        Ordering::Less
+    );
+
+    // Input order must not leak into the result.
+    let names = ["readme.md", "Readme.md", "banana", "README.md", "Apple"];
+    let mut reversed = names;
+    reversed.reverse();
+    for paths in [names, reversed] {
+        let tree = tree_from_paths(".", paths, sort);
+        let sorted: Vec<_> = tree.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            sorted,
+            ["Apple", "banana", "README.md", "Readme.md", "readme.md"]
+        );
+    }
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    for name in names {
+        fs::write(root.join(name), "").unwrap();
+    }
+    let options = TreeOptions {
+        sort,
+        ..TreeOptions::default()
+    };
+    let mut out = Vec::new();
+    render(root, &mut out, &options).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    let listed: Vec<_> = text.lines().skip(1).map(|l| &l[10..]).collect();
+    assert_eq!(
+        listed,
+        ["Apple", "banana", "README.md", "Readme.md", "readme.md"]
+    );
+}
+
+/// Sibling order never depends on the order in which the filesystem lists a
+/// directory: trees created in opposite orders render to the same golden
+/// text, and names mixing UTF‑8 and other bytes still sort consistently.
+#[test]
+fn sibling_order_ignores_enumeration_order() {
+    use tree::{render, Grouping, SortOptions, TreeOptions};
+
+    // ASCII only, and no names differing just in case, so every filesystem
+    // stores them as given.
+    let entries = [
+        "src/",
+        "src/main.rs",
+        "src/lib.rs",
+        "Docs/",
+        "docs-old/",
+        "b.txt",
+        "B-2.txt",
+        "a.txt",
+        "_x",
+        "10.md",
+        "9.md",
+    ];
+    let mut reversed = entries;
+    reversed.reverse();
+    let render_lines = |order: &[&str], sort| {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path();
+        fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+        for entry in order {
+            if let Some(dir) = entry.strip_suffix('/') {
+                fs::create_dir_all(root.join(dir)).unwrap();
+            } else {
+                fs::create_dir_all(root.join(entry).parent().unwrap()).unwrap();
+                fs::write(root.join(entry), "").unwrap();
+            }
+        }
+        let options = TreeOptions {
+            sort,
+            ..TreeOptions::default()
+        };
+        let mut out = Vec::new();
+        render(root, &mut out, &options).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        text.lines().skip(1).map(str::to_owned).collect::<Vec<_>>()
+    };
+
+    let grouped = SortOptions::default();
+    let golden = [
+        "├── Docs/",
+        "├── docs-old/",
+        "├── src/",
+        "│   ├── lib.rs",
+        "│   └── main.rs",
+        "├── 10.md",
+        "├── 9.md",
+        "├── B-2.txt",
+        "├── _x",
+        "├── a.txt",
+        "└── b.txt",
+    ];
+    assert_eq!(render_lines(&entries, grouped), golden);
+    assert_eq!(render_lines(&reversed, grouped), golden);
+
+    let folded = SortOptions::grouped(Grouping::Mixed).ignoring_case(true);
+    let golden = [
+        "├── 10.md",
+        "├── 9.md",
+        "├── _x",
+        "├── a.txt",
+        "├── B-2.txt",
+        "├── b.txt",
+        "├── Docs/",
+        "├── docs-old/",
+        "└── src/",
+        "    ├── lib.rs",
+        "    └── main.rs",
+    ];
+    assert_eq!(render_lines(&entries, folded), golden);
+    assert_eq!(render_lines(&reversed, folded), golden);
+
+    // Byte‑wise comparison for non‑UTF‑8 names alone would make this a cycle.
+    #[cfg(unix)]
+    {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let names = [
+            OsStr::new("caf\u{e9}"),
+            OsStr::new("cafg"),
+            OsStr::from_bytes(b"caf\xC0"),
+        ];
+        for sort in [grouped, folded] {
+            for start in 0..names.len() {
+                for swap in [false, true] {
+                    let mut order = names;
+                    order.rotate_left(start);
+                    if swap {
+                        order.swap(0, 1);
+                    }
+                    order.sort_by(|a, b| sort.compare_names(a, b));
+                    assert_eq!(order, names, "{sort:?}");
+                }
+            }
+        }
+    }
+}
+
+/// `redaction` strips the root prefix and rewrites names and paths in the
+/// text, JSON and checksum outputs alike.
+#[test]
+fn redaction_rewrites_displayed_paths() {
+    use tree::{render, OutputFormat, PathRedaction, Redaction, TreeOptions};
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path().join("acme-billing");
+    fs::create_dir_all(root.join("acme-core")).unwrap();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    fs::write(root.join("acme-core/lib.rs"), "").unwrap();
+
+    let options = TreeOptions {
+        redaction: PathRedaction {
+            strip_prefix: Some(tmp.path().to_path_buf()),
+            rules: vec!["acme-([a-z]+)=corp-$1".parse::<Redaction>().unwrap()],
+        },
+        ..TreeOptions::default()
+    };
+    let render_as = |format| {
+        let mut out = Vec::new();
+        let options = TreeOptions {
+            format,
+            ..options.clone()
+        };
+        render(&root, &mut out, &options).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+
+    let text = render_as(OutputFormat::Text);
+    assert!(text.starts_with("corp-billing\n├── corp-core/\n"), "{text}");
+    let json = render_as(OutputFormat::Json);
+    assert!(json.contains(r#""path": "corp-core/lib.rs""#), "{json}");
+    assert!(!json.contains("acme"), "{json}");
+    let sums = render_as(OutputFormat::Sha256sum);
+    assert!(sums.contains("  corp-core/lib.rs\n"), "{sums}");
+
+    assert!("no-separator".parse::<Redaction>().is_err());
+    assert!("([=x".parse::<Redaction>().is_err());
+}
+
+/// `overlay` merges directories across layers, lets upper non-directories
+/// hide lower entries, and notes the layers behind every entry.
+#[test]
+fn overlay_merges_layers_like_overlayfs() {
+    use tree::{overlay, render_node, TreeOptions};
+
+    let tmp = TempDir::new().unwrap();
+    let (lower, upper) = (tmp.path().join("lower"), tmp.path().join("upper"));
+    fs::create_dir_all(lower.join("etc")).unwrap();
+    fs::create_dir_all(lower.join("cache")).unwrap();
+    fs::create_dir_all(upper.join("etc")).unwrap();
+    fs::write(lower.join("etc/hosts"), "").unwrap();
+    fs::write(lower.join("etc/motd"), "").unwrap();
+    fs::write(lower.join("cache/blob"), "").unwrap();
+    fs::write(upper.join("etc/motd"), "").unwrap();
+    fs::write(upper.join("cache"), "").unwrap();
+    for root in [&lower, &upper] {
+        fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    }
+
+    let tree = overlay(&[&lower, &upper], &TreeOptions::default()).unwrap();
+    let mut out = Vec::new();
+    render_node(&tree, &mut out, &TreeOptions::default()).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    let body: Vec<&str> = text.lines().skip(1).collect();
+    assert_eq!(
+        body,
+        [
+            "├── etc/  [1 2]",
+            "│   ├── hosts  [1]",
+            "│   └── motd  [2] shadows 1",
+            "└── cache  [2] shadows 1",
+        ],
+        "{text}"
+    );
+}
+
+/// Image tarballs are read from their manifest; squashing applies whiteouts
+/// and opaque markers, while single layers list them as stored.
+#[cfg(feature = "docker")]
+#[test]
+fn image_layers_squash_with_whiteouts() {
+    use flate2::{write::GzEncoder, Compression};
+    use tree::{render_node, Image, SortOptions, TreeOptions};
+
+    fn tarball(entries: &[&str]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for path in entries {
+            let mut header = tar::Header::new_gnu();
+            if path.ends_with('/') {
+                header.set_entry_type(tar::EntryType::Directory);
+            }
+            header.set_size(0);
+            header.set_mode(0o755);
+            builder.append_data(&mut header, path, &[][..]).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+    fn append(builder: &mut tar::Builder<Vec<u8>>, path: &str, data: &[u8]) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, path, data).unwrap();
+    }
+
+    let lower = tarball(&["etc/", "etc/hosts", "etc/motd", "var/cache/", "var/cache/a"]);
+    let upper = tarball(&[
+        "etc/.wh.motd",
+        "var/cache/.wh..wh..opq",
+        "var/cache/b",
+        "app/main",
+    ]);
+    let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+    std::io::Write::write_all(&mut gzip, &upper).unwrap();
+    let mut image = tar::Builder::new(Vec::new());
+    append(&mut image, "lower/layer.tar", &lower);
+    append(&mut image, "blobs/sha256/upper", &gzip.finish().unwrap());
+    append(
+        &mut image,
+        "manifest.json",
+        br#"[{"Config":"config.json","Layers":["lower/layer.tar","blobs/sha256/upper"]}]"#,
+    );
+    let image = Image::read_from(image.into_inner().unwrap().as_slice()).unwrap();
+    assert_eq!(image.layers.len(), 2);
+
+    let render = |tree| {
+        let mut out = Vec::new();
+        render_node(&tree, &mut out, &TreeOptions::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    let squashed = render(image.squashed_tree("app:latest", SortOptions::default()));
+    assert_eq!(
+        squashed,
+        "app:latest\n├── app/\n│   └── main\n├── etc/\n│   └── hosts\n└── var/\n    └── cache/\n        └── b\n"
+    );
+    let upper = render(image.layer_tree(1, SortOptions::default()).unwrap());
+    assert!(upper.contains(".wh.motd"), "{upper}");
+    assert!(image.layer_tree(2, SortOptions::default()).is_none());
+}
+
+/// `render_json_limited` keeps entries breadth-first and marks every
+/// directory that lost children with the number left out.
+#[test]
+fn json_limited_truncates_breadth_first() {
+    use tree::{render_json_limited, TreeNode, TreeOptions};
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    fs::create_dir_all(root.join("a/deep")).unwrap();
+    fs::write(root.join("a/one.txt"), "").unwrap();
+    fs::write(root.join("a/two.txt"), "").unwrap();
+    fs::write(root.join("b.txt"), "").unwrap();
+
+    // Root level: a, .tree_ignore, b.txt; one slot left for a's children.
+    let mut out = Vec::new();
+    render_json_limited(root, 4, &mut out, &TreeOptions::default()).unwrap();
+    let tree: TreeNode = serde_json::from_slice(&out).unwrap();
+    assert_eq!(tree.truncated, None);
+    let names: Vec<&str> = tree.children.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["a", ".tree_ignore", "b.txt"]);
+    let a = &tree.children[0];
+    assert_eq!(a.children.len(), 1);
+    assert_eq!(a.truncated, Some(2));
+    assert!(a.children[0].children.is_empty());
+
+    let mut out = Vec::new();
+    render_json_limited(root, 1, &mut out, &TreeOptions::default()).unwrap();
+    let json = String::from_utf8(out).unwrap();
+    assert!(json.contains(r#""truncated": 2"#), "{json}");
+    assert!(!json.contains("one.txt"), "{json}");
+}
+
+/// With a stat timeout, lookups that answer in time behave exactly as
+/// without one and nothing is counted as timed out.
+#[test]
+fn stat_timeout_keeps_prompt_lookups() {
+    use std::{sync::Arc, time::Duration};
+    use tree::{scan, ScanMetrics, TreeOptions};
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    fs::write(root.join("data.bin"), vec![0u8; 300]).unwrap();
+
+    let metrics = Arc::new(ScanMetrics::default());
+    let options = TreeOptions {
+        stat_timeout: Some(Duration::from_secs(30)),
+        metrics: Some(Arc::clone(&metrics)),
+        ..TreeOptions::default()
+    };
+    let tree = scan(root, &options).unwrap();
+    let data = tree.children.iter().find(|c| c.name == "data.bin").unwrap();
+    assert_eq!(data.size, Some(300));
+    assert_eq!(data.error, None);
+    assert_eq!(metrics.stat_timeouts(), 0);
+    assert!(metrics.stat_calls() >= 2);
+}
+
+#[test]
+fn retry_leaves_healthy_scans_alone_and_logs_retries() {
+    use std::{path::PathBuf, sync::Arc, time::Duration};
+    use tree::{scan, Diagnostic, ScanMetrics, TreeOptions};
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src/lib.rs"), "").unwrap();
+
+    let metrics = Arc::new(ScanMetrics::default());
+    let options = TreeOptions {
+        retry: 3,
+        metrics: Some(Arc::clone(&metrics)),
+        ..TreeOptions::default()
+    };
+    assert_eq!(
+        scan(root, &options).unwrap(),
+        scan(root, &TreeOptions::default()).unwrap()
+    );
+    assert_eq!(metrics.retries(), 0);
+    assert!(!metrics.summary(Duration::from_secs(1)).contains("retries"));
+
+    let retried = Diagnostic::Retried(PathBuf::from("/mnt/nfs/a"), "timed out".into(), 2);
+    assert_eq!(retried.code(), "retried");
+    assert!(!retried.is_warning());
+    assert_eq!(
+        retried.to_string(),
+        "retrying /mnt/nfs/a after timed out (retry 2)"
+    );
+}
+
+/// Watch events report created, removed and modified entries in path
+/// order, without flagging a directory just because its contents changed.
+#[test]
+fn test_watch_changes_between_snapshots() {
+    use std::time::SystemTime;
+    use tree::{changes, EventKind, Snapshot, TreeOptions, Watcher};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src/lib.rs"), "a").unwrap();
+    fs::write(root.join("gone.txt"), "x").unwrap();
+
+    let options = TreeOptions::default();
+    let before = Snapshot::capture(root, &options, false).unwrap();
+    let mut watcher = Watcher::new(root, &options).unwrap();
+    fs::write(root.join("src/lib.rs"), "abc").unwrap();
+    fs::remove_file(root.join("gone.txt")).unwrap();
+    fs::create_dir(root.join("docs")).unwrap();
+    fs::write(root.join("docs/guide.md"), "").unwrap();
+    let after = Snapshot::capture(root, &options, false).unwrap();
+
+    let events = changes(&before, &after, SystemTime::now());
+    let events: Vec<(EventKind, &str)> = events.iter().map(|e| (e.kind, e.path.as_str())).collect();
+    assert_eq!(
+        events,
+        [
+            (EventKind::Created, "docs"),
+            (EventKind::Created, "docs/guide.md"),
+            (EventKind::Removed, "gone.txt"),
+            (EventKind::Modified, "src/lib.rs"),
+        ]
+    );
+    assert_eq!(watcher.poll().unwrap().len(), 4);
+    assert!(watcher.poll().unwrap().is_empty());
+}
+
+/// Watching skips ignored directories entirely and `include` narrows the
+/// reported paths to matching globs.
+#[test]
+fn test_watch_respects_ignores_and_include_globs() {
+    use tree::{TreeOptions, Watcher};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\ntarget\n").unwrap();
+    fs::create_dir_all(root.join("target/debug")).unwrap();
+    fs::create_dir_all(root.join("src")).unwrap();
+
+    let options = TreeOptions::default();
+    let mut all = Watcher::new(root, &options).unwrap();
+    let mut narrow = Watcher::new(root, &options)
+        .unwrap()
+        .include(&["*.rs", "/docs/"])
+        .unwrap();
+    fs::write(root.join("target/debug/app.o"), "obj").unwrap();
+    fs::write(root.join("src/lib.rs"), "").unwrap();
+    fs::write(root.join("src/notes.txt"), "").unwrap();
+    fs::create_dir(root.join("docs")).unwrap();
+    fs::write(root.join("docs/guide.md"), "").unwrap();
+
+    let paths = |events: Vec<tree::WatchEvent>| -> Vec<String> {
+        events.into_iter().map(|e| e.path).collect()
+    };
+    assert_eq!(
+        paths(all.poll().unwrap()),
+        ["docs", "docs/guide.md", "src/lib.rs", "src/notes.txt"]
+    );
+    assert_eq!(
+        paths(narrow.poll().unwrap()),
+        ["docs", "docs/guide.md", "src/lib.rs"]
+    );
+    assert!(Watcher::new(root, &options)
+        .unwrap()
+        .include(&["{"])
+        .is_err());
+}
+
+/// `--notify-cmd` templates expand every placeholder once, quoting paths
+/// for the shell.
+#[cfg(unix)]
+#[test]
+fn test_notify_command_expands_placeholders() {
+    use std::{path::Path, time::SystemTime};
+    use tree::{notify_command, EventKind, WatchEvent};
+
+    let events = [
+        WatchEvent {
+            time: SystemTime::UNIX_EPOCH,
+            kind: EventKind::Created,
+            path: "it's {root}.txt".to_owned(),
+        },
+        WatchEvent {
+            time: SystemTime::UNIX_EPOCH,
+            kind: EventKind::Removed,
+            path: "b".to_owned(),
+        },
+    ];
+    assert_eq!(
+        notify_command(
+            "make {event} {count} {path} in {root} {unknown}",
+            Path::new("/srv/app"),
+            &events
+        ),
+        r"make created 2 'it'\''s {root}.txt' 'b' in '/srv/app' {unknown}"
+    );
+    assert_eq!(notify_command("true", Path::new("."), &[]), "true");
+}
+
+/// The debouncer holds a burst from a scripted event source until it has
+/// been quiet long enough, and batching reduces it to net changes.
+#[test]
+fn test_debouncer_coalesces_bursts_from_mock_source() {
+    use std::{
+        collections::VecDeque,
+        time::{Duration, Instant, SystemTime},
+    };
+    use tree::{Debouncer, EventKind, EventSource, TreeError, WatchEvent};
+
+    struct Script(VecDeque<Vec<WatchEvent>>);
+    impl EventSource for Script {
+        fn poll(&mut self) -> Result<Vec<WatchEvent>, TreeError> {
+            Ok(self.0.pop_front().unwrap_or_default())
+        }
+    }
+    let event = |kind, path: &str| WatchEvent {
+        time: SystemTime::UNIX_EPOCH,
+        kind,
+        path: path.to_owned(),
+    };
+    let burst = || {
+        Script(VecDeque::from([
+            vec![
+                event(EventKind::Created, "out.o"),
+                event(EventKind::Created, "tmp"),
+            ],
+            vec![
+                event(EventKind::Modified, "out.o"),
+                event(EventKind::Removed, "tmp"),
+            ],
+            vec![event(EventKind::Removed, "lib.a")],
+            vec![],
+            vec![],
+        ]))
+    };
+    let ms = Duration::from_millis;
+    let start = Instant::now();
+
+    // Batched: nothing until 300 ms of quiet after the last event, then the
+    // net change only.
+    let mut source = burst();
+    let mut debouncer = Debouncer::new(ms(300), true);
+    assert_eq!(debouncer.drive(&mut source, start).unwrap(), None);
+    assert_eq!(debouncer.drive(&mut source, start + ms(100)).unwrap(), None);
+    assert_eq!(debouncer.drive(&mut source, start + ms(200)).unwrap(), None);
+    assert_eq!(debouncer.drive(&mut source, start + ms(400)).unwrap(), None);
+    let released = debouncer
+        .drive(&mut source, start + ms(500))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        released,
+        [
+            event(EventKind::Removed, "lib.a"),
+            event(EventKind::Created, "out.o")
+        ]
+    );
+    assert_eq!(debouncer.take(start + ms(900)), None);
+
+    // Unbatched without a delay: every poll with events is released as is.
+    let mut source = burst();
+    let mut debouncer = Debouncer::new(Duration::ZERO, false);
+    assert_eq!(
+        debouncer.drive(&mut source, start).unwrap().unwrap().len(),
+        2
+    );
+    assert_eq!(
+        debouncer.drive(&mut source, start).unwrap().unwrap().len(),
+        2
+    );
+
+    // A burst that cancels out releases nothing.
+    let mut debouncer = Debouncer::new(Duration::ZERO, true);
+    debouncer.push(
+        vec![
+            event(EventKind::Created, "x"),
+            event(EventKind::Removed, "x"),
+        ],
+        start,
+    );
+    assert_eq!(debouncer.take(start), None);
+}
+
+/// A cancelled token stops every walk with `TreeError::Cancelled`, which
+/// counts the entries listed so far.
+#[test]
+fn cancelled_token_stops_the_walk() {
+    use tree::{render, scan, CancelToken, TreeError, TreeOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir(root.join("a")).unwrap();
+    fs::write(root.join("b.txt"), "b").unwrap();
+
+    let token = CancelToken::new();
+    let options = TreeOptions {
+        cancel: Some(token.clone()),
+        ..TreeOptions::default()
+    };
+    let mut out = Vec::new();
+    render(root, &mut out, &options).unwrap();
+    assert!(!token.is_cancelled());
+
+    token.cancel();
+    let mut out = Vec::new();
+    let err = render(root, &mut out, &options).unwrap_err();
+    assert!(matches!(err, TreeError::Cancelled(_)), "{err:?}");
+    assert_eq!(err.code(), "cancelled");
+    assert!(err.to_string().starts_with("scan interrupted after "));
+    assert!(matches!(scan(root, &options), Err(TreeError::Cancelled(_))));
+}
+
+/// Rates parse as operations or bytes per second, and a throttled scan is
+/// paced once the one-second burst is spent.
+#[test]
+fn throttle_paces_operations_past_the_burst() {
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+    use tree::{render, Rate, Throttle, TreeOptions};
+
+    assert_eq!("200".parse(), Ok(Rate::Ops(200)));
+    assert_eq!("200/s".parse(), Ok(Rate::Ops(200)));
+    assert_eq!("50ops/s".parse(), Ok(Rate::Ops(50)));
+    assert_eq!("10MB/s".parse(), Ok(Rate::Bytes(10 * 1024 * 1024)));
+    assert!("0".parse::<Rate>().is_err());
+    assert!("fast".parse::<Rate>().is_err());
+    assert_eq!(Rate::Ops(200).to_string(), "200 ops/s");
+
+    let throttle = Throttle::new(Rate::Ops(40));
+    let start = Instant::now();
+    throttle.bytes(1_000_000);
+    throttle.ops(40);
+    assert!(start.elapsed() < Duration::from_millis(200));
+    throttle.ops(10);
+    assert!(start.elapsed() >= Duration::from_millis(200));
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    for i in 0..5 {
+        fs::write(root.join(format!("f{i}")), "x").unwrap();
+    }
+    let options = TreeOptions {
+        throttle: Some(Arc::new(Throttle::new(Rate::Bytes(1024)))),
+        ..TreeOptions::default()
+    };
+    let mut out = Vec::new();
+    render(root, &mut out, &options).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap().lines().count(), 6);
+}
+
+/// Roots on filesystems without Unix permissions or modification times,
+/// as reported by a mocked metadata provider, show `-` in those columns.
+#[test]
+fn test_metadata_columns_degrade_per_root() {
+    use std::{
+        path::Path,
+        sync::Arc,
+        time::{Duration, SystemTime},
+    };
+    use tree::{render, MetadataSupport, TimeStyle, TreeOptions};
+
+    assert!(!MetadataSupport::of_filesystem("vfat").permissions);
+    assert!(!MetadataSupport::of_filesystem("exfat").permissions);
+    assert_eq!(
+        MetadataSupport::of_filesystem("ext4"),
+        MetadataSupport::FULL
+    );
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    let file = fs::File::create(root.join("a.txt")).unwrap();
+    file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        .unwrap();
+
+    let lines = |support: MetadataSupport| {
+        let options = TreeOptions {
+            permissions: true,
+            time_style: Some("+%Y-%m-%d".parse::<TimeStyle>().unwrap()),
+            metadata_provider: Some(Arc::new(move |_: &Path| support)),
+            ..TreeOptions::default()
+        };
+        let mut out = Vec::new();
+        render(root, &mut out, &options).unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .last()
+            .unwrap()
+            .to_owned()
+    };
+    let full = lines(MetadataSupport::FULL);
+    assert!(full.contains("2023-11-14]  a.txt"), "{full}");
+    assert!(!full.contains("[-]"), "{full}");
+    let fat = lines(MetadataSupport {
+        permissions: false,
+        times: true,
+    });
+    assert!(fat.ends_with("[-]  [2023-11-14]  a.txt"), "{fat}");
+    let untimed = lines(MetadataSupport {
+        permissions: false,
+        times: false,
+    });
+    assert!(untimed.ends_with("[-]  [-]  a.txt"), "{untimed}");
+}
+
+/// A decoration cache shared between renders recomputes notes and hashes
+/// only for files whose modification time or size changed, and sweeps
+/// files that are gone.
+#[test]
+fn test_decoration_cache_reuses_unchanged_entries() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use tree::{render, Annotation, DecorationCache, EntryInfo, TreeOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src/a.rs"), "a").unwrap();
+    fs::write(root.join("src/b.rs"), "b").unwrap();
+    fs::write(root.join("c.txt"), "c").unwrap();
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counted = Arc::clone(&calls);
+    let cache = Arc::new(DecorationCache::new());
+    let options = TreeOptions {
+        hash_dirs: true,
+        decoration_cache: Some(Arc::clone(&cache)),
+        ..TreeOptions::default()
+    }
+    .annotator(move |entry: &EntryInfo<'_>| {
+        counted.fetch_add(1, Ordering::Relaxed);
+        Some(Annotation::new(format!("#{}", entry.depth)))
+    });
+    let frame = || {
+        let mut out = Vec::new();
+        render(root, &mut out, &options).unwrap();
+        cache.sweep();
+        String::from_utf8(out).unwrap()
+    };
+
+    let first = frame();
+    // Three file notes, one directory note, three file digests.
+    assert_eq!(calls.load(Ordering::Relaxed), 4);
+    assert_eq!((cache.hits(), cache.misses(), cache.len()), (0, 6, 3));
+
+    assert_eq!(frame(), first);
+    assert_eq!(calls.load(Ordering::Relaxed), 5);
+    assert_eq!((cache.hits(), cache.misses()), (6, 6));
+
+    fs::write(root.join("src/a.rs"), "changed").unwrap();
+    fs::remove_file(root.join("c.txt")).unwrap();
+    let changed = frame();
+    assert_ne!(changed, first);
+    assert!(!changed.contains("c.txt"));
+    assert_eq!(calls.load(Ordering::Relaxed), 7);
+    assert_eq!((cache.hits(), cache.misses(), cache.len()), (8, 8, 2));
+    assert!(cache
+        .summary()
+        .starts_with("decoration cache: 8 hits, 8 misses"));
+}
+
+/// Filter expressions test files only, keep every directory, combine with
+/// `&`, `|` and `!`, and print back in their own syntax.
+#[test]
+fn test_filter_expr_semantics() {
+    use std::time::{Duration, SystemTime};
+    use tree::{render, Cmp, EntryKind, FilterExpr, FilterTest, TreeOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::create_dir_all(root.join("docs")).unwrap();
+    let month_ago = SystemTime::now() - Duration::from_secs(30 * 86_400);
+    for (name, len) in [
+        ("src/lib.rs", 2000),
+        ("src/small.rs", 10),
+        ("docs/old.md", 10),
+    ] {
+        let file = fs::File::create(root.join(name)).unwrap();
+        file.set_len(len).unwrap();
+        file.set_modified(month_ago).unwrap();
+    }
+    fs::write(root.join("notes.txt"), "new").unwrap();
+
+    let big_rust = FilterExpr::from(FilterTest::Size(Cmp::Greater, 1024))
+        .and(FilterTest::Ext("RS".to_owned()));
+    let expr = big_rust.or(FilterTest::Age(Cmp::Less, Duration::from_secs(7 * 86_400)));
+    assert_eq!(expr.to_string(), "size>1024 & ext=RS | mtime<1w");
+
+    let options = TreeOptions {
+        filter_expr: Some(expr),
+        ..TreeOptions::default()
+    };
+    let mut out = Vec::new();
+    render(root, &mut out, &options).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    for shown in ["docs", "src", "lib.rs", "notes.txt"] {
+        assert!(text.contains(shown), "{shown} missing:\n{text}");
+    }
+    for hidden in ["small.rs", "old.md"] {
+        assert!(!text.contains(hidden), "{hidden} shown:\n{text}");
+    }
+
+    let not_md = FilterExpr::from(FilterTest::Name("*.md".to_owned())).negate();
+    assert!(!not_md.matches(&root.join("docs/old.md"), EntryKind::File));
+    assert!(not_md.matches(&root.join("docs"), EntryKind::Directory));
+    assert!(not_md.matches(&root.join("src/lib.rs"), EntryKind::File));
+    assert_eq!(not_md.to_string(), "!name=*.md");
+    assert_eq!(not_md.clone().negate().to_string(), "name=*.md");
+
+    let glob = |pattern: &str, name: &str| {
+        FilterExpr::from(FilterTest::Name(pattern.to_owned()))
+            .matches(&root.join(name), EntryKind::File)
+    };
+    assert!(glob("l?b.*", "lib.rs"));
+    assert!(glob("*a*b*", "xaxxbx"));
+    assert!(!glob("*.rs", "lib.rsx"));
+
+    let grouped = FilterExpr::from(FilterTest::Type(EntryKind::Symlink))
+        .and(not_md.or(FilterTest::Name("my file*".to_owned())));
+    assert_eq!(
+        grouped.to_string(),
+        r#"type=link & (!name=*.md | name="my file*")"#
+    );
+    assert_eq!(FilterExpr::All(Vec::new()).to_string(), "true");
+}
+
+/// Filter expressions parse with `!` over `&` over `|`, print back to an
+/// equivalent string, and report problems with the column they start at.
+#[test]
+fn test_filter_expr_parsing() {
+    use std::time::Duration;
+    use tree::{Cmp, EntryKind, FilterExpr, FilterTest};
+
+    let parse = |text: &str| text.parse::<FilterExpr>();
+    let expr = parse("(size>1K & ext=.rs) | mtime<7d").unwrap();
+    let expected = FilterExpr::from(FilterTest::Size(Cmp::Greater, 1024))
+        .and(FilterTest::Ext("rs".to_owned()))
+        .or(FilterTest::Age(Cmp::Less, Duration::from_secs(7 * 86_400)));
+    assert_eq!(expr.to_string(), expected.to_string());
+
+    for text in [
+        "size>1024 & ext=RS | mtime<1w",
+        "!name=*.md",
+        r#"type=link & (!name=*.md | name="my file*")"#,
+        "true",
+        "false",
+    ] {
+        assert_eq!(parse(text).unwrap().to_string(), text);
+    }
+    assert_eq!(
+        parse("type!=directory").unwrap().to_string(),
+        FilterExpr::from(FilterTest::Type(EntryKind::Directory))
+            .negate()
+            .to_string()
+    );
+    assert_eq!(
+        parse(r#"name="a \"b\"""#).unwrap().to_string(),
+        FilterExpr::from(FilterTest::Name(r#"a "b""#.to_owned())).to_string()
+    );
+
+    let error = |text: &str| parse(text).unwrap_err().to_string();
+    assert_eq!(
+        error("size>1M & colour=red"),
+        "invalid filter at column 11: unknown field `colour` (expected size, mtime, name, ext or type)"
+    );
+    assert_eq!(
+        error("size>lots"),
+        "invalid filter at column 6: invalid size `lots`"
+    );
+    assert_eq!(
+        error("name<b"),
+        "invalid filter at column 1: `name` only supports = and !="
+    );
+    assert_eq!(error("(ext=rs"), "invalid filter at column 8: expected `)`");
+    assert_eq!(
+        error("name=\"open"),
+        "invalid filter at column 6: unclosed quote"
+    );
+    assert_eq!(
+        error("ext=rs ext=md"),
+        "invalid filter at column 8: unexpected input after the expression"
+    );
+}
+
+/// Deeply nested filter expressions are refused with the column of the
+/// first group too many instead of overflowing the parser's stack.
+#[test]
+fn test_filter_expr_nesting_limit() {
+    use tree::FilterExpr;
+
+    let nested = |depth: usize| format!("{}type=file{}", "(".repeat(depth), ")".repeat(depth));
+    assert!(nested(256).parse::<FilterExpr>().is_ok());
+    assert_eq!(
+        nested(20_000)
+            .parse::<FilterExpr>()
+            .unwrap_err()
+            .to_string(),
+        "invalid filter at column 257: nested more than 256 levels deep"
+    );
+    let negated = format!("{}type=file", "!".repeat(20_000));
+    assert_eq!(
+        negated.parse::<FilterExpr>().unwrap_err().to_string(),
+        "invalid filter at column 257: nested more than 256 levels deep"
+    );
+}
@@ -267,3 +267,2405 @@ fn render_sorting_and_order() {
 
     assert!(m_pos < z_pos && z_pos < a_pos && a_pos < b_pos);
 }
+
+/// `--format json` prints a parseable JSON document describing the tree.
+#[test]
+fn format_json_prints_valid_document() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--format", "json"])
+        .arg(root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // Minified by default when piped; `--pretty` restores the indentation.
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 1);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["type"], "directory");
+
+    let pretty = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--format", "json", "--pretty"])
+        .arg(root)
+        .output()
+        .unwrap();
+    assert!(String::from_utf8(pretty.stdout)
+        .unwrap()
+        .contains("\n  \"name\": "));
+    let src = &json["children"][0];
+    assert_eq!(src["name"], "src");
+    assert_eq!(src["children"][0]["path"], "src/main.rs");
+    assert_eq!(src["children"][0]["size"], 12);
+}
+
+/// `--export` prints the tree and bundles tree.txt, tree.json and stats.csv.
+#[test]
+fn export_writes_archive_with_all_outputs() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path().join("project");
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src/lib.rs"), "// lib").unwrap();
+    let archive_path = tmp.path().join("manifest.tar.gz");
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg(&root)
+        .arg("--export")
+        .arg(&archive_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("lib.rs"));
+
+    let file = fs::File::open(&archive_path).unwrap();
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    let mut contents = std::collections::BTreeMap::new();
+    for entry in archive.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let name = entry.path().unwrap().display().to_string();
+        let mut body = String::new();
+        std::io::Read::read_to_string(&mut entry, &mut body).unwrap();
+        contents.insert(name, body);
+    }
+
+    assert!(contents["tree.txt"].contains("└── lib.rs"));
+    assert!(contents["tree.json"].contains("\"src/lib.rs\""));
+    assert!(contents["stats.csv"].starts_with("metric,value\n"));
+    assert!(contents["stats.csv"].contains("directories,1\n"));
+}
+
+/// `tree verify` succeeds on an unchanged tree and reports every difference
+/// with a non-zero exit status once the tree drifts from the manifest.
+#[test]
+fn verify_reports_missing_extra_and_changed_entries() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path().join("app");
+    fs::create_dir_all(root.join("bin")).unwrap();
+    fs::write(root.join("bin/server"), "v1").unwrap();
+    fs::write(root.join("config.toml"), "a = 1").unwrap();
+
+    let manifest = tmp.path().join("manifest.json");
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--format", "json"])
+        .arg(&root)
+        .output()
+        .unwrap();
+    fs::write(&manifest, output.stdout).unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg("verify")
+        .arg("--manifest")
+        .arg(&manifest)
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("OK:"));
+
+    fs::remove_file(root.join("config.toml")).unwrap();
+    fs::write(root.join("bin/server"), "v2-longer").unwrap();
+    fs::write(root.join("stray.log"), "").unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg("verify")
+        .arg("--manifest")
+        .arg(&manifest)
+        .arg(&root)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("missing  config.toml"))
+        .stdout(predicate::str::contains("extra    stray.log"))
+        .stdout(predicate::str::contains(
+            "changed  bin/server (size 2 -> 9)",
+        ))
+        .stderr(predicate::str::contains("3 difference(s) from manifest"));
+}
+
+/// A symlink to its own directory is reported as a loop with a non-zero
+/// exit status, instead of rendering nested copies until the kernel stops
+/// resolving the path.
+#[cfg(unix)]
+#[test]
+fn symlink_loops_fail_with_limit_exceeded() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::write(root.join("file.txt"), "").unwrap();
+    std::os::unix::fs::symlink(".", root.join("a")).unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg(root)
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("symlink hop limit of 20 exceeded"));
+    // Directory digests are computed before anything is printed.
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg(root)
+        .arg("--hash-dirs")
+        .assert()
+        .code(3)
+        .stdout("")
+        .stderr(predicate::str::contains("symlink hop limit of 20 exceeded"));
+}
+
+/// `tree verify` never creates `.tree_ignore` in the directory it checks,
+/// so repeated runs agree.
+#[test]
+fn verify_does_not_write_into_the_target() {
+    let tmp = TempDir::new().unwrap();
+    let (source, deployed) = (tmp.path().join("source"), tmp.path().join("deployed"));
+    for root in [&source, &deployed] {
+        fs::create_dir_all(root.join("node_modules")).unwrap();
+        fs::write(root.join("app.js"), "run()").unwrap();
+    }
+    let manifest = tmp.path().join("manifest.json");
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--quiet", "--format", "json"])
+        .arg(&source)
+        .output()
+        .unwrap();
+    fs::write(&manifest, output.stdout).unwrap();
+
+    for _ in 0..2 {
+        Command::cargo_bin("tree")
+            .unwrap()
+            .arg("verify")
+            .arg("--manifest")
+            .arg(&manifest)
+            .arg(&deployed)
+            .assert()
+            .failure()
+            .stdout("missing  .tree_ignore\n");
+        assert!(!deployed.join(".tree_ignore").exists());
+    }
+}
+
+/// `--format sha256sum` emits coreutils-compatible `HASH  path` lines.
+#[test]
+fn format_sha256sum_matches_coreutils_layout() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    fs::create_dir(root.join("docs")).unwrap();
+    fs::write(root.join("docs/a.txt"), "abc").unwrap();
+    fs::write(root.join("empty"), "").unwrap();
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--format", "sha256sum"])
+        .arg(root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        text,
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  docs/a.txt\n\
+         e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  .tree_ignore\n\
+         e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  empty\n"
+    );
+}
+
+/// `--format sha256sum` escapes backslashes, newlines and carriage returns
+/// in names and marks those lines with a leading `\`, as coreutils does.
+#[cfg(unix)]
+#[test]
+fn format_sha256sum_escapes_special_names() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::write(root.join("a\\b"), "").unwrap();
+    fs::write(root.join("c\nd"), "").unwrap();
+    fs::write(root.join("e\rf"), "").unwrap();
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--format", "sha256sum"])
+        .arg(root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let empty = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        text,
+        format!("\\{empty}  a\\\\b\n\\{empty}  c\\nd\n\\{empty}  e\\rf\n")
+    );
+}
+
+/// A file `--format sha256sum` cannot read is reported on stderr and left
+/// out, the rest of the manifest is still written, and the run exits with 2.
+/// Skipped when running with privileges that bypass permission checks.
+#[cfg(unix)]
+#[test]
+fn format_sha256sum_skips_unreadable_files() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::write(root.join("a.txt"), "").unwrap();
+    fs::write(root.join("b.txt"), "").unwrap();
+    fs::write(root.join("c.txt"), "").unwrap();
+    let locked = root.join("b.txt");
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+    if fs::File::open(&locked).is_ok() {
+        return;
+    }
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--format", "sha256sum"])
+        .arg(root)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let empty = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!("{empty}  a.txt\n{empty}  c.txt\n")
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(&format!(
+            "cannot read {}: permission denied",
+            locked.display()
+        )),
+        "{stderr}"
+    );
+}
+
+/// Built-in themes colour entries by role when colour is forced on, and
+/// redirected output stays plain by default.
+#[test]
+fn color_always_applies_builtin_theme() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("notes.txt"), "").unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .env("TREE_CONFIG", root.join("missing.toml"))
+        .args(["--color", "always", "--theme", "monochrome-bold"])
+        .arg(root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("── \u{1b}[1msrc\u{1b}[0m/"))
+        .stdout(predicate::str::contains("── notes.txt"));
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg(root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\u{1b}[").not());
+}
+
+/// Themes defined in the configuration file override roles of their base
+/// and can be selected as the default; unknown names are rejected.
+#[test]
+fn config_file_defines_custom_theme() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path().join("project");
+    fs::create_dir_all(root.join("src")).unwrap();
+    let config = tmp.path().join("config.toml");
+    fs::write(
+        &config,
+        "theme = \"mine\"\n[themes.mine]\nbase = \"monochrome-bold\"\ndirectory = \"underline 208\"\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .env("TREE_CONFIG", &config)
+        .args(["--color", "always"])
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\u{1b}[4;38;5;208msrc\u{1b}[0m/"));
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .env("TREE_CONFIG", &config)
+        .args(["--color", "always", "--theme", "nope"])
+        .arg(&root)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown theme `nope`"));
+}
+
+/// `--hyperlinks` is silently disabled when stdout is not a terminal.
+#[test]
+fn hyperlinks_are_disabled_when_not_a_tty() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join("a.txt"), "").unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg("--hyperlinks")
+        .arg(tmp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("a.txt"))
+        .stdout(predicate::str::contains("\u{1b}]8").not());
+}
+
+/// `--annotate-from` appends the first README heading (or first line of a
+/// plain file) next to each directory.
+#[test]
+fn annotate_from_describes_directories() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::create_dir(root.join("api")).unwrap();
+    fs::create_dir(root.join("web")).unwrap();
+    fs::create_dir(root.join("misc")).unwrap();
+    fs::write(
+        root.join("api/README.md"),
+        "\n## REST API server\n\nDetails",
+    )
+    .unwrap();
+    fs::write(root.join("web/README.md"), "Frontend\n# Web client").unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--annotate-from", "README.md"])
+        .arg(root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("api/  # REST API server\n"))
+        .stdout(predicate::str::contains("web/  # Web client\n"))
+        .stdout(predicate::str::contains("misc/\n"));
+
+    fs::write(root.join("misc/.dirdesc"), "\n  Odds and ends  \n").unwrap();
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--annotate-from", ".dirdesc"])
+        .arg(root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("misc/  # Odds and ends\n"));
+}
+
+/// `tree doc --inject` rewrites only the marked block, is idempotent, and
+/// `--check` fails once the tree drifts from the document.
+#[test]
+fn doc_injects_tree_between_markers() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path().join("project");
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src/lib.rs"), "").unwrap();
+    let readme = tmp.path().join("README.md");
+    fs::write(
+        &readme,
+        "# Project\n\n<!-- tree -->\nold\n<!-- tree -->\n\nFooter\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["doc", "--inject"])
+        .arg(&readme)
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Updated"));
+
+    let text = fs::read_to_string(&readme).unwrap();
+    assert!(text.starts_with("# Project\n\n<!-- tree -->\n```text\n"));
+    assert!(text.contains("│   └── lib.rs\n"));
+    assert!(text.ends_with("```\n<!-- tree -->\n\nFooter\n"));
+    assert!(!text.contains("old"));
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["doc", "--check", "--inject"])
+        .arg(&readme)
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("up to date"));
+
+    fs::write(root.join("src/new.rs"), "").unwrap();
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["doc", "--check", "--inject"])
+        .arg(&readme)
+        .arg(&root)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("out of date"));
+}
+
+/// Unreadable directories are annotated in text and JSON output instead of
+/// silently losing their contents. Skipped when running with privileges that
+/// bypass permission checks (e.g. as root).
+#[cfg(unix)]
+#[test]
+fn unreadable_directory_is_annotated() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    let locked = root.join("locked");
+    fs::create_dir(&locked).unwrap();
+    fs::write(locked.join("secret.txt"), "x").unwrap();
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+    let restore = || fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+    if fs::read_dir(&locked).is_ok() {
+        restore();
+        return;
+    }
+
+    let text = Command::cargo_bin("tree")
+        .unwrap()
+        .arg(root)
+        .output()
+        .unwrap();
+    let json = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--format", "json"])
+        .arg(root)
+        .output()
+        .unwrap();
+    let hidden = Command::cargo_bin("tree")
+        .unwrap()
+        .arg("--hide-errors")
+        .arg(root)
+        .output()
+        .unwrap();
+    let strict = Command::cargo_bin("tree")
+        .unwrap()
+        .arg("--strict")
+        .arg(root)
+        .output()
+        .unwrap();
+    restore();
+
+    assert!(text.status.success());
+    assert_eq!(strict.status.code(), Some(2));
+    assert_eq!(strict.stdout, text.stdout);
+
+    let text = String::from_utf8(text.stdout).unwrap();
+    assert!(
+        text.contains("├── locked/ [error: permission denied]\n"),
+        "{text}"
+    );
+    assert!(!text.contains("secret.txt"));
+
+    let json: serde_json::Value = serde_json::from_slice(&json.stdout).unwrap();
+    let node = json["children"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|c| c["name"] == "locked")
+        .unwrap();
+    assert_eq!(node["error"], "permission denied");
+
+    let hidden = String::from_utf8(hidden.stdout).unwrap();
+    assert!(hidden.contains("├── locked/\n"), "{hidden}");
+}
+
+/// `--report-header` prefixes the tree with root, time, filters and totals.
+#[test]
+fn report_header_precedes_tree() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), "target\n").unwrap();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--report-header", "-d"])
+        .arg(root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let text = String::from_utf8(output.stdout).unwrap();
+    let (header, tree) = text.split_once("\n\n").unwrap();
+
+    let canonical = fs::canonicalize(root).unwrap();
+    assert!(header.starts_with(&format!("root:     {}\n", canonical.display())));
+    let scanned = header
+        .lines()
+        .find_map(|l| l.strip_prefix("scanned:  "))
+        .unwrap();
+    assert_eq!(scanned.len(), "2025-01-01T00:00:00Z".len());
+    assert!(scanned.ends_with('Z') && scanned.as_bytes()[10] == b'T');
+    assert!(header
+        .contains("filters:  .gitignore, .ignore, .tree_ignore (1 patterns), directories only\n"));
+    assert!(header.ends_with("totals:   1 directories, 2 files, 0 symlinks, 20 bytes"));
+    assert!(tree.starts_with(&root.display().to_string()));
+}
+
+/// Creating the default `.tree_ignore` is announced on stderr only, so
+/// redirected output contains nothing but the tree; `-q` silences it.
+#[test]
+fn ignore_file_notice_stays_off_stdout() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join("file.txt"), "").unwrap();
+
+    let first = Command::cargo_bin("tree")
+        .unwrap()
+        .arg(tmp.path())
+        .output()
+        .unwrap();
+    assert!(first.status.success());
+    let stderr = String::from_utf8(first.stderr).unwrap();
+    assert!(
+        stderr.starts_with("tree: created default ignore file"),
+        "{stderr}"
+    );
+    assert_eq!(
+        String::from_utf8(first.stdout).unwrap(),
+        format!("{}\n├── .tree_ignore\n└── file.txt\n", tmp.path().display())
+    );
+
+    fs::remove_file(tmp.path().join(".tree_ignore")).unwrap();
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg("-q")
+        .arg(tmp.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+
+    // An existing ignore file produces no notice at all.
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg(tmp.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+}
+
+/// `--charset ascii` draws the tree with ASCII connectors, and
+/// `CLICOLOR_FORCE` colours output that is not a terminal.
+#[test]
+fn ascii_charset_and_clicolor_force() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir(tmp.path().join("src")).unwrap();
+    fs::write(tmp.path().join("src/lib.rs"), "").unwrap();
+    fs::write(tmp.path().join("z.txt"), "").unwrap();
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--charset", "ascii"])
+        .arg(tmp.path())
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "{}\n|-- src/\n|   `-- lib.rs\n`-- z.txt\n",
+            tmp.path().display()
+        )
+    );
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .env_remove("NO_COLOR")
+        .env("CLICOLOR_FORCE", "1")
+        .env(tree::CONFIG_ENV, tmp.path().join("missing.toml"))
+        .arg(tmp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[1;34msrc\x1b[0m/"));
+}
+
+/// `--level-colors` paints each depth's guides with the next palette colour,
+/// and a config theme can replace the palette.
+#[test]
+fn level_colors_cycle_palette_by_depth() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path().join("root");
+    fs::create_dir_all(root.join("a/b")).unwrap();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::write(root.join("a/b/c.txt"), "").unwrap();
+    let config = tmp.path().join("config.toml");
+    fs::write(
+        &config,
+        "[themes.plain]\nbase = \"monochrome-bold\"\ndirectory = \"plain\"\nfile = \"plain\"\nlevels = [\"red\", \"green\"]\n",
+    )
+    .unwrap();
+
+    let run = |args: &[&str]| {
+        let output = Command::cargo_bin("tree")
+            .unwrap()
+            .env(tree::CONFIG_ENV, &config)
+            .args(["--color", "always", "--level-colors"])
+            .args(args)
+            .arg(&root)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let default = run(&[]);
+    assert!(default.contains("\x1b[34m└── \x1b[0m"), "{default:?}");
+    assert!(default.contains("    \x1b[35m└── \x1b[0m"), "{default:?}");
+
+    let custom = run(&["--theme", "plain"]);
+    assert!(
+        custom.ends_with("\n        \x1b[31m└── \x1b[0mc.txt\n"),
+        "{custom:?}"
+    );
+}
+
+/// `--age-colors` paints names by modification age with the theme's age
+/// palette, newest first.
+#[test]
+fn age_colors_paint_by_modification_time() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path().join("root");
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::write(root.join("fresh.txt"), "").unwrap();
+    let old = fs::File::create(root.join("old.txt")).unwrap();
+    old.set_modified(std::time::SystemTime::now() - std::time::Duration::from_secs(3 * 86_400))
+        .unwrap();
+    let config = tmp.path().join("config.toml");
+    fs::write(&config, "[themes.heat]\nages = [\"red\", \"blue\"]\n").unwrap();
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .env(tree::CONFIG_ENV, &config)
+        .args(["--color", "always", "--theme", "heat", "--age-colors=1d"])
+        .arg(&root)
+        .output()
+        .unwrap();
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.contains("\x1b[31mfresh.txt\x1b[0m"), "{text:?}");
+    assert!(text.contains("\x1b[34mold.txt\x1b[0m"), "{text:?}");
+}
+
+/// `--size-colors` paints file names by size with the theme's size
+/// palette, smallest first, and leaves directories in their role style.
+#[test]
+fn size_colors_paint_files_by_size() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path().join("root");
+    fs::create_dir_all(root.join("dir")).unwrap();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::write(root.join("small.txt"), "x").unwrap();
+    fs::write(root.join("large.bin"), vec![0u8; 4096]).unwrap();
+    let config = tmp.path().join("config.toml");
+    fs::write(
+        &config,
+        "[themes.heat]\ndirectory = \"bold\"\nsizes = [\"green\", \"red\"]\n",
+    )
+    .unwrap();
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .env(tree::CONFIG_ENV, &config)
+        .args(["--color", "always", "--theme", "heat", "--size-colors=1K"])
+        .arg(&root)
+        .output()
+        .unwrap();
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.contains("\x1b[32msmall.txt\x1b[0m"), "{text:?}");
+    assert!(text.contains("\x1b[31mlarge.bin\x1b[0m"), "{text:?}");
+    assert!(text.contains("\x1b[1mdir\x1b[0m"), "{text:?}");
+}
+
+/// `--format rust-fixture` and `--format ts-fixture` emit self-contained
+/// source literals of the layout.
+#[test]
+fn fixture_formats_emit_code_literals() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir_all(tmp.path().join("src/empty")).unwrap();
+    fs::write(tmp.path().join("src/lib.rs"), "").unwrap();
+    fs::write(tmp.path().join("say \"hi\".txt"), "").unwrap();
+
+    let run = |format: &str| {
+        let output = Command::cargo_bin("tree")
+            .unwrap()
+            .args(["--format", format])
+            .arg(tmp.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let rust = run("rust-fixture");
+    assert!(rust.contains("pub enum FixtureEntry {"));
+    assert!(rust.ends_with(
+        r#"pub const TREE: &[FixtureEntry] = &[
+    FixtureEntry::Dir(
+        "src",
+        &[
+            FixtureEntry::Dir(
+                "empty",
+                &[],
+            ),
+            FixtureEntry::File("lib.rs"),
+        ],
+    ),
+    FixtureEntry::File("say \"hi\".txt"),
+];
+"#
+    ));
+
+    let ts = run("ts-fixture");
+    assert!(ts.contains("export type FixtureEntry ="));
+    assert!(ts.ends_with(
+        r#"export const tree: FixtureEntry[] = [
+  { type: "directory", name: "src", children: [
+    { type: "directory", name: "empty", children: [] },
+    { type: "file", name: "lib.rs" },
+  ] },
+  { type: "file", name: "say \"hi\".txt" },
+];
+"#
+    ));
+}
+
+/// `--format plain-indent` names each entry's kind in words on indented
+/// lines, with no box drawing or escape sequences even when colour is
+/// forced.
+#[test]
+fn plain_indent_format_spells_out_kinds() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir_all(tmp.path().join("src/empty")).unwrap();
+    fs::write(tmp.path().join("src/lib.rs"), "").unwrap();
+    fs::write(tmp.path().join("README.md"), "").unwrap();
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--format", "plain-indent", "--color", "always"])
+        .arg(tmp.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let body = stdout.split_once('\n').unwrap().1;
+    assert_eq!(
+        body,
+        "  folder src, 2 items\n    folder empty, empty\n    file lib.rs\n  file README.md\n"
+    );
+    assert!(stdout.starts_with("folder ") && stdout.contains(", 2 items\n  folder src"));
+    assert!(!stdout.contains('\x1b') && !stdout.contains('─'));
+}
+
+/// Bidirectional controls in names are escaped in text outputs so they
+/// cannot reorder the line, kept raw in JSON, and printed as is with
+/// `--keep-bidi`.
+#[test]
+fn bidi_controls_in_names_are_escaped() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::write(tmp.path().join("invoice\u{202e}fdp.exe"), "").unwrap();
+    fs::write(tmp.path().join("\u{2067}שלום\u{2069}.txt"), "").unwrap();
+
+    let run = |args: &[&str]| {
+        let output = Command::cargo_bin("tree")
+            .unwrap()
+            .args(args)
+            .arg(tmp.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let is_bidi = |c: char| matches!(c, '\u{202e}' | '\u{2067}' | '\u{2069}');
+
+    let text = run(&[]);
+    assert!(text.contains("invoice\\u{202e}fdp.exe"), "{text}");
+    assert!(text.contains("\\u{2067}שלום\\u{2069}.txt"), "{text}");
+    assert!(!text.contains(is_bidi), "{text}");
+
+    let plain = run(&["--format", "plain-indent"]);
+    assert!(plain.contains("file invoice\\u{202e}fdp.exe"), "{plain}");
+    assert!(!plain.contains(is_bidi), "{plain}");
+
+    assert!(run(&["--format", "json"]).contains("invoice\u{202e}fdp.exe"));
+    assert!(run(&["--keep-bidi"]).contains("invoice\u{202e}fdp.exe"));
+}
+
+/// `--auto-skip-over N` leaves directories with more than N entries
+/// unexpanded and reports their size instead.
+#[test]
+fn auto_skip_over_large_directories() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir_all(tmp.path().join("deps/pkg")).unwrap();
+    for i in 0..1200 {
+        fs::write(tmp.path().join(format!("deps/pkg/{i}.js")), "").unwrap();
+    }
+    fs::create_dir(tmp.path().join("src")).unwrap();
+    fs::write(tmp.path().join("src/main.rs"), "").unwrap();
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--auto-skip-over", "1000"])
+        .arg(tmp.path())
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "{}\n├── deps/ … skipped (1,201 entries)\n└── src/\n    └── main.rs\n",
+            tmp.path().display()
+        )
+    );
+}
+
+/// `--suggest-ignores` writes a first-run `.tree_ignore` holding only the
+/// bulky well-known directories it finds, and the tree skips them.
+#[test]
+fn suggest_ignores_lists_only_large_known_directories() {
+    let tmp = TempDir::new().unwrap();
+    fs::create_dir_all(tmp.path().join("node_modules/pkg")).unwrap();
+    for i in 0..=tree::SUGGEST_THRESHOLD {
+        fs::write(tmp.path().join(format!("node_modules/pkg/{i}.js")), "").unwrap();
+    }
+    fs::create_dir(tmp.path().join("target")).unwrap();
+    fs::write(tmp.path().join("target/small"), "").unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg("--suggest-ignores")
+        .arg(tmp.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("with node_modules\n"))
+        .stdout(predicate::str::contains("node_modules").not())
+        .stdout(predicate::str::contains("target/"));
+
+    let patterns = fs::read_to_string(tmp.path().join(".tree_ignore")).unwrap();
+    assert!(patterns.ends_with("\n\nnode_modules\n"), "{patterns:?}");
+}
+
+/// `--timing` reports the walk's counters on stderr and leaves stdout alone.
+/// Entry kinds come from the directory listing, so a plain tree needs no
+/// `stat` calls at all.
+#[test]
+fn timing_reports_scan_metrics() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir(tmp.path().join("src")).unwrap();
+    fs::write(tmp.path().join("src/lib.rs"), "").unwrap();
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .arg("--timing")
+        .arg(tmp.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap().lines().count(), 3);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("tree: 2 entries in "), "{stderr}");
+    assert!(
+        stderr.contains(" entries/s), 2 directories, 0 stat calls"),
+        "{stderr}"
+    );
+}
+
+/// `tree config show` prints the merged configuration as TOML that parses
+/// back to the same settings; `tree config path` prints the file in use.
+#[test]
+fn config_show_and_path() {
+    let tmp = TempDir::new().unwrap();
+    let config = tmp.path().join("config.toml");
+    fs::write(
+        &config,
+        "[themes.ocean]\nbase = \"high-contrast\"\ndirectory = \"bold #1e90ff\"\nlevels = [\"208\", \"bright-red underline\"]\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .env(tree::CONFIG_ENV, &config)
+        .args(["config", "path"])
+        .assert()
+        .success()
+        .stdout(format!("{}\n", config.display()));
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .env(tree::CONFIG_ENV, &config)
+        .args(["config", "show", "--theme", "ocean"])
+        .output()
+        .unwrap();
+    let shown = String::from_utf8(output.stdout).unwrap();
+    assert!(shown.starts_with(&format!(
+        "# file: {}\ntheme = \"ocean\"\n",
+        config.display()
+    )));
+
+    let reparsed = tmp.path().join("shown.toml");
+    fs::write(&reparsed, &shown).unwrap();
+    assert_eq!(
+        tree::Config::from_file(&reparsed).unwrap(),
+        tree::Config::from_file(&config)
+            .unwrap()
+            .effective(Some("ocean"))
+    );
+}
+
+/// `--time-style` prefixes each line with the entry's modification time.
+#[test]
+fn time_style_prefixes_mtime() {
+    use std::time::{Duration, SystemTime};
+
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    let file = fs::File::create(tmp.path().join("a.txt")).unwrap();
+    file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        .unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--time-style", "+%Y-%m-%d"])
+        .arg(tmp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with("└── [2023-11-14]  a.txt\n"));
+}
+
+/// `--du` prefixes every line, the root included, with a `du -h` size.
+#[test]
+fn du_prefixes_sizes() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir(tmp.path().join("src")).unwrap();
+    fs::write(tmp.path().join("src/lib.rs"), "x".repeat(10_000)).unwrap();
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .arg("--du")
+        .arg(tmp.path())
+        .output()
+        .unwrap();
+    let text = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<_> = text.lines().collect();
+    assert_eq!(lines.len(), 3, "{text}");
+    assert!(
+        lines[0].starts_with('[') && lines[0].ends_with(&format!("]  {}", tmp.path().display()))
+    );
+    assert!(
+        lines[1].starts_with("└── [") && lines[1].ends_with("]  src/"),
+        "{text}"
+    );
+    assert!(lines[2].ends_with("]  lib.rs"), "{text}");
+}
+
+/// `--json-errors` reports fatal errors and warnings as one JSON object per
+/// line on stderr, keyed by stable codes.
+#[test]
+fn json_errors_report_stable_codes() {
+    let tmp = TempDir::new().unwrap();
+    let missing = tmp.path().join("missing");
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .arg("--json-errors")
+        .arg(&missing)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let record: serde_json::Value = serde_json::from_str(stderr.trim()).unwrap();
+    assert_eq!(record["level"], "error");
+    assert_eq!(record["code"], "path-missing");
+    assert!(record["message"]
+        .as_str()
+        .unwrap()
+        .contains("does not exist"));
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .arg("--json-errors")
+        .arg(tmp.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let record: serde_json::Value = serde_json::from_str(stderr.trim()).unwrap();
+    assert_eq!(record["level"], "info");
+    assert_eq!(record["code"], "created-ignore-file");
+    assert!(record["path"].as_str().unwrap().ends_with(".tree_ignore"));
+}
+
+/// Exit statuses follow the documented matrix: 1 for usage and validation
+/// errors, 3 for I/O failures, and `--strict` alone never fails a clean run.
+#[test]
+fn exit_codes_follow_matrix() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join(".tree_ignore"), "").unwrap();
+
+    let status = |args: &[&std::ffi::OsStr]| {
+        Command::cargo_bin("tree")
+            .unwrap()
+            .args(args)
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+    let root = tmp.path().as_os_str();
+    let missing = tmp.path().join("missing");
+    let archive = missing.join("out.tar.gz");
+
+    assert_eq!(status(&["--strict".as_ref(), root]), Some(0));
+    assert_eq!(status(&["--no-such-flag".as_ref()]), Some(1));
+    assert_eq!(status(&[missing.as_os_str()]), Some(1));
+    assert_eq!(
+        status(&["--export".as_ref(), archive.as_os_str(), root]),
+        Some(3)
+    );
+}
+
+/// `--paths-from -` renders exactly the listed paths without walking PATH.
+#[test]
+fn paths_from_stdin() {
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--paths-from", "-", "project"])
+        .write_stdin("src/main.rs\nCargo.toml\n")
+        .assert()
+        .success()
+        .stdout("project\n├── src/\n│   └── main.rs\n└── Cargo.toml\n");
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--paths-from", "-", "--format", "json"])
+        .write_stdin("a/b\0c\0")
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["children"][0]["children"][0]["path"], "a/b");
+    assert_eq!(json["children"][1]["type"], "file");
+}
+
+/// `--git-rev` shows a committed layout, not the working directory. Skipped
+/// when git is not installed.
+#[test]
+fn git_rev_lists_committed_tree() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    let git = |args: &[&str]| {
+        std::process::Command::new("git")
+            .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+            .arg("-C")
+            .arg(root)
+            .args(args)
+            .output()
+            .is_ok_and(|output| output.status.success())
+    };
+    if !git(&["init", "-q"]) {
+        return;
+    }
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src/lib.rs"), "").unwrap();
+    fs::write(root.join("Cargo.toml"), "").unwrap();
+    assert!(git(&["add", "."]));
+    assert!(git(&["commit", "-qm", "init"]));
+    fs::remove_file(root.join("Cargo.toml")).unwrap();
+    fs::write(root.join("untracked.txt"), "").unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--git-rev", "HEAD"])
+        .arg(root)
+        .assert()
+        .success()
+        .stdout("HEAD\n├── src/\n│   └── lib.rs\n└── Cargo.toml\n");
+    assert!(!root.join(".tree_ignore").exists());
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--git-rev", "no-such-rev"])
+        .arg(root)
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("no-such-rev"));
+}
+
+/// `--export-ignore` hides what `git archive` leaves out, including
+/// patterns from nested `.gitattributes` files.
+#[test]
+fn export_ignore_hides_archive_exclusions() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n.git\n").unwrap();
+    fs::create_dir(root.join(".git")).unwrap();
+    fs::write(
+        root.join(".gitattributes"),
+        "# release contents\n/tests export-ignore\n*.md export-ignore\nREADME.md -export-ignore\n.gitattributes export-ignore\n",
+    )
+    .unwrap();
+    fs::create_dir_all(root.join("tests")).unwrap();
+    fs::write(root.join("tests/it.rs"), "").unwrap();
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(
+        root.join("src/.gitattributes"),
+        "local.rs binary export-ignore\nNOTES.md -export-ignore\n",
+    )
+    .unwrap();
+    fs::write(root.join("src/lib.rs"), "").unwrap();
+    fs::write(root.join("src/local.rs"), "").unwrap();
+    fs::write(root.join("src/NOTES.md"), "").unwrap();
+    fs::write(root.join("README.md"), "").unwrap();
+    fs::write(root.join("CHANGELOG.md"), "").unwrap();
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .arg("--export-ignore")
+        .arg(root)
+        .output()
+        .unwrap();
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        text.ends_with("├── src/\n│   ├── NOTES.md\n│   └── lib.rs\n└── README.md\n"),
+        "{text}"
+    );
+
+    let plain = Command::cargo_bin("tree")
+        .unwrap()
+        .arg(root)
+        .output()
+        .unwrap();
+    let plain = String::from_utf8(plain.stdout).unwrap();
+    assert!(
+        plain.contains("tests/") && plain.contains("CHANGELOG.md"),
+        "{plain}"
+    );
+}
+
+/// `--xattrs` lists attribute names after the entry and `-Z` prefixes the
+/// security context. Skipped on filesystems without user attributes.
+#[cfg(unix)]
+#[test]
+fn xattrs_and_context_are_shown() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::write(root.join("tagged"), "").unwrap();
+    fs::write(root.join("plain"), "").unwrap();
+    if xattr::set(root.join("tagged"), "user.origin", b"web").is_err() {
+        return;
+    }
+    xattr::set(root.join("tagged"), "user.checked", b"1").unwrap();
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--xattrs", "--context"])
+        .arg(root)
+        .output()
+        .unwrap();
+    let text = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<_> = text.lines().skip(1).collect();
+    assert_eq!(lines.len(), 2, "{text}");
+    assert!(
+        lines[0].starts_with("├── [") && lines[0].ends_with("]  plain"),
+        "{text}"
+    );
+    assert!(
+        lines[1].ends_with("]  tagged [xattrs: user.checked, user.origin]"),
+        "{text}"
+    );
+}
+
+/// `-p` shows `ls -l` permissions and marks entries carrying an ACL with
+/// `+`. The ACL half is skipped where POSIX ACLs cannot be set.
+#[cfg(unix)]
+#[test]
+fn permissions_show_mode_and_acl_marker() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir(root.join("shared")).unwrap();
+    fs::set_permissions(root.join("shared"), fs::Permissions::from_mode(0o1777)).unwrap();
+    fs::write(root.join("tool"), "").unwrap();
+    fs::set_permissions(root.join("tool"), fs::Permissions::from_mode(0o4750)).unwrap();
+
+    let run = || {
+        let output = Command::cargo_bin("tree")
+            .unwrap()
+            .arg("-p")
+            .arg(root)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let text = run();
+    assert!(text.contains("├── [drwxrwxrwt]  shared/\n"), "{text}");
+    assert!(text.contains("└── [-rwsr-x---]  tool\n"), "{text}");
+
+    // A POSIX ACL matching mode 0750 that also grants read access to uid
+    // 1000 (setting it rewrites the mode bits from the ACL).
+    let mut acl = 2u32.to_le_bytes().to_vec();
+    for (tag, perm, id) in [
+        (1u16, 7u16, u32::MAX),
+        (2, 4, 1000),
+        (4, 5, u32::MAX),
+        (0x10, 5, u32::MAX),
+        (0x20, 0, u32::MAX),
+    ] {
+        acl.extend(tag.to_le_bytes());
+        acl.extend(perm.to_le_bytes());
+        acl.extend(id.to_le_bytes());
+    }
+    if cfg!(target_os = "linux")
+        && xattr::set(root.join("tool"), "system.posix_acl_access", &acl).is_ok()
+    {
+        let text = run();
+        assert!(text.contains("└── [-rwsr-x---+]  tool\n"), "{text}");
+    }
+}
+
+/// `tree budget` passes within its limits and otherwise lists the largest
+/// contributors and fails.
+#[test]
+fn budget_subcommand_guards_limits() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir(root.join("data")).unwrap();
+    fs::write(root.join("data/blob.bin"), vec![0; 300 * 1024]).unwrap();
+    fs::write(root.join("notes.txt"), "hi").unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["budget", "--max-size", "1M", "--max-files", "10"])
+        .arg(root)
+        .assert()
+        .success()
+        .stdout("size:  301K (limit 1.0M)\nfiles: 2 (limit 10)\n");
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["budget", "--max-size", "100K", "--top", "1"])
+        .arg(root)
+        .assert()
+        .code(1)
+        .stdout(
+            "size:  301K (limit 100K)\nfiles: 2 (no limit)\nlargest contributors:\n    300K         1 file(s)  data\n",
+        )
+        .stderr(predicate::str::contains("over budget: size"));
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["budget", "--max-size", "lots"])
+        .arg(root)
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("invalid size `lots`"));
+}
+
+/// `tree check-collisions` marks case collisions and fails; a clean tree
+/// passes.
+#[test]
+fn check_collisions_subcommand() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src/lib.rs"), "").unwrap();
+    fs::write(root.join("README.md"), "").unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg("check-collisions")
+        .arg(root)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("OK: no collisions"));
+
+    fs::write(root.join("Readme.md"), "").unwrap();
+    if fs::read_dir(root).unwrap().count() < 4 {
+        return; // case-insensitive filesystem: the collision cannot exist
+    }
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .arg("check-collisions")
+        .arg(root)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        text.ends_with("├── README.md  [collides: case]\n└── Readme.md  [collides: case]\n"),
+        "{text}"
+    );
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["check-collisions", "--format", "json"])
+        .arg(root)
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json[0]["names"],
+        serde_json::json!(["README.md", "Readme.md"])
+    );
+    assert_eq!(json[0]["kind"], "case");
+    assert_eq!(String::from_utf8(output.stdout).unwrap().lines().count(), 1);
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["check-collisions", "--format", "json", "--pretty"])
+        .arg(root)
+        .output()
+        .unwrap();
+    let pretty = String::from_utf8(output.stdout).unwrap();
+    assert!(pretty.starts_with("[\n  {\n    \""), "{pretty}");
+}
+
+/// Metadata columns are right‑aligned per directory, before the names by
+/// default or after them at `--columns-at`, counting wide characters twice.
+#[cfg(unix)]
+#[test]
+fn metadata_columns_align_per_directory() {
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, SystemTime};
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir(root.join("\u{6587}\u{6863}")).unwrap();
+    fs::set_permissions(
+        root.join("\u{6587}\u{6863}"),
+        fs::Permissions::from_mode(0o755),
+    )
+    .unwrap();
+    for (name, age) in [("notes", 0), ("old-readme", 3 * 86_400)] {
+        let file = fs::File::create(root.join(name)).unwrap();
+        file.set_permissions(fs::Permissions::from_mode(0o644))
+            .unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(age))
+            .unwrap();
+    }
+
+    let run = |args: &[&str]| {
+        let output = Command::cargo_bin("tree")
+            .unwrap()
+            .args(args)
+            .arg(root)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let text = run(&["--time-style", "relative"]);
+    assert!(text.contains("├── [  just now]  notes\n"), "{text}");
+    assert!(text.contains("└── [3 days ago]  old-readme\n"), "{text}");
+
+    let text = run(&["-p", "--columns-at", "16"]);
+    assert!(
+        text.contains("├── \u{6587}\u{6863}/       [drwxr-xr-x]\n"),
+        "{text}"
+    );
+    assert!(text.contains("├── notes       [-rw-r--r--]\n"), "{text}");
+    assert!(text.contains("└── old-readme  [-rw-r--r--]\n"), "{text}");
+}
+
+/// `--count-only` prints the totals instead of the tree.
+#[test]
+fn count_only_prints_totals() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir_all(root.join("src/bin")).unwrap();
+    fs::write(root.join("src/lib.rs"), "pub fn f() {}\n").unwrap();
+    fs::write(root.join("src/bin/main.rs"), "fn main() {}\n").unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg("--count-only")
+        .arg(root)
+        .assert()
+        .success()
+        .stdout("2 directories, 2 files, 27 bytes\n");
+}
+
+/// `--numeric-locale` groups the digits of the totals.
+#[test]
+fn numeric_locale_groups_totals() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::write(root.join("data.bin"), vec![0u8; 1_234_567]).unwrap();
+
+    for (locale, bytes) in [
+        ("en", "1,234,567"),
+        ("de_DE.UTF-8", "1.234.567"),
+        ("c", "1234567"),
+    ] {
+        Command::cargo_bin("tree")
+            .unwrap()
+            .args(["--count-only", "--numeric-locale", locale])
+            .arg(root)
+            .assert()
+            .success()
+            .stdout(format!("0 directories, 1 files, {bytes} bytes\n"));
+    }
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--count-only", "--numeric-locale", "klingon"])
+        .arg(root)
+        .assert()
+        .failure();
+}
+
+/// `--shape-stats` prints the depth histogram and summary instead of the
+/// tree.
+#[test]
+fn shape_stats_replace_the_tree() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::create_dir_all(root.join("src/bin")).unwrap();
+    fs::write(root.join("src/lib.rs"), "").unwrap();
+    fs::write(root.join("src/bin/main.rs"), "").unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg("--shape-stats")
+        .arg(root)
+        .assert()
+        .success()
+        .stdout(concat!(
+            "depth  entries\n",
+            "    1        1  ####################\n",
+            "    2        2  ########################################\n",
+            "    3        1  ####################\n",
+            "max depth:       3\n",
+            "average fanout:  1.3\n",
+            "deepest path:    src/bin/main.rs\n",
+        ));
+}
+
+/// FIFOs and sockets get `ls -F` markers and are never opened: hashing a
+/// tree holding a FIFO with no writer must not block.
+#[cfg(unix)]
+#[test]
+fn special_files_are_marked_and_never_opened() {
+    use std::os::unix::net::UnixListener;
+    use std::time::Duration;
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::write(root.join("data.txt"), "x").unwrap();
+    let _listener = UnixListener::bind(root.join("app.sock")).unwrap();
+    let has_fifo = std::process::Command::new("mkfifo")
+        .arg(root.join("pipe"))
+        .status()
+        .is_ok_and(|s| s.success());
+
+    let run = |format: &str| {
+        let output = Command::cargo_bin("tree")
+            .unwrap()
+            .args(["--format", format])
+            .arg(root)
+            .timeout(Duration::from_secs(20))
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{format} did not finish");
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let text = run("text");
+    assert!(text.contains("├── app.sock=\n"), "{text}");
+    if has_fifo {
+        assert!(text.contains("└── pipe|\n"), "{text}");
+    }
+    let json = run("json");
+    assert!(json.contains(r#""type":"socket""#), "{json}");
+    let sums = run("sha256sum");
+    assert_eq!(sums.lines().count(), 1, "{sums}");
+    assert!(sums.ends_with("  data.txt\n"), "{sums}");
+}
+
+/// `--find-root` walks up to the directory holding the marker and shows
+/// the whole project from there.
+#[test]
+fn find_root_walks_up_to_marker() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path().join("project");
+    fs::create_dir_all(project.join("src/deep/er")).unwrap();
+    fs::write(project.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::write(project.join("Cargo.toml"), "").unwrap();
+    fs::write(project.join("src/deep/er/.tree_ignore"), "").unwrap();
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--find-root", "Cargo.toml"])
+        .arg(project.join("src/deep/er"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let text = String::from_utf8(output.stdout).unwrap();
+    let root = project.canonicalize().unwrap();
+    assert!(text.starts_with(&format!("{}\n", root.display())), "{text}");
+    assert!(text.contains("Cargo.toml"), "{text}");
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--find-root", "no-such-marker"])
+        .arg(project.join("src"))
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("no `no-such-marker` found"));
+}
+
+/// Several paths with `--format json` form one document with a `roots`
+/// array, pretty or minified; text output lists the trees in turn.
+#[test]
+fn multiple_roots_aggregate_into_one_json_document() {
+    let tmp = TempDir::new().unwrap();
+    for (dir, file) in [("api", "main.go"), ("web", "app.ts"), ("empty", "")] {
+        let dir = tmp.path().join(dir);
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+        if !file.is_empty() {
+            fs::write(dir.join(file), "").unwrap();
+        }
+    }
+    let roots = ["api", "web", "empty"].map(|d| tmp.path().join(d));
+
+    for style in ["--pretty", "--minify"] {
+        let output = Command::cargo_bin("tree")
+            .unwrap()
+            .args(["--format", "json", style])
+            .args(&roots)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let roots = json["roots"].as_array().unwrap();
+        assert_eq!(roots.len(), 3);
+        assert_eq!(roots[0]["children"][0]["name"], "main.go");
+        assert_eq!(roots[1]["children"][0]["path"], "app.ts");
+        assert!(roots[2].get("children").is_none());
+    }
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(&roots[..2])
+        .output()
+        .unwrap();
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.contains("└── main.go\n\n"), "{text}");
+    assert!(text.ends_with("└── app.ts\n"), "{text}");
+}
+
+/// `--tee FILE` writes the same rendering to stdout and to FILE.
+#[test]
+fn tee_copies_the_rendering_to_a_file() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path().join("project");
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+    let copy = tmp.path().join("tree.txt");
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .arg("--quiet")
+        .arg("--tee")
+        .arg(&copy)
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.contains("main.rs"), "{text}");
+    assert_eq!(fs::read_to_string(&copy).unwrap(), text);
+}
+
+/// Reports printed instead of the tree, by `--count-only`, `--shape-stats`
+/// and subcommands, go to the `--tee` file as well.
+#[test]
+fn tee_copies_reports_to_a_file() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path().join("project");
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    let other = tmp.path().join("other");
+    fs::create_dir(&other).unwrap();
+    let copy = tmp.path().join("report.txt");
+
+    let runs: [&[&std::ffi::OsStr]; 5] = [
+        &["--count-only".as_ref(), root.as_ref()],
+        &["--shape-stats".as_ref(), root.as_ref()],
+        &["audit".as_ref(), root.as_ref()],
+        &["check-collisions".as_ref(), root.as_ref()],
+        &["diff".as_ref(), root.as_ref(), other.as_ref()],
+    ];
+    for args in runs {
+        let output = Command::cargo_bin("tree")
+            .unwrap()
+            .args(args)
+            .arg("--tee")
+            .arg(&copy)
+            .output()
+            .unwrap();
+        let text = String::from_utf8(output.stdout).unwrap();
+        assert!(!text.is_empty(), "{args:?}");
+        assert_eq!(fs::read_to_string(&copy).unwrap(), text, "{args:?}");
+    }
+}
+
+/// `--timing` reports after trees that come from a path list or a Git
+/// revision too, not only after walking the disk.
+#[test]
+fn timing_reports_after_paths_from() {
+    let tmp = TempDir::new().unwrap();
+    let list = tmp.path().join("paths.txt");
+    fs::write(&list, "src/main.rs\nREADME.md\n").unwrap();
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--timing", "--paths-from"])
+        .arg(&list)
+        .arg(tmp.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("main.rs"));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("tree: "), "{stderr}");
+    assert!(stderr.contains(" entries/s)"), "{stderr}");
+}
+
+/// `--budget-bytes` keeps shallow entries and source directories first and
+/// summarises what did not fit with `+N more` lines.
+#[test]
+fn budget_keeps_priority_entries_and_summarises_the_rest() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    fs::create_dir_all(root.join("src/deep/er")).unwrap();
+    fs::create_dir_all(root.join("docs")).unwrap();
+    for name in [
+        "src/a.rs",
+        "src/b.rs",
+        "src/deep/er/x.rs",
+        "docs/1.md",
+        "docs/2.md",
+    ] {
+        fs::write(root.join(name), "").unwrap();
+    }
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--quiet", "--budget-bytes", "120"])
+        .arg(root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.contains("src/\n"), "{text}");
+    assert!(text.contains("a.rs"), "{text}");
+    assert!(!text.contains("x.rs"), "{text}");
+    assert!(text.contains(" more\n"), "{text}");
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--quiet", "--budget-tokens", "1000"])
+        .arg(root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("x.rs").and(predicate::str::contains("more").not()));
+}
+
+/// `--user` keeps only files of the given owner but every directory;
+/// unknown names are rejected.
+#[cfg(unix)]
+#[test]
+fn user_filter_shows_only_owned_files() {
+    use std::os::unix::fs::MetadataExt;
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    fs::create_dir(root.join("shared")).unwrap();
+    fs::write(root.join("shared/report.txt"), "").unwrap();
+    let uid = fs::metadata(root.join("shared/report.txt")).unwrap().uid();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--quiet", "--user", &uid.to_string()])
+        .arg(root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("report.txt"));
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--quiet", "--user", &(uid + 1).to_string()])
+        .arg(root)
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("shared/").and(predicate::str::contains("report.txt").not()),
+        );
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--quiet", "--owner-group", "no-such-group-here"])
+        .arg(root)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "unknown group `no-such-group-here`",
+        ));
+}
+
+/// `--writable-only` and `--readonly-only` split files by whether the
+/// current user may write them; `--mark-unreadable` flags unreadable ones.
+/// Root may write and read everything.
+#[cfg(unix)]
+#[test]
+fn access_filters_follow_the_current_user() {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    fs::write(root.join("rw.txt"), "").unwrap();
+    fs::write(root.join("ro.txt"), "").unwrap();
+    fs::write(root.join("secret.txt"), "").unwrap();
+    fs::set_permissions(root.join("ro.txt"), fs::Permissions::from_mode(0o444)).unwrap();
+    fs::set_permissions(root.join("secret.txt"), fs::Permissions::from_mode(0o000)).unwrap();
+    let is_root = fs::metadata(root.join("rw.txt")).unwrap().uid() == 0;
+
+    let run = |flag: &str| {
+        let output = Command::cargo_bin("tree")
+            .unwrap()
+            .args(["--quiet", flag])
+            .arg(root)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let writable = run("--writable-only");
+    assert!(writable.contains("rw.txt"), "{writable}");
+    assert_eq!(writable.contains("ro.txt"), is_root, "{writable}");
+    let readonly = run("--readonly-only");
+    assert!(!readonly.contains("rw.txt"), "{readonly}");
+    assert_eq!(readonly.contains("ro.txt"), !is_root, "{readonly}");
+    let marked = run("--mark-unreadable");
+    assert_eq!(
+        marked.contains("secret.txt  [unreadable]"),
+        !is_root,
+        "{marked}"
+    );
+    assert!(!marked.contains("rw.txt  [unreadable]"), "{marked}");
+}
+
+/// `--filter` keeps matching files and every directory, still honours
+/// `.tree_ignore`, and rejects a malformed expression with its column.
+#[test]
+fn filter_expression_selects_files() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), "target\n").unwrap();
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::create_dir_all(root.join("target")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+    fs::write(root.join("src/notes.md"), "").unwrap();
+    fs::write(root.join("target/build.rs"), "").unwrap();
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--quiet", "--filter", "ext=rs & size>0"])
+        .arg(root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.contains("src") && text.contains("main.rs"), "{text}");
+    for hidden in ["notes.md", "target", "build.rs"] {
+        assert!(!text.contains(hidden), "{hidden} shown:\n{text}");
+    }
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--filter", "ext=rs &"])
+        .arg(root)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid filter at column 9"));
+}
+
+/// `tree audit` marks setuid and world-writable entries, skips sticky
+/// shared directories and fails when anything is found.
+#[cfg(unix)]
+#[test]
+fn audit_flags_risky_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    fs::create_dir_all(root.join("bin")).unwrap();
+    fs::write(root.join("bin/helper"), "").unwrap();
+    fs::create_dir(root.join("drop")).unwrap();
+    fs::write(root.join("shared.txt"), "").unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg("audit")
+        .arg(root)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("OK: nothing to report"));
+
+    let mode = |path: &str, mode: u32| {
+        fs::set_permissions(root.join(path), fs::Permissions::from_mode(mode)).unwrap();
+    };
+    mode("bin/helper", 0o4755);
+    mode("drop", 0o1777);
+    mode("shared.txt", 0o666);
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .arg("audit")
+        .arg(root)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.contains("helper  [setuid]\n"), "{text}");
+    assert!(text.contains("shared.txt  [world-writable]\n"), "{text}");
+    assert!(!text.contains("drop"), "{text}");
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["audit", "--format", "json"])
+        .arg(root)
+        .output()
+        .unwrap();
+    let findings: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(findings[0]["path"], "bin/helper");
+    assert_eq!(findings[0]["issues"][0], "setuid");
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["audit", "--format", "json", "--minify"])
+        .arg(root)
+        .output()
+        .unwrap();
+    let minified = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        minified.starts_with("[{\"path\":\"bin/helper\""),
+        "{minified}"
+    );
+    assert_eq!(minified.lines().count(), 1, "{minified}");
+}
+
+/// `--count-only` counts a directory reached twice once, unless
+/// `--no-dedupe` is given.
+#[cfg(unix)]
+#[test]
+fn count_only_dedupes_directories_reached_twice() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    fs::write(root.join(".tree_ignore"), "").unwrap();
+    fs::create_dir(root.join("data")).unwrap();
+    fs::write(root.join("data/big.bin"), vec![0u8; 1000]).unwrap();
+    std::os::unix::fs::symlink(root.join("data"), root.join("mirror")).unwrap();
+
+    let count = |extra: &[&str]| {
+        let output = Command::cargo_bin("tree")
+            .unwrap()
+            .args(["--quiet", "--count-only"])
+            .args(extra)
+            .arg(root)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert!(
+        count(&[]).starts_with("1 directories, 2 files"),
+        "{}",
+        count(&[])
+    );
+    let all = count(&["--no-dedupe"]);
+    assert!(all.starts_with("2 directories, 3 files"), "{all}");
+}
+
+/// `--record` saves the scan and `tree render` reproduces the output from
+/// the recording alone, in any format, after the directory is gone.
+#[test]
+fn recorded_scans_render_without_the_directory() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path().join("project");
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+    fs::write(root.join("README.md"), "# project").unwrap();
+    let recording = tmp.path().join("scan.bin");
+
+    let live = Command::cargo_bin("tree")
+        .unwrap()
+        .arg("--quiet")
+        .arg("--record")
+        .arg(&recording)
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert!(live.status.success());
+    fs::remove_dir_all(&root).unwrap();
+
+    let replay = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["render", "--root-name"])
+        .arg(&root)
+        .arg(&recording)
+        .output()
+        .unwrap();
+    assert!(replay.status.success());
+    assert_eq!(replay.stdout, live.stdout);
+    assert!(String::from_utf8_lossy(&replay.stdout).contains("└── main.rs"));
+
+    let json = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["render", "--format", "json"])
+        .arg(&recording)
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json.stdout).unwrap();
+    assert_eq!(json["name"], ".");
+    assert_eq!(json["children"][0]["children"][0]["path"], "src/main.rs");
+    assert_eq!(json["children"][0]["children"][0]["size"], 12);
+
+    let pretty = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["render", "--format", "json", "--pretty"])
+        .arg(&recording)
+        .output()
+        .unwrap();
+    assert!(String::from_utf8(pretty.stdout)
+        .unwrap()
+        .starts_with("{\n  \"name\": \".\""));
+}
+
+/// `--record` refuses columns that a recording cannot reproduce instead of
+/// silently dropping them.
+#[test]
+fn record_refuses_unrecorded_columns() {
+    let tmp = TempDir::new().unwrap();
+    for flag in ["--du", "-p", "-D"] {
+        Command::cargo_bin("tree")
+            .unwrap()
+            .arg(flag)
+            .arg("--record")
+            .arg(tmp.path().join("scan.bin"))
+            .arg(tmp.path())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+    assert!(!tmp.path().join("scan.bin").exists());
+}
+
+/// `tree diff` lists the changes between two directories and exits with 1
+/// when they differ; `--quiet` prints only the counts.
+#[test]
+fn diff_reports_changes_and_exit_status() {
+    let tmp = TempDir::new().unwrap();
+    let (old, new) = (tmp.path().join("old"), tmp.path().join("new"));
+    for dir in [&old, &new] {
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join(".tree_ignore"), "").unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+    }
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg("diff")
+        .args([&old, &new])
+        .assert()
+        .success()
+        .stdout("");
+
+    fs::write(new.join("src/lib.rs"), "").unwrap();
+    fs::write(new.join("src/main.rs"), "fn main() { run() }").unwrap();
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg("diff")
+        .args([&old, &new])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("added    src/lib.rs"))
+        .stdout(predicate::str::contains("modified src/main.rs"));
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["diff", "--quiet"])
+        .args([&old, &new])
+        .assert()
+        .code(1)
+        .stdout("1 added, 0 removed, 1 modified\n");
+}
+
+/// `tree diff` exits with 2 when it cannot compare, so scripts can tell
+/// errors from differences.
+#[test]
+fn diff_failures_exit_with_two() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join(".tree_ignore"), "").unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg("diff")
+        .arg(tmp.path())
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("<NEW>"));
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg("diff")
+        .arg(tmp.path())
+        .arg(tmp.path().join("missing"))
+        .assert()
+        .code(2);
+}
+
+/// `tree diff --tree` shows the changes in a tree and collapses identical
+/// subtrees into one line.
+#[test]
+fn diff_tree_collapses_identical_subtrees() {
+    let tmp = TempDir::new().unwrap();
+    let (old, new) = (tmp.path().join("old"), tmp.path().join("new"));
+    for dir in [&old, &new] {
+        fs::create_dir_all(dir.join("vendor/lib")).unwrap();
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join(".tree_ignore"), "").unwrap();
+        fs::write(dir.join("vendor/lib/a.rs"), "a").unwrap();
+        fs::write(dir.join("vendor/lib/b.rs"), "b").unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+    }
+    fs::write(new.join("src/main.rs"), "fn main() { run() }").unwrap();
+    fs::write(old.join("src/old.rs"), "").unwrap();
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["diff", "--tree"])
+        .args([&old, &new])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        text.ends_with(
+            "├── src/\n\
+             │   ├── main.rs  [modified: size 12 -> 19]\n\
+             │   └── old.rs  [removed]\n\
+             ├── vendor/  (identical, 3 entries)\n\
+             └── .tree_ignore\n"
+        ),
+        "{text}"
+    );
+}
+
+/// `tree diff --base` compares two copies with a snapshot of their common
+/// base and tells one-sided changes from agreeing and conflicting ones.
+#[test]
+fn three_way_diff_flags_conflicts() {
+    let tmp = TempDir::new().unwrap();
+    let (left, right) = (tmp.path().join("left"), tmp.path().join("right"));
+    for dir in [&left, &right] {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join(".tree_ignore"), "").unwrap();
+        for file in ["keep.txt", "same.txt", "clash.txt", "gone.txt"] {
+            fs::write(dir.join(file), "base").unwrap();
+        }
+    }
+    let base = tmp.path().join("base.snap");
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["snapshot", "--quiet", "-o"])
+        .arg(&base)
+        .arg(&left)
+        .assert()
+        .success();
+
+    fs::write(left.join("only-left.txt"), "").unwrap();
+    fs::remove_file(right.join("gone.txt")).unwrap();
+    for dir in [&left, &right] {
+        fs::write(dir.join("same.txt"), "changed alike").unwrap();
+    }
+    fs::write(left.join("clash.txt"), "left edit").unwrap();
+    fs::write(right.join("clash.txt"), "the right side's edit").unwrap();
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .arg("diff")
+        .arg("--base")
+        .arg(&base)
+        .args([&left, &right])
+        .assert()
+        .code(1)
+        .stdout(
+            "conflict clash.txt (left modified, right modified)\n\
+             right    gone.txt (removed)\n\
+             left     only-left.txt (added)\n\
+             both     same.txt (modified)\n",
+        );
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["diff", "--quiet", "--base"])
+        .arg(&base)
+        .args([&left, &right])
+        .assert()
+        .code(1)
+        .stdout("1 left, 1 right, 1 both, 1 conflicts\n");
+}
+
+/// `--idle-io` lowers the priority of the scan without changing its
+/// output; where it cannot, a warning is printed and the scan still runs.
+#[test]
+fn idle_io_scans_normally() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::write(tmp.path().join("a.txt"), "a").unwrap();
+
+    let output = Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--idle-io", "--count-only"])
+        .arg(tmp.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("1 file"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.is_empty() || stderr.contains("--idle-io ignored"),
+        "{stderr}"
+    );
+}
+
+/// Ctrl-C stops the walk after a complete line and reports how far it got
+/// with exit status 130.
+#[cfg(unix)]
+#[test]
+fn ctrl_c_interrupts_with_partial_summary() {
+    use std::{process::Stdio, thread, time::Duration};
+
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    for i in 0..200 {
+        fs::create_dir(tmp.path().join(format!("d{i:03}"))).unwrap();
+    }
+
+    let child = std::process::Command::new(assert_cmd::cargo::cargo_bin("tree"))
+        .args(["--throttle", "20"])
+        .arg(tmp.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    thread::sleep(Duration::from_millis(1500));
+    let killed = std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(output.status.code(), Some(130));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("scan interrupted after"), "{stderr}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.ends_with('\n'), "{stdout}");
+    assert!(stdout.lines().count() < 200, "{stdout}");
+}
+
+/// `--watch` refuses `--record`, which would print once and exit instead
+/// of watching.
+#[test]
+fn watch_conflicts_with_record() {
+    let tmp = TempDir::new().unwrap();
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--watch", "--record"])
+        .arg(tmp.path().join("scan.tree"))
+        .arg(tmp.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+/// `--watch` refuses the prompt budgets, which would print once and exit
+/// instead of watching.
+#[test]
+fn watch_conflicts_with_budgets() {
+    let tmp = TempDir::new().unwrap();
+    for budget in ["--budget-tokens", "--budget-bytes"] {
+        Command::cargo_bin("tree")
+            .unwrap()
+            .args(["--watch", budget, "20"])
+            .arg(tmp.path())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+}
+
+/// `--watch --log-changes` appends one JSON line per change and re-prints
+/// the tree; Ctrl-C ends the watch successfully.
+#[cfg(unix)]
+#[test]
+fn watch_logs_changes_as_json_lines() {
+    use std::{process::Stdio, thread, time::Duration};
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path().join("root");
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::write(root.join("old.txt"), "old").unwrap();
+    let log = tmp.path().join("changes.jsonl");
+    fs::write(&log, "{\"earlier\":true}\n").unwrap();
+
+    let child = std::process::Command::new(assert_cmd::cargo::cargo_bin("tree"))
+        .args(["--watch", "--watch-interval", "100", "--log-changes"])
+        .arg(&log)
+        .arg(&root)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    thread::sleep(Duration::from_millis(700));
+    fs::write(root.join("new.txt"), "new").unwrap();
+    fs::remove_file(root.join("old.txt")).unwrap();
+    thread::sleep(Duration::from_millis(700));
+    std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("old.txt") && stdout.contains("new.txt"),
+        "{stdout}"
+    );
+    let lines: Vec<serde_json::Value> = fs::read_to_string(&log)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3, "{lines:?}");
+    assert_eq!(lines[0]["earlier"], true);
+    assert_eq!(lines[1]["event"], "created");
+    assert_eq!(lines[1]["path"], "new.txt");
+    assert_eq!(lines[2]["event"], "removed");
+    assert_eq!(lines[2]["path"], "old.txt");
+    assert!(lines[1]["time"].as_str().unwrap().ends_with('Z'));
+}
+
+/// `--watch --format ndjson` streams each change as a JSON line on stdout
+/// instead of re-printing the tree; without `--watch` the format is refused.
+#[cfg(unix)]
+#[test]
+fn watch_streams_ndjson_events() {
+    use std::{process::Stdio, thread, time::Duration};
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path().join("root");
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    fs::write(root.join("old.txt"), "old").unwrap();
+
+    let child = std::process::Command::new(assert_cmd::cargo::cargo_bin("tree"))
+        .args(["--watch", "--watch-interval", "100", "--format", "ndjson"])
+        .arg(&root)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    thread::sleep(Duration::from_millis(700));
+    fs::write(root.join("new.txt"), "new").unwrap();
+    thread::sleep(Duration::from_millis(700));
+    std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let lines: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 1, "{lines:?}");
+    assert_eq!(lines[0]["event"], "created");
+    assert_eq!(lines[0]["path"], "new.txt");
+    assert!(lines[0]["time"].as_str().unwrap().ends_with('Z'));
+
+    Command::cargo_bin("tree")
+        .unwrap()
+        .args(["--format", "ndjson"])
+        .arg(&root)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("needs --watch"));
+}
+
+/// `--notify-cmd` runs once per batch of watched changes with the
+/// placeholders expanded.
+#[cfg(unix)]
+#[test]
+fn watch_runs_notify_command() {
+    use std::{process::Stdio, thread, time::Duration};
+
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path().join("root");
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join(".tree_ignore"), ".tree_ignore\n").unwrap();
+    let record = tmp.path().join("notified.txt");
+
+    let child = std::process::Command::new(assert_cmd::cargo::cargo_bin("tree"))
+        .args(["--watch", "--watch-interval", "100", "--notify-cmd"])
+        .arg(format!(
+            "echo {{event}} {{count}} {{path}} >> '{}'",
+            record.display()
+        ))
+        .arg(&root)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    thread::sleep(Duration::from_millis(700));
+    fs::write(root.join("a b.txt"), "new").unwrap();
+    thread::sleep(Duration::from_millis(700));
+    std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    assert_eq!(fs::read_to_string(&record).unwrap(), "created 1 a b.txt\n");
+}